@@ -0,0 +1,153 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Aliases from the config file
+use std::collections::HashMap;
+
+/// Raw command line arguments
+use std::ffi::OsString;
+
+/// Splits an alias into arguments like a shell would, without running one.
+///
+/// Words are separated by whitespace. Single quotes keep their content as
+/// is, double quotes only treat `\"` and `\\` as escapes, and a backslash
+/// outside quotes escapes the next character. A `~` alone or followed by `/`
+/// at the start of a word is the home directory. Nothing else is expanded,
+/// no variables, globs or command substitutions.
+///
+/// ### Errors
+/// Fails on an unterminated quote or a trailing backslash.
+pub fn split(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(anyhow!("Unterminated ' in {}", line)),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                            word.extend(chars.next());
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err(anyhow!("Unterminated \" in {}", line)),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(anyhow!("Trailing \\ in {}", line)),
+            },
+            '~' if word.is_none()
+                && (matches!(chars.peek(), None | Some('/'))
+                    || chars.peek().is_some_and(|c| c.is_whitespace())) =>
+            {
+                let home = dirs::home_dir().context("Unable to determine the home directory")?;
+                word = Some(home.to_string_lossy().into_owned());
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Position of the subcommand in `args`, skipping the program name and the root options of `command`
+fn subcommand_position(args: &[OsString], command: &clap::Command) -> Option<usize> {
+    let takes_value = |arg: &clap::Arg| arg.get_action().takes_values();
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        let arg = arg.to_str()?;
+        if arg == "--" {
+            return None;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            // --name=value carries its value, --name value is followed by it
+            let value_follows = !long.contains('=')
+                && command
+                    .get_arguments()
+                    .any(|known| known.get_long() == Some(long) && takes_value(known));
+            index += if value_follows { 2 } else { 1 };
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            let mut shorts = shorts.chars();
+            let value_follows = shorts.next().is_some_and(|short| {
+                command
+                    .get_arguments()
+                    .any(|known| known.get_short() == Some(short) && takes_value(known))
+            }) && shorts.next().is_none();
+            index += if value_follows { 2 } else { 1 };
+        } else {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Replaces a user-defined alias in `args` by the arguments it stands for.
+///
+/// The alias is the word in place of the subcommand, e.g. `payroll` in
+/// `cli-tool --quiet payroll --dry-run` with `payroll = "send-batch
+/// ~/payroll.csv --profile prod"`, and the arguments after it are kept, so
+/// this runs `cli-tool --quiet send-batch ~/payroll.csv --profile prod
+/// --dry-run`. Aliases are split with [`split`], never by a shell, and may
+/// start with another alias. Commands and their built-in aliases always
+/// win, an alias named like one is never used. `load` is only called when
+/// the subcommand isn't a known one, so a broken config file doesn't get in
+/// the way of other commands.
+///
+/// ### Errors
+/// Fails if `load` does, on an alias that can't be split or is empty, or on aliases referring to each other in a loop.
+pub fn expand(
+    mut args: Vec<OsString>,
+    command: &clap::Command,
+    load: impl FnOnce() -> Result<HashMap<String, String>>,
+) -> Result<Vec<OsString>> {
+    let mut load = Some(load);
+    let mut aliases = HashMap::new();
+    let mut expanded = Vec::new();
+    loop {
+        // Unknown words are left for the parser to report
+        let Some(position) = subcommand_position(&args, command) else {
+            return Ok(args);
+        };
+        let Some(name) = args[position].to_str().map(str::to_owned) else {
+            return Ok(args);
+        };
+        if command.find_subcommand(&name).is_some() {
+            return Ok(args);
+        }
+        if let Some(load) = load.take() {
+            aliases = load()?;
+        }
+        let Some(line) = aliases.get(&name) else {
+            return Ok(args);
+        };
+
+        if expanded.contains(&name) {
+            return Err(anyhow!(
+                "Alias {} refers to itself through {}",
+                name,
+                expanded.join(" -> ")
+            ));
+        }
+        let words = split(line).with_context(|| format!("Invalid alias {}", name))?;
+        if words.is_empty() {
+            return Err(anyhow!("Alias {} is empty", name));
+        }
+        args.splice(position..=position, words.into_iter().map(OsString::from));
+        expanded.push(name);
+    }
+}
@@ -0,0 +1,587 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize totals in command output
+use serde::Serialize;
+
+/// Totals by denom
+use std::collections::BTreeMap;
+
+/// Locale set once at startup
+use std::sync::OnceLock;
+
+/// Bank protobuf types
+use cosmos::proto::cosmos::bank::v1beta1 as bank;
+
+/// Raw gRPC queries
+use crate::grpc;
+
+/// Display units known without querying the chain: chain id, display name,
+/// base denom and exponent
+const SNAPSHOT: &[(&str, &str, &str, u32)] = &[
+    ("osmosis-1", "osmo", "uosmo", 6),
+    ("osmosis-1", "ion", "uion", 6),
+    (
+        "osmosis-1",
+        "atom",
+        "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2",
+        6,
+    ),
+    (
+        "osmosis-1",
+        "usdc",
+        "ibc/498A0751C798A0D9A389AA3691123DADA57DAA4FE165D5C75894505B876BA6E4",
+        6,
+    ),
+    ("osmo-test-5", "osmo", "uosmo", 6),
+    ("osmo-test-5", "ion", "uion", 6),
+    ("cosmoshub-4", "atom", "uatom", 6),
+    ("juno-1", "juno", "ujuno", 6),
+];
+
+/// An amount as typed by the user, e.g. 1.5osmo or 110uosmo
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HumanAmount {
+    /// Decimal amount, e.g. 1.5
+    pub amount: String,
+    /// Denom as typed, either a base denom or a display unit
+    pub denom: String,
+}
+
+impl HumanAmount {
+    /// Parses an amount written in `locale`, e.g. 1.234,56osmo with [`NumberLocale::De`].
+    ///
+    /// ### Errors
+    /// Fails on a missing denom, or a number that isn't valid or is ambiguous in `locale`, see [`NumberLocale::normalize`].
+    pub fn parse_in(input: &str, locale: NumberLocale) -> Result<Self> {
+        let input = input.trim();
+        let split = input
+            .find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')
+            .ok_or_else(|| anyhow!("Missing denom in amount {}, e.g. 1.5osmo", input))?;
+        let (amount, denom) = input.split_at(split);
+        let amount = locale
+            .normalize(amount)
+            .with_context(|| format!("Invalid amount {}", input))?;
+        let amount = amount.as_str();
+
+        if amount.is_empty() || amount.starts_with('.') || amount.ends_with('.') {
+            return Err(anyhow!("Invalid amount {}, e.g. 1.5osmo", input));
+        }
+        if amount.matches('.').count() > 1 {
+            return Err(anyhow!(
+                "Invalid amount {}, more than one decimal point",
+                input
+            ));
+        }
+
+        Ok(HumanAmount {
+            amount: amount.to_owned(),
+            denom: denom.to_owned(),
+        })
+    }
+}
+
+impl std::str::FromStr for HumanAmount {
+    type Err = anyhow::Error;
+
+    /// Parses an amount in the locale of `--number-locale`, see [`number_locale`]
+    fn from_str(input: &str) -> Result<Self> {
+        HumanAmount::parse_in(input, number_locale())
+    }
+}
+
+impl std::fmt::Display for HumanAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.amount, self.denom)
+    }
+}
+
+/// Environment variable selecting the number locale, like `--number-locale`
+pub const NUMBER_LOCALE_ENV: &str = "CLI_TOOL_NUMBER_LOCALE";
+
+/// Locale of amounts typed by the user, set once at startup
+static NUMBER_LOCALE: OnceLock<NumberLocale> = OnceLock::new();
+
+/// How typed amounts separate thousands and decimals, see `--number-locale`
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum NumberLocale {
+    /// `.` before decimals and no thousands separators, e.g. 1234.56osmo
+    #[default]
+    Plain,
+    /// `,` between thousands and `.` before decimals, e.g. 1,234.56osmo
+    En,
+    /// `.` between thousands and `,` before decimals, e.g. 1.234,56osmo
+    De,
+}
+
+impl NumberLocale {
+    /// Thousands separator and decimal separator, `None` for plain numbers
+    fn separators(self) -> Option<(char, char)> {
+        match self {
+            NumberLocale::Plain => None,
+            NumberLocale::En => Some((',', '.')),
+            NumberLocale::De => Some(('.', ',')),
+        }
+    }
+
+    /// Rewrites a number written in this locale as a plain one, e.g. 1.234,56 as 1234.56 with `de`.
+    ///
+    /// Thousands separators must split the whole part in groups of three
+    /// digits. A number with a single separator followed by exactly three
+    /// digits, e.g. 1,234 or 1.234, is refused: it means a thousand times
+    /// more in one locale than in the other, and a mix-up would send the
+    /// wrong amount.
+    ///
+    /// ### Errors
+    /// Fails with the expected form if the number isn't valid in this locale, or is ambiguous.
+    pub fn normalize(self, number: &str) -> Result<String> {
+        let Some((thousands, decimal)) = self.separators() else {
+            if number.contains(',') {
+                return Err(anyhow!(
+                    "{} has a comma, amounts use . before decimals and no thousands separators. \
+                     Pass --number-locale en for 1,234.56 or de for 1.234,56",
+                    number
+                ));
+            }
+            return Ok(number.to_owned());
+        };
+
+        let separators = number.matches([thousands, decimal]).count();
+        if separators == 1 && !number.starts_with('0') {
+            if let Some((whole, fraction)) = number.split_once([thousands, decimal]) {
+                if (1..=3).contains(&whole.len()) && fraction.len() == 3 {
+                    return Err(anyhow!(
+                        "{} is ambiguous, it's {}{} or {}.{} depending on the locale. \
+                         Write it without the separator, or with fewer or more decimals",
+                        number,
+                        whole,
+                        fraction,
+                        whole,
+                        fraction
+                    ));
+                }
+            }
+        }
+
+        let (whole, fraction) = match number.split_once(decimal) {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (number, None),
+        };
+        if let Some(fraction) = fraction {
+            if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+                return Err(anyhow!(
+                    "{} has an invalid decimal part, only digits follow {}",
+                    number,
+                    decimal
+                ));
+            }
+        }
+        let groups: Vec<_> = whole.split(thousands).collect();
+        let valid = groups
+            .iter()
+            .all(|group| group.chars().all(|c| c.is_ascii_digit()))
+            && (1..=3).contains(&groups[0].len())
+            && !groups[0].starts_with('0')
+            && groups[1..].iter().all(|group| group.len() == 3);
+        if whole.is_empty() || (groups.len() > 1 && !valid) {
+            return Err(anyhow!(
+                "{} isn't a number in this locale, e.g. {}",
+                number,
+                match self {
+                    NumberLocale::De => "1.234,56",
+                    _ => "1,234.56",
+                }
+            ));
+        }
+        let whole = groups.concat();
+        Ok(match fraction {
+            Some(fraction) => format!("{}.{}", whole, fraction),
+            None => whole,
+        })
+    }
+
+    /// Locale given with `--number-locale` in raw arguments, or else in [`NUMBER_LOCALE_ENV`].
+    ///
+    /// Amounts are parsed along with the arguments, so the locale has to be
+    /// known before they are.
+    ///
+    /// ### Errors
+    /// Fails on an unknown locale.
+    pub fn from_args(args: &[std::ffi::OsString]) -> Result<NumberLocale> {
+        let mut given = None;
+        let mut args = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+            if let Some(value) = arg.strip_prefix("--number-locale=") {
+                given = Some(value.to_owned());
+            } else if arg == "--number-locale" {
+                given = args.next().map(|value| value.into_owned());
+            }
+        }
+        let Some(value) = given.or_else(|| std::env::var(NUMBER_LOCALE_ENV).ok()) else {
+            return Ok(NumberLocale::default());
+        };
+        <NumberLocale as clap::ValueEnum>::from_str(&value, true)
+            .map_err(|_| anyhow!("Unknown number locale {}, expected plain, en or de", value))
+    }
+}
+
+/// Sets the locale typed amounts are parsed in, only the first call has an effect
+pub fn set_number_locale(locale: NumberLocale) {
+    let _ = NUMBER_LOCALE.set(locale);
+}
+
+/// Locale typed amounts are parsed in, [`NumberLocale::Plain`] unless set with [`set_number_locale`]
+pub fn number_locale() -> NumberLocale {
+    NUMBER_LOCALE.get().copied().unwrap_or_default()
+}
+
+/// An amount of a single denom in base units, with checked arithmetic.
+///
+/// Sums of many coins, e.g. a batch or the past sends to a destination, go
+/// through this type so an overflow is an error instead of a wrapped or
+/// saturated total. See [`Totals`] to add up coins of several denoms.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct CoinAmount(pub u128);
+
+impl CoinAmount {
+    /// No funds
+    pub const ZERO: CoinAmount = CoinAmount(0);
+
+    /// Amount of a coin, refusing a coin of another denom than `denom`
+    pub fn of(coin: &cosmos::Coin, denom: &str) -> Result<Self> {
+        if coin.denom != denom {
+            return Err(anyhow!(
+                "Expected an amount of {}, got {}{}",
+                denom,
+                coin.amount,
+                coin.denom
+            ));
+        }
+        coin.amount
+            .parse()
+            .map(CoinAmount)
+            .with_context(|| format!("Invalid amount {} for denom {}", coin.amount, coin.denom))
+    }
+
+    /// `self + other`, failing on overflow
+    pub fn checked_add(self, other: CoinAmount) -> Result<Self> {
+        self.0
+            .checked_add(other.0)
+            .map(CoinAmount)
+            .ok_or_else(|| anyhow!("Amount overflow adding {} to {}", other, self))
+    }
+
+    /// `self - other`, failing if `other` is larger
+    pub fn checked_sub(self, other: CoinAmount) -> Result<Self> {
+        self.0
+            .checked_sub(other.0)
+            .map(CoinAmount)
+            .ok_or_else(|| anyhow!("Amount underflow subtracting {} from {}", other, self))
+    }
+
+    /// `self * factor`, failing on overflow
+    pub fn checked_mul(self, factor: u128) -> Result<Self> {
+        self.0
+            .checked_mul(factor)
+            .map(CoinAmount)
+            .ok_or_else(|| anyhow!("Amount overflow multiplying {} by {}", self, factor))
+    }
+
+    /// `percent`% of the amount, rounded down, without overflowing on large amounts
+    pub fn percent(self, percent: u128) -> Result<Self> {
+        let whole = CoinAmount(self.0 / 100).checked_mul(percent)?;
+        let part = CoinAmount(self.0 % 100).checked_mul(percent)?;
+        whole.checked_add(CoinAmount(part.0 / 100))
+    }
+
+    /// Coin of `denom` with this amount
+    pub fn coin(self, denom: &str) -> cosmos::Coin {
+        cosmos::Coin {
+            denom: denom.to_owned(),
+            amount: self.0.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CoinAmount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Totals of coins by denom, keeping every denom apart
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Totals(BTreeMap<String, CoinAmount>);
+
+impl Totals {
+    /// Adds a coin to the total of its denom.
+    ///
+    /// ### Errors
+    /// Fails if the amount is invalid or the total overflows, leaving the totals unchanged.
+    pub fn add(&mut self, coin: &cosmos::Coin) -> Result<()> {
+        let amount = CoinAmount::of(coin, &coin.denom)?;
+        let total = self
+            .get(&coin.denom)
+            .checked_add(amount)
+            .with_context(|| format!("Total of {} overflows", coin.denom))?;
+        self.0.insert(coin.denom.clone(), total);
+        Ok(())
+    }
+
+    /// Total of a denom, zero if none was added
+    pub fn get(&self, denom: &str) -> CoinAmount {
+        self.0.get(denom).copied().unwrap_or_default()
+    }
+
+    /// Whether nothing was added
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Totals by denom, sorted by denom
+    pub fn iter(&self) -> impl Iterator<Item = (&str, CoinAmount)> {
+        self.0
+            .iter()
+            .map(|(denom, amount)| (denom.as_str(), *amount))
+    }
+
+    /// Totals as coins, sorted by denom
+    pub fn coins(&self) -> Vec<cosmos::Coin> {
+        self.iter()
+            .map(|(denom, amount)| amount.coin(denom))
+            .collect()
+    }
+}
+
+/// Converts a decimal amount into base units, e.g. 1.5 with exponent 6 is 1500000.
+///
+/// ### Errors
+/// Fails if the amount has more decimal places than the exponent allows,
+/// since that can't be represented in base units, or if it overflows.
+pub fn to_base_units(amount: &str, exponent: u32) -> Result<u128> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if fraction.len() > exponent as usize {
+        return Err(anyhow!(
+            "Amount {} has more than {} decimal places",
+            amount,
+            exponent
+        ));
+    }
+
+    // Pad the fraction to the exponent, 1.5 with exponent 6 => "1" + "500000"
+    let digits = format!("{}{:0<width$}", whole, fraction, width = exponent as usize);
+    digits
+        .parse::<u128>()
+        .with_context(|| format!("Amount {} is too large", amount))
+}
+
+/// Converts an amount in base units into a decimal amount, e.g. 1500000 with exponent 6 is 1.5
+pub fn from_base_units(amount: u128, exponent: u32) -> String {
+    let digits = format!("{:0>width$}", amount, width = exponent as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - exponent as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_owned()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Display unit of a base denom in the embedded snapshot, e.g. osmo with exponent 6 for uosmo
+pub fn snapshot_unit(chain_id: &str, base: &str) -> Option<(&'static str, u32)> {
+    SNAPSHOT
+        .iter()
+        .find(|(chain, _, denom, _)| *chain == chain_id && *denom == base)
+        .map(|(_, display, _, exponent)| (*display, *exponent))
+}
+
+/// Display unit resolved for a denom
+struct Unit {
+    /// Base denom
+    base: String,
+    /// Exponent of the display unit relative to the base denom
+    exponent: u32,
+}
+
+/// Looks up a denom in the chain's bank metadata, by base, display, symbol or unit alias
+async fn from_metadata(cosmos: &cosmos::Cosmos, denom: &str) -> Result<Option<Unit>> {
+    let client = grpc::Client::connect(cosmos).await?;
+    let mut next_key = Vec::new();
+
+    loop {
+        let (metadatas, next) = metadata_page(&client, next_key).await?;
+        if let Some(unit) = metadatas
+            .iter()
+            .find_map(|metadata| find_unit(metadata, denom))
+        {
+            return Ok(Some(unit));
+        }
+        match next {
+            Some(next) => next_key = next,
+            None => return Ok(None),
+        }
+    }
+}
+
+/// One page of the chain's bank metadata, with the key of the next page if there's one
+async fn metadata_page(
+    client: &grpc::Client,
+    key: Vec<u8>,
+) -> Result<(Vec<bank::Metadata>, Option<Vec<u8>>)> {
+    let response: bank::QueryDenomsMetadataResponse = client
+        .query(
+            "/cosmos.bank.v1beta1.Query/DenomsMetadata",
+            bank::QueryDenomsMetadataRequest {
+                pagination: Some(cosmos::proto::cosmos::base::query::v1beta1::PageRequest {
+                    key,
+                    offset: 0,
+                    limit: 200,
+                    count_total: false,
+                    reverse: false,
+                }),
+            },
+        )
+        .await?;
+    let next = response
+        .pagination
+        .map(|page| page.next_key)
+        .filter(|key| !key.is_empty());
+    Ok((response.metadatas, next))
+}
+
+/// Unit of one denom's metadata that `denom` names, by base, display, symbol or unit alias
+fn find_unit(metadata: &bank::Metadata, denom: &str) -> Option<Unit> {
+    if metadata.base == denom {
+        return Some(Unit {
+            base: metadata.base.clone(),
+            exponent: 0,
+        });
+    }
+    let symbol_match = metadata.symbol.eq_ignore_ascii_case(denom);
+    metadata
+        .denom_units
+        .iter()
+        .find(|unit| {
+            unit.denom.eq_ignore_ascii_case(denom)
+                || unit
+                    .aliases
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(denom))
+                || (symbol_match && unit.denom == metadata.display)
+        })
+        .map(|unit| Unit {
+            base: metadata.base.clone(),
+            exponent: unit.exponent,
+        })
+}
+
+/// Looks up a display unit in the embedded snapshot
+fn from_snapshot(chain_id: &str, denom: &str) -> Option<Unit> {
+    SNAPSHOT
+        .iter()
+        .find(|(chain, display, _, _)| *chain == chain_id && display.eq_ignore_ascii_case(denom))
+        .map(|(_, _, base, exponent)| Unit {
+            base: (*base).to_owned(),
+            exponent: *exponent,
+        })
+}
+
+/// Resolves a human amount into a coin in base units.
+///
+/// The denom is looked up in the chain's bank denom metadata first, then in
+/// an embedded snapshot of common display units. An unknown denom is taken as
+/// a base denom, which only allows whole amounts.
+///
+/// ### Errors
+/// Fails if the amount can't be represented in base units.
+pub async fn resolve(cosmos: &cosmos::Cosmos, input: &HumanAmount) -> Result<cosmos::Coin> {
+    let chain_id = cosmos.get_cosmos_builder().chain_id().to_owned();
+
+    let unit = match from_metadata(cosmos, &input.denom).await {
+        Ok(Some(unit)) => Some(unit),
+        Ok(None) => from_snapshot(&chain_id, &input.denom),
+        Err(error) => {
+            tracing::warn!("Unable to query denom metadata: {:#}", error);
+            from_snapshot(&chain_id, &input.denom)
+        }
+    };
+    to_coin(input, unit)
+}
+
+/// Converts a human amount into base units of its resolved unit, or of the denom itself if unknown
+fn to_coin(input: &HumanAmount, unit: Option<Unit>) -> Result<cosmos::Coin> {
+    let unit = unit.unwrap_or_else(|| Unit {
+        base: input.denom.clone(),
+        exponent: 0,
+    });
+
+    let amount = to_base_units(&input.amount, unit.exponent)
+        .with_context(|| format!("Unable to convert {} to {}", input, unit.base))?;
+    if unit.exponent > 0 {
+        tracing::info!("Resolved {} to {}{}", input, amount, unit.base);
+    }
+
+    Ok(cosmos::Coin {
+        denom: unit.base,
+        amount: amount.to_string(),
+    })
+}
+
+/// Bank metadata of a chain, queried once to resolve the amounts of a whole batch.
+///
+/// Resolving an amount with [`resolve`] connects and pages through the
+/// metadata again, these resolve every row from the same query.
+pub struct Units {
+    /// Chain id, for the embedded snapshot
+    chain_id: String,
+    /// Metadata of every denom that has some, empty if it couldn't be queried
+    metadatas: Vec<bank::Metadata>,
+}
+
+impl Units {
+    /// Queries all of the chain's bank denom metadata with `client`.
+    ///
+    /// If it can't be queried, amounts are resolved with the embedded snapshot alone.
+    pub async fn query(client: &grpc::Client, chain_id: &str) -> Units {
+        let mut metadatas = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            match metadata_page(client, next_key).await {
+                Ok((page, next)) => {
+                    metadatas.extend(page);
+                    match next {
+                        Some(next) => next_key = next,
+                        None => break,
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!("Unable to query denom metadata: {:#}", error);
+                    metadatas.clear();
+                    break;
+                }
+            }
+        }
+        Units {
+            chain_id: chain_id.to_owned(),
+            metadatas,
+        }
+    }
+
+    /// Resolves a human amount like [`resolve`] does, without querying the chain again.
+    ///
+    /// ### Errors
+    /// Fails if the amount can't be represented in base units.
+    pub fn resolve(&self, input: &HumanAmount) -> Result<cosmos::Coin> {
+        let unit = self
+            .metadatas
+            .iter()
+            .find_map(|metadata| find_unit(metadata, &input.denom))
+            .or_else(|| from_snapshot(&self.chain_id, &input.denom));
+        to_coin(input, unit)
+    }
+}
@@ -0,0 +1,340 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Encode protobuf messages
+use prost::Message;
+
+/// Serialize signing requests
+use serde::{Deserialize, Serialize};
+
+/// Encode links and keys
+use base64::Engine;
+
+/// Check signatures before broadcasting
+use k256::ecdsa::signature::Verifier;
+
+/// Transaction protobuf types
+use cosmos::proto::cosmos::tx::v1beta1::{Fee, SignDoc, TxRaw};
+
+/// Deadlines of chain calls
+use crate::deadline::{self, Phase};
+/// Raw gRPC queries
+use crate::grpc;
+/// Addresses of public keys
+use crate::keys;
+/// Transaction building and fees
+use crate::signing::{self, SignMode};
+/// Transaction messages
+use crate::tx::Msg;
+
+/// Scheme of signing request links
+pub const LINK_SCHEME: &str = "cosmos-sign";
+
+/// Version of the signing request format
+pub const VERSION: u32 = 1;
+
+/// What a companion wallet is asked to sign, carried by the QR code and link.
+///
+/// The wallet signs `sign_doc` with SIGN_MODE_DIRECT and hands back the
+/// 64 byte compact signature. The desktop never sees the key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignRequest {
+    /// Format version, see [`VERSION`]
+    pub version: u32,
+    /// Chain id the signature is valid for
+    pub chain_id: String,
+    /// Address expected to sign
+    pub signer: String,
+    /// Account number of the signer
+    pub account_number: u64,
+    /// Sequence the transaction is signed with
+    pub sequence: u64,
+    /// What the transaction does, shown by the wallet before approval
+    pub description: String,
+    /// Protobuf encoded SignDoc, base64
+    pub sign_doc: String,
+}
+
+impl SignRequest {
+    /// Link carrying the request, e.g. cosmos-sign://sign?request=eyJ2...
+    pub fn link(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).context("Error serializing signing request")?;
+        Ok(format!(
+            "{}://sign?request={}",
+            LINK_SCHEME,
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+        ))
+    }
+
+    /// Parses a link made by [`SignRequest::link`].
+    ///
+    /// ### Errors
+    /// Fails if it isn't a signing request link or its version is unknown.
+    pub fn from_link(link: &str) -> Result<SignRequest> {
+        let encoded = link
+            .trim()
+            .strip_prefix(&format!("{}://sign?request=", LINK_SCHEME))
+            .ok_or_else(|| anyhow!("Not a {} signing request link", LINK_SCHEME))?;
+        let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(encoded)
+            .context("Invalid signing request link")?;
+        let request: SignRequest =
+            serde_json::from_slice(&json).context("Invalid signing request")?;
+        if request.version != VERSION {
+            return Err(anyhow!(
+                "Signing request version {} isn't supported, expected {}",
+                request.version,
+                VERSION
+            ));
+        }
+        Ok(request)
+    }
+
+    /// The link as a QR code drawn with Unicode blocks, for a terminal
+    pub fn qr(&self) -> Result<String> {
+        let code = qrcode::QrCode::new(self.link()?.as_bytes())
+            .context("The signing request is too large for a QR code")?;
+        Ok(code
+            .render::<qrcode::render::unicode::Dense1x2>()
+            .quiet_zone(true)
+            .build())
+    }
+}
+
+/// A transaction built for a remote signer, waiting for its signature
+#[derive(Clone, Debug)]
+pub struct Pending {
+    /// Request to show to the companion wallet
+    pub request: SignRequest,
+    /// Compressed secp256k1 public key of the signer
+    public_key: Vec<u8>,
+    /// Encoded transaction body
+    body_bytes: Vec<u8>,
+    /// Encoded auth info, with the fee and the signer's sequence
+    auth_info_bytes: Vec<u8>,
+    /// Bytes the signature must cover
+    sign_doc: Vec<u8>,
+}
+
+/// Account and key a remote wallet signs for
+pub struct RemoteSigner {
+    /// Address of the account
+    pub address: cosmos::Address,
+    /// Compressed secp256k1 public key
+    pub public_key: Vec<u8>,
+    /// Chain id the signature is valid for
+    pub chain_id: String,
+    /// Account number
+    pub account_number: u64,
+    /// Sequence to sign with
+    pub sequence: u64,
+}
+
+impl Pending {
+    /// Builds the transaction of `messages` for `signer`, paying `fee`, as SIGN_MODE_DIRECT
+    pub fn new(signer: &RemoteSigner, messages: &[Msg], fee: Fee, description: &str) -> Pending {
+        let body_bytes = signing::body(messages, "").encode_to_vec();
+        let auth_info_bytes = signing::auth_info(
+            signing::secp256k1_key_any(&signer.public_key),
+            SignMode::Direct,
+            signer.sequence,
+            fee,
+        )
+        .encode_to_vec();
+        let sign_doc = SignDoc {
+            body_bytes: body_bytes.clone(),
+            auth_info_bytes: auth_info_bytes.clone(),
+            chain_id: signer.chain_id.clone(),
+            account_number: signer.account_number,
+        }
+        .encode_to_vec();
+
+        Pending {
+            request: SignRequest {
+                version: VERSION,
+                chain_id: signer.chain_id.clone(),
+                signer: signer.address.to_string(),
+                account_number: signer.account_number,
+                sequence: signer.sequence,
+                description: description.to_owned(),
+                sign_doc: base64::engine::general_purpose::STANDARD.encode(&sign_doc),
+            },
+            public_key: signer.public_key.clone(),
+            body_bytes,
+            auth_info_bytes,
+            sign_doc,
+        }
+    }
+
+    /// Assembles the signed transaction once the signature checks out against the signer's key.
+    ///
+    /// ### Errors
+    /// Fails if the signature was made by another key or over other bytes,
+    /// e.g. an approval of an older request, so nothing invalid is broadcast.
+    pub fn complete(self, signature: &[u8]) -> Result<TxRaw> {
+        let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&self.public_key)
+            .context("Invalid public key of the signer")?;
+        let parsed = k256::ecdsa::Signature::from_slice(signature)
+            .context("Invalid signature, expected 64 bytes")?;
+        key.verify(&self.sign_doc, &parsed).map_err(|_| {
+            anyhow!(
+                "The signature isn't from {} over this request, it may approve another transaction",
+                self.request.signer
+            )
+        })?;
+        Ok(TxRaw {
+            body_bytes: self.body_bytes,
+            auth_info_bytes: self.auth_info_bytes,
+            signatures: vec![signature.to_vec()],
+        })
+    }
+}
+
+/// Public key a remote wallet signs for `address` with, `given` with `--pubkey` or `on_chain` in its account.
+///
+/// A given key must be the key of the address, and the same as the account's
+/// if it has one: the simulation and the signature check would otherwise be
+/// for another account.
+///
+/// ### Errors
+/// Fails if no key is known, or the given key doesn't belong to `address`.
+pub fn signer_public_key(
+    address: &str,
+    hrp: &str,
+    given: Option<Vec<u8>>,
+    on_chain: Option<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let key = match (given, on_chain) {
+        (Some(given), Some(on_chain)) if given != on_chain => {
+            return Err(anyhow!(
+                "The --pubkey given isn't the public key of {} on chain, {}",
+                address,
+                hex::encode(on_chain)
+            ))
+        }
+        (Some(key), _) | (None, Some(key)) => key,
+        (None, None) => {
+            return Err(anyhow!(
+                "{} never signed a transaction, so its public key isn't on chain. Pass it with --pubkey",
+                address
+            ))
+        }
+    };
+    let owner = keys::address_of(&key, hrp)?;
+    if owner != address {
+        return Err(anyhow!(
+            "Public key {} is the key of {}, not {}",
+            hex::encode(&key),
+            owner,
+            address
+        ));
+    }
+    Ok(key)
+}
+
+/// Queries the account of `address` and builds the transaction a remote wallet has to sign.
+///
+/// The public key comes from the account, set by its first transaction; an
+/// account that never signed needs it given as `public_key`, see
+/// [`signer_public_key`]. The gas is simulated, which doesn't need a signature.
+///
+/// ### Errors
+/// Fails if the account doesn't exist, its public key is unknown or doesn't
+/// match `public_key`, or the simulation fails.
+pub async fn prepare(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    address: cosmos::Address,
+    public_key: Option<Vec<u8>>,
+    messages: &[Msg],
+    fee_granter: Option<cosmos::Address>,
+    description: &str,
+) -> Result<Pending> {
+    let account = deadline::within(Phase::Query, "account", async {
+        Ok(cosmos.get_base_account(address).await?)
+    })
+    .await
+    .with_context(|| format!("Error getting account info for {}", address))?;
+    let on_chain = account
+        .pub_key
+        .map(|key| {
+            cosmos::proto::cosmos::crypto::secp256k1::PubKey::decode(key.value.as_slice())
+                .map(|key| key.key)
+                .with_context(|| format!("{} doesn't have a secp256k1 public key", address))
+        })
+        .transpose()?;
+    let public_key = signer_public_key(
+        &address.to_string(),
+        cosmos.get_address_hrp().as_str(),
+        public_key,
+        on_chain,
+    )?;
+
+    let gas_limit = signing::estimate_gas(
+        client,
+        signing::secp256k1_key_any(&public_key),
+        messages,
+        "",
+        account.sequence,
+    )
+    .await?;
+    let mut fee = signing::fee(cosmos, gas_limit);
+    if let Some(granter) = fee_granter {
+        fee.granter = granter.to_string();
+    }
+
+    let signer = RemoteSigner {
+        address,
+        public_key,
+        chain_id: cosmos.get_cosmos_builder().chain_id().to_owned(),
+        account_number: account.account_number,
+        sequence: account.sequence,
+    };
+    Ok(Pending::new(&signer, messages, fee, description))
+}
+
+/// Decodes bytes given as hex or base64
+fn decode(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim();
+    hex::decode(input)
+        .ok()
+        .or_else(|| base64::engine::general_purpose::STANDARD.decode(input).ok())
+        .or_else(|| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(input)
+                .ok()
+        })
+}
+
+/// Parses the signature returned by the wallet: hex, base64, or a `cosmos-sign://signature?value=...` link.
+///
+/// ### Errors
+/// Fails unless it decodes to a 64 byte compact signature.
+pub fn parse_signature(input: &str) -> Result<Vec<u8>> {
+    let input = input.trim();
+    let value = input
+        .strip_prefix(&format!("{}://signature?value=", LINK_SCHEME))
+        .unwrap_or(input);
+    match decode(value) {
+        Some(signature) if signature.len() == 64 => Ok(signature),
+        Some(signature) => Err(anyhow!(
+            "The signature has {} bytes, expected a 64 byte compact signature",
+            signature.len()
+        )),
+        None => Err(anyhow!("The signature isn't hex or base64")),
+    }
+}
+
+/// Parses a compressed secp256k1 public key given as hex or base64, e.g. from `keys show --pubkey --format hex`.
+///
+/// ### Errors
+/// Fails unless it decodes to 33 bytes.
+pub fn parse_public_key(input: &str) -> Result<Vec<u8>> {
+    match decode(input) {
+        Some(key) if key.len() == 33 => Ok(key),
+        _ => Err(anyhow!(
+            "Invalid public key {}, expected a 33 byte compressed key as hex or base64",
+            input
+        )),
+    }
+}
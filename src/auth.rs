@@ -0,0 +1,147 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Decode account types
+use prost::Message;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Account protobuf types
+use cosmos::proto::cosmos::auth::v1beta1 as auth;
+
+/// Raw gRPC queries
+use crate::grpc;
+
+/// Type url of plain accounts
+const BASE_ACCOUNT: &str = "/cosmos.auth.v1beta1.BaseAccount";
+
+/// Type url of module accounts
+const MODULE_ACCOUNT: &str = "/cosmos.auth.v1beta1.ModuleAccount";
+
+/// An account owned by a chain module, e.g. distribution or bonded_tokens_pool
+#[derive(Serialize, Clone, Debug)]
+pub struct ModuleAccount {
+    /// Module name
+    pub name: String,
+    /// Account address
+    pub address: String,
+    /// Module permissions, e.g. minter or burner
+    pub permissions: Vec<String>,
+}
+
+/// Queries every module account of the chain.
+///
+/// Chains without the query answer with an empty list, so callers don't
+/// block sends they can't check.
+pub async fn module_accounts(client: &grpc::Client) -> Result<Vec<ModuleAccount>> {
+    let response: Result<auth::QueryModuleAccountsResponse> = client
+        .query(
+            "/cosmos.auth.v1beta1.Query/ModuleAccounts",
+            auth::QueryModuleAccountsRequest {},
+        )
+        .await;
+    let accounts = match response {
+        Ok(response) => response.accounts,
+        Err(error) if grpc::is_not_found(&error) => {
+            tracing::debug!("Module accounts can't be queried: {:#}", error);
+            return Ok(Vec::new());
+        }
+        Err(error) => return Err(error.context("Error querying module accounts")),
+    };
+
+    accounts
+        .into_iter()
+        .filter(|account| account.type_url == MODULE_ACCOUNT)
+        .map(|account| {
+            let account = auth::ModuleAccount::decode(account.value.as_slice())
+                .context("Invalid module account")?;
+            Ok(ModuleAccount {
+                address: account
+                    .base_account
+                    .map(|base| base.address)
+                    .unwrap_or_default(),
+                name: account.name,
+                permissions: account.permissions,
+            })
+        })
+        .collect()
+}
+
+/// The module account at an address, `None` for any other address
+pub async fn module_account(
+    client: &grpc::Client,
+    address: cosmos::Address,
+) -> Result<Option<ModuleAccount>> {
+    let address = address.to_string();
+    Ok(module_accounts(client)
+        .await?
+        .into_iter()
+        .find(|account| account.address == address))
+}
+
+/// Whether an account exists at an address, which it does once it received funds or signed a transaction
+pub async fn account_exists(client: &grpc::Client, address: cosmos::Address) -> Result<bool> {
+    let response: Result<auth::QueryAccountResponse> = client
+        .query(
+            "/cosmos.auth.v1beta1.Query/Account",
+            auth::QueryAccountRequest {
+                address: address.to_string(),
+            },
+        )
+        .await;
+    match response {
+        Ok(_) => Ok(true),
+        Err(error) if grpc::is_not_found(&error) => Ok(false),
+        Err(error) => Err(error.context(format!("Error querying account {}", address))),
+    }
+}
+
+/// How much an account was used on chain, as far as its account tells
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    /// No account, it never received funds or signed a transaction
+    Unused,
+    /// Received funds but never signed a transaction, so nobody has used its key yet
+    ReceivedOnly,
+    /// Signed transactions, or isn't a plain account, e.g. a vesting or module account
+    Active,
+}
+
+impl Activity {
+    /// Activity of an account as returned by the auth module, `None` if it doesn't exist
+    pub fn of(account: Option<&prost_types::Any>) -> Result<Activity> {
+        let Some(account) = account else {
+            return Ok(Activity::Unused);
+        };
+        if account.type_url != BASE_ACCOUNT {
+            return Ok(Activity::Active);
+        }
+        // The sequence counts signed transactions, the public key is set by the first one
+        let account =
+            auth::BaseAccount::decode(account.value.as_slice()).context("Invalid base account")?;
+        Ok(if account.sequence == 0 && account.pub_key.is_none() {
+            Activity::ReceivedOnly
+        } else {
+            Activity::Active
+        })
+    }
+}
+
+/// Queries how much the account at an address was used, see [`Activity`]
+pub async fn activity(client: &grpc::Client, address: cosmos::Address) -> Result<Activity> {
+    let response: Result<auth::QueryAccountResponse> = client
+        .query(
+            "/cosmos.auth.v1beta1.Query/Account",
+            auth::QueryAccountRequest {
+                address: address.to_string(),
+            },
+        )
+        .await;
+    match response {
+        Ok(response) => Activity::of(response.account.as_ref()),
+        Err(error) if grpc::is_not_found(&error) => Ok(Activity::Unused),
+        Err(error) => Err(error.context(format!("Error querying account {}", address))),
+    }
+}
@@ -0,0 +1,267 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Decode authorizations
+use prost::Message;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Authz protobuf types
+use cosmos::proto::cosmos::authz::v1beta1 as authz;
+use cosmos::proto::cosmos::bank::v1beta1::SendAuthorization;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Transaction messages
+use crate::tx::{self, Msg};
+
+/// Type url of the bank send authorization
+const SEND_AUTHORIZATION: &str = "/cosmos.bank.v1beta1.SendAuthorization";
+
+/// Type url of the generic authorization
+const GENERIC_AUTHORIZATION: &str = "/cosmos.authz.v1beta1.GenericAuthorization";
+
+/// Message type authorized by a send authorization
+pub const MSG_SEND: &str = "/cosmos.bank.v1beta1.MsgSend";
+
+/// Permission given to a grantee
+#[derive(Clone, Debug)]
+pub enum Authorization {
+    /// Bank sends up to a spend limit, optionally only to some addresses
+    Send {
+        /// Maximum the grantee may send in total
+        spend_limit: Vec<cosmos::Coin>,
+        /// Only these destinations are allowed, any if empty
+        allow_list: Vec<cosmos::Address>,
+    },
+    /// Any message of one type, without limits
+    Generic {
+        /// Message type url, e.g. /cosmos.gov.v1.MsgVote
+        msg_type: String,
+    },
+}
+
+impl Authorization {
+    /// Message type url the authorization covers, needed to revoke it
+    pub fn msg_type(&self) -> &str {
+        match self {
+            Authorization::Send { .. } => MSG_SEND,
+            Authorization::Generic { msg_type } => msg_type,
+        }
+    }
+
+    /// Protobuf Any and amino JSON of the authorization
+    fn encode(&self) -> (prost_types::Any, serde_json::Value) {
+        match self {
+            Authorization::Send {
+                spend_limit,
+                allow_list,
+            } => {
+                let allow_list: Vec<String> = allow_list.iter().map(ToString::to_string).collect();
+                let mut amino = serde_json::json!({ "spend_limit": tx::amino_coins(spend_limit) });
+                if !allow_list.is_empty() {
+                    amino["allow_list"] = allow_list.clone().into();
+                }
+                let value = SendAuthorization {
+                    spend_limit: spend_limit.clone(),
+                    allow_list,
+                };
+                (
+                    prost_types::Any {
+                        type_url: SEND_AUTHORIZATION.to_owned(),
+                        value: value.encode_to_vec(),
+                    },
+                    serde_json::json!({ "type": "cosmos-sdk/SendAuthorization", "value": amino }),
+                )
+            }
+            Authorization::Generic { msg_type } => (
+                prost_types::Any {
+                    type_url: GENERIC_AUTHORIZATION.to_owned(),
+                    value: authz::GenericAuthorization {
+                        msg: msg_type.clone(),
+                    }
+                    .encode_to_vec(),
+                },
+                serde_json::json!({
+                    "type": "cosmos-sdk/GenericAuthorization",
+                    "value": { "msg": msg_type },
+                }),
+            ),
+        }
+    }
+}
+
+/// Expands the `send` shorthand into the MsgSend type url
+pub fn msg_type_url(msg_type: &str) -> String {
+    match msg_type {
+        "send" => MSG_SEND.to_owned(),
+        msg_type => msg_type.to_owned(),
+    }
+}
+
+/// Grants `grantee` permission to act on behalf of `granter`
+pub fn grant(
+    granter: cosmos::Address,
+    grantee: cosmos::Address,
+    authorization: &Authorization,
+    expiration: Option<chrono::DateTime<chrono::Utc>>,
+) -> Msg {
+    let (any, amino_authorization) = authorization.encode();
+
+    let mut amino_grant = serde_json::json!({ "authorization": amino_authorization });
+    if let Some(expiration) = expiration {
+        amino_grant["expiration"] = tx::amino_time(expiration);
+    }
+    let amino = serde_json::json!({
+        "grant": amino_grant,
+        "grantee": grantee.to_string(),
+        "granter": granter.to_string(),
+    });
+
+    tx::message(
+        "/cosmos.authz.v1beta1.MsgGrant",
+        &authz::MsgGrant {
+            granter: granter.to_string(),
+            grantee: grantee.to_string(),
+            grant: Some(authz::Grant {
+                authorization: Some(any),
+                expiration: expiration.map(tx::timestamp),
+            }),
+        },
+        format!(
+            "Grant {} from {} to {}",
+            authorization.msg_type(),
+            granter,
+            grantee
+        ),
+    )
+    .with_amino("cosmos-sdk/MsgGrant", amino)
+}
+
+/// Revokes the permission of `grantee` to send messages of `msg_type` for `granter`
+pub fn revoke(granter: cosmos::Address, grantee: cosmos::Address, msg_type: &str) -> Msg {
+    let amino = serde_json::json!({
+        "grantee": grantee.to_string(),
+        "granter": granter.to_string(),
+        "msg_type_url": msg_type,
+    });
+    tx::message(
+        "/cosmos.authz.v1beta1.MsgRevoke",
+        &authz::MsgRevoke {
+            granter: granter.to_string(),
+            grantee: grantee.to_string(),
+            msg_type_url: msg_type.to_owned(),
+        },
+        format!("Revoke {} from {} to {}", msg_type, granter, grantee),
+    )
+    .with_amino("cosmos-sdk/MsgRevoke", amino)
+}
+
+/// Executes messages signed by `grantee` on behalf of their granters.
+///
+/// The amino form is only attached when every inner message has one.
+pub fn exec(grantee: cosmos::Address, messages: Vec<Msg>) -> Msg {
+    let description = format!(
+        "Exec as {}: {}",
+        grantee,
+        messages
+            .iter()
+            .map(|msg| msg.description.as_str())
+            .collect::<Vec<_>>()
+            .join("; ")
+    );
+    let amino_msgs: Option<Vec<_>> = messages.iter().map(|msg| msg.amino.clone()).collect();
+
+    let msg = tx::message(
+        "/cosmos.authz.v1beta1.MsgExec",
+        &authz::MsgExec {
+            grantee: grantee.to_string(),
+            msgs: messages.iter().map(Msg::to_any).collect(),
+        },
+        description,
+    );
+    match amino_msgs {
+        Some(msgs) => msg.with_amino(
+            "cosmos-sdk/MsgExec",
+            serde_json::json!({ "grantee": grantee.to_string(), "msgs": msgs }),
+        ),
+        None => msg,
+    }
+}
+
+/// An authz grant between two accounts
+#[derive(Serialize, Clone, Debug)]
+pub struct GrantInfo {
+    /// Account giving the permission
+    pub granter: String,
+    /// Account receiving the permission
+    pub grantee: String,
+    /// Message type url covered
+    pub msg_type: String,
+    /// Spend limit of a send authorization
+    pub spend_limit: Option<String>,
+    /// When the grant expires, RFC 3339
+    pub expiration: Option<String>,
+}
+
+/// Converts a protobuf grant into a [`GrantInfo`]
+fn grant_info(grant: authz::GrantAuthorization) -> GrantInfo {
+    let any = grant.authorization.unwrap_or_default();
+    let (msg_type, spend_limit) = match any.type_url.as_str() {
+        SEND_AUTHORIZATION => (
+            MSG_SEND.to_owned(),
+            SendAuthorization::decode(any.value.as_slice())
+                .ok()
+                .map(|send| tx::format_coins(&send.spend_limit)),
+        ),
+        GENERIC_AUTHORIZATION => (
+            authz::GenericAuthorization::decode(any.value.as_slice())
+                .map(|generic| generic.msg)
+                .unwrap_or_default(),
+            None,
+        ),
+        other => (other.to_owned(), None),
+    };
+    GrantInfo {
+        granter: grant.granter,
+        grantee: grant.grantee,
+        msg_type,
+        spend_limit,
+        expiration: grant
+            .expiration
+            .and_then(|time| chrono::DateTime::from_timestamp(time.seconds, time.nanos as u32))
+            .map(|time| time.to_rfc3339()),
+    }
+}
+
+/// Grants given and received by an address
+pub async fn list(client: &grpc::Client, address: cosmos::Address) -> Result<Vec<GrantInfo>> {
+    let given: authz::QueryGranterGrantsResponse = client
+        .query(
+            "/cosmos.authz.v1beta1.Query/GranterGrants",
+            authz::QueryGranterGrantsRequest {
+                granter: address.to_string(),
+                pagination: None,
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying grants given by {}", address))?;
+    let received: authz::QueryGranteeGrantsResponse = client
+        .query(
+            "/cosmos.authz.v1beta1.Query/GranteeGrants",
+            authz::QueryGranteeGrantsRequest {
+                grantee: address.to_string(),
+                pagination: None,
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying grants received by {}", address))?;
+
+    Ok(given
+        .grants
+        .into_iter()
+        .chain(received.grants)
+        .map(grant_info)
+        .collect())
+}
@@ -0,0 +1,188 @@
+/// Error handling
+use anyhow::Result;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Per-denom comparison
+use std::collections::BTreeMap;
+
+/// Bounded concurrent queries
+use futures::stream::{self, StreamExt};
+
+/// Checked per-denom totals
+use crate::amount::Totals;
+/// Chain operations
+use crate::chain::ChainClient;
+/// Parse coin amounts
+use crate::policy::parse_amount;
+
+/// Difference between the balances of two addresses for one denom
+#[derive(Serialize)]
+pub struct DenomDiff {
+    /// Denom being compared
+    pub denom: String,
+    /// Balance of the first address
+    pub a: u128,
+    /// Balance of the second address
+    pub b: u128,
+    /// `b - a`, negative when the first address holds more
+    pub difference: String,
+}
+
+/// Compares two sets of balances denom by denom.
+///
+/// Denoms held by only one of the addresses are compared against zero.
+/// Results are sorted by denom.
+pub fn diff(a: &[cosmos::Coin], b: &[cosmos::Coin]) -> Result<Vec<DenomDiff>> {
+    // Both balances for every denom
+    let mut denoms: BTreeMap<&str, (u128, u128)> = BTreeMap::new();
+    for coin in a {
+        denoms.entry(&coin.denom).or_default().0 = parse_amount(coin)?;
+    }
+    for coin in b {
+        denoms.entry(&coin.denom).or_default().1 = parse_amount(coin)?;
+    }
+
+    Ok(denoms
+        .into_iter()
+        .map(|(denom, (a, b))| {
+            let difference = if b >= a {
+                (b - a).to_string()
+            } else {
+                format!("-{}", a - b)
+            };
+            DenomDiff {
+                denom: denom.to_owned(),
+                a,
+                b,
+                difference,
+            }
+        })
+        .collect())
+}
+
+/// Balance of one denom held by an address
+#[derive(Serialize)]
+pub struct Balance {
+    /// Denom held
+    pub denom: String,
+    /// Amount in base units
+    pub amount: u128,
+}
+
+/// Balances of one scanned address
+#[derive(Serialize)]
+pub struct AddressBalances {
+    /// Address queried
+    pub address: String,
+    /// Balances held, empty when the query failed
+    pub balances: Vec<Balance>,
+    /// Error message when the query failed
+    pub error: Option<String>,
+}
+
+/// Result of scanning many addresses
+#[derive(Serialize)]
+pub struct Scan {
+    /// Every address, in input order
+    pub addresses: Vec<AddressBalances>,
+    /// Sum of every denom over the addresses that were queried successfully
+    pub totals: Totals,
+}
+
+/// Parses a list of addresses, one per line.
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn parse_addresses(input: &str) -> Result<Vec<cosmos::Address>> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .map_err(|error| anyhow::anyhow!("Invalid address {}: {}", line, error))
+        })
+        .collect()
+}
+
+/// Queries the balances of many addresses with at most `concurrency` queries in flight.
+///
+/// A failed query doesn't stop the scan, it is reported on the address and
+/// left out of the totals.
+///
+/// ### Errors
+/// Fails if a total overflows.
+pub async fn scan(
+    chain: &impl ChainClient,
+    addresses: Vec<cosmos::Address>,
+    concurrency: usize,
+) -> Result<Scan> {
+    // Keep the input order so the output lines up with the file
+    let addresses: Vec<AddressBalances> = stream::iter(addresses)
+        .map(|address| async move {
+            let result = chain.all_balances(address).await.and_then(|coins| {
+                coins
+                    .iter()
+                    .map(|coin| {
+                        Ok(Balance {
+                            denom: coin.denom.clone(),
+                            amount: parse_amount(coin)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            });
+            match result {
+                Ok(balances) => AddressBalances {
+                    address: address.to_string(),
+                    balances,
+                    error: None,
+                },
+                Err(error) => {
+                    tracing::warn!("Failed to retrieve balances for {}: {:#}", address, error);
+                    AddressBalances {
+                        address: address.to_string(),
+                        balances: Vec::new(),
+                        error: Some(format!("{:#}", error)),
+                    }
+                }
+            }
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    // Per-denom totals
+    let mut totals = Totals::default();
+    for balance in addresses.iter().flat_map(|address| &address.balances) {
+        totals.add(&cosmos::Coin {
+            denom: balance.denom.clone(),
+            amount: balance.amount.to_string(),
+        })?;
+    }
+
+    Ok(Scan { addresses, totals })
+}
+
+/// Formats a scan as CSV with `address,denom,amount,error` rows.
+///
+/// Totals follow as rows with the address `total`, failed queries as rows with
+/// an empty denom and the error in a fourth column.
+pub fn to_csv(scan: &Scan) -> String {
+    let mut csv = String::from("address,denom,amount,error\n");
+    for address in &scan.addresses {
+        if let Some(error) = &address.error {
+            csv += &format!("{},,,\"{}\"\n", address.address, error.replace('"', "\"\""));
+        }
+        for balance in &address.balances {
+            csv += &format!(
+                "{},{},{},\n",
+                address.address, balance.denom, balance.amount
+            );
+        }
+    }
+    for (denom, amount) in scan.totals.iter() {
+        csv += &format!("total,{},{},\n", denom, amount);
+    }
+    csv
+}
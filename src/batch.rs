@@ -0,0 +1,484 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Run sends concurrently
+use futures::StreamExt;
+
+/// Lines in flight and lines sent
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Checkpoint files
+use std::path::{Path, PathBuf};
+
+/// History and progress shared by concurrent sends
+use std::sync::Mutex;
+
+/// Serialize command output and checkpoints
+use serde::{Deserialize, Serialize};
+
+/// Checkpoint names
+use sha2::{Digest, Sha256};
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Parse and add up amounts
+use crate::amount::{CoinAmount, HumanAmount, Totals};
+/// Chain operations
+use crate::chain::ChainClient;
+/// CW20 token contracts
+use crate::cw20;
+/// Broadcasts without a known result
+use crate::error::Unconfirmed;
+/// Local transaction log
+use crate::journal;
+/// Spend policies
+use crate::policy::{self, PolicyConfig};
+/// Sending funds
+use crate::send::{SendRequest, TxOutcome};
+/// Stop starting sends when cancelled
+use crate::tasks::CancellationToken;
+/// Transaction building and broadcasting
+use crate::tx;
+/// Address checks
+use crate::validate;
+
+/// Directory of batch checkpoints inside the data directory
+const CHECKPOINT_DIR: &str = "batches";
+
+/// Error of the lines a cancelled batch didn't start
+pub const NOT_SENT: &str = "Not sent, the batch was interrupted";
+
+/// A line of a batch file
+#[derive(Clone, Debug)]
+pub struct Row {
+    /// Line number in the file, from 1
+    pub line: usize,
+    /// Address receiving the funds
+    pub destination: cosmos::Address,
+    /// Amount as written, e.g. 1.5osmo
+    pub amount: HumanAmount,
+}
+
+/// Result of one send of a batch
+#[derive(Serialize, Clone, Debug)]
+pub struct Outcome {
+    /// Line number in the file
+    pub line: usize,
+    /// Address receiving the funds
+    pub destination: String,
+    /// Amount in base units
+    pub amount: String,
+    /// Txhash, missing if nothing was broadcast
+    pub txhash: Option<String>,
+    /// Response code, 0 on success
+    pub code: Option<u32>,
+    /// Why the send failed, if it did
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    /// Whether the funds were sent
+    pub fn succeeded(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Where a running batch is, for status reports
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Status {
+    /// When the batch started
+    pub started: Option<chrono::DateTime<chrono::Utc>>,
+    /// Sends in the batch
+    pub total: usize,
+    /// Sends that succeeded
+    pub succeeded: usize,
+    /// Sends that failed
+    pub failed: usize,
+    /// Lines being sent right now
+    pub in_flight: BTreeSet<usize>,
+    /// Totals by denom of the sends that succeeded so far
+    pub sent: Totals,
+    /// Why the last failed send failed
+    pub last_error: Option<String>,
+}
+
+/// A line of a [`Checkpoint`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Sent {
+    /// Txhash of the broadcast transaction
+    pub txhash: String,
+    /// Response code, missing if the result isn't known, e.g. the wait for inclusion failed
+    pub code: Option<u32>,
+}
+
+/// The lines of a batch file broadcast so far, kept next to the journal so an interrupted batch can be resumed
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Broadcast transaction of each line, by line number
+    pub lines: BTreeMap<usize, Sent>,
+}
+
+impl Checkpoint {
+    /// Where the checkpoint of a batch file is kept, named after a hash of its content
+    pub fn path(input: &str) -> Result<PathBuf> {
+        let name = hex::encode(&Sha256::digest(input.as_bytes())[..16]);
+        Ok(journal::data_dir()?
+            .join(CHECKPOINT_DIR)
+            .join(format!("{}.json", name)))
+    }
+
+    /// Loads a checkpoint, `None` if there's none
+    pub fn load(path: &Path) -> Result<Option<Checkpoint>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading batch checkpoint {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid batch checkpoint {}", path.display()))
+            .map(Some)
+    }
+
+    /// Writes the checkpoint to a temporary file renamed over `path`, so it's never half written
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Error creating {}", dir.display()))?;
+        }
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Error writing batch checkpoint {}", partial.display()))?;
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("Error writing batch checkpoint {}", path.display()))
+    }
+
+    /// Records the transaction of an outcome, if anything was broadcast
+    pub fn record(&mut self, outcome: &Outcome) {
+        if let Some(txhash) = &outcome.txhash {
+            self.lines.insert(
+                outcome.line,
+                Sent {
+                    txhash: txhash.clone(),
+                    code: outcome.code,
+                },
+            );
+        }
+    }
+
+    /// Lines that were sent successfully
+    pub fn completed(&self) -> BTreeSet<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, sent)| sent.code == Some(0))
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    /// Lines broadcast without a known result, with their txhash
+    pub fn unconfirmed(&self) -> Vec<(usize, String)> {
+        self.lines
+            .iter()
+            .filter(|(_, sent)| sent.code.is_none())
+            .map(|(line, sent)| (*line, sent.txhash.clone()))
+            .collect()
+    }
+}
+
+/// [`Status`] updated by the sends of a batch and read by status reports.
+///
+/// With a checkpoint, every send that finishes is also recorded in the
+/// checkpoint file right away, so an interrupted or killed batch knows what
+/// it sent.
+#[derive(Default)]
+pub struct Progress {
+    /// Where the batch is
+    status: Mutex<Status>,
+    /// Checkpoint and its file, if kept
+    checkpoint: Option<(PathBuf, Mutex<Checkpoint>)>,
+}
+
+impl Progress {
+    /// Progress recording finished sends in `checkpoint`, saved to `path`
+    pub fn with_checkpoint(path: PathBuf, checkpoint: Checkpoint) -> Progress {
+        Progress {
+            status: Mutex::default(),
+            checkpoint: Some((path, Mutex::new(checkpoint))),
+        }
+    }
+
+    /// Current status
+    pub fn snapshot(&self) -> Status {
+        self.lock().clone()
+    }
+
+    /// Current checkpoint, `None` if none is kept
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        self.checkpoint.as_ref().map(|(_, checkpoint)| {
+            checkpoint
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone()
+        })
+    }
+
+    /// Locks the status, a panicked send doesn't stop the others from reporting
+    fn lock(&self) -> std::sync::MutexGuard<'_, Status> {
+        self.status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records a finished send in the checkpoint, a checkpoint that can't be saved doesn't stop the batch
+    fn record(&self, outcome: &Outcome) {
+        let Some((path, checkpoint)) = &self.checkpoint else {
+            return;
+        };
+        let mut checkpoint = checkpoint
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        checkpoint.record(outcome);
+        if let Err(error) = checkpoint.save(path) {
+            tracing::warn!("Line {}: {:#}", outcome.line, error);
+        }
+    }
+}
+
+/// Parses a batch file of `address,amount` lines.
+///
+/// Blank lines, `#` comments and an `address,amount` header are skipped.
+///
+/// ### Errors
+/// Fails on the first malformed line, naming it, so nothing is sent from a bad file.
+pub fn parse(input: &str) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.eq_ignore_ascii_case("address,amount") {
+            continue;
+        }
+        let (destination, amount) = line.split_once(',').ok_or_else(|| {
+            anyhow!(
+                "Line {}: expected address,amount, got {}",
+                line_number,
+                line
+            )
+        })?;
+        rows.push(Row {
+            line: line_number,
+            destination: validate::parse_address(destination)
+                .with_context(|| format!("Line {}", line_number))?,
+            amount: amount
+                .trim()
+                .parse()
+                .with_context(|| format!("Line {}: invalid amount", line_number))?,
+        });
+    }
+    if rows.is_empty() {
+        return Err(anyhow!("No sends found in the batch file"));
+    }
+    Ok(rows)
+}
+
+/// Adds up what a batch sends by denom and checks the sender holds it.
+///
+/// Fees are paid on top of the totals, so a wallet holding exactly the
+/// totals in the fee denom still runs short.
+///
+/// ### Returns
+/// The totals by denom, for the report.
+///
+/// ### Errors
+/// Fails if a total overflows, or if the wallet holds less than the batch sends of a denom.
+pub async fn plan(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    requests: &[(usize, SendRequest)],
+) -> Result<Totals> {
+    let mut totals = Totals::default();
+    for (line, request) in requests {
+        totals
+            .add(&request.coin)
+            .with_context(|| format!("Line {}", line))?;
+    }
+
+    let held = chain.all_balances(wallet.get_address()).await?;
+    for (denom, needed) in totals.iter() {
+        let available = match held.iter().find(|coin| coin.denom == denom) {
+            Some(coin) => CoinAmount::of(coin, denom)?,
+            None => CoinAmount::ZERO,
+        };
+        if available < needed {
+            return Err(anyhow!(
+                "The batch sends {}{} but {} only holds {}{}",
+                needed,
+                denom,
+                wallet,
+                available,
+                denom
+            ));
+        }
+    }
+    Ok(totals)
+}
+
+/// Totals by denom of the sends that succeeded
+pub fn sent(outcomes: &[Outcome]) -> Result<Totals> {
+    let mut totals = Totals::default();
+    for outcome in outcomes.iter().filter(|outcome| outcome.succeeded()) {
+        totals.add(&policy::parse_coin(&outcome.amount)?)?;
+    }
+    Ok(totals)
+}
+
+/// Sends one request of a batch, checking it against the shared history.
+///
+/// The amount counts towards the destination's limit as soon as the check
+/// passes, so concurrent sends to the same destination can't both slip under it.
+async fn send(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    request: &SendRequest,
+    policy: &PolicyConfig,
+    history: &Mutex<Vec<journal::Entry>>,
+) -> Result<TxOutcome> {
+    let destination = request.destination.to_string();
+    let amount = tx::format_coins(std::slice::from_ref(&request.coin));
+
+    let index = {
+        let mut history = history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        policy::check_send(
+            policy,
+            &history,
+            &chain.chain_id(),
+            &wallet.to_string(),
+            &destination,
+            &request.coin,
+        )?;
+        history.push(journal::Entry {
+            timestamp: chrono::Utc::now(),
+            chain: chain.chain_id(),
+            from: wallet.to_string(),
+            to: destination.clone(),
+            amount: amount.clone(),
+            fee: None,
+            txhash: None,
+            code: Some(0),
+            error: None,
+        });
+        history.len() - 1
+    };
+
+    let message = match request.cw20 {
+        Some(contract) => {
+            let tokens = policy::parse_amount(&request.coin)?;
+            cw20::transfer(wallet.get_address(), contract, request.destination, tokens)?
+        }
+        None => tx::msg_send(
+            wallet.get_address(),
+            request.destination,
+            vec![request.coin.clone()],
+        ),
+    };
+    let result = tx::broadcast(chain, wallet, vec![message], &destination, &amount).await;
+
+    // Only what arrived counts towards the limit
+    let code = result.as_ref().ok().map(|response| response.code);
+    if code != Some(0) {
+        history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())[index]
+            .code = code;
+    }
+    Ok(result?.into())
+}
+
+/// Sends every request, `concurrency` at a time, one transaction each.
+///
+/// Unlike a loop over [`crate::send::execute_transaction`], the journal is read once
+/// for the whole batch and the destinations' balances aren't queried. With a
+/// sequence cache on the chain, see [`crate::chain::Chain::with_sequence_cache`],
+/// concurrent sends are signed with consecutive sequences without querying
+/// or simulating each one, and their waits for inclusion overlap.
+///
+/// `progress` is kept up to date as sends start and finish. Once `cancel`
+/// is cancelled no further send starts, the ones in flight are finished.
+///
+/// ### Returns
+/// An outcome per request, in input order. A failed send doesn't stop the
+/// batch, a send that wasn't started has the error [`NOT_SENT`].
+///
+/// ### Errors
+/// Fails before sending anything if the journal can't be read.
+pub async fn run(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    requests: Vec<(usize, SendRequest)>,
+    policy: &PolicyConfig,
+    concurrency: usize,
+    progress: &Progress,
+    cancel: &CancellationToken,
+) -> Result<Vec<Outcome>> {
+    let history = Mutex::new(journal::read_all()?);
+    let history = &history;
+    {
+        let mut status = progress.lock();
+        status.started = Some(chrono::Utc::now());
+        status.total = requests.len();
+    }
+
+    Ok(futures::stream::iter(requests)
+        .map(|(line, request)| async move {
+            let mut outcome = Outcome {
+                line,
+                destination: request.destination.to_string(),
+                amount: format!("{}{}", request.coin.amount, request.coin.denom),
+                txhash: None,
+                code: None,
+                error: None,
+            };
+            if cancel.is_cancelled() {
+                outcome.error = Some(NOT_SENT.to_owned());
+                return outcome;
+            }
+
+            progress.lock().in_flight.insert(line);
+            match send(chain, wallet, &request, policy, history).await {
+                Ok(response) => {
+                    if let Err(failed) = response.check() {
+                        outcome.error = Some(failed.to_string());
+                    }
+                    outcome.txhash = Some(response.txhash);
+                    outcome.code = Some(response.code);
+                }
+                Err(error) => {
+                    // A broadcast that wasn't confirmed may still be included
+                    outcome.txhash = error
+                        .downcast_ref::<Unconfirmed>()
+                        .map(|unconfirmed| unconfirmed.txhash.clone());
+                    outcome.error = Some(format!("{:#}", error));
+                }
+            }
+            progress.record(&outcome);
+
+            let mut status = progress.lock();
+            status.in_flight.remove(&line);
+            if outcome.succeeded() {
+                status.succeeded += 1;
+                if let Err(error) = status.sent.add(&request.coin) {
+                    tracing::warn!("Line {}: {:#}", line, error);
+                }
+            } else {
+                status.failed += 1;
+                status.last_error.clone_from(&outcome.error);
+            }
+            drop(status);
+            outcome
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await)
+}
@@ -0,0 +1,129 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Futures returned by chain operations
+use std::future::Future;
+
+/// Client shared by clones
+use std::sync::Arc;
+
+/// Deadlines of chain calls
+use crate::deadline::{self, Phase};
+/// Raw gRPC queries
+use crate::grpc;
+
+/// Custom signing modes
+use crate::signing::{self, SequenceCache, SignMode};
+/// Transaction messages
+use crate::tx::{Msg, TxResponse};
+
+/// Chain operations used by the core logic.
+///
+/// Implemented by [`Chain`] for a live connection; tests and other programs
+/// can provide their own implementation to run the same logic without a node.
+pub trait ChainClient: Sync {
+    /// Chain id, e.g. osmo-test-5
+    fn chain_id(&self) -> String;
+
+    /// All bank balances of an address
+    fn all_balances(
+        &self,
+        address: cosmos::Address,
+    ) -> impl Future<Output = Result<Vec<cosmos::Coin>>> + Send;
+
+    /// Signs and broadcasts a transaction, waiting for it to be included.
+    ///
+    /// A transaction rejected with a non-zero code is returned as `Ok`.
+    fn sign_and_broadcast(
+        &self,
+        wallet: &cosmos::Wallet,
+        messages: &[Msg],
+    ) -> impl Future<Output = Result<TxResponse>> + Send;
+}
+
+/// Live connection to a chain
+#[derive(Clone)]
+pub struct Chain {
+    /// Connection from the cosmos crate
+    cosmos: cosmos::Cosmos,
+    /// Signing mode requested with `--sign-mode`, if any
+    sign_mode: Option<SignMode>,
+    /// Address paying the fees through a feegrant allowance, if any
+    fee_granter: Option<cosmos::Address>,
+    /// Locally incremented sequences, for many transactions in a row
+    sequences: Option<SequenceCache>,
+    /// gRPC client for our own signing, connected on first use and shared by clones
+    client: Arc<tokio::sync::OnceCell<grpc::Client>>,
+}
+
+impl Chain {
+    /// Wraps a connection, signing with `sign_mode` when given
+    pub fn new(cosmos: cosmos::Cosmos, sign_mode: Option<SignMode>) -> Self {
+        Chain {
+            cosmos,
+            sign_mode,
+            fee_granter: None,
+            sequences: None,
+            client: Arc::default(),
+        }
+    }
+
+    /// Has `granter` pay the fees of every transaction, when given
+    pub fn with_fee_granter(mut self, granter: Option<cosmos::Address>) -> Self {
+        self.fee_granter = granter;
+        self
+    }
+
+    /// Caches account sequences between transactions instead of querying them for each.
+    ///
+    /// Used when sending many transactions rapidly, where querying the
+    /// sequence races with transactions still in the mempool.
+    pub fn with_sequence_cache(mut self) -> Self {
+        self.sequences = Some(SequenceCache::default());
+        self
+    }
+
+    /// gRPC client connected to the same endpoint, reused by every transaction
+    pub async fn grpc(&self) -> Result<&grpc::Client> {
+        self.client
+            .get_or_try_init(|| grpc::Client::connect(&self.cosmos))
+            .await
+    }
+
+    /// Connection from the cosmos crate, for operations outside [`ChainClient`]
+    pub fn cosmos(&self) -> &cosmos::Cosmos {
+        &self.cosmos
+    }
+}
+
+impl ChainClient for Chain {
+    fn chain_id(&self) -> String {
+        self.cosmos.get_cosmos_builder().chain_id().to_owned()
+    }
+
+    async fn all_balances(&self, address: cosmos::Address) -> Result<Vec<cosmos::Coin>> {
+        deadline::within(Phase::Query, "all balances", async {
+            Ok(self.cosmos.all_balances(address).await?)
+        })
+        .await
+        .with_context(|| format!("Failed to retrieve all balances for {}", address))
+    }
+
+    async fn sign_and_broadcast(
+        &self,
+        wallet: &cosmos::Wallet,
+        messages: &[Msg],
+    ) -> Result<TxResponse> {
+        // Our own signing times simulating, broadcasting and confirming separately
+        signing::sign_and_broadcast(
+            &self.cosmos,
+            self.grpc().await?,
+            wallet,
+            messages,
+            self.sign_mode.unwrap_or(SignMode::Direct),
+            self.fee_granter,
+            self.sequences.as_ref(),
+        )
+        .await
+    }
+}
@@ -0,0 +1,3447 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Parse input
+use clap::{CommandFactory, Parser, Subcommand};
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Whether confirmations can be asked, and raw transaction files
+use std::io::{IsTerminal, Read, Write};
+
+/// Sends of a manifest by chain and key
+use std::collections::BTreeMap;
+
+/// Progress shared with status reports
+use std::sync::Arc;
+
+/// Shared state passed to every command
+use crate::context::{AppContext, Output};
+
+/// Core logic
+use crate::send::{SendRequest, TxOutcome};
+use crate::{
+    amount, approval, auth, authz, balances, batch, config, context, custom, cw20, daemon,
+    deadline, display, error, expr, faucet, feegrant, gov, grpc, ibc, journal, keys, manifest,
+    policy, progress, queue, registry, schedule, send, status, swap, tasks, telemetry, templates,
+    tx, txfile, validate, vesting, wasm, watch,
+};
+
+/// Command line interface
+#[derive(Parser)]
+pub struct Cli {
+    /// Options shared by every command
+    #[clap(flatten)]
+    pub global: context::GlobalOpts,
+    /// Command to run
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+/// Available commands
+#[derive(Subcommand)]
+pub enum Command {
+    /// Send funds to another wallet
+    #[clap(visible_alias = "s")]
+    Send(Transaction),
+    /// Send funds to every address,amount line of a file, one transaction each
+    #[clap(visible_alias = "sb")]
+    SendBatch(SendBatchArgs),
+    /// Send funds on several chains at once, every chain,from,to,amount line of a manifest
+    SendManifest(SendManifestArgs),
+    /// Send funds from an account whose key stays on a companion wallet, approved from a QR code
+    SendRemote(SendRemoteArgs),
+    /// Queue transactions and send them one at a time per account, for scripts sharing a wallet
+    #[clap(subcommand)]
+    Queue(QueueCommand),
+    /// Show the balances of an address
+    #[clap(visible_alias = "b")]
+    Balance(BalanceArgs),
+    /// Query the balances of many addresses concurrently and total them per denom
+    #[clap(visible_alias = "ba")]
+    BalanceAll(BalanceAllArgs),
+    /// Inspect the local transaction log
+    #[clap(subcommand)]
+    Log(LogCommand),
+    /// Check, export and decode transactions
+    #[clap(subcommand)]
+    Tx(TxCommand),
+    /// Monitor an address and report every transfer it receives or sends
+    #[clap(visible_alias = "w")]
+    Watch(WatchArgs),
+    /// List the latest transfers an address received or sent, from the node's transaction index
+    History(HistoryArgs),
+    /// List governance proposals and vote on them
+    #[clap(subcommand)]
+    Gov(GovCommand),
+    /// Let other accounts act on your behalf with bounded permissions
+    #[clap(subcommand)]
+    Authz(AuthzCommand),
+    /// Manage allowances paying the fees of other accounts
+    #[clap(subcommand)]
+    Feegrant(FeegrantCommand),
+    /// Inspect named keys and import watch-only ones
+    #[clap(subcommand)]
+    Keys(KeysCommand),
+    /// Transfer tokens to another chain over IBC
+    #[clap(subcommand)]
+    Ibc(IbcCommand),
+    /// Execute and inspect CosmWasm contracts
+    #[clap(subcommand)]
+    Wasm(WasmCommand),
+    /// Manage the cached chain registry used to resolve `--network`
+    #[clap(subcommand)]
+    Chains(ChainsCommand),
+    /// Swap tokens through an Osmosis pool after quoting the expected output
+    Swap(SwapArgs),
+    /// List the built-in and user-defined transaction templates
+    Templates,
+    /// Run a transaction template
+    RunTemplate {
+        /// Template name, see `templates`
+        name: String,
+        /// Template parameter, e.g. pct=20, repeat for several
+        #[clap(long = "param", value_parser = parse_param)]
+        params: Vec<(String, String)>,
+        /// Print the messages without broadcasting them
+        #[clap(long)]
+        dry_run: bool,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// Run the jobs of the [daemon] config section for several profiles concurrently
+    Daemon {
+        /// How often to log per-profile metrics
+        #[clap(long, default_value = "60s", value_parser = humantime::parse_duration)]
+        report_interval: std::time::Duration,
+    },
+    /// Hand out testnet tokens from the wallet over HTTP, within rate limits
+    Faucet(FaucetArgs),
+    /// Print a shell completion script, e.g. `cli-tool completions bash > /etc/bash_completion.d/cli-tool`
+    Completions {
+        /// Shell to complete commands in
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page, e.g. `cli-tool man > cli-tool.1`
+    Man,
+}
+
+/// Chain registry commands
+#[derive(Subcommand)]
+pub enum ChainsCommand {
+    /// List the chains `--network` can name
+    List,
+    /// Fetch chains from the chain registry into the cache
+    Update {
+        /// Registry names, e.g. juno, every known chain when missing
+        names: Vec<String>,
+    },
+}
+
+/// IBC commands
+#[derive(Subcommand)]
+pub enum IbcCommand {
+    /// Send tokens over an ICS-20 channel after checking the channel is healthy
+    Transfer {
+        /// Amount to send, e.g. 110uosmo or 1.5osmo
+        coin: String,
+        /// Address receiving the funds on the counterparty chain
+        receiver: String,
+        /// Source channel, e.g. channel-0
+        #[clap(long)]
+        channel: String,
+        /// How long the packet may wait to be relayed before timing out
+        #[clap(long, default_value = "10m", value_parser = humantime::parse_duration)]
+        timeout: std::time::Duration,
+        /// Maximum pending packets on the channel before refusing the transfer
+        #[clap(long, default_value_t = 100)]
+        max_pending: u64,
+        /// Skip the channel health check
+        #[clap(long)]
+        skip_health_check: bool,
+        /// Send over a per-transaction or daily spend limit, after confirming on the terminal
+        #[clap(long)]
+        override_limit: bool,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// Check whether the tokens of a timed-out or failed transfer were refunded
+    Refund {
+        /// Txhash of the transfer
+        txhash: String,
+        /// Your address, the transfer must have been sent from it
+        #[clap(long)]
+        address: Option<cosmos::Address>,
+    },
+}
+
+/// CosmWasm commands
+#[derive(Subcommand)]
+pub enum WasmCommand {
+    /// Check that the code stored under a code id matches its source
+    Verify {
+        /// Code id to check
+        code_id: u64,
+        /// A .wasm file, a url to download one from, or git+<repository>[#rev] to build with the optimizer
+        #[clap(long)]
+        source: wasm::Source,
+    },
+    /// Execute a contract, optionally profiling the gas of each message
+    Execute {
+        /// Contract address
+        contract: cosmos::Address,
+        /// JSON execute message, repeat for several messages
+        #[clap(long = "msg", required = true)]
+        msgs: Vec<String>,
+        /// Funds sent with every message, e.g. 1osmo, comma separated for several denoms
+        #[clap(long)]
+        funds: Option<String>,
+        /// Run every message N times in its own transaction and report min/avg/max gas used
+        #[clap(long, value_name = "N")]
+        profile_gas: Option<usize>,
+        /// Refuse to execute unless the contract's code matches this source, see `wasm verify`
+        #[clap(long)]
+        verify_source: Option<wasm::Source>,
+        /// Send funds over a per-transaction or daily spend limit, after confirming on the terminal
+        #[clap(long)]
+        override_limit: bool,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+}
+
+/// Authz commands
+#[derive(Subcommand)]
+pub enum AuthzCommand {
+    /// Give another account permission to send messages for you
+    Grant {
+        /// Account receiving the permission
+        grantee: cosmos::Address,
+        /// Permission to give
+        #[clap(subcommand)]
+        authorization: GrantAuthorization,
+    },
+    /// Take back a permission
+    Revoke {
+        /// Account holding the permission
+        grantee: cosmos::Address,
+        /// Message type url of the permission, or `send`
+        msg_type: String,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// Send a message on behalf of an account which granted you permission
+    #[clap(subcommand)]
+    Exec(ExecMessage),
+    /// List grants given and received by an address
+    List {
+        /// Address to query
+        address: cosmos::Address,
+    },
+}
+
+/// Permissions `authz grant` can give
+#[derive(Subcommand)]
+pub enum GrantAuthorization {
+    /// Bank sends up to a spend limit
+    Send {
+        /// Maximum the grantee may send in total, e.g. 1000uosmo, comma separated for several denoms
+        #[clap(long)]
+        spend_limit: String,
+        /// Only allow sends to these addresses, comma separated
+        #[clap(long, value_delimiter = ',')]
+        allow_list: Vec<cosmos::Address>,
+        /// Expiration and signer
+        #[clap(flatten)]
+        grant: GrantArgs,
+    },
+    /// Any message of one type, without limits
+    Generic {
+        /// Message type url, e.g. /cosmos.gov.v1.MsgVote
+        #[clap(long)]
+        msg_type: String,
+        /// Expiration and signer
+        #[clap(flatten)]
+        grant: GrantArgs,
+    },
+}
+
+/// Options shared by every `authz grant` permission
+#[derive(clap::Args)]
+pub struct GrantArgs {
+    /// Expire the grant after this long, e.g. 30d
+    #[clap(long, value_parser = humantime::parse_duration)]
+    expires_in: Option<std::time::Duration>,
+    /// Capture environment variable mnemonic, unless --key selects a named key
+    #[clap(env = "COSMOS_WALLET")]
+    origin: Option<cosmos::SeedPhrase>,
+}
+
+/// Messages `authz exec` can send for a granter
+#[derive(Subcommand)]
+pub enum ExecMessage {
+    /// Send funds from the granter's account
+    Send {
+        /// Account which granted the permission
+        granter: cosmos::Address,
+        /// Amount to send, e.g. 110uosmo or 1.5osmo
+        coin: String,
+        /// Destination address to receive the funds
+        #[clap(value_parser = validate::parse_address)]
+        destination: cosmos::Address,
+        /// Refuse to send unless the destination account already exists on chain
+        #[clap(long)]
+        require_existing: bool,
+        /// Allow a plain bank send to a contract address, most contracts can't recover such funds
+        #[clap(long)]
+        to_contract: bool,
+        /// Send over a per-transaction or daily spend limit, after confirming on the terminal
+        #[clap(long)]
+        override_limit: bool,
+        /// Send without asking for confirmation of a first-time destination
+        #[clap(short, long)]
+        yes: bool,
+        /// Capture environment variable mnemonic of the grantee, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// Vote on a proposal with the granter's stake
+    Vote {
+        /// Account which granted the permission
+        granter: cosmos::Address,
+        /// Proposal id
+        id: u64,
+        /// Vote option
+        #[clap(value_enum)]
+        option: gov::VoteOption,
+        /// Capture environment variable mnemonic of the grantee, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+}
+
+/// Feegrant commands
+#[derive(Subcommand)]
+pub enum FeegrantCommand {
+    /// Pay the fees of another account
+    Grant {
+        /// Account whose fees are paid
+        grantee: cosmos::Address,
+        /// Maximum fees the grantee may spend, e.g. 1osmo, comma separated for several denoms
+        #[clap(long)]
+        spend_limit: Option<String>,
+        /// Expire the allowance after this long, e.g. 30d
+        #[clap(long, value_parser = humantime::parse_duration)]
+        expires_in: Option<std::time::Duration>,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// Stop paying the fees of another account
+    Revoke {
+        /// Account whose fees were paid
+        grantee: cosmos::Address,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+    /// List allowances granted to and by an address
+    List {
+        /// Address to query
+        address: cosmos::Address,
+    },
+}
+
+/// Key commands
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Show the address or public key of a key
+    Show {
+        /// Key name from the config file, `default` uses COSMOS_WALLET
+        #[clap(default_value = keys::DEFAULT_KEY)]
+        name: String,
+        /// Show the public key instead of the address
+        #[clap(long)]
+        pubkey: bool,
+        /// Public key format
+        #[clap(long, value_enum, default_value_t)]
+        format: keys::PubkeyFormat,
+    },
+    /// Add an address to the config file as a key that can be queried and watched but never signs
+    Import {
+        /// Key name, e.g. treasury
+        name: String,
+        /// Address of the key
+        address: cosmos::Address,
+        /// Import the address without a mnemonic, the only kind of import
+        #[clap(long, required = true)]
+        watch_only: bool,
+    },
+}
+
+/// Governance commands
+#[derive(Subcommand)]
+pub enum GovCommand {
+    /// List proposals in their voting period with status and tally
+    Proposals,
+    /// Show a proposal
+    Show {
+        /// Proposal id
+        id: u64,
+    },
+    /// Vote on a proposal
+    Vote {
+        /// Proposal id
+        id: u64,
+        /// Vote option
+        #[clap(value_enum)]
+        option: gov::VoteOption,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+}
+
+/// Address to query
+#[derive(clap::Args)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct BalanceArgs {
+    /// Balance subcommand
+    #[clap(subcommand)]
+    command: Option<BalanceCommand>,
+    /// Address to query, or the name of a key
+    #[clap(required = true)]
+    address: Option<String>,
+    /// Query the balance of a CW20 token contract instead of the bank balances
+    #[clap(long)]
+    cw20: Option<cosmos::Address>,
+}
+
+/// Balance subcommands
+#[derive(Subcommand)]
+pub enum BalanceCommand {
+    /// Compare the balances of two addresses denom by denom
+    Diff {
+        /// First address or key name
+        a: String,
+        /// Second address or key name
+        b: String,
+    },
+}
+
+/// Sends of a batch file
+#[derive(clap::Args)]
+pub struct SendBatchArgs {
+    /// File with one address,amount line per send, e.g. osmo1...,1.5osmo, `-` for stdin
+    file: std::path::PathBuf,
+    /// Maximum number of sends in flight
+    #[clap(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Refuse the batch unless every destination account already exists on chain
+    #[clap(long)]
+    require_existing: bool,
+    /// Only send the lines an earlier, interrupted run of the same file didn't
+    #[clap(long)]
+    resume: bool,
+    /// Send every line again, forgetting what an earlier run of the same file sent
+    #[clap(long, conflicts_with = "resume")]
+    restart: bool,
+    /// Capture environment variable mnemonic, unless --key selects a named key
+    #[clap(env = "COSMOS_WALLET")]
+    origin: Option<cosmos::SeedPhrase>,
+}
+
+/// Swap through an Osmosis pool
+#[derive(clap::Args)]
+pub struct SwapArgs {
+    /// Amount to swap, e.g. 100uosmo or 1.5osmo
+    coin: String,
+    /// Denom to receive, e.g. uion
+    #[clap(long)]
+    to: String,
+    /// Pool to swap through
+    #[clap(long)]
+    pool: u64,
+    /// Maximum accepted shortfall from the quoted output, e.g. 1% or 0.5%
+    #[clap(long, default_value = "1%", value_parser = swap::parse_slippage)]
+    slippage: u128,
+    /// Print the quote without swapping
+    #[clap(long)]
+    dry_run: bool,
+    /// Swap over a per-transaction or daily spend limit, after confirming on the terminal
+    #[clap(long)]
+    override_limit: bool,
+    /// Capture environment variable mnemonic, unless --key selects a named key
+    #[clap(env = "COSMOS_WALLET")]
+    origin: Option<cosmos::SeedPhrase>,
+}
+
+/// Send approved on a companion wallet
+#[derive(clap::Args)]
+pub struct SendRemoteArgs {
+    /// Amount to send, e.g. 110uosmo or 1.5osmo
+    coin: amount::HumanAmount,
+    /// Destination address to receive the funds
+    #[clap(value_parser = validate::parse_address)]
+    destination: cosmos::Address,
+    /// Address sending the funds, or the name of a watch-only key
+    #[clap(long)]
+    from: String,
+    /// Public key of the sender as hex or base64, only needed if it never signed a transaction
+    #[clap(long)]
+    pubkey: Option<String>,
+    /// Refuse to send unless the destination account already exists on chain
+    #[clap(long)]
+    require_existing: bool,
+    /// Allow a plain bank send to a contract address, most contracts can't recover such funds
+    #[clap(long)]
+    to_contract: bool,
+    /// Send over a per-transaction or daily spend limit, after confirming on the terminal
+    #[clap(long)]
+    override_limit: bool,
+    /// Send without asking for confirmation of a first-time destination
+    #[clap(short, long)]
+    yes: bool,
+}
+
+/// Queue commands
+#[derive(Subcommand)]
+pub enum QueueCommand {
+    /// Add a transaction to the queue, checked now and sent by `queue run`
+    #[clap(subcommand)]
+    Add(QueueIntent),
+    /// List the items of the queue, oldest first
+    List {
+        /// Only list items with this status
+        #[clap(long, value_enum)]
+        status: Option<queue::Status>,
+    },
+    /// Send the pending items, one at a time per account and the accounts concurrently
+    Run {
+        /// Keep running and send items as they are added, until Ctrl-C
+        #[clap(long)]
+        follow: bool,
+        /// How often to look for new items with --follow, e.g. 2s
+        #[clap(long, default_value = "2s", value_parser = humantime::parse_duration)]
+        interval: std::time::Duration,
+        /// Times a send failing before broadcast is tried again
+        #[clap(long, default_value_t = 3)]
+        retries: u32,
+        /// Delay before the first retry, doubled for every further one, e.g. 5s
+        #[clap(long, default_value = "5s", value_parser = humantime::parse_duration)]
+        retry_delay: std::time::Duration,
+    },
+}
+
+/// Transactions that can be queued
+#[derive(Subcommand)]
+pub enum QueueIntent {
+    /// Queue a bank send
+    Send(QueueSendArgs),
+}
+
+/// Send to queue
+#[derive(clap::Args)]
+pub struct QueueSendArgs {
+    /// Amount to send, e.g. 110uosmo or 1.5osmo
+    coin: amount::HumanAmount,
+    /// Destination address to receive the funds
+    #[clap(value_parser = validate::parse_address)]
+    destination: cosmos::Address,
+    /// Named key sending the funds, the --key key or `default` (COSMOS_WALLET) if missing
+    #[clap(long)]
+    from: Option<String>,
+    /// Refuse to queue unless the destination account already exists on chain
+    #[clap(long)]
+    require_existing: bool,
+    /// Allow a plain bank send to a contract address, most contracts can't recover such funds
+    #[clap(long)]
+    to_contract: bool,
+    /// Queue without asking for confirmation of a first-time destination
+    #[clap(short, long)]
+    yes: bool,
+}
+
+/// Sends of a manifest
+#[derive(clap::Args)]
+pub struct SendManifestArgs {
+    /// File with one chain,from,to,amount line per send, e.g. juno,treasury,juno1...,5juno, `-` for stdin
+    file: std::path::PathBuf,
+    /// Maximum number of sends in flight per key and chain
+    #[clap(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Refuse the sends of a key unless every destination account already exists on chain
+    #[clap(long)]
+    require_existing: bool,
+}
+
+/// Faucet settings
+#[derive(clap::Args)]
+pub struct FaucetArgs {
+    /// Address to serve on
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    listen: std::net::SocketAddr,
+    /// Amount sent per request, e.g. 1osmo
+    #[clap(long)]
+    amount: amount::HumanAmount,
+    /// Requests allowed per client IP, e.g. 1/day or 5/2h
+    #[clap(long, default_value = "1/day")]
+    per_ip_limit: faucet::Rate,
+    /// Requests allowed per receiving address
+    #[clap(long, default_value = "1/day")]
+    per_address_limit: faucet::Rate,
+    /// Only serve clients in this network, e.g. 10.0.0.0/8 or 203.0.113.7, repeat for several
+    #[clap(long = "allow")]
+    allow: Vec<faucet::IpNet>,
+    /// Tell clients apart by X-Forwarded-For behind this many reverse proxies, 1 if no count is given
+    #[clap(long, value_name = "PROXIES", num_args = 0..=1, default_missing_value = "1")]
+    trust_proxy: Option<usize>,
+    /// Serve on a chain that doesn't look like a testnet
+    #[clap(long)]
+    allow_mainnet: bool,
+    /// Capture environment variable mnemonic, unless --key selects a named key
+    #[clap(env = "COSMOS_WALLET")]
+    origin: Option<cosmos::SeedPhrase>,
+}
+
+/// Addresses to scan
+#[derive(clap::Args)]
+pub struct BalanceAllArgs {
+    /// File with one address per line, `-` for stdin
+    #[clap(long)]
+    file: std::path::PathBuf,
+    /// Maximum number of queries in flight
+    #[clap(long, default_value_t = 16)]
+    concurrency: usize,
+    /// Output format, JSON by default with `--output json`, CSV otherwise
+    #[clap(long, value_enum)]
+    format: Option<ScanFormat>,
+}
+
+/// Output formats of `balance-all`
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ScanFormat {
+    /// address,denom,amount rows
+    Csv,
+    /// Per-address balances and totals
+    Json,
+}
+
+/// Address to monitor
+#[derive(clap::Args)]
+pub struct WatchArgs {
+    /// Address to monitor, or the name of a key
+    address: String,
+    /// How often to poll for new blocks, e.g. 5s
+    #[clap(long, default_value = "5s", value_parser = humantime::parse_duration)]
+    interval: std::time::Duration,
+    /// Shell command to run for each event, receives the event as JSON on stdin
+    #[clap(long)]
+    exec: Option<String>,
+}
+
+/// Address whose transfers to list
+#[derive(clap::Args)]
+pub struct HistoryArgs {
+    /// Address to look up, or the name of a key
+    address: String,
+    /// Number of latest transactions to list the transfers of
+    #[clap(long, default_value_t = 20)]
+    limit: usize,
+}
+
+/// Transaction log commands
+#[derive(Subcommand)]
+pub enum LogCommand {
+    /// List recorded broadcast attempts, most recent last
+    List {
+        /// Only show the last N entries
+        #[clap(long)]
+        limit: Option<usize>,
+    },
+    /// Show every recorded attempt for a txhash
+    Show {
+        /// Transaction txhash
+        txhash: String,
+    },
+}
+
+/// Transaction commands
+#[derive(Subcommand)]
+pub enum TxCommand {
+    /// Check whether a broadcast transaction was included, e.g. after an interrupted send
+    Status {
+        /// Transaction txhash
+        txhash: String,
+    },
+    /// Export exactly what was signed and broadcast in a transaction
+    Export {
+        /// Transaction txhash
+        txhash: String,
+        /// json with the chain, height and time, or proto for the raw signed bytes
+        #[clap(long, value_enum, default_value_t)]
+        format: txfile::Format,
+        /// File to write, stdout if missing
+        #[clap(long)]
+        out: Option<std::path::PathBuf>,
+        /// Encrypt the file with the passphrase in CLI_TOOL_EXPORT_PASSPHRASE
+        #[clap(long)]
+        encrypt: bool,
+    },
+    /// Decode a transaction file and print its messages, signatures and fee
+    Import {
+        /// File written by `tx export`, or raw or base64 TxRaw bytes, `-` for stdin
+        file: std::path::PathBuf,
+    },
+    /// Sign and broadcast messages of any type given as JSON, e.g. of a chain's own modules
+    Broadcast {
+        /// JSON message with its `@type`, or an array of them, `-` for stdin; repeat for several files
+        #[clap(long = "msg-json", required = true)]
+        msg_json: Vec<std::path::PathBuf>,
+        /// Descriptor set with the schemas of the message types, e.g. from `buf build -o msgs.binpb`
+        #[clap(long)]
+        proto_descriptors: Vec<std::path::PathBuf>,
+        /// Broadcast without asking for confirmation of the messages
+        #[clap(short, long)]
+        yes: bool,
+        /// Send over a spend limit, or messages whose spending isn't known, after confirming on the terminal
+        #[clap(long)]
+        override_limit: bool,
+        /// Capture environment variable mnemonic, unless --key selects a named key
+        #[clap(env = "COSMOS_WALLET")]
+        origin: Option<cosmos::SeedPhrase>,
+    },
+}
+
+/// Transaction to execute
+#[derive(clap::Args)]
+pub struct Transaction {
+    /// Amount to send to another wallet, e.g. 110uosmo, 1.5osmo, 'balance * 0.25' or '100osmo - fee',
+    /// a plain integer with --cw20, or the denom to empty with --all
+    coin: String,
+    /// Destination address to receive the funds
+    #[clap(value_parser = validate::parse_address)]
+    destination: cosmos::Address,
+    /// Refuse to send unless the destination account already exists on chain
+    #[clap(long)]
+    require_existing: bool,
+    /// Send tokens of a CW20 contract instead of a bank denom
+    #[clap(long)]
+    cw20: Option<cosmos::Address>,
+    /// Send the whole spendable balance of the denom given as the amount, less the fee, e.g. --all osmo
+    #[clap(long, conflicts_with = "cw20")]
+    all: bool,
+    /// Amount to keep with --all, e.g. 1osmo for future gas
+    #[clap(long, requires = "all")]
+    leave: Option<amount::HumanAmount>,
+    /// Allow a plain bank send to a contract address, most contracts can't recover such funds
+    #[clap(long)]
+    to_contract: bool,
+    /// Wait until this time before sending, e.g. 2024-07-01T12:00:00Z
+    #[clap(long)]
+    at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Send again at this interval, e.g. 24h
+    #[clap(long, value_parser = humantime::parse_duration)]
+    every: Option<std::time::Duration>,
+    /// Stop after this many sends, forever if missing
+    #[clap(long, requires = "every")]
+    count: Option<u32>,
+    /// Send over a per-transaction or daily spend limit, after confirming on the terminal
+    #[clap(long, conflicts_with = "every")]
+    override_limit: bool,
+    /// Send without asking for confirmation of an amount expression or a first-time destination
+    #[clap(short, long)]
+    yes: bool,
+    /// Capture environment variable mnemonic, unless --key selects a named key
+    #[clap(env = "COSMOS_WALLET")]
+    origin: Option<cosmos::SeedPhrase>,
+}
+
+/// Parses a `name=value` template parameter
+fn parse_param(param: &str) -> Result<(String, String)> {
+    let (name, value) = param
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid parameter {}, expected name=value", param))?;
+    Ok((name.trim().to_owned(), value.to_owned()))
+}
+
+/// Runs a parsed command line
+pub async fn run(cli: Cli) -> Result<()> {
+    // Generated from the command tree alone, a broken config file doesn't get in the way
+    if let Command::Completions { .. } | Command::Man = cli.command {
+        return generate(&cli.command);
+    }
+
+    // Load the configuration file, defaults if missing
+    let config = config::load()?;
+
+    // Every phase of talking to a chain gets the same deadline
+    deadline::set_timeout(cli.global.timeout);
+
+    // Global options are resolved once for every command
+    let ctx = AppContext::new(cli.global, config)?;
+
+    // Init subscriber to handle traces, spinners replace the info logs on a terminal
+    let level = match (progress::init(ctx.quiet, ctx.verbose), ctx.verbose) {
+        (progress::Mode::Quiet, _) => tracing::Level::ERROR,
+        (progress::Mode::Spinner, _) => tracing::Level::WARN,
+        (progress::Mode::Plain, 0) => tracing::Level::INFO,
+        (progress::Mode::Plain, 1) => tracing::Level::DEBUG,
+        (progress::Mode::Plain, _) => tracing::Level::TRACE,
+    };
+    let _telemetry = telemetry::init_tracing(level, ctx.timings)?;
+
+    // Long-running modes are scraped while they run
+    if let Some(addr) = ctx.metrics_addr {
+        telemetry::serve_metrics(addr)?;
+    }
+
+    tracing::info!("Rust Cli Tool has started");
+
+    // Ctrl-C and SIGTERM cancel the shared token instead of killing the process
+    tasks::cancel_on_signals(ctx.shutdown.clone());
+
+    // Long-running commands stop by themselves once cancelled, the others are dropped
+    let result = if cli.command.stops_on_cancel() {
+        execute(&ctx, cli.command).await
+    } else {
+        tasks::until_cancelled(&ctx.shutdown, execute(&ctx, cli.command)).await
+    };
+
+    // Failed commands are reported too, a timeout is when timings matter most
+    if ctx.timings {
+        eprintln!("{}", telemetry::timing_report());
+    }
+    result
+}
+
+impl Command {
+    /// Whether the command watches [`AppContext::shutdown`] and wraps up by itself when cancelled
+    fn stops_on_cancel(&self) -> bool {
+        match self {
+            Command::Send(transaction) => transaction.at.is_some() || transaction.every.is_some(),
+            Command::SendBatch(_)
+            | Command::SendManifest(_)
+            | Command::Watch(_)
+            | Command::Queue(QueueCommand::Run { .. })
+            | Command::Daemon { .. }
+            | Command::Faucet(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Runs a command
+async fn execute(ctx: &AppContext, command: Command) -> Result<()> {
+    match command {
+        Command::Send(transaction) => send(ctx, &transaction).await,
+        Command::SendBatch(args) => send_batch(ctx, &args).await,
+        Command::SendManifest(args) => send_manifest(ctx, &args).await,
+        Command::SendRemote(args) => send_remote(ctx, &args).await,
+        Command::Queue(command) => queue_command(ctx, command).await,
+        Command::Balance(args) => balance(ctx, &args).await,
+        Command::BalanceAll(args) => balance_all(ctx, &args).await,
+        Command::Faucet(args) => run_faucet(ctx, args).await,
+        Command::Log(command) => log(ctx, command),
+        Command::Tx(command) => tx_command(ctx, command).await,
+        Command::Watch(args) => watch(ctx, &args).await,
+        Command::History(args) => history(ctx, &args).await,
+        Command::Gov(command) => governance(ctx, command).await,
+        Command::Authz(command) => authz_command(ctx, command).await,
+        Command::Feegrant(command) => feegrant_command(ctx, command).await,
+        Command::Keys(command) => keys(ctx, command).await,
+        Command::Ibc(command) => ibc_command(ctx, command).await,
+        Command::Wasm(command) => wasm_command(ctx, command).await,
+        Command::Chains(command) => chains(ctx, command).await,
+        Command::Swap(args) => swap_command(ctx, &args).await,
+        Command::Templates => list_templates(&ctx),
+        Command::RunTemplate {
+            name,
+            params,
+            dry_run,
+            origin,
+        } => run_template(ctx, &name, params, dry_run, origin.as_ref()).await,
+        Command::Daemon { report_interval } => daemon::run(ctx, report_interval).await,
+        Command::Completions { .. } | Command::Man => generate(&command),
+    }
+}
+
+/// Prints the completion script or man page of the real command tree
+fn generate(command: &Command) -> Result<()> {
+    let mut tree = Cli::command();
+    let mut stdout = std::io::stdout();
+    match command {
+        Command::Completions { shell } => {
+            let name = tree.get_name().to_owned();
+            clap_complete::generate(*shell, &mut tree, name, &mut stdout);
+        }
+        _ => clap_mangen::Man::new(tree)
+            .render(&mut stdout)
+            .context("Error writing the man page")?,
+    }
+    Ok(())
+}
+
+/// Executes the transaction and reports the outcome
+async fn send(ctx: &AppContext, transaction: &Transaction) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+
+    // Load the wallet
+    // Get wallet from SeedPhrase::Mnemonic
+    let wallet = ctx.signer(cosmos_addr, transaction.origin.as_ref())?;
+
+    // What needs confirming before signing, asked once
+    let mut notes = Vec::new();
+
+    // Vec which contains the Coin to send => 100 uosmo
+    // CW20 amounts are plain integers, the contract identifies the token
+    let coin: cosmos::Coin = match transaction.cw20 {
+        Some(contract) => cosmos::Coin {
+            denom: cw20::denom(contract),
+            amount: transaction
+                .coin
+                .parse::<u128>()
+                .with_context(|| format!("Invalid CW20 amount {}", transaction.coin))?
+                .to_string(),
+        },
+        None if transaction.all => {
+            let coin = expr::max_send(
+                &chain,
+                &wallet,
+                transaction.destination,
+                &transaction.coin,
+                transaction.leave.as_ref(),
+                ctx.fee_granter,
+            )
+            .await?;
+            notes.push(format!(
+                "--all {} resolves to {} ({}{})",
+                transaction.coin,
+                ctx.formatter(cosmos_addr).coin(&coin),
+                coin.amount,
+                coin.denom
+            ));
+            coin
+        }
+        None => {
+            let expr = expr::parse(&transaction.coin)?;
+            let coin = expr::resolve(
+                &chain,
+                &wallet,
+                transaction.destination,
+                &expr,
+                ctx.fee_granter,
+            )
+            .await?;
+
+            // Expressions depend on live values, the concrete amount is confirmed before signing
+            if !expr.is_literal() {
+                notes.push(format!(
+                    "{} resolves to {} ({}{})",
+                    transaction.coin,
+                    ctx.formatter(cosmos_addr).coin(&coin),
+                    coin.amount,
+                    coin.denom
+                ));
+            }
+            coin
+        }
+    };
+    let client = grpc::Client::connect(cosmos_addr).await?;
+    notes.extend(
+        check_send_destination(
+            &client,
+            transaction.destination,
+            wallet.get_address(),
+            transaction.require_existing,
+            transaction.cw20.is_some() || transaction.to_contract,
+        )
+        .await?,
+    );
+    if !notes.is_empty() {
+        confirm(&notes.join("\n"), transaction.yes).await?;
+    }
+
+    // Show the approximate value before anything is signed
+    if let Some(prices) = ctx.prices().await {
+        tracing::info!(
+            "Amount: {}{}",
+            ctx.formatter(cosmos_addr).coin(&coin),
+            prices.annotate(&coin)
+        );
+    }
+
+    let policy = spend_policy(
+        ctx,
+        cosmos_addr.get_cosmos_builder().chain_id(),
+        &wallet.to_string(),
+        std::slice::from_ref(&coin),
+        transaction.override_limit,
+    )
+    .await?;
+    let request = SendRequest {
+        coin,
+        destination: transaction.destination,
+        cw20: transaction.cw20,
+    };
+
+    let schedule = schedule::Schedule {
+        at: transaction.at,
+        every: transaction.every,
+        count: transaction.count,
+    };
+    if schedule.is_immediate() {
+        // Execute the transaction
+        let outcome = send::execute_transaction(&chain, &wallet, &request, &policy)
+            .await
+            .context("Error encountered during transaction execution")?;
+
+        return report(ctx, outcome);
+    }
+
+    // Sequence and gas are fetched again for every run
+    schedule::run(schedule, &ctx.shutdown, |run| {
+        let (chain, wallet, request, policy) = (&chain, &wallet, &request, &policy);
+        async move {
+            tracing::info!("Scheduled send {}", run);
+            let outcome = send::execute_transaction(chain, wallet, request, policy)
+                .await
+                .context("Error encountered during transaction execution")?;
+            report(ctx, outcome)
+        }
+    })
+    .await
+}
+
+/// Sends funds from an account whose key is on a companion wallet.
+///
+/// The transaction is built here and shown as a QR code and link for the
+/// wallet to sign; the returned signature is checked against the sender's
+/// public key before it is broadcast, so the desktop never holds the key.
+async fn send_remote(ctx: &AppContext, args: &SendRemoteArgs) -> Result<()> {
+    let from = keys::address(&ctx.config, &args.from)?.ok_or_else(|| {
+        anyhow!(
+            "Key {} has a mnemonic, send with `cli-tool send` instead",
+            args.from
+        )
+    })?;
+    let public_key = args
+        .pubkey
+        .as_deref()
+        .map(approval::parse_public_key)
+        .transpose()?;
+
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let coin = amount::resolve(cosmos_addr, &args.coin).await?;
+
+    let client = grpc::Client::connect(cosmos_addr).await?;
+    let note = check_send_destination(
+        &client,
+        args.destination,
+        from,
+        args.require_existing,
+        args.to_contract,
+    )
+    .await?;
+    if let Some(note) = note {
+        confirm(&note, args.yes).await?;
+    }
+
+    // Refuse sends over the configured limits before asking for a signature
+    let history = journal::read_all()?;
+    let destination = args.destination.to_string();
+    let chain_id = cosmos_addr.get_cosmos_builder().chain_id();
+    policy::check_destination(&ctx.config.policy, &history, chain_id, &destination, &coin)?;
+    spend_policy(
+        ctx,
+        chain_id,
+        &from.to_string(),
+        std::slice::from_ref(&coin),
+        args.override_limit,
+    )
+    .await?;
+
+    let amount = tx::format_coins(std::slice::from_ref(&coin));
+    let description = format!(
+        "Send {} ({}) from {} to {}",
+        ctx.formatter(cosmos_addr).coin(&coin),
+        amount,
+        from,
+        destination
+    );
+    let messages = vec![tx::msg_send(from, args.destination, vec![coin])];
+    let pending = approval::prepare(
+        cosmos_addr,
+        &client,
+        from,
+        public_key,
+        &messages,
+        ctx.fee_granter,
+        &description,
+    )
+    .await?;
+
+    // The request goes to stderr, stdout is left for the outcome
+    eprintln!("{}", pending.request.qr()?);
+    eprintln!("{}", pending.request.link()?);
+    eprintln!(
+        "{}, sequence {}. Scan the code or open the link with the companion wallet and approve it",
+        description, pending.request.sequence
+    );
+    let answer = read_answer("Signature from the wallet: ").await?;
+    if answer.trim().is_empty() {
+        return Err(anyhow!("No signature given, nothing was sent"));
+    }
+    let signed = pending.complete(&approval::parse_signature(&answer)?)?;
+
+    let result =
+        tx::broadcast_signed(cosmos_addr, &client, from, signed, &destination, &amount).await?;
+    report(ctx, result.into())
+}
+
+/// Runs a queue command
+async fn queue_command(ctx: &AppContext, command: QueueCommand) -> Result<()> {
+    let store = queue::Store::open()?;
+    match command {
+        QueueCommand::Add(QueueIntent::Send(args)) => {
+            // Everything that can be checked is checked now, not when the item's turn comes
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+            let from = args
+                .from
+                .as_deref()
+                .or(ctx.key.as_deref())
+                .unwrap_or(keys::DEFAULT_KEY);
+            let wallet = ctx.wallet(cosmos_addr, &keys::seed_phrase(&ctx.config, from)?)?;
+            let coin = amount::resolve(cosmos_addr, &args.coin).await?;
+            let client = grpc::Client::connect(cosmos_addr).await?;
+            let note = check_send_destination(
+                &client,
+                args.destination,
+                wallet.get_address(),
+                args.require_existing,
+                args.to_contract,
+            )
+            .await?;
+            if let Some(note) = note {
+                confirm(&note, args.yes).await?;
+            }
+
+            // Items run on the profile or network they were added with
+            let chain_name = ctx
+                .profile
+                .clone()
+                .unwrap_or_else(|| ctx.network.name().to_owned());
+            let item = store.add(
+                &chain_name,
+                from,
+                queue::Intent::Send {
+                    destination: args.destination.to_string(),
+                    amount: tx::format_coins(&[coin]),
+                },
+            )?;
+            ctx.print(&item, || format!("Queued {} as {}", item.intent, item.id))
+        }
+        QueueCommand::List { status } => {
+            let items: Vec<_> = store
+                .list()?
+                .into_iter()
+                .filter(|item| status.map_or(true, |status| item.status == status))
+                .collect();
+            ctx.print(&items, || {
+                items.iter().map(queue_line).collect::<Vec<_>>().join("\n")
+            })
+        }
+        QueueCommand::Run {
+            follow,
+            interval,
+            retries,
+            retry_delay,
+        } => {
+            let _lock = store.lock()?;
+            let retry = queue::Retry {
+                retries,
+                delay: retry_delay,
+            };
+            let mut failed = 0;
+            loop {
+                let started = chrono::Utc::now();
+
+                // Grouped by chain, then by key, in queue order
+                let mut groups: BTreeMap<String, BTreeMap<String, Vec<queue::Item>>> =
+                    BTreeMap::new();
+                for item in store.list()? {
+                    if matches!(
+                        item.status,
+                        queue::Status::Pending | queue::Status::Broadcast
+                    ) {
+                        groups
+                            .entry(item.chain.clone())
+                            .or_default()
+                            .entry(item.key.clone())
+                            .or_default()
+                            .push(item);
+                    }
+                }
+
+                let results =
+                    futures::future::join_all(groups.into_iter().map(|(chain_name, accounts)| {
+                        queue_chain(ctx, &store, chain_name, accounts, retry)
+                    }))
+                    .await;
+                for item in results.into_iter().flatten() {
+                    let item = item?;
+                    if item.updated < started {
+                        continue;
+                    }
+                    if item.status == queue::Status::Failed {
+                        failed += 1;
+                    }
+                    ctx.print(&item, || queue_line(&item))?;
+                }
+
+                if !follow || !tasks::sleep(&ctx.shutdown, interval).await {
+                    break;
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow!("{} queue items failed", failed));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// One line describing a queue item
+fn queue_line(item: &queue::Item) -> String {
+    format!(
+        "{} {:?} {} {} key {} {}",
+        item.id,
+        item.status,
+        item.chain,
+        item.intent,
+        item.key,
+        match (&item.error, &item.txhash) {
+            (Some(error), _) => format!("error: {}", error),
+            (None, Some(txhash)) => format!("txhash {}", txhash),
+            (None, None) => "-".to_owned(),
+        }
+    )
+}
+
+/// Sends the queued items of one chain over one connection, the keys concurrently.
+///
+/// A chain that can't be reached leaves its items pending for the next run.
+///
+/// ### Returns
+/// The items of every key as they were left.
+async fn queue_chain(
+    ctx: &AppContext,
+    store: &queue::Store,
+    chain_name: String,
+    accounts: BTreeMap<String, Vec<queue::Item>>,
+    retry: queue::Retry,
+) -> Vec<Result<queue::Item>> {
+    // Sequences are tracked locally for every key of the chain
+    let connected = tasks::until_cancelled(&ctx.shutdown, async {
+        let chain = ctx
+            .for_chain(&chain_name)?
+            .connect()
+            .await?
+            .with_sequence_cache();
+        let client = grpc::Client::connect(chain.cosmos()).await?;
+        anyhow::Ok((chain, client))
+    })
+    .await;
+    let (chain, client) = match connected {
+        Ok(connected) => connected,
+        Err(error) => {
+            tracing::warn!(
+                "Queued items on {} stay pending, error connecting: {:#}",
+                chain_name,
+                error
+            );
+            return Vec::new();
+        }
+    };
+    let cosmos_addr = chain.cosmos();
+
+    let runs = accounts.into_iter().map(|(key, mut items)| {
+        let (chain, client) = (&chain, &client);
+        async move {
+            // Broadcasts of an earlier run are looked up before anything else is sent
+            queue::reconcile(client, store, &mut items).await?;
+            let wallet = keys::seed_phrase(&ctx.config, &key)
+                .and_then(|seed| ctx.wallet(cosmos_addr, &seed));
+            let wallet = match wallet {
+                Ok(wallet) => wallet,
+                Err(error) => {
+                    queue::fail_pending(store, &mut items, &error)?;
+                    return Ok(items);
+                }
+            };
+            queue::run_account(
+                chain,
+                &wallet,
+                store,
+                items,
+                &ctx.config.policy,
+                retry,
+                &ctx.shutdown,
+            )
+            .await
+        }
+    });
+    futures::future::join_all(runs)
+        .await
+        .into_iter()
+        .flat_map(|result: Result<Vec<queue::Item>>| match result {
+            Ok(items) => items.into_iter().map(Ok).collect(),
+            Err(error) => vec![Err(error)],
+        })
+        .collect()
+}
+
+/// Refuses destinations a send must not go to, before anything is signed.
+///
+/// ### Returns
+/// A note to confirm if the destination was never sent to and looks unused.
+///
+/// ### Errors
+/// Fails if the destination is on another chain, doesn't exist while
+/// `require_existing`, is a module account, or is a contract unless
+/// `allow_contract`.
+async fn check_send_destination(
+    client: &grpc::Client,
+    destination: cosmos::Address,
+    sender: cosmos::Address,
+    require_existing: bool,
+    allow_contract: bool,
+) -> Result<Option<String>> {
+    // A destination on another chain or, if required, without an account is refused
+    validate::check_destination(client, destination, sender, require_existing).await?;
+
+    // Module accounts reject bank sends or swallow the funds
+    if let Some(module) = auth::module_account(client, destination).await? {
+        return Err(anyhow!(
+            "{} is the account of the {} module, which doesn't accept sends",
+            destination,
+            module.name
+        ));
+    }
+
+    // Bank sends to a contract don't call it, the funds are usually stuck
+    if !allow_contract && wasm::is_contract(client, destination).await? {
+        return Err(anyhow!(
+            "{} is a contract, which usually can't recover funds sent with a plain bank send. \
+             Execute the contract instead, or pass --to-contract if it accepts bank sends",
+            destination
+        ));
+    }
+
+    // A destination never sent to before is checked for activity on chain
+    let history = journal::read_all()?;
+    validate::first_time_warning(client, &history, destination).await
+}
+
+/// Checks what a send spends against the spend limits, letting it over them with `--override-limit`.
+///
+/// Overriding is confirmed by typing `override` on the terminal, `--yes`
+/// doesn't answer it, so a script can't send over a limit by itself.
+///
+/// ### Returns
+/// The policies to send with, without the spend limits once overridden.
+///
+/// ### Errors
+/// Fails if a limit is exceeded and not overridden, or the journal can't be read.
+async fn spend_policy(
+    ctx: &AppContext,
+    chain_id: &str,
+    from: &str,
+    coins: &[cosmos::Coin],
+    override_limit: bool,
+) -> Result<policy::PolicyConfig> {
+    let history = journal::read_all()?;
+    let error = match policy::check_spend(&ctx.config.policy, &history, chain_id, from, coins) {
+        Ok(()) => return Ok(ctx.config.policy.clone()),
+        Err(error) if error.is::<error::SpendLimitExceeded>() => error,
+        Err(error) => return Err(error),
+    };
+    if !override_limit {
+        return Err(anyhow!(
+            "{}. Pass --override-limit to send it anyway",
+            error
+        ));
+    }
+    confirm_override(&error.to_string()).await?;
+    tracing::warn!("Spend limit overridden: {}", error);
+    Ok(ctx.config.policy.without_spend_limits())
+}
+
+/// Asks to type `override` on the terminal to go past a spend limit `reason` refuses.
+///
+/// ### Errors
+/// Fails if the answer isn't `override`, or there's no terminal to ask on.
+async fn confirm_override(reason: &str) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "{}. --override-limit is confirmed on a terminal, --yes doesn't answer it",
+            reason
+        ));
+    }
+
+    eprintln!("{}", reason);
+    let answer = read_answer("Type override to send over the limit: ").await?;
+    if answer.trim() != "override" {
+        return Err(anyhow!("Cancelled"));
+    }
+    Ok(())
+}
+
+/// Shows `summary` and asks to go ahead, `yes` answering for the user.
+///
+/// ### Errors
+/// Fails if the user doesn't answer yes, or if there's no terminal to ask on and `yes` is false.
+async fn confirm(summary: &str, yes: bool) -> Result<()> {
+    eprintln!("{}", summary);
+    if yes {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Not confirmed, pass --yes to proceed without a terminal"
+        ));
+    }
+
+    let answer = read_answer("Proceed? [y/N] ").await?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(anyhow!("Cancelled")),
+    }
+}
+
+/// Shows `prompt` and reads a line from stdin
+async fn read_answer(prompt: &str) -> Result<String> {
+    // Read off the runtime so Ctrl-C still cancels while waiting for the answer
+    eprint!("{}", prompt);
+    tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).map(|_| answer)
+    })
+    .await
+    .context("Error reading the answer")?
+    .context("Error reading the answer")
+}
+
+/// Prints the outcome of a broadcast transaction, failing on a non-zero code
+fn report(ctx: &AppContext, outcome: TxOutcome) -> Result<()> {
+    ctx.print(&outcome, || outcome.to_string())?;
+
+    // All good
+    match outcome.check() {
+        Ok(()) => {
+            tracing::info!("Transaction completed successfully: {}", outcome.txhash);
+            Ok(())
+        }
+        Err(failed) => {
+            tracing::error!("Transaction failed: {}", outcome.txhash);
+            Err(failed.into())
+        }
+    }
+}
+
+/// Serves the faucet until interrupted
+async fn run_faucet(ctx: &AppContext, args: FaucetArgs) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let chain_id = cosmos_addr.get_cosmos_builder().chain_id().to_owned();
+    if !args.allow_mainnet && !faucet::looks_like_testnet(&chain_id) {
+        return Err(anyhow!(
+            "{} doesn't look like a testnet, pass --allow-mainnet to give its tokens away anyway",
+            chain_id
+        ));
+    }
+
+    let wallet = ctx.signer(cosmos_addr, args.origin.as_ref())?;
+    let coin = amount::resolve(cosmos_addr, &args.amount).await?;
+    tracing::info!(
+        "Handing out {} per request from {} on {}",
+        ctx.formatter(cosmos_addr).coin(&coin),
+        wallet,
+        chain_id
+    );
+
+    let faucet = faucet::Faucet::new(
+        chain,
+        wallet,
+        coin,
+        args.per_ip_limit,
+        args.per_address_limit,
+        args.allow,
+    );
+    faucet::serve(
+        faucet,
+        args.listen,
+        args.trust_proxy.unwrap_or(0),
+        ctx.shutdown.clone(),
+    )
+    .await
+}
+
+/// Sends every line of a batch file, reusing locally incremented sequences.
+///
+/// Every finished send is recorded in a checkpoint of the file, so a run
+/// interrupted by Ctrl-C, which finishes the sends in flight, can be resumed
+/// with `--resume`.
+async fn send_batch(ctx: &AppContext, args: &SendBatchArgs) -> Result<()> {
+    // Read the file before connecting, a bad file fails fast
+    let input = if args.file.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Error reading sends from stdin")?
+    } else {
+        std::fs::read_to_string(&args.file)
+            .with_context(|| format!("Error reading sends from {}", args.file.display()))?
+    };
+    let rows = batch::parse(&input)?;
+    let lines: Vec<_> = rows.iter().map(|row| row.line).collect();
+
+    // An earlier run of the same file left a checkpoint of what it broadcast
+    let checkpoint_path = batch::Checkpoint::path(&input)?;
+    let previous =
+        batch::Checkpoint::load(&checkpoint_path)?.filter(|previous| !previous.lines.is_empty());
+    let mut checkpoint = match previous {
+        Some(_) if args.restart => batch::Checkpoint::default(),
+        Some(previous) if args.resume => previous,
+        Some(previous) => {
+            return Err(anyhow!(
+                "An earlier run of this batch file broadcast {} of its lines. \
+                 Pass --resume to only send the others, or --restart to send every line again",
+                previous.lines.len()
+            ))
+        }
+        None if args.resume => {
+            return Err(anyhow!(
+                "Nothing to resume, no earlier run of this batch file was interrupted"
+            ))
+        }
+        None => batch::Checkpoint::default(),
+    };
+
+    // Nothing is sent until the checks pass, Ctrl-C stops them right away
+    let prepare = async {
+        // Connect to the blockchain, sequences are tracked locally for the whole batch
+        let chain = ctx.connect().await?.with_sequence_cache();
+        let cosmos_addr = chain.cosmos();
+        let client = grpc::Client::connect(cosmos_addr).await?;
+
+        let wallet = ctx.signer(cosmos_addr, args.origin.as_ref())?;
+
+        // Broadcasts of the earlier run without a known result are looked up, unknown ones aren't sent again
+        let mut unconfirmed = Vec::new();
+        for (line, txhash) in checkpoint.unconfirmed() {
+            match txfile::inclusion(&client, &txhash).await? {
+                Some(inclusion) => {
+                    checkpoint.lines.insert(
+                        line,
+                        batch::Sent {
+                            txhash,
+                            code: Some(inclusion.code),
+                        },
+                    );
+                }
+                None => unconfirmed.push(batch::Outcome {
+                    line,
+                    destination: String::new(),
+                    amount: String::new(),
+                    error: Some(format!(
+                        "Broadcast as {} by the earlier run but not included yet, \
+                         check with `cli-tool tx status {}` and resume again",
+                        txhash, txhash
+                    )),
+                    txhash: Some(txhash),
+                    code: None,
+                }),
+            }
+        }
+        let completed = checkpoint.completed();
+        if args.resume {
+            tracing::info!(
+                "Resuming, {} of {} lines were sent by the earlier run",
+                completed.len(),
+                lines.len()
+            );
+        }
+
+        // Module accounts reject bank sends or swallow the funds
+        let modules = auth::module_accounts(&client).await?;
+        let units =
+            amount::Units::query(&client, cosmos_addr.get_cosmos_builder().chain_id()).await;
+        let mut requests = Vec::new();
+        for row in rows {
+            if completed.contains(&row.line) {
+                continue;
+            }
+            if let Some(outcome) = unconfirmed
+                .iter_mut()
+                .find(|outcome| outcome.line == row.line)
+            {
+                outcome.destination = row.destination.to_string();
+                outcome.amount = row.amount.to_string();
+                continue;
+            }
+            validate::check_destination(
+                &client,
+                row.destination,
+                wallet.get_address(),
+                args.require_existing,
+            )
+            .await
+            .with_context(|| format!("Line {}", row.line))?;
+            if let Some(module) = modules
+                .iter()
+                .find(|module| module.address == row.destination.to_string())
+            {
+                return Err(anyhow!(
+                    "Line {}: {} is the account of the {} module, which doesn't accept sends",
+                    row.line,
+                    row.destination,
+                    module.name
+                ));
+            }
+            let coin = units
+                .resolve(&row.amount)
+                .with_context(|| format!("Line {}: invalid amount {}", row.line, row.amount))?;
+            requests.push((
+                row.line,
+                SendRequest {
+                    coin,
+                    destination: row.destination,
+                    cw20: None,
+                },
+            ));
+        }
+
+        // Nothing is sent unless the wallet covers the whole batch
+        let planned = batch::plan(&chain, &wallet, &requests).await?;
+        anyhow::Ok((
+            chain,
+            wallet,
+            requests,
+            planned,
+            unconfirmed,
+            completed.len(),
+        ))
+    };
+    let (chain, wallet, requests, planned, unconfirmed, sent_before) =
+        tasks::until_cancelled(&ctx.shutdown, prepare).await?;
+
+    let display = ctx.formatter(chain.cosmos());
+    tracing::info!(
+        "Sending {} in {} transactions from {}, {} at a time",
+        display.coins(&planned.coins()),
+        requests.len(),
+        wallet,
+        args.concurrency
+    );
+
+    // SIGUSR1 reports how far the batch got without stopping it
+    let progress = Arc::new(batch::Progress::with_checkpoint(
+        checkpoint_path.clone(),
+        checkpoint,
+    ));
+    let reported = progress.clone();
+    let _reporting = status::report_on_sigusr1(
+        &ctx.shutdown,
+        ctx.status_file.clone(),
+        move || serde_json::json!({ "batch": reported.snapshot() }),
+    );
+    let mut outcomes = batch::run(
+        &chain,
+        &wallet,
+        requests,
+        &ctx.config.policy,
+        args.concurrency,
+        &progress,
+        &ctx.shutdown,
+    )
+    .await?;
+    outcomes.extend(unconfirmed);
+    outcomes.sort_by_key(|outcome| outcome.line);
+
+    // The checkpoint is only needed until every line went through
+    let completed = progress
+        .checkpoint()
+        .map(|checkpoint| checkpoint.completed())
+        .unwrap_or_default();
+    if lines.iter().all(|line| completed.contains(line)) {
+        if let Err(error) = std::fs::remove_file(&checkpoint_path) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!(
+                    "Error removing batch checkpoint {}: {}",
+                    checkpoint_path.display(),
+                    error
+                );
+            }
+        }
+    }
+
+    let sent = batch::sent(&outcomes)?;
+    let succeeded = outcomes
+        .iter()
+        .filter(|outcome| outcome.succeeded())
+        .count();
+    ctx.print(&outcomes, || {
+        outcomes
+            .iter()
+            .map(|outcome| {
+                format!(
+                    "line {} {} {} {}",
+                    outcome.line,
+                    outcome.destination,
+                    display.coin_list(&outcome.amount),
+                    match (&outcome.error, &outcome.txhash) {
+                        (Some(error), _) => format!("failed: {}", error),
+                        (None, Some(txhash)) => format!("txhash {}", txhash),
+                        (None, None) => "-".to_owned(),
+                    }
+                )
+            })
+            .chain(std::iter::once(format!(
+                "sent {} in {} of {} transactions{}",
+                if sent.is_empty() {
+                    "nothing".to_owned()
+                } else {
+                    display.coins(&sent.coins())
+                },
+                succeeded,
+                outcomes.len(),
+                match sent_before {
+                    0 => String::new(),
+                    sent_before => format!(", {} lines were sent by the earlier run", sent_before),
+                }
+            )))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+
+    let not_sent = outcomes
+        .iter()
+        .filter(|outcome| outcome.error.as_deref() == Some(batch::NOT_SENT))
+        .count();
+    if not_sent > 0 {
+        return Err(anyhow!(
+            "Interrupted with {} of {} sends not started, run the same command with --resume to send them",
+            not_sent,
+            outcomes.len()
+        ));
+    }
+    let failed = outcomes.len() - succeeded;
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} sends failed, run the same command with --resume to retry them",
+            failed,
+            outcomes.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Sends every line of a manifest, chains and keys concurrently, and reports a table of the outcomes
+async fn send_manifest(ctx: &AppContext, args: &SendManifestArgs) -> Result<()> {
+    // Read the file before connecting, a bad file fails fast
+    let input = if args.file.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Error reading sends from stdin")?
+    } else {
+        std::fs::read_to_string(&args.file)
+            .with_context(|| format!("Error reading sends from {}", args.file.display()))?
+    };
+    let groups = manifest::group(manifest::parse(&input)?);
+
+    // Unknown chains and keys fail before connecting anywhere
+    for (chain, wallets) in &groups {
+        ctx.for_chain(chain)
+            .with_context(|| format!("Invalid chain {} in the manifest", chain))?;
+        for from in wallets.keys() {
+            keys::seed_phrase(&ctx.config, from)?;
+        }
+    }
+
+    // SIGUSR1 reports how far each key got on each chain
+    let progress: BTreeMap<String, Arc<batch::Progress>> = groups
+        .iter()
+        .flat_map(|(chain, wallets)| {
+            wallets
+                .keys()
+                .map(move |from| (format!("{}/{}", chain, from), Arc::default()))
+        })
+        .collect();
+    let reported = progress.clone();
+    let _reporting = status::report_on_sigusr1(&ctx.shutdown, ctx.status_file.clone(), move || {
+        let batches: BTreeMap<_, _> = reported
+            .iter()
+            .map(|(name, progress)| (name.clone(), progress.snapshot()))
+            .collect();
+        serde_json::json!({ "manifest": batches })
+    });
+
+    tracing::info!(
+        "Sending from {} keys on {} chains",
+        progress.len(),
+        groups.len()
+    );
+    let mut outcomes: Vec<_> = futures::future::join_all(
+        groups
+            .into_iter()
+            .map(|(chain, wallets)| send_manifest_chain(ctx, chain, wallets, args, &progress)),
+    )
+    .await
+    .into_iter()
+    .flatten()
+    .collect();
+    outcomes.sort_by_key(|outcome| outcome.send.line);
+
+    let chains = manifest::summarize(&outcomes)?;
+    let json = serde_json::json!({ "sends": outcomes, "chains": chains });
+    ctx.print(&json, || {
+        let width = |column: fn(&manifest::Outcome) -> &str| {
+            outcomes
+                .iter()
+                .map(|outcome| column(outcome).len())
+                .max()
+                .unwrap_or_default()
+        };
+        let (chain_width, from_width) = (
+            width(|outcome| outcome.chain.as_str()),
+            width(|outcome| outcome.from.as_str()),
+        );
+        outcomes
+            .iter()
+            .map(|outcome| {
+                format!(
+                    "line {:<4} {:<chain_width$} {:<from_width$} {} {} {}",
+                    outcome.send.line,
+                    outcome.chain,
+                    outcome.from,
+                    outcome.send.destination,
+                    outcome.send.amount,
+                    match (&outcome.send.error, &outcome.send.txhash) {
+                        (Some(error), _) => format!("failed: {}", error),
+                        (None, Some(txhash)) => format!("txhash {}", txhash),
+                        (None, None) => "-".to_owned(),
+                    }
+                )
+            })
+            .chain(chains.iter().map(|summary| {
+                format!(
+                    "{:<chain_width$} sent {} in {} of {} transactions",
+                    summary.chain,
+                    if summary.sent.is_empty() {
+                        "nothing".to_owned()
+                    } else {
+                        tx::format_coins(&summary.sent.coins())
+                    },
+                    summary.succeeded,
+                    summary.succeeded + summary.failed
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+
+    let failed = outcomes
+        .iter()
+        .filter(|outcome| !outcome.send.succeeded())
+        .count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} sends failed", failed, outcomes.len()));
+    }
+    Ok(())
+}
+
+/// Sends the lines of a manifest on one chain over one connection, the keys concurrently.
+///
+/// A chain that can't be reached, or a key with a line failing its checks,
+/// is reported as failed sends without sending anything for it.
+async fn send_manifest_chain(
+    ctx: &AppContext,
+    chain_name: String,
+    wallets: BTreeMap<String, Vec<manifest::Row>>,
+    args: &SendManifestArgs,
+    progress: &BTreeMap<String, Arc<batch::Progress>>,
+) -> Vec<manifest::Outcome> {
+    let failed = |rows: &[manifest::Row], error: anyhow::Error| {
+        rows.iter()
+            .map(|row| manifest::Outcome::failed(row, &error))
+            .collect::<Vec<_>>()
+    };
+
+    // Sequences are tracked locally for every key of the chain
+    let connected = tasks::until_cancelled(&ctx.shutdown, async {
+        let chain = ctx
+            .for_chain(&chain_name)?
+            .connect()
+            .await?
+            .with_sequence_cache();
+        let client = grpc::Client::connect(chain.cosmos()).await?;
+        let modules = auth::module_accounts(&client).await?;
+        let units =
+            amount::Units::query(&client, chain.cosmos().get_cosmos_builder().chain_id()).await;
+        anyhow::Ok((chain, client, modules, units))
+    })
+    .await;
+    let (chain, client, modules, units) = match connected {
+        Ok(connected) => connected,
+        Err(error) => {
+            let rows: Vec<_> = wallets.into_values().flatten().collect();
+            return failed(
+                &rows,
+                error.context(format!("Error connecting to {}", chain_name)),
+            );
+        }
+    };
+    let cosmos_addr = chain.cosmos();
+
+    let runs = wallets.into_iter().map(|(from, rows)| {
+        let (chain, chain_name, client, modules, units) =
+            (&chain, &chain_name, &client, &modules, &units);
+        async move {
+            let prepared = tasks::until_cancelled(&ctx.shutdown, async {
+                let wallet = ctx.wallet(cosmos_addr, &keys::seed_phrase(&ctx.config, &from)?)?;
+                let mut requests = Vec::new();
+                for row in &rows {
+                    validate::check_destination(
+                        client,
+                        row.destination,
+                        wallet.get_address(),
+                        args.require_existing,
+                    )
+                    .await
+                    .with_context(|| format!("Line {}", row.line))?;
+
+                    // Module accounts reject bank sends or swallow the funds
+                    if let Some(module) = modules
+                        .iter()
+                        .find(|module| module.address == row.destination.to_string())
+                    {
+                        return Err(anyhow!(
+                            "Line {}: {} is the account of the {} module, which doesn't accept sends",
+                            row.line,
+                            row.destination,
+                            module.name
+                        ));
+                    }
+                    let coin = units
+                        .resolve(&row.amount)
+                        .with_context(|| {
+                            format!("Line {}: invalid amount {}", row.line, row.amount)
+                        })?;
+                    requests.push((
+                        row.line,
+                        SendRequest {
+                            coin,
+                            destination: row.destination,
+                            cw20: None,
+                        },
+                    ));
+                }
+                anyhow::Ok((wallet, requests))
+            })
+            .await;
+
+            match prepared {
+                Ok((wallet, requests)) => {
+                    let progress = &progress[&format!("{}/{}", chain_name, from)];
+                    let sends = manifest::WalletSends {
+                        chain: chain_name.clone(),
+                        from,
+                        requests,
+                    };
+                    manifest::run_wallet(
+                        chain,
+                        &wallet,
+                        sends,
+                        &ctx.config.policy,
+                        args.concurrency,
+                        progress,
+                        &ctx.shutdown,
+                    )
+                    .await
+                }
+                Err(error) => failed(&rows, error),
+            }
+        }
+    });
+    futures::future::join_all(runs)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Runs a governance command
+async fn governance(ctx: &AppContext, command: GovCommand) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let client = grpc::Client::connect(cosmos_addr).await?;
+
+    match command {
+        GovCommand::Proposals => {
+            let proposals = gov::active_proposals(&client).await?;
+            ctx.print(&proposals, || {
+                proposals
+                    .iter()
+                    .map(|proposal| {
+                        format!(
+                            "#{} [{}] {} (ends {}) yes {} no {} abstain {} veto {}",
+                            proposal.id,
+                            proposal.status,
+                            proposal.title,
+                            proposal.voting_end.as_deref().unwrap_or("-"),
+                            proposal.tally.yes,
+                            proposal.tally.no,
+                            proposal.tally.abstain,
+                            proposal.tally.no_with_veto
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        GovCommand::Show { id } => {
+            let proposal = gov::show(&client, id).await?;
+            ctx.print(&proposal, || {
+                format!(
+                    "#{} {}\nStatus: {}\nVoting ends: {}\nTally: yes {} no {} abstain {} veto {}\n\n{}",
+                    proposal.id,
+                    proposal.title,
+                    proposal.status,
+                    proposal.voting_end.as_deref().unwrap_or("-"),
+                    proposal.tally.yes,
+                    proposal.tally.no,
+                    proposal.tally.abstain,
+                    proposal.tally.no_with_veto,
+                    proposal.summary
+                )
+            })
+        }
+        GovCommand::Vote { id, option, origin } => {
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            tracing::info!("Voting {:?} on proposal {} as {}", option, id, wallet);
+
+            let result = tx::broadcast(
+                &chain,
+                &wallet,
+                vec![gov::vote(wallet.get_address(), id, option)],
+                &format!("proposal {}", id),
+                "",
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+    }
+}
+
+/// Runs an authz command
+async fn authz_command(ctx: &AppContext, command: AuthzCommand) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+
+    match command {
+        AuthzCommand::Grant {
+            grantee,
+            authorization,
+        } => {
+            let (authorization, grant) = match authorization {
+                GrantAuthorization::Send {
+                    spend_limit,
+                    allow_list,
+                    grant,
+                } => {
+                    let mut limit = Vec::new();
+                    for coin in spend_limit.split(',') {
+                        limit.push(amount::resolve(cosmos_addr, &coin.parse()?).await?);
+                    }
+                    let authorization = authz::Authorization::Send {
+                        spend_limit: limit,
+                        allow_list,
+                    };
+                    (authorization, grant)
+                }
+                GrantAuthorization::Generic { msg_type, grant } => {
+                    (authz::Authorization::Generic { msg_type }, grant)
+                }
+            };
+
+            let wallet = ctx.signer(cosmos_addr, grant.origin.as_ref())?;
+            if let authz::Authorization::Send { spend_limit, .. } = &authorization {
+                tracing::info!(
+                    "Granting {} sends of up to {}",
+                    grantee,
+                    tx::format_coins(spend_limit)
+                );
+            }
+            let message = authz::grant(
+                wallet.get_address(),
+                grantee,
+                &authorization,
+                expiration(grant.expires_in)?,
+            );
+            // Nothing moves until the grantee sends, so spend limits don't count the grant
+            let result =
+                tx::broadcast(&chain, &wallet, vec![message], &grantee.to_string(), "").await?;
+            report(ctx, result.into())
+        }
+        AuthzCommand::Revoke {
+            grantee,
+            msg_type,
+            origin,
+        } => {
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            let message = authz::revoke(
+                wallet.get_address(),
+                grantee,
+                &authz::msg_type_url(&msg_type),
+            );
+            let result =
+                tx::broadcast(&chain, &wallet, vec![message], &grantee.to_string(), "").await?;
+            report(ctx, result.into())
+        }
+        AuthzCommand::Exec(ExecMessage::Send {
+            granter,
+            coin,
+            destination,
+            require_existing,
+            to_contract,
+            override_limit,
+            yes,
+            origin,
+        }) => {
+            let coin = amount::resolve(cosmos_addr, &coin.parse()?).await?;
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+
+            // Delegated sends go through the same checks as direct ones, spending the granter's funds
+            let client = grpc::Client::connect(cosmos_addr).await?;
+            let note = check_send_destination(
+                &client,
+                destination,
+                granter,
+                require_existing,
+                to_contract,
+            )
+            .await?;
+            if let Some(note) = note {
+                confirm(&note, yes).await?;
+            }
+            let history = journal::read_all()?;
+            let chain_id = cosmos_addr.get_cosmos_builder().chain_id();
+            policy::check_destination(
+                &ctx.config.policy,
+                &history,
+                chain_id,
+                &destination.to_string(),
+                &coin,
+            )?;
+            spend_policy(
+                ctx,
+                chain_id,
+                &granter.to_string(),
+                std::slice::from_ref(&coin),
+                override_limit,
+            )
+            .await?;
+
+            let amount = tx::format_coins(std::slice::from_ref(&coin));
+            let message = authz::exec(
+                wallet.get_address(),
+                vec![tx::msg_send(granter, destination, vec![coin])],
+            );
+            let result = tx::broadcast_for(
+                &chain,
+                &wallet,
+                granter,
+                vec![message],
+                &destination.to_string(),
+                &amount,
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+        AuthzCommand::Exec(ExecMessage::Vote {
+            granter,
+            id,
+            option,
+            origin,
+        }) => {
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            tracing::info!(
+                "Voting {:?} on proposal {} for {} as {}",
+                option,
+                id,
+                granter,
+                wallet
+            );
+            let message = authz::exec(wallet.get_address(), vec![gov::vote(granter, id, option)]);
+            let result = tx::broadcast(
+                &chain,
+                &wallet,
+                vec![message],
+                &format!("proposal {}", id),
+                "",
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+        AuthzCommand::List { address } => {
+            let client = grpc::Client::connect(cosmos_addr).await?;
+            let grants = authz::list(&client, address).await?;
+            ctx.print(&grants, || {
+                grants
+                    .iter()
+                    .map(|grant| {
+                        format!(
+                            "{} -> {} {} limit {} expires {}",
+                            grant.granter,
+                            grant.grantee,
+                            grant.msg_type,
+                            grant.spend_limit.as_deref().unwrap_or("none"),
+                            grant.expiration.as_deref().unwrap_or("never")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+    }
+}
+
+/// Runs a feegrant command
+async fn feegrant_command(ctx: &AppContext, command: FeegrantCommand) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+
+    match command {
+        FeegrantCommand::Grant {
+            grantee,
+            spend_limit,
+            expires_in,
+            origin,
+        } => {
+            let mut limit = Vec::new();
+            for coin in spend_limit.iter().flat_map(|limit| limit.split(',')) {
+                limit.push(amount::resolve(cosmos_addr, &coin.parse()?).await?);
+            }
+            let expiration = expiration(expires_in)?;
+
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            if !limit.is_empty() {
+                tracing::info!(
+                    "Granting {} fees of up to {}",
+                    grantee,
+                    tx::format_coins(&limit)
+                );
+            }
+            // Nothing moves until the grantee pays fees, so spend limits don't count the allowance
+            let result = tx::broadcast(
+                &chain,
+                &wallet,
+                vec![feegrant::grant(
+                    wallet.get_address(),
+                    grantee,
+                    limit,
+                    expiration,
+                )],
+                &grantee.to_string(),
+                "",
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+        FeegrantCommand::Revoke { grantee, origin } => {
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            let result = tx::broadcast(
+                &chain,
+                &wallet,
+                vec![feegrant::revoke(wallet.get_address(), grantee)],
+                &grantee.to_string(),
+                "",
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+        FeegrantCommand::List { address } => {
+            let client = grpc::Client::connect(cosmos_addr).await?;
+            let allowances = feegrant::list(&client, address).await?;
+            ctx.print(&allowances, || {
+                allowances
+                    .iter()
+                    .map(|allowance| {
+                        format!(
+                            "{} -> {} {} limit {} expires {}",
+                            allowance.granter,
+                            allowance.grantee,
+                            allowance.kind,
+                            allowance.spend_limit.as_deref().unwrap_or("none"),
+                            allowance.expiration.as_deref().unwrap_or("never")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+    }
+}
+
+/// Time at which something granted now for `expires_in` expires
+fn expiration(
+    expires_in: Option<std::time::Duration>,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    expires_in
+        .map(|duration| {
+            chrono::Duration::from_std(duration)
+                .map(|duration| chrono::Utc::now() + duration)
+                .context("Invalid expiration")
+        })
+        .transpose()
+}
+
+/// Runs an IBC command
+async fn ibc_command(ctx: &AppContext, command: IbcCommand) -> Result<()> {
+    match command {
+        IbcCommand::Transfer {
+            coin,
+            receiver,
+            channel,
+            timeout,
+            max_pending,
+            skip_health_check,
+            override_limit,
+            origin,
+        } => {
+            // Connect to the blockchain
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+            let client = grpc::Client::connect(cosmos_addr).await?;
+
+            // Refuse to send into a channel the funds can't get through
+            if skip_health_check {
+                tracing::warn!("Skipping health check of channel {}", channel);
+            } else {
+                let health = ibc::channel_health(&client, ibc::TRANSFER_PORT, &channel).await?;
+                tracing::info!(
+                    "Channel {} to {} is {}, client {} is {}, {} pending packets",
+                    health.channel,
+                    health
+                        .counterparty_chain_id
+                        .as_deref()
+                        .unwrap_or("unknown chain"),
+                    health.state,
+                    health.client_id,
+                    health.client_status,
+                    health.pending_packets
+                );
+                ibc::ensure_healthy(&health, max_pending)?;
+            }
+
+            let coin = amount::resolve(cosmos_addr, &coin.parse()?).await?;
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            spend_policy(
+                ctx,
+                cosmos_addr.get_cosmos_builder().chain_id(),
+                &wallet.to_string(),
+                std::slice::from_ref(&coin),
+                override_limit,
+            )
+            .await?;
+            let message = ibc::transfer(wallet.get_address(), &receiver, &channel, &coin, timeout)?;
+
+            let result = tx::broadcast(
+                &chain,
+                &wallet,
+                vec![message],
+                &receiver,
+                &tx::format_coins(std::slice::from_ref(&coin)),
+            )
+            .await?;
+            report(ctx, result.into())
+        }
+        IbcCommand::Refund { txhash, address } => {
+            // Connect to the blockchain
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+            let client = grpc::Client::connect(cosmos_addr).await?;
+
+            let (_, _, response) = cosmos_addr
+                .get_transaction_body(txhash.clone())
+                .await
+                .with_context(|| format!("Error getting transaction {}", txhash))?;
+            if response.code != 0 {
+                return Err(anyhow!(
+                    "Transaction {} failed with code {}, no packet was sent and no tokens left the account",
+                    txhash,
+                    response.code
+                ));
+            }
+            let packet = ibc::sent_packet(&response)?;
+            if let Some(address) = address {
+                if packet.sender != address.to_string() {
+                    return Err(anyhow!(
+                        "Transfer {} was sent by {}, not {}",
+                        txhash,
+                        packet.sender,
+                        address
+                    ));
+                }
+            }
+
+            let status = ibc::packet_status(&client, &packet).await?;
+            let json = serde_json::json!({ "packet": packet, "status": status });
+            ctx.print(&json, || {
+                format!(
+                    "Packet {} on {}/{}: {} from {} to {}\n{}",
+                    packet.sequence,
+                    packet.src_port,
+                    packet.src_channel,
+                    packet.amount,
+                    packet.sender,
+                    packet.receiver,
+                    status.describe(&packet, cosmos_addr.get_cosmos_builder().chain_id())
+                )
+            })
+        }
+    }
+}
+
+/// Runs a CosmWasm command
+async fn wasm_command(ctx: &AppContext, command: WasmCommand) -> Result<()> {
+    match command {
+        WasmCommand::Verify { code_id, source } => {
+            // Connect to the blockchain
+            let chain = ctx.connect().await?;
+            let client = grpc::Client::connect(chain.cosmos()).await?;
+
+            let verification = wasm::verify(&client, code_id, &source).await?;
+            ctx.print(&verification, || {
+                let artifacts = verification
+                    .artifacts
+                    .iter()
+                    .map(|artifact| format!("  {} {}", artifact.hash, artifact.name))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "Code {} uploaded by {}\nOn-chain hash: {}\nArtifacts:\n{}",
+                    code_id, verification.creator, verification.on_chain_hash, artifacts
+                )
+            })?;
+
+            match verification.matched {
+                Some(name) => {
+                    tracing::info!("Code {} matches {}", code_id, name);
+                    Ok(())
+                }
+                None => Err(anyhow!(
+                    "Code {} doesn't match any artifact of the source, don't trust messages to contracts using it",
+                    code_id
+                )),
+            }
+        }
+        WasmCommand::Execute {
+            contract,
+            msgs,
+            funds,
+            profile_gas,
+            verify_source,
+            override_limit,
+            origin,
+        } => {
+            // Connect to the blockchain
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+
+            // Check the code before trusting it with funds
+            if let Some(source) = verify_source {
+                let client = grpc::Client::connect(cosmos_addr).await?;
+                let code_id = wasm::contract_code_id(&client, contract).await?;
+                let verification = wasm::verify(&client, code_id, &source).await?;
+                let Some(name) = verification.matched else {
+                    return Err(anyhow!(
+                        "Code {} of contract {} doesn't match the source (on-chain hash {}), refusing to execute",
+                        code_id,
+                        contract,
+                        verification.on_chain_hash
+                    ));
+                };
+                tracing::info!("Code {} of {} matches {}", code_id, contract, name);
+            }
+
+            let mut coins = Vec::new();
+            for coin in funds.iter().flat_map(|funds| funds.split(',')) {
+                coins.push(amount::resolve(cosmos_addr, &coin.parse()?).await?);
+            }
+            let amount = tx::format_coins(&coins);
+
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            let messages = msgs
+                .iter()
+                .map(|msg| {
+                    let json = serde_json::from_str(msg)
+                        .with_context(|| format!("Invalid JSON message {}", msg))?;
+                    wasm::execute(wallet.get_address(), contract, json, coins.clone())
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            // Every message carries the funds, each transaction moves all of its messages' funds
+            let per_tx = if profile_gas.is_some() {
+                1
+            } else {
+                messages.len()
+            };
+            let mut sent = amount::Totals::default();
+            for coin in std::iter::repeat(&coins).take(per_tx).flatten() {
+                sent.add(coin)?;
+            }
+            let chain_id = cosmos_addr.get_cosmos_builder().chain_id();
+            let policy = spend_policy(
+                ctx,
+                chain_id,
+                &wallet.to_string(),
+                &sent.coins(),
+                override_limit,
+            )
+            .await?;
+
+            let Some(runs) = profile_gas else {
+                let result = tx::broadcast(
+                    &chain,
+                    &wallet,
+                    messages,
+                    &contract.to_string(),
+                    &tx::format_coins(&sent.coins()),
+                )
+                .await?;
+                return report(ctx, result.into());
+            };
+
+            // Each message gets its own transactions so the gas is attributed to it
+            let mut stats = Vec::new();
+            for (text, message) in msgs.iter().zip(messages) {
+                let mut samples = Vec::new();
+                for run in 1..=runs {
+                    // The runs add up towards the daily limit
+                    policy::check_spend(
+                        &policy,
+                        &journal::read_all()?,
+                        chain_id,
+                        &wallet.to_string(),
+                        &sent.coins(),
+                    )?;
+                    let result = tx::broadcast(
+                        &chain,
+                        &wallet,
+                        vec![message.clone()],
+                        &contract.to_string(),
+                        &amount,
+                    )
+                    .await?;
+                    TxOutcome::from(result.clone()).check()?;
+                    tracing::info!(
+                        "Run {}/{} of {}: {} gas used, txhash {}",
+                        run,
+                        runs,
+                        text,
+                        result.gas_used,
+                        result.txhash
+                    );
+                    samples.push(result.gas_used as u64);
+                }
+                stats.push(wasm::GasStats::new(text.clone(), &samples));
+            }
+
+            ctx.print(&stats, || {
+                stats
+                    .iter()
+                    .map(|stats| {
+                        format!(
+                            "{} runs of {}: min {} avg {} max {}",
+                            stats.runs, stats.message, stats.min, stats.avg, stats.max
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+    }
+}
+
+/// Runs a transaction file command
+async fn tx_command(ctx: &AppContext, command: TxCommand) -> Result<()> {
+    let passphrase = || {
+        std::env::var(txfile::PASSPHRASE_ENV)
+            .with_context(|| format!("Set {} to the passphrase", txfile::PASSPHRASE_ENV))
+    };
+
+    match command {
+        TxCommand::Status { txhash } => {
+            let chain = ctx.connect().await?;
+            let client = grpc::Client::connect(chain.cosmos()).await?;
+            let inclusion = txfile::inclusion(&client, &txhash).await?;
+            let json = serde_json::json!({ "txhash": txhash, "included": inclusion });
+            ctx.print(&json, || match &inclusion {
+                Some(inclusion) if inclusion.code == 0 => format!(
+                    "{} was included at height {}, {}",
+                    inclusion.txhash, inclusion.height, inclusion.timestamp
+                ),
+                Some(inclusion) => format!(
+                    "{} was included at height {} but failed with code {}: {}",
+                    inclusion.txhash, inclusion.height, inclusion.code, inclusion.raw_log
+                ),
+                None => format!(
+                    "{} isn't known to the node: it's not included yet, or was dropped from the mempool",
+                    txhash
+                ),
+            })
+        }
+        TxCommand::Export {
+            txhash,
+            format,
+            out,
+            encrypt,
+        } => {
+            // Connect to the blockchain
+            let chain = ctx.connect().await?;
+            let client = grpc::Client::connect(chain.cosmos()).await?;
+
+            let export = txfile::fetch(
+                &client,
+                chain.cosmos().get_cosmos_builder().chain_id(),
+                &txhash,
+            )
+            .await?;
+
+            // The second endpoint must have the same transaction in the same block
+            if let Some(verifier) = ctx.verifier(&chain).await? {
+                verifier.check_block(&client, export.height).await?;
+                let confirmed = txfile::fetch(verifier.client(), &export.chain_id, &txhash)
+                    .await
+                    .with_context(|| format!("Error verifying {} on {}", txhash, verifier.url()))?;
+                if confirmed.height != export.height || confirmed.tx != export.tx {
+                    return Err(anyhow!(
+                        "{} differs on the verification endpoint {}, included at height {} there and {} here",
+                        txhash,
+                        verifier.url(),
+                        confirmed.height,
+                        export.height
+                    ));
+                }
+            }
+            let mut bytes = txfile::encode(&export, format)?;
+            if encrypt {
+                bytes = txfile::encrypt(&bytes, &passphrase()?)?;
+            }
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, &bytes)
+                        .with_context(|| format!("Error writing {}", path.display()))?;
+                    tracing::info!("Exported {} to {}", export.txhash, path.display());
+                }
+                None => std::io::stdout()
+                    .write_all(&bytes)
+                    .context("Error writing the export")?,
+            }
+            Ok(())
+        }
+        TxCommand::Import { file } => {
+            let mut bytes = if file.as_os_str() == "-" {
+                let mut bytes = Vec::new();
+                std::io::stdin()
+                    .read_to_end(&mut bytes)
+                    .context("Error reading the transaction from stdin")?;
+                bytes
+            } else {
+                std::fs::read(&file).with_context(|| format!("Error reading {}", file.display()))?
+            };
+            if txfile::is_encrypted(&bytes) {
+                bytes = txfile::decrypt(&bytes, &passphrase()?)?;
+            }
+
+            let review = txfile::review(&bytes)?;
+            ctx.print(&review, || print_review(&review))
+        }
+        TxCommand::Broadcast {
+            msg_json,
+            proto_descriptors,
+            yes,
+            override_limit,
+            origin,
+        } => {
+            // Messages are encoded before connecting, a bad file fails fast
+            let schemas = custom::Schemas::load(&proto_descriptors)?;
+            let mut messages = Vec::new();
+            for path in &msg_json {
+                let input = if path.as_os_str() == "-" {
+                    std::io::read_to_string(std::io::stdin())
+                        .context("Error reading messages from stdin")?
+                } else {
+                    std::fs::read_to_string(path)
+                        .with_context(|| format!("Error reading {}", path.display()))?
+                };
+                messages.extend(
+                    schemas
+                        .messages(&input)
+                        .with_context(|| format!("In {}", path.display()))?,
+                );
+            }
+
+            // What the messages spend is added up, a type whose spending isn't known is refused
+            let mut sent = amount::Totals::default();
+            let mut unknown = Vec::new();
+            for msg in &messages {
+                match custom::funds(msg)? {
+                    Some(coins) => {
+                        for coin in &coins {
+                            sent.add(coin)?;
+                        }
+                    }
+                    None => unknown.push(msg.type_url.as_str()),
+                }
+            }
+            if !unknown.is_empty() {
+                let reason = format!(
+                    "What {} spends isn't known, it can't be held to the spend limits",
+                    unknown.join(", ")
+                );
+                if !override_limit {
+                    return Err(anyhow!(
+                        "{}. Pass --override-limit to broadcast it anyway",
+                        reason
+                    ));
+                }
+                confirm_override(&reason).await?;
+                tracing::warn!("Spend limits overridden: {}", reason);
+            }
+
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+            let wallet = ctx.signer(cosmos_addr, origin.as_ref())?;
+            spend_policy(
+                ctx,
+                cosmos_addr.get_cosmos_builder().chain_id(),
+                &wallet.to_string(),
+                &sent.coins(),
+                override_limit,
+            )
+            .await?;
+
+            // Only the funds the messages move are checked, not the rest of their contents
+            let types: Vec<_> = messages.iter().map(|msg| msg.type_url.as_str()).collect();
+            let amount = tx::format_coins(&sent.coins());
+            confirm(
+                &format!(
+                    "Signing {} messages as {}: {}, spending {}. Their other contents aren't checked",
+                    messages.len(),
+                    wallet.get_address(),
+                    types.join(", "),
+                    if amount.is_empty() { "nothing" } else { amount.as_str() }
+                ),
+                yes,
+            )
+            .await?;
+            let to = types.join(",");
+            let result = tx::broadcast(&chain, &wallet, messages, &to, &amount).await?;
+            report(ctx, result.into())
+        }
+    }
+}
+
+/// Text form of a decoded transaction file
+fn print_review(review: &txfile::Review) -> String {
+    let mut lines = Vec::new();
+    match (&review.export, review.hash_matches) {
+        (Some(export), Some(false)) => lines.push(format!(
+            "Txhash: {} DOESN'T MATCH the exported {}, these bytes aren't exactly what was broadcast",
+            review.txhash, export.txhash
+        )),
+        (Some(export), _) => {
+            lines.push(format!("Txhash: {} (verified)", review.txhash));
+            lines.push(format!(
+                "Included on {} at height {}, {}",
+                export.chain_id, export.height, export.timestamp
+            ));
+        }
+        (None, _) => lines.push(format!("Txhash: {}", review.txhash)),
+    }
+    if !review.memo.is_empty() {
+        lines.push(format!("Memo: {}", review.memo));
+    }
+    if review.timeout_height != 0 {
+        lines.push(format!("Timeout height: {}", review.timeout_height));
+    }
+
+    let mut fee = format!(
+        "Fee: {}, gas limit {}",
+        review.fee.amount, review.fee.gas_limit
+    );
+    if let Some(payer) = &review.fee.payer {
+        fee.push_str(&format!(", paid by {}", payer));
+    }
+    if let Some(granter) = &review.fee.granter {
+        fee.push_str(&format!(", granted by {}", granter));
+    }
+    lines.push(fee);
+
+    lines.push("Messages:".to_owned());
+    for (index, message) in review.messages.iter().enumerate() {
+        lines.push(format!("  {}. {}", index + 1, message.type_url));
+        let value = serde_json::to_string_pretty(&message.value).unwrap_or_default();
+        lines.extend(value.lines().map(|line| format!("     {}", line)));
+    }
+
+    lines.push("Signatures:".to_owned());
+    for (index, signer) in review.signers.iter().enumerate() {
+        lines.push(format!(
+            "  {}. {} {}, {}, sequence {}",
+            index + 1,
+            signer.key_type.as_deref().unwrap_or("key known on chain"),
+            signer.public_key.as_deref().unwrap_or(""),
+            signer.sign_mode,
+            signer.sequence
+        ));
+        lines.push(format!("     {}", signer.signature));
+    }
+    lines.join("\n")
+}
+
+/// Runs a chain registry command
+async fn chains(ctx: &AppContext, command: ChainsCommand) -> Result<()> {
+    match command {
+        ChainsCommand::List => {
+            let chains: Vec<_> = registry::all()?.into_values().collect();
+            ctx.print(&chains, || {
+                chains
+                    .iter()
+                    .map(|entry| {
+                        let chain = &entry.chain;
+                        format!(
+                            "{} ({}) prefix {} fee {} {} gRPC endpoints [{:?}]",
+                            chain.chain_name,
+                            chain.chain_id,
+                            chain.bech32_prefix,
+                            chain
+                                .fees
+                                .fee_tokens
+                                .first()
+                                .map_or("-", |token| token.denom.as_str()),
+                            chain.apis.grpc.len(),
+                            entry.source
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }
+        ChainsCommand::Update { names } => {
+            let updated = registry::update(&names).await?;
+            tracing::info!(
+                "Updated {} chains in {}",
+                updated.len(),
+                registry::cache_path()?.display()
+            );
+            ctx.print(&updated, || updated.join("\n"))
+        }
+    }
+}
+
+/// Quotes a swap and executes it unless `--dry-run` is given
+async fn swap_command(ctx: &AppContext, args: &SwapArgs) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let client = grpc::Client::connect(cosmos_addr).await?;
+
+    let token_in = amount::resolve(cosmos_addr, &args.coin.parse()?).await?;
+    // The output denom may be a display unit too, e.g. ion
+    let token_out_denom = amount::resolve(
+        cosmos_addr,
+        &amount::HumanAmount {
+            amount: "0".to_owned(),
+            denom: args.to.clone(),
+        },
+    )
+    .await?
+    .denom;
+
+    let quote = swap::quote(
+        &client,
+        args.pool,
+        &token_in,
+        &token_out_denom,
+        args.slippage,
+    )
+    .await?;
+    let display = ctx.formatter(cosmos_addr);
+    ctx.print(&quote, || {
+        format!(
+            "Swap {} in pool {}: expect {} (at least {}), spot price {}, price impact {:.2}%",
+            display.coin_list(&quote.token_in),
+            quote.pool_id,
+            display.amount(&quote.expected_out.to_string(), &quote.token_out_denom),
+            display.amount(&quote.min_out.to_string(), &quote.token_out_denom),
+            quote.spot_price,
+            quote.price_impact
+        )
+    })?;
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let wallet = ctx.signer(cosmos_addr, args.origin.as_ref())?;
+    spend_policy(
+        ctx,
+        cosmos_addr.get_cosmos_builder().chain_id(),
+        &wallet.to_string(),
+        std::slice::from_ref(&token_in),
+        args.override_limit,
+    )
+    .await?;
+    let amount = quote.token_in.clone();
+    let message = swap::swap(wallet.get_address(), token_in, &quote);
+    let result = tx::broadcast(
+        &chain,
+        &wallet,
+        vec![message],
+        &format!("pool {}", args.pool),
+        &amount,
+    )
+    .await?;
+    report(ctx, result.into())
+}
+
+/// Prints every template with its parameters
+fn list_templates(ctx: &AppContext) -> Result<()> {
+    let templates: Vec<_> = templates::all()?.into_values().collect();
+    ctx.print(&templates, || {
+        templates
+            .iter()
+            .map(|template| {
+                let params = template
+                    .params
+                    .iter()
+                    .map(|(name, param)| match &param.default {
+                        Some(default) => {
+                            format!("  --param {}={} {}", name, default, param.description)
+                        }
+                        None => format!("  --param {}=<required> {}", name, param.description),
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}: {}\n{}",
+                    template.name,
+                    template.description,
+                    params.join("\n")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Builds the messages of a template and broadcasts them in one transaction
+async fn run_template(
+    ctx: &AppContext,
+    name: &str,
+    params: Vec<(String, String)>,
+    dry_run: bool,
+    origin: Option<&cosmos::SeedPhrase>,
+) -> Result<()> {
+    let template = templates::all()?
+        .remove(name)
+        .ok_or_else(|| anyhow!("Template {} not found, see `templates`", name))?;
+
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let wallet = ctx.signer(cosmos_addr, origin)?;
+
+    let (messages, sends) = templates::build(
+        cosmos_addr,
+        &template,
+        wallet.get_address(),
+        params.into_iter().collect(),
+    )
+    .await?;
+    for message in &messages {
+        tracing::info!("{}", message.description);
+    }
+    if dry_run {
+        let json: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                serde_json::json!({ "type_url": message.type_url, "description": message.description })
+            })
+            .collect();
+        return ctx.print(&json, || {
+            messages
+                .iter()
+                .map(|message| message.description.clone())
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+    }
+
+    // Sends in a template are held to the same limits as `send`
+    let history = journal::read_all()?;
+    for (destination, coin) in &sends {
+        policy::check_destination(
+            &ctx.config.policy,
+            &history,
+            cosmos_addr.get_cosmos_builder().chain_id(),
+            &destination.to_string(),
+            coin,
+        )?;
+    }
+    let sent: Vec<_> = sends.into_iter().map(|(_, coin)| coin).collect();
+    policy::check_spend(
+        &ctx.config.policy,
+        &history,
+        cosmos_addr.get_cosmos_builder().chain_id(),
+        &wallet.to_string(),
+        &sent,
+    )?;
+
+    let result = tx::broadcast(
+        &chain,
+        &wallet,
+        messages,
+        &format!("template {}", name),
+        &tx::format_coins(&sent),
+    )
+    .await?;
+    report(ctx, result.into())
+}
+
+/// Runs a key command
+async fn keys(ctx: &AppContext, command: KeysCommand) -> Result<()> {
+    match command {
+        KeysCommand::Show {
+            name,
+            pubkey,
+            format,
+        } => {
+            // Watch-only keys have nothing to derive
+            if ctx
+                .config
+                .keys
+                .get(&name)
+                .is_some_and(|key| key.watch_only())
+            {
+                if pubkey {
+                    return Err(anyhow!(
+                        "Key {} is watch-only, only its address is known",
+                        name
+                    ));
+                }
+                let address = keys::address(&ctx.config, &name)?
+                    .ok_or_else(|| anyhow!("Key {} has no address", name))?;
+                let json = serde_json::json!({
+                    "name": name,
+                    "address": address.to_string(),
+                    "watch_only": true,
+                });
+                return ctx.print(&json, || format!("{} (watch-only)", address));
+            }
+
+            // Addresses depend on the network's bech32 prefix
+            let chain = ctx.connect().await?;
+            let cosmos_addr = chain.cosmos();
+            let seed = keys::seed_phrase(&ctx.config, &name)?;
+            let wallet = ctx.wallet(cosmos_addr, &seed)?;
+
+            if pubkey {
+                let hrp = cosmos_addr.get_address_hrp();
+                let key = keys::format_pubkey(wallet.public_key_bytes(), hrp.as_str(), format)?;
+                let json = serde_json::json!({
+                    "name": name,
+                    "address": wallet.to_string(),
+                    "pubkey": key,
+                });
+                return ctx.print(&json, || key.clone());
+            }
+
+            let json = serde_json::json!({ "name": name, "address": wallet.to_string() });
+            ctx.print(&json, || wallet.to_string())
+        }
+        KeysCommand::Import {
+            name,
+            address,
+            watch_only: _,
+        } => {
+            let path = config::path()?;
+            keys::import_watch_only(&path, &name, address)?;
+            let json = serde_json::json!({
+                "name": name,
+                "address": address.to_string(),
+                "watch_only": true,
+                "config": path,
+            });
+            ctx.print(&json, || {
+                format!(
+                    "Added watch-only key {} for {} to {}",
+                    name,
+                    address,
+                    path.display()
+                )
+            })
+        }
+    }
+}
+
+/// Resolves an argument naming an address or a key, deriving the address of keys with a mnemonic
+fn address_arg(ctx: &AppContext, cosmos: &cosmos::Cosmos, input: &str) -> Result<cosmos::Address> {
+    match keys::address(&ctx.config, input)? {
+        Some(address) => Ok(address),
+        None => Ok(ctx
+            .wallet(cosmos, &keys::seed_phrase(&ctx.config, input)?)?
+            .get_address()),
+    }
+}
+
+/// Prints the balances of an address
+async fn balance(ctx: &AppContext, args: &BalanceArgs) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+
+    if let Some(BalanceCommand::Diff { a, b }) = &args.command {
+        let a = address_arg(ctx, cosmos_addr, a)?;
+        let b = address_arg(ctx, cosmos_addr, b)?;
+        return balance_diff(ctx, cosmos_addr, a, b).await;
+    }
+    let address = args
+        .address
+        .as_deref()
+        .ok_or_else(|| anyhow!("Missing address to query"))?;
+    let address = address_arg(ctx, cosmos_addr, address)?;
+
+    // CW20 tokens live in the contract, not in the bank module
+    if let Some(contract) = args.cw20 {
+        let balance = cosmos::Coin {
+            denom: cw20::denom(contract),
+            amount: cw20::balance(cosmos_addr, contract, address).await?,
+        };
+        return print_balances(ctx, &ctx.formatter(cosmos_addr), &[balance]).await;
+    }
+
+    let balances = cosmos_addr
+        .all_balances(address)
+        .await
+        .context("Failed to retrieve all balances for the Cosmos address")?;
+
+    // The bank balance of a vesting account includes tokens it can't spend yet
+    let client = grpc::Client::connect(cosmos_addr).await?;
+    let display = ctx.formatter(cosmos_addr);
+    if let Some(schedule) = vesting::schedule(&client, address).await? {
+        let spendable = vesting::spendable(&client, address).await?;
+        return print_vesting(ctx, &display, &balances, &spendable, &schedule);
+    }
+    print_balances(ctx, &display, &balances).await
+}
+
+/// Prints the total, spendable and locked balances and the schedule of a vesting account
+fn print_vesting(
+    ctx: &AppContext,
+    display: &display::Formatter,
+    balances: &[cosmos::Coin],
+    spendable: &[cosmos::Coin],
+    schedule: &vesting::Schedule,
+) -> Result<()> {
+    // Locked is what the bank holds beyond what can be spent
+    let diffs = balances::diff(spendable, balances)?;
+    let json = serde_json::json!({
+        "balances": diffs
+            .iter()
+            .map(|diff| serde_json::json!({
+                "denom": diff.denom,
+                "amount": diff.b.to_string(),
+                "spendable": diff.a.to_string(),
+                "locked": diff.b.saturating_sub(diff.a).to_string(),
+                "display": display.amount(&diff.b.to_string(), &diff.denom),
+            }))
+            .collect::<Vec<_>>(),
+        "vesting": schedule,
+    });
+    ctx.print(&json, || {
+        let mut lines: Vec<String> = diffs
+            .iter()
+            .map(|diff| {
+                format!(
+                    "Denom: {}, Balance: {}, Spendable: {}, Locked: {}",
+                    display.denom(&diff.denom),
+                    display.amount(&diff.b.to_string(), &diff.denom),
+                    display.amount(&diff.a.to_string(), &diff.denom),
+                    display.amount(&diff.b.saturating_sub(diff.a).to_string(), &diff.denom)
+                )
+            })
+            .collect();
+        lines.push(format!(
+            "Vesting: {} {}, from {} until {}",
+            schedule.kind,
+            display.coin_list(&schedule.original_vesting),
+            schedule.start.as_deref().unwrap_or("-"),
+            schedule.end.as_deref().unwrap_or("never")
+        ));
+        for period in &schedule.periods {
+            lines.push(format!(
+                "  {} vests at {}",
+                display.coin_list(&period.amount),
+                period.end
+            ));
+        }
+        lines.join("\n")
+    })
+}
+
+/// Prints the balances of every address in a file and their per-denom totals
+async fn balance_all(ctx: &AppContext, args: &BalanceAllArgs) -> Result<()> {
+    // Read the addresses before connecting, a bad file fails fast
+    let input = if args.file.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Error reading addresses from stdin")?
+    } else {
+        std::fs::read_to_string(&args.file)
+            .with_context(|| format!("Error reading addresses from {}", args.file.display()))?
+    };
+    let addresses = balances::parse_addresses(&input)?;
+
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+
+    tracing::info!(
+        "Scanning {} addresses, {} at a time",
+        addresses.len(),
+        args.concurrency
+    );
+    let scan = balances::scan(&chain, addresses, args.concurrency).await?;
+
+    let format = args.format.unwrap_or(match ctx.output {
+        Output::Json => ScanFormat::Json,
+        Output::Text => ScanFormat::Csv,
+    });
+    match format {
+        ScanFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&scan).context("Error serializing output")?
+        ),
+        ScanFormat::Csv => print!("{}", balances::to_csv(&scan)),
+    }
+    Ok(())
+}
+
+/// Prints the per-denom differences between the balances of two addresses
+async fn balance_diff(
+    ctx: &AppContext,
+    cosmos_addr: &cosmos::Cosmos,
+    a: cosmos::Address,
+    b: cosmos::Address,
+) -> Result<()> {
+    // Fetch both concurrently
+    let (balances_a, balances_b) = tokio::try_join!(
+        async {
+            cosmos_addr
+                .all_balances(a)
+                .await
+                .with_context(|| format!("Failed to retrieve balances for {}", a))
+        },
+        async {
+            cosmos_addr
+                .all_balances(b)
+                .await
+                .with_context(|| format!("Failed to retrieve balances for {}", b))
+        },
+    )?;
+
+    let diffs = balances::diff(&balances_a, &balances_b)?;
+    let display = ctx.formatter(cosmos_addr);
+    ctx.print(&diffs, || {
+        diffs
+            .iter()
+            .map(|diff| {
+                format!(
+                    "Denom: {}, {}: {}, {}: {}, Difference: {}",
+                    display.denom(&diff.denom),
+                    a,
+                    display.amount(&diff.a.to_string(), &diff.denom),
+                    b,
+                    display.amount(&diff.b.to_string(), &diff.denom),
+                    display.amount(&diff.difference, &diff.denom)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Prints a list of balances in the requested output format
+async fn print_balances(
+    ctx: &AppContext,
+    display: &display::Formatter,
+    balances: &[cosmos::Coin],
+) -> Result<()> {
+    // Fiat annotations are opt-in
+    let prices = ctx.prices().await;
+
+    // Proto coins don't implement Serialize
+    let json: Vec<_> = balances
+        .iter()
+        .map(|coin| {
+            let fiat = prices.as_ref().and_then(|prices| prices.value(coin));
+            serde_json::json!({
+                "denom": coin.denom,
+                "amount": coin.amount,
+                "display": display.coin(coin),
+                "fiat": fiat,
+            })
+        })
+        .collect();
+    ctx.print(&json, || {
+        balances
+            .iter()
+            .map(|balance| {
+                format!(
+                    "Denom: {}, Balance: {}{}",
+                    display.denom(&balance.denom),
+                    display.coin(balance),
+                    prices
+                        .as_ref()
+                        .map(|prices| prices.annotate(balance))
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Prints the entries of the local transaction log
+fn log(ctx: &AppContext, command: LogCommand) -> Result<()> {
+    let entries = match command {
+        LogCommand::List { limit } => {
+            let entries = journal::read_all()?;
+            let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+            entries.into_iter().skip(skip).collect()
+        }
+        LogCommand::Show { txhash } => {
+            let entries = journal::find(&txhash)?;
+            if entries.is_empty() {
+                return Err(anyhow!("No journal entries found for txhash {}", txhash));
+            }
+            entries
+        }
+    };
+
+    if ctx.output == Output::Json {
+        return ctx.print(&entries, String::new);
+    }
+
+    for entry in entries {
+        let display = display::Formatter::new(&ctx.config.display, &entry.chain);
+        println!(
+            "{} chain {} from {} to {} amount {} fee {} txhash {} code {}{}",
+            entry.timestamp.to_rfc3339(),
+            entry.chain,
+            entry.from,
+            entry.to,
+            display.coin_list(&entry.amount),
+            entry
+                .fee
+                .as_deref()
+                .map_or_else(|| "-".to_owned(), |fee| display.coin_list(fee)),
+            entry.txhash.as_deref().unwrap_or("-"),
+            entry
+                .code
+                .map_or_else(|| "-".to_owned(), |code| code.to_string()),
+            entry
+                .error
+                .map(|error| format!(" error {}", error))
+                .unwrap_or_default(),
+        );
+    }
+    Ok(())
+}
+
+/// Prints the latest transfers involving an address
+async fn history(ctx: &AppContext, args: &HistoryArgs) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let client = grpc::Client::connect(cosmos_addr).await?;
+    let display = ctx.formatter(cosmos_addr);
+
+    let address = address_arg(ctx, cosmos_addr, &args.address)?;
+    let events = watch::history(&client, &address.to_string(), args.limit).await?;
+    ctx.print(&events, || {
+        if events.is_empty() {
+            return format!("No transfers found for {}", address);
+        }
+        events
+            .iter()
+            .map(|event| {
+                format!(
+                    "height {} txhash {} {:?} {} counterparty {}",
+                    event.height,
+                    event.txhash,
+                    event.direction,
+                    display.coin_list(&event.amount),
+                    event.counterparty
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Prints every transfer involving the watched address until interrupted
+async fn watch(ctx: &AppContext, args: &WatchArgs) -> Result<()> {
+    // Connect to the blockchain
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let display = &ctx.formatter(cosmos_addr);
+
+    let address = address_arg(ctx, cosmos_addr, &args.address)?;
+    let verifier = ctx.verifier(&chain).await?;
+    watch::watch(
+        cosmos_addr,
+        address,
+        args.interval,
+        verifier.as_ref(),
+        &std::sync::atomic::AtomicI64::default(),
+        &ctx.shutdown,
+        |event| async move {
+            ctx.print(&event, || {
+                format!(
+                    "height {} txhash {} {:?} {} counterparty {}",
+                    event.height,
+                    event.txhash,
+                    event.direction,
+                    display.coin_list(&event.amount),
+                    event.counterparty
+                )
+            })?;
+            if let Some(command) = &args.exec {
+                watch::exec(command, &event).await?;
+            }
+            Ok(())
+        },
+    )
+    .await
+}
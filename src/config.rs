@@ -0,0 +1,69 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Deserialize the configuration file
+use serde::Deserialize;
+
+/// Filesystem access
+use std::path::PathBuf;
+
+/// Named profiles
+use std::collections::HashMap;
+
+/// Network profiles
+use crate::context::Profile;
+/// Daemon jobs
+use crate::daemon::DaemonConfig;
+/// Denom display preferences
+use crate::display::DisplayConfig;
+/// Named keys
+use crate::keys::KeyConfig;
+/// Spend policies
+use crate::policy::PolicyConfig;
+
+/// Environment variable which overrides the configuration file location
+pub const CONFIG_ENV: &str = "CLI_TOOL_CONFIG";
+
+/// Tool configuration, read from `config.toml`
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Limits enforced before broadcasting
+    pub policy: PolicyConfig,
+    /// Named network profiles, selected with `--profile`
+    pub profiles: HashMap<String, Profile>,
+    /// Named keys, shown with `keys show`
+    pub keys: HashMap<String, KeyConfig>,
+    /// Jobs run by `daemon`
+    pub daemon: DaemonConfig,
+    /// Denom display preferences by chain id
+    pub display: HashMap<String, DisplayConfig>,
+    /// Command shortcuts, e.g. payroll = "send-batch ~/payroll.csv --profile prod"
+    pub aliases: HashMap<String, String>,
+}
+
+/// Path of the configuration file.
+///
+/// Uses `CLI_TOOL_CONFIG` when set, otherwise the platform config directory,
+/// e.g. `~/.config/cli-tool/config.toml` on Linux.
+pub fn path() -> Result<PathBuf> {
+    match std::env::var_os(CONFIG_ENV) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(dirs::config_dir()
+            .context("Unable to determine the config directory for this platform")?
+            .join("cli-tool")
+            .join("config.toml")),
+    }
+}
+
+/// Loads the configuration file, falling back to defaults when it doesn't exist
+pub fn load() -> Result<Config> {
+    let path = path()?;
+    if !path.exists() {
+        tracing::debug!("No config file at {}, using defaults", path.display());
+        return Ok(Config::default());
+    }
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Error reading config file {}", path.display()))?;
+    toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+}
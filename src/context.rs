@@ -0,0 +1,394 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Locale of typed amounts
+use crate::amount::NumberLocale;
+/// Live chain connections
+use crate::chain::{Chain, ChainClient};
+/// Configuration file
+use crate::config::Config;
+/// Deadlines of chain calls
+use crate::deadline::{self, Phase};
+/// Denom display
+use crate::display;
+/// Named keys selected with `--key`
+use crate::keys;
+/// Fiat prices of amounts
+use crate::prices;
+/// Progress reporting
+use crate::progress;
+/// Chain registry
+use crate::registry;
+/// Custom signing modes
+use crate::signing::SignMode;
+/// Cancellation on Ctrl-C
+use crate::tasks::CancellationToken;
+/// Checking blocks against a second endpoint
+use crate::verify::Verifier;
+
+/// Output format for command results
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Output {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// JSON documents
+    Json,
+}
+
+/// Options shared by every command
+#[derive(clap::Args)]
+pub struct GlobalOpts {
+    /// Network to connect to, e.g. osmosis-testnet, or a chain registry name or chain id, e.g. juno
+    #[clap(long, global = true, env = "COSMOS_NETWORK")]
+    pub network: Option<String>,
+    /// Override the gRPC endpoint of the network
+    #[clap(long, global = true, env = "COSMOS_GRPC")]
+    pub grpc: Option<String>,
+    /// Profile from the config file providing network and endpoint defaults
+    #[clap(long, global = true, env = "CLI_TOOL_PROFILE")]
+    pub profile: Option<String>,
+    /// Output format
+    #[clap(long, global = true, value_enum, default_value_t)]
+    pub output: Output,
+    /// Sign transactions with an explicit signing mode instead of the cosmos crate default
+    #[clap(long, global = true, value_enum)]
+    pub sign_mode: Option<SignMode>,
+    /// Address paying the fees of transactions through a feegrant allowance
+    #[clap(long, global = true)]
+    pub fee_granter: Option<cosmos::Address>,
+    /// Named key signing transactions instead of the COSMOS_WALLET mnemonic, see `keys`
+    #[clap(long, global = true, env = "CLI_TOOL_KEY")]
+    pub key: Option<String>,
+    /// Annotate amounts with their approximate fiat value
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "usd"
+    )]
+    pub show_fiat: Option<prices::Fiat>,
+    /// Increase log verbosity, -v for debug and -vv for trace
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Only print results and errors, without progress or logs
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Serve Prometheus metrics on this address, e.g. 0.0.0.0:9100
+    #[clap(long, global = true, env = "CLI_TOOL_METRICS_ADDR")]
+    pub metrics_addr: Option<std::net::SocketAddr>,
+    /// File the status report of daemon and batch runs is written to on SIGUSR1, stderr if missing
+    #[clap(long, global = true, env = "CLI_TOOL_STATUS_FILE")]
+    pub status_file: Option<std::path::PathBuf>,
+    /// Print how long connecting, simulating, signing, broadcasting and confirming took at the end
+    #[clap(long, global = true)]
+    pub timings: bool,
+    /// Second, independent gRPC endpoint the blocks of height-pinned operations are checked against
+    #[clap(long, global = true, env = "CLI_TOOL_VERIFY_GRPC")]
+    pub verify_grpc: Option<String>,
+    /// How typed amounts separate thousands and decimals, e.g. de for 1.234,56osmo
+    #[clap(
+        long,
+        global = true,
+        env = "CLI_TOOL_NUMBER_LOCALE",
+        value_enum,
+        default_value_t
+    )]
+    pub number_locale: NumberLocale,
+    /// Give up on connecting, a query, a simulation, a broadcast or the wait for inclusion after this long, e.g. 30s
+    #[clap(long, global = true, env = "CLI_TOOL_TIMEOUT", value_parser = humantime::parse_duration)]
+    pub timeout: Option<std::time::Duration>,
+}
+
+/// Network settings of a profile, a `[profiles.<name>]` section of the config file
+#[derive(serde::Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Network to connect to, e.g. osmosis-mainnet
+    pub network: Option<String>,
+    /// gRPC endpoint override
+    pub grpc: Option<String>,
+    /// Second gRPC endpoint blocks are checked against
+    pub verify_grpc: Option<String>,
+}
+
+/// A network to connect to
+#[derive(Clone, Debug)]
+pub enum Network {
+    /// Network built into the cosmos crate
+    Builtin(cosmos::CosmosNetwork),
+    /// Chain from the chain registry
+    Registry(registry::ChainInfo),
+}
+
+impl Network {
+    /// Resolves a network name, built-in networks first, then the chain registry.
+    ///
+    /// ### Errors
+    /// Returns an error if the name is neither, see `chains list`.
+    pub fn resolve(name: &str) -> Result<Network> {
+        if let Ok(network) = name.parse::<cosmos::CosmosNetwork>() {
+            return Ok(Network::Builtin(network));
+        }
+        match registry::find(name)? {
+            Some(chain) => Ok(Network::Registry(chain)),
+            None => Err(anyhow!(
+                "Unknown network {}, see `chains list` or fetch it with `chains update {}`",
+                name,
+                name
+            )),
+        }
+    }
+
+    /// Name shown in logs
+    pub fn name(&self) -> &str {
+        match self {
+            Network::Builtin(network) => network.as_str(),
+            Network::Registry(chain) => &chain.chain_name,
+        }
+    }
+}
+
+/// Global state parsed once and passed to every command handler
+pub struct AppContext {
+    /// Network to connect to
+    pub network: Network,
+    /// gRPC endpoint override
+    pub grpc: Option<String>,
+    /// Name of the active profile, if any
+    pub profile: Option<String>,
+    /// Output format
+    pub output: Output,
+    /// Signing mode requested with `--sign-mode`, if any
+    pub sign_mode: Option<SignMode>,
+    /// Fee granter requested with `--fee-granter`, if any
+    pub fee_granter: Option<cosmos::Address>,
+    /// Named key selected with `--key`, if any
+    pub key: Option<String>,
+    /// Fiat currency for amount annotations, if requested
+    pub show_fiat: Option<prices::Fiat>,
+    /// Log verbosity
+    pub verbose: u8,
+    /// Whether progress and logs are silenced
+    pub quiet: bool,
+    /// Address serving Prometheus metrics, if any
+    pub metrics_addr: Option<std::net::SocketAddr>,
+    /// File status reports are written to, stderr if missing
+    pub status_file: Option<std::path::PathBuf>,
+    /// Whether a timing report is printed after the command
+    pub timings: bool,
+    /// Second gRPC endpoint blocks are checked against, if any
+    pub verify_grpc: Option<String>,
+    /// Configuration file
+    pub config: Config,
+    /// Cancelled on Ctrl-C, long-running commands stop when it is
+    pub shutdown: CancellationToken,
+}
+
+impl AppContext {
+    /// Builds the context from the global options and the configuration file.
+    ///
+    /// Command line flags take precedence over the selected profile, and
+    /// Osmosis Testnet is used when neither names a network.
+    ///
+    /// ### Errors
+    /// Returns an error if the profile doesn't exist or the network is unknown.
+    pub fn new(opts: GlobalOpts, config: Config) -> Result<Self> {
+        // Defaults from the selected profile
+        let profile = match &opts.profile {
+            Some(name) => config
+                .profiles
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Profile {} not found in the config file", name))?,
+            None => Profile::default(),
+        };
+
+        let network = match opts.network.as_ref().or(profile.network.as_ref()) {
+            Some(network) => Network::resolve(network)?,
+            None => Network::Builtin(cosmos::CosmosNetwork::OsmosisTestnet),
+        };
+
+        Ok(AppContext {
+            network,
+            grpc: opts.grpc.or(profile.grpc),
+            profile: opts.profile,
+            output: opts.output,
+            sign_mode: opts.sign_mode,
+            fee_granter: opts.fee_granter,
+            key: opts.key,
+            show_fiat: opts.show_fiat,
+            verbose: opts.verbose,
+            quiet: opts.quiet,
+            metrics_addr: opts.metrics_addr,
+            status_file: opts.status_file,
+            timings: opts.timings,
+            verify_grpc: opts.verify_grpc.or(profile.verify_grpc),
+            config,
+            shutdown: CancellationToken::new(),
+        })
+    }
+
+    /// Context for another profile from the config file, keeping every other global option.
+    ///
+    /// Used by the daemon to run several profiles in one process.
+    pub fn for_profile(&self, name: &str) -> Result<AppContext> {
+        let profile = self
+            .config
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("Profile {} not found in the config file", name))?;
+        let network = match &profile.network {
+            Some(network) => Network::resolve(network)
+                .with_context(|| format!("Invalid network in profile {}", name))?,
+            None => self.network.clone(),
+        };
+
+        Ok(AppContext {
+            network,
+            grpc: profile.grpc.clone(),
+            profile: Some(name.to_owned()),
+            output: self.output,
+            sign_mode: self.sign_mode,
+            fee_granter: self.fee_granter,
+            key: self.key.clone(),
+            show_fiat: self.show_fiat,
+            verbose: self.verbose,
+            quiet: self.quiet,
+            metrics_addr: self.metrics_addr,
+            status_file: self.status_file.clone(),
+            timings: self.timings,
+            verify_grpc: profile.verify_grpc.clone(),
+            config: self.config.clone(),
+            shutdown: self.shutdown.clone(),
+        })
+    }
+
+    /// Context for a profile of the config file, or else for a network by name, keeping every other global option.
+    ///
+    /// Used to send on several chains in one invocation.
+    ///
+    /// ### Errors
+    /// Returns an error if `name` is neither a profile nor a known network.
+    pub fn for_chain(&self, name: &str) -> Result<AppContext> {
+        if self.config.profiles.contains_key(name) {
+            return self.for_profile(name);
+        }
+        Ok(AppContext {
+            network: Network::resolve(name)?,
+            grpc: None,
+            profile: None,
+            output: self.output,
+            sign_mode: self.sign_mode,
+            fee_granter: self.fee_granter,
+            key: self.key.clone(),
+            show_fiat: self.show_fiat,
+            verbose: self.verbose,
+            quiet: self.quiet,
+            metrics_addr: self.metrics_addr,
+            status_file: self.status_file.clone(),
+            timings: self.timings,
+            verify_grpc: None,
+            config: self.config.clone(),
+            shutdown: self.shutdown.clone(),
+        })
+    }
+
+    /// Connects to the configured network
+    #[tracing::instrument(name = "connect", level = "debug", skip_all)]
+    pub async fn connect(&self) -> Result<Chain> {
+        let target = match &self.profile {
+            Some(profile) => format!("{} (profile {})", self.network.name(), profile),
+            None => self.network.name().to_owned(),
+        };
+        let stage = progress::stage(format!("Connecting to {}", target));
+        let builder = deadline::within(Phase::Connect, &target, async {
+            match &self.network {
+                Network::Builtin(network) => {
+                    let mut builder = network.builder().await.with_context(|| {
+                        format!("Error loading settings for {}", self.network.name())
+                    })?;
+                    if let Some(grpc) = &self.grpc {
+                        builder.set_grpc_url(grpc);
+                    }
+                    Ok(builder)
+                }
+                Network::Registry(chain) => registry::builder(chain, self.grpc.as_deref()).await,
+            }
+        })
+        .await?;
+        let cosmos = builder
+            .build()
+            .with_context(|| format!("Error connecting to {}", self.network.name()))?;
+        stage.finish(format!("Connected to {}", target));
+        Ok(Chain::new(cosmos, self.sign_mode).with_fee_granter(self.fee_granter))
+    }
+
+    /// Connects to the second endpoint of `--verify-grpc` or the profile, checking it serves the chain of `chain`.
+    ///
+    /// ### Errors
+    /// Fails if the endpoint can't be reached or serves another chain.
+    pub async fn verifier(&self, chain: &Chain) -> Result<Option<Verifier>> {
+        let Some(url) = &self.verify_grpc else {
+            return Ok(None);
+        };
+        Verifier::connect(url, &chain.chain_id()).await.map(Some)
+    }
+
+    /// Loads a wallet for the connected network from a seed phrase
+    pub fn wallet(
+        &self,
+        cosmos: &cosmos::Cosmos,
+        seed: &cosmos::SeedPhrase,
+    ) -> Result<cosmos::Wallet> {
+        seed.with_hrp(cosmos.get_address_hrp())
+            .context("Error identifying the wallet")
+    }
+
+    /// Wallet signing a command's transactions: the key selected with `--key`,
+    /// otherwise the command's `COSMOS_WALLET` mnemonic.
+    ///
+    /// ### Errors
+    /// Fails if the selected key is watch-only or its mnemonic is missing, or
+    /// if no key is selected and `COSMOS_WALLET` isn't set.
+    pub fn signer(
+        &self,
+        cosmos: &cosmos::Cosmos,
+        origin: Option<&cosmos::SeedPhrase>,
+    ) -> Result<cosmos::Wallet> {
+        match (&self.key, origin) {
+            (Some(name), _) => self.wallet(cosmos, &keys::seed_phrase(&self.config, name)?),
+            (None, Some(seed)) => self.wallet(cosmos, seed),
+            (None, None) => Err(anyhow!(
+                "Set {} to the mnemonic of the signing wallet, or select a named key with --key",
+                keys::DEFAULT_MNEMONIC_ENV
+            )),
+        }
+    }
+
+    /// Formats the amounts of the connected network with its display preferences
+    pub fn formatter(&self, cosmos: &cosmos::Cosmos) -> display::Formatter {
+        display::Formatter::new(&self.config.display, cosmos.get_cosmos_builder().chain_id())
+    }
+
+    /// Loads fiat prices when `--show-fiat` was given
+    pub async fn prices(&self) -> Option<prices::Prices> {
+        prices::load(self.show_fiat?).await
+    }
+
+    /// Prints a result as JSON when requested, otherwise as the given text
+    pub fn print<T: Serialize>(&self, value: &T, text: impl FnOnce() -> String) -> Result<()> {
+        match self.output {
+            Output::Text => println!("{}", text()),
+            Output::Json => println!(
+                "{}",
+                serde_json::to_string(value).context("Error serializing output")?
+            ),
+        }
+        Ok(())
+    }
+}
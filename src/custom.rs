@@ -0,0 +1,204 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Descriptor files
+use std::path::Path;
+
+/// Encode protobuf messages
+use prost::Message;
+
+/// Decode encoded messages
+use base64::Engine;
+
+/// Protobuf messages built from JSON with their schema
+use prost_reflect::{DescriptorPool, DynamicMessage};
+
+/// Messages that move funds
+use cosmos::proto::cosmos::bank::v1beta1 as bank;
+use cosmos::proto::cosmwasm::wasm::v1 as wasm;
+use ibc_proto::ibc::applications::transfer::v1::MsgTransfer;
+
+/// Transaction messages
+use crate::tx::Msg;
+
+/// Key of the type url of a JSON message
+pub const TYPE_KEY: &str = "@type";
+
+/// Key of the protobuf bytes of a message given already encoded, hex or base64
+pub const VALUE_KEY: &str = "@value";
+
+/// Key of the amino JSON form of a message, `{"type": ..., "value": ...}`, for `--sign-mode amino-json`
+pub const AMINO_KEY: &str = "@amino";
+
+/// Protobuf schemas of message types the tool doesn't model, from descriptor sets.
+///
+/// A descriptor set is written by `buf build -o msgs.binpb` or
+/// `protoc --include_imports --descriptor_set_out=msgs.binpb`; it must
+/// include the files the messages import, e.g. the cosmos coin type.
+#[derive(Clone, Debug)]
+pub struct Schemas {
+    /// Loaded descriptors, empty without descriptor sets
+    pool: DescriptorPool,
+}
+
+impl Schemas {
+    /// Loads the descriptor sets at `paths`.
+    ///
+    /// ### Errors
+    /// Fails if a file can't be read, isn't a descriptor set, or misses a file it imports.
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Schemas> {
+        let mut pool = DescriptorPool::new();
+        for path in paths {
+            let path = path.as_ref();
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Error reading descriptor set {}", path.display()))?;
+            pool.decode_file_descriptor_set(bytes.as_slice())
+                .with_context(|| format!("Invalid descriptor set {}", path.display()))?;
+        }
+        Ok(Schemas { pool })
+    }
+
+    /// Encodes one JSON message.
+    ///
+    /// The message names its type url with `@type`. Its fields follow the
+    /// protobuf JSON mapping and are encoded with the type's descriptor,
+    /// unless `@value` gives the encoded bytes instead. `@amino` optionally
+    /// gives the amino JSON form.
+    ///
+    /// ### Errors
+    /// Fails if `@type` is missing, the type has no descriptor, or the fields don't match it.
+    pub fn message(&self, json: &serde_json::Value) -> Result<Msg> {
+        let mut fields = json
+            .as_object()
+            .cloned()
+            .ok_or_else(|| anyhow!("Expected a JSON object with {}, got {}", TYPE_KEY, json))?;
+        let type_url = match fields.remove(TYPE_KEY) {
+            Some(serde_json::Value::String(type_url)) => type_url,
+            _ => return Err(anyhow!("Message {} has no {} string", json, TYPE_KEY)),
+        };
+        let amino = fields.remove(AMINO_KEY);
+        let name = type_url.rsplit('/').next().unwrap_or_default();
+
+        let value = match fields.remove(VALUE_KEY) {
+            Some(value) => {
+                if !fields.is_empty() {
+                    return Err(anyhow!(
+                        "Message {} has {} and other fields, give one or the other",
+                        type_url,
+                        VALUE_KEY
+                    ));
+                }
+                decode(&value).with_context(|| format!("Invalid {} of {}", VALUE_KEY, type_url))?
+            }
+            None => {
+                let descriptor = self.pool.get_message_by_name(name).ok_or_else(|| {
+                    anyhow!(
+                        "No descriptor for {}, pass a descriptor set with --proto-descriptors \
+                         or give the encoded message as {}",
+                        type_url,
+                        VALUE_KEY
+                    )
+                })?;
+                DynamicMessage::deserialize(descriptor, serde_json::Value::Object(fields))
+                    .with_context(|| format!("Invalid fields of {}", type_url))?
+                    .encode_to_vec()
+            }
+        };
+
+        let msg = Msg {
+            type_url: type_url.clone(),
+            value,
+            description: format!("Custom message {}", type_url),
+            amino: None,
+        };
+        match amino {
+            None => Ok(msg),
+            Some(amino) => {
+                let amino_type = amino
+                    .get("type")
+                    .and_then(|amino_type| amino_type.as_str())
+                    .ok_or_else(|| anyhow!("{} of {} has no type string", AMINO_KEY, type_url))?
+                    .to_owned();
+                let value = amino.get("value").cloned().unwrap_or_default();
+                Ok(msg.with_amino(&amino_type, value))
+            }
+        }
+    }
+
+    /// Encodes the messages of a `--msg-json` file: one JSON message or an array of them.
+    ///
+    /// ### Errors
+    /// Fails on invalid JSON or on the first message that can't be encoded, naming it by index.
+    pub fn messages(&self, input: &str) -> Result<Vec<Msg>> {
+        let json: serde_json::Value = serde_json::from_str(input).context("Invalid JSON")?;
+        let messages = match json {
+            serde_json::Value::Array(messages) => messages,
+            message => vec![message],
+        };
+        if messages.is_empty() {
+            return Err(anyhow!("No messages found"));
+        }
+        messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                self.message(message)
+                    .with_context(|| format!("Message {}", index + 1))
+            })
+            .collect()
+    }
+}
+
+/// Coins a message moves out of the accounts signing it, for the spend limits.
+///
+/// Bank sends and multi-sends, IBC transfers and contract executions with
+/// funds are known; a multi-send counts all of its inputs.
+///
+/// ### Returns
+/// `None` for a message type that isn't known, whose spending can't be told.
+///
+/// ### Errors
+/// Fails if a message of a known type doesn't decode.
+pub fn funds(msg: &Msg) -> Result<Option<Vec<cosmos::Coin>>> {
+    let value = msg.value.as_slice();
+    let invalid = || format!("Invalid {} message", msg.type_url);
+    let coins = match msg.type_url.as_str() {
+        "/cosmos.bank.v1beta1.MsgSend" => {
+            bank::MsgSend::decode(value).with_context(invalid)?.amount
+        }
+        "/cosmos.bank.v1beta1.MsgMultiSend" => bank::MsgMultiSend::decode(value)
+            .with_context(invalid)?
+            .inputs
+            .into_iter()
+            .flat_map(|input| input.coins)
+            .collect(),
+        "/ibc.applications.transfer.v1.MsgTransfer" => MsgTransfer::decode(value)
+            .with_context(invalid)?
+            .token
+            .map(|token| cosmos::Coin {
+                denom: token.denom,
+                amount: token.amount,
+            })
+            .into_iter()
+            .collect(),
+        "/cosmwasm.wasm.v1.MsgExecuteContract" => {
+            wasm::MsgExecuteContract::decode(value)
+                .with_context(invalid)?
+                .funds
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(coins))
+}
+
+/// Decodes protobuf bytes given as hex or base64
+fn decode(value: &serde_json::Value) -> Result<Vec<u8>> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| anyhow!("Expected a hex or base64 string, got {}", value))?
+        .trim();
+    hex::decode(text)
+        .ok()
+        .or_else(|| base64::engine::general_purpose::STANDARD.decode(text).ok())
+        .ok_or_else(|| anyhow!("{} isn't hex or base64", text))
+}
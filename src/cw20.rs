@@ -0,0 +1,75 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Contract messages
+use serde::{Deserialize, Serialize};
+
+/// CW20 execute messages
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ExecuteMsg {
+    /// Moves tokens from the sender to the recipient
+    Transfer {
+        /// Address receiving the tokens
+        recipient: String,
+        /// Amount in the token's base units
+        amount: String,
+    },
+}
+
+/// CW20 query messages
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum QueryMsg {
+    /// Token balance of an address
+    Balance {
+        /// Address to query
+        address: String,
+    },
+}
+
+/// Response to [`QueryMsg::Balance`]
+#[derive(Deserialize)]
+struct BalanceResponse {
+    /// Balance in the token's base units
+    balance: String,
+}
+
+/// Denom used in the journal and policies for a CW20 token
+pub fn denom(contract: cosmos::Address) -> String {
+    format!("cw20:{}", contract)
+}
+
+/// Queries the CW20 balance of an address
+pub async fn balance(
+    cosmos: &cosmos::Cosmos,
+    contract: cosmos::Address,
+    address: cosmos::Address,
+) -> Result<String> {
+    let response: BalanceResponse = cosmos
+        .make_contract(contract)
+        .query(QueryMsg::Balance {
+            address: address.to_string(),
+        })
+        .await
+        .with_context(|| format!("Error querying CW20 balance from contract {}", contract))?;
+    Ok(response.balance)
+}
+
+/// Message executing the contract's `transfer`, moving tokens from `sender` to `recipient`
+pub fn transfer(
+    sender: cosmos::Address,
+    contract: cosmos::Address,
+    recipient: cosmos::Address,
+    amount: u128,
+) -> Result<crate::tx::Msg> {
+    let json = serde_json::to_value(ExecuteMsg::Transfer {
+        recipient: recipient.to_string(),
+        amount: amount.to_string(),
+    })
+    .context("Error serializing CW20 transfer message")?;
+
+    let mut msg = crate::wasm::execute(sender, contract, json, vec![])?;
+    msg.description = format!("Transfer {} CW20 {} to {}", amount, contract, recipient);
+    Ok(msg)
+}
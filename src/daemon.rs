@@ -0,0 +1,278 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Deserialize daemon settings
+use serde::{Deserialize, Serialize};
+
+/// Per-profile state shared between tasks
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Shared state passed to every command
+use crate::context::AppContext;
+/// Automation rules
+use crate::rules::{self, Rule};
+/// Status reports on SIGUSR1
+use crate::status;
+/// Jobs start and stop together
+use crate::tasks::{self, CancellationToken, TaskGroup};
+/// Monitor addresses for transfers
+use crate::watch;
+
+/// Delay before restarting a failed job
+const RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// Daemon settings, the `[daemon]` section of the config file
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Addresses to watch, each with the profile to connect with
+    pub watch: Vec<WatchJob>,
+    /// Automation rules evaluated against the transfers of an address
+    pub rules: Vec<Rule>,
+}
+
+/// An address watched by the daemon, a `[[daemon.watch]]` entry
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WatchJob {
+    /// Profile providing the network and endpoint
+    pub profile: String,
+    /// Address to monitor
+    pub address: String,
+    /// How often to poll for new blocks
+    #[serde(default = "default_interval", with = "humantime_serde")]
+    pub interval: Duration,
+    /// Shell command to run for each event
+    pub exec: Option<String>,
+}
+
+/// Default poll interval for watch jobs
+fn default_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Counters kept for each profile
+#[derive(Default)]
+pub struct Metrics {
+    /// Transfers seen
+    pub events: AtomicU64,
+    /// Jobs that stopped with an error
+    pub errors: AtomicU64,
+    /// Jobs restarted after an error
+    pub restarts: AtomicU64,
+    /// Funds forwarded by rules
+    pub forwards: AtomicU64,
+}
+
+/// Point-in-time copy of [`Metrics`]
+#[derive(Serialize)]
+pub struct MetricsSnapshot {
+    /// Transfers seen
+    pub events: u64,
+    /// Jobs that stopped with an error
+    pub errors: u64,
+    /// Jobs restarted after an error
+    pub restarts: u64,
+    /// Funds forwarded by rules
+    pub forwards: u64,
+}
+
+impl Metrics {
+    /// Reads the current counters
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            events: self.events.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            forwards: self.forwards.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Metrics of every profile run by the daemon
+pub type ProfileMetrics = BTreeMap<String, Arc<Metrics>>;
+
+/// Runs a single watch job until it fails or `cancel` is cancelled, continuing after the block in `progress`
+async fn run_watch(
+    ctx: &AppContext,
+    job: &WatchJob,
+    metrics: &Metrics,
+    progress: &AtomicI64,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let address: cosmos::Address = job
+        .address
+        .parse()
+        .with_context(|| format!("Invalid address {} in daemon.watch", job.address))?;
+    let chain = tasks::until_cancelled(cancel, ctx.connect()).await?;
+    let verifier = tasks::until_cancelled(cancel, ctx.verifier(&chain)).await?;
+
+    watch::watch(
+        chain.cosmos(),
+        address,
+        job.interval,
+        verifier.as_ref(),
+        progress,
+        cancel,
+        |event| async move {
+            metrics.events.fetch_add(1, Ordering::Relaxed);
+            let json = serde_json::json!({ "profile": job.profile, "event": event });
+            ctx.print(&json, || {
+                format!(
+                    "[{}] height {} txhash {} {:?} {} counterparty {}",
+                    job.profile,
+                    event.height,
+                    event.txhash,
+                    event.direction,
+                    event.amount,
+                    event.counterparty
+                )
+            })?;
+            if let Some(command) = &job.exec {
+                watch::exec(command, &event).await?;
+            }
+            Ok(())
+        },
+    )
+    .await
+}
+
+/// Runs `job` until cancelled, restarting it after a delay whenever it fails
+async fn supervise<F, Fut>(
+    name: &str,
+    metrics: &Metrics,
+    cancel: &CancellationToken,
+    mut job: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    loop {
+        if let Err(error) = job().await {
+            metrics.errors.fetch_add(1, Ordering::Relaxed);
+            tracing::error!("{} failed: {:#}", name, error);
+        }
+        if !tasks::sleep(cancel, RESTART_DELAY).await {
+            return Ok(());
+        }
+        metrics.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Runs every configured job concurrently until interrupted.
+///
+/// Each job runs in its own task of a [`TaskGroup`] with a context for its
+/// profile, so a failing chain or endpoint only affects its own jobs: a job
+/// that stops with an error is restarted after a delay. Per-profile metrics
+/// are logged every `report_interval`, and written as a status report on
+/// SIGUSR1, see [`status::report_on_sigusr1`]. On Ctrl-C every job is cancelled and
+/// finishes the transfer it's handling, e.g. a rule's forward, before the
+/// daemon exits.
+///
+/// ### Errors
+/// Returns an error if no jobs are configured, a job names an unknown
+/// profile, or a task panicked, which stops the other jobs as well.
+pub async fn run(ctx: &AppContext, report_interval: Duration) -> Result<()> {
+    let jobs = &ctx.config.daemon.watch;
+    let rules = &ctx.config.daemon.rules;
+    if jobs.is_empty() && rules.is_empty() {
+        return Err(anyhow!(
+            "No jobs configured in the [daemon] section of the config file"
+        ));
+    }
+
+    // One context and one set of metrics per profile
+    let mut metrics = ProfileMetrics::new();
+    let mut group = TaskGroup::new(&ctx.shutdown);
+    for job in jobs.iter().cloned() {
+        let profile_ctx = Arc::new(ctx.for_profile(&job.profile)?);
+        let profile_metrics = metrics.entry(job.profile.clone()).or_default().clone();
+        let cancel = group.token();
+
+        // A restarted job continues after the last block it processed
+        let name = format!("[{}] Watch of {}", job.profile, job.address);
+        group.spawn(name.clone(), async move {
+            let progress = AtomicI64::default();
+            supervise(&name, &profile_metrics, &cancel, || {
+                run_watch(&profile_ctx, &job, &profile_metrics, &progress, &cancel)
+            })
+            .await
+        });
+    }
+    for rule in rules.iter().cloned() {
+        let profile_ctx = Arc::new(ctx.for_profile(&rule.profile)?);
+        let profile_metrics = metrics.entry(rule.profile.clone()).or_default().clone();
+        let cancel = group.token();
+
+        let name = format!("[{}] Rule {}", rule.profile, rule.name);
+        group.spawn(name.clone(), async move {
+            let progress = AtomicI64::default();
+            supervise(&name, &profile_metrics, &cancel, || {
+                rules::run(&profile_ctx, &rule, &profile_metrics, &progress, &cancel)
+            })
+            .await
+        });
+    }
+    tracing::info!(
+        "Daemon running {} jobs and {} rules for {} profiles",
+        jobs.len(),
+        rules.len(),
+        metrics.len()
+    );
+
+    // The report stops with the jobs
+    let report_metrics = metrics.clone();
+    let cancel = group.token();
+    group.spawn("Metrics report", async move {
+        let mut report = tokio::time::interval(report_interval);
+        report.tick().await;
+        loop {
+            tokio::select! {
+                _ = report.tick() => log_metrics(&report_metrics),
+                _ = cancel.cancelled() => return Ok(()),
+            }
+        }
+    });
+
+    // SIGUSR1 reports the per-profile counters so far
+    let started = chrono::Utc::now();
+    let (status_metrics, configured) = (metrics.clone(), (jobs.len(), rules.len()));
+    let _reporting =
+        status::report_on_sigusr1(&group.token(), ctx.status_file.clone(), move || {
+            serde_json::json!({
+                "daemon": {
+                    "started": started,
+                    "watch_jobs": configured.0,
+                    "rules": configured.1,
+                    "profiles": status_metrics
+                        .iter()
+                        .map(|(profile, metrics)| (profile.clone(), metrics.snapshot()))
+                        .collect::<BTreeMap<_, _>>(),
+                }
+            })
+        });
+
+    let result = group.join().await;
+    tracing::info!("Daemon stopped");
+    log_metrics(&metrics);
+    result
+}
+
+/// Logs the metrics of every profile
+fn log_metrics(metrics: &ProfileMetrics) {
+    for (profile, metrics) in metrics {
+        let snapshot = metrics.snapshot();
+        tracing::info!(
+            "[{}] events {} errors {} restarts {} forwards {}",
+            profile,
+            snapshot.events,
+            snapshot.errors,
+            snapshot.restarts,
+            snapshot.forwards
+        );
+    }
+}
@@ -0,0 +1,101 @@
+/// Error handling
+use anyhow::Result;
+
+/// Futures given a deadline
+use std::future::Future;
+
+/// Timeout set once at startup
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Error of a phase running out of time
+use crate::error::TimedOut;
+
+/// Timeout of every phase, set once at startup from `--timeout`
+static TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// Step of talking to a chain that gets its own deadline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Loading the network settings and opening a connection
+    Connect,
+    /// A query answered by the node
+    Query,
+    /// Simulating a transaction for its gas
+    Simulate,
+    /// Handing a signed transaction to the node
+    Broadcast,
+    /// Waiting for a broadcast transaction to be included
+    Confirm,
+}
+
+impl Phase {
+    /// Phase of a gRPC call by method path, broadcasts and simulations being calls too
+    pub fn of(path: &str) -> Phase {
+        match path {
+            "/cosmos.tx.v1beta1.Service/Simulate" => Phase::Simulate,
+            "/cosmos.tx.v1beta1.Service/BroadcastTx" => Phase::Broadcast,
+            _ => Phase::Query,
+        }
+    }
+
+    /// What the phase was doing, for error messages
+    pub fn describe(self) -> &'static str {
+        match self {
+            Phase::Connect => "connecting to the node, it may be down or unreachable",
+            Phase::Query => "waiting for the node to answer a query",
+            Phase::Simulate => "simulating the transaction, nothing was broadcast",
+            Phase::Broadcast => {
+                "broadcasting the transaction, it may have reached the node and still be included"
+            }
+            Phase::Confirm => {
+                "waiting for the transaction to be included, it was broadcast and may still be included"
+            }
+        }
+    }
+}
+
+/// Sets the timeout of every phase, only the first call has an effect
+pub fn set_timeout(timeout: Option<Duration>) {
+    let _ = TIMEOUT.set(timeout);
+}
+
+/// Timeout of every phase, none unless set with [`set_timeout`]
+pub fn timeout() -> Option<Duration> {
+    TIMEOUT.get().copied().flatten()
+}
+
+/// Runs `future` within the timeout of `--timeout`, if any.
+///
+/// `what` names the endpoint or call, e.g. a gRPC method path.
+///
+/// ### Errors
+/// Fails with [`TimedOut`] once the timeout passes, dropping `future`.
+pub async fn within<T>(
+    phase: Phase,
+    what: &str,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    within_timeout(timeout(), phase, what, future).await
+}
+
+/// Runs `future` within `timeout`, like [`within`] with an explicit timeout
+pub async fn within_timeout<T>(
+    timeout: Option<Duration>,
+    phase: Phase,
+    what: &str,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+    match tokio::time::timeout(timeout, future).await {
+        Ok(result) => result,
+        Err(_) => Err(TimedOut {
+            phase,
+            what: what.to_owned(),
+            after: timeout,
+        }
+        .into()),
+    }
+}
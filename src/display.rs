@@ -0,0 +1,155 @@
+/// Deserialize display preferences and serialize command output
+use serde::{Deserialize, Serialize};
+
+/// Preferences by chain id and denom
+use std::collections::HashMap;
+
+/// Embedded display units
+use crate::amount;
+
+/// How IBC denoms without a configured symbol are shown
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IbcStyle {
+    /// Abbreviated hash, e.g. ibc/2739…5EB2
+    #[default]
+    Short,
+    /// Full denom
+    Full,
+}
+
+/// Symbol and exponent shown for a denom
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DenomDisplay {
+    /// Symbol, e.g. OSMO
+    pub symbol: String,
+    /// Exponent of the symbol relative to the base denom, e.g. 6
+    #[serde(default)]
+    pub exponent: u32,
+}
+
+/// Display preferences of a chain, a `[display.<chain-id>]` section of the config file
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// How IBC denoms without a symbol are shown
+    pub ibc: IbcStyle,
+    /// Symbols by base denom, replacing the built-in ones
+    pub denoms: HashMap<String, DenomDisplay>,
+}
+
+/// A coin in base units with the form shown to the user
+#[derive(Serialize, Clone, Debug)]
+pub struct DisplayCoin {
+    /// Amount in base units
+    pub amount: String,
+    /// Full base denom
+    pub denom: String,
+    /// Amount as shown, e.g. 1.5 OSMO
+    pub display: String,
+}
+
+/// Formats amounts for one chain, used by every command printing coins
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    /// Chain the denoms belong to
+    chain_id: String,
+    /// Preferences of the chain
+    config: DisplayConfig,
+}
+
+impl Formatter {
+    /// Formatter for a chain, with its preferences from the config file if any
+    pub fn new(preferences: &HashMap<String, DisplayConfig>, chain_id: &str) -> Self {
+        Formatter {
+            chain_id: chain_id.to_owned(),
+            config: preferences.get(chain_id).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Symbol and exponent of a denom, configured ones first, then the built-in units
+    fn unit(&self, denom: &str) -> Option<(String, u32)> {
+        if let Some(unit) = self.config.denoms.get(denom) {
+            return Some((unit.symbol.clone(), unit.exponent));
+        }
+        amount::snapshot_unit(&self.chain_id, denom)
+            .map(|(display, exponent)| (display.to_uppercase(), exponent))
+    }
+
+    /// Denom as shown, e.g. OSMO, or an abbreviated IBC denom
+    pub fn denom(&self, denom: &str) -> String {
+        if let Some((symbol, _)) = self.unit(denom) {
+            return symbol;
+        }
+        match denom.strip_prefix("ibc/") {
+            Some(hash) if self.config.ibc == IbcStyle::Short && hash.len() > 8 => {
+                format!("ibc/{}…{}", &hash[..4], &hash[hash.len() - 4..])
+            }
+            _ => denom.to_owned(),
+        }
+    }
+
+    /// Amount in base units as shown, e.g. 1500000 uosmo is 1.5 OSMO, differences may be negative
+    pub fn amount(&self, amount: &str, denom: &str) -> String {
+        let (sign, digits) = match amount.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", amount),
+        };
+        match (self.unit(denom), digits.parse::<u128>()) {
+            (Some((symbol, exponent)), Ok(base)) => format!(
+                "{}{} {}",
+                sign,
+                amount::from_base_units(base, exponent),
+                symbol
+            ),
+            _ => format!("{}{}", amount, self.denom(denom)),
+        }
+    }
+
+    /// Coin as shown
+    pub fn coin(&self, coin: &cosmos::Coin) -> String {
+        self.amount(&coin.amount, &coin.denom)
+    }
+
+    /// Coins as shown, comma separated
+    pub fn coins(&self, coins: &[cosmos::Coin]) -> String {
+        coins
+            .iter()
+            .map(|coin| self.coin(coin))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// A comma separated list of base unit coins such as 110uosmo,5uion, as shown.
+    ///
+    /// Parts which aren't a coin, e.g. an empty amount, are kept as they are.
+    pub fn coin_list(&self, list: &str) -> String {
+        if list.is_empty() {
+            return "-".to_owned();
+        }
+        list.split(',')
+            .map(|part| {
+                let split = part
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(part.len());
+                let (amount, denom) = part.split_at(split);
+                if amount.is_empty() || denom.is_empty() {
+                    part.to_owned()
+                } else {
+                    self.amount(amount, denom)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Coin with its base amount and denom kept for JSON output
+    pub fn json(&self, amount: &str, denom: &str) -> DisplayCoin {
+        DisplayCoin {
+            amount: amount.to_owned(),
+            denom: denom.to_owned(),
+            display: self.amount(amount, denom),
+        }
+    }
+}
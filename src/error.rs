@@ -0,0 +1,92 @@
+/// A transaction the chain rejected with a non-zero code
+#[derive(Debug, Clone)]
+pub struct TxFailed {
+    /// Transaction response code
+    pub code: u32,
+    /// Block height reported for the transaction
+    pub height: i64,
+    /// Transaction txhash
+    pub txhash: String,
+}
+
+impl std::fmt::Display for TxFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to execute transaction: code {} heigth {} txhash {}",
+            self.code, self.height, self.txhash
+        )
+    }
+}
+
+impl std::error::Error for TxFailed {}
+
+/// A broadcast transaction whose inclusion couldn't be confirmed, it may still be included
+#[derive(Debug, Clone)]
+pub struct Unconfirmed {
+    /// Transaction txhash
+    pub txhash: String,
+}
+
+impl std::fmt::Display for Unconfirmed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Error waiting for transaction {}", self.txhash)
+    }
+}
+
+/// A phase of talking to the chain that didn't finish within `--timeout`
+#[derive(Debug, Clone)]
+pub struct TimedOut {
+    /// Phase that ran out of time
+    pub phase: crate::deadline::Phase,
+    /// Endpoint or call, e.g. a gRPC method path
+    pub what: String,
+    /// Timeout that passed
+    pub after: std::time::Duration,
+}
+
+impl std::fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Timed out after {} {} ({})",
+            humantime::format_duration(self.after),
+            self.phase.describe(),
+            self.what
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// A transaction over a per-transaction or daily spend limit, see `[policy]`; `--override-limit` can let it through
+#[derive(Debug, Clone)]
+pub struct SpendLimitExceeded {
+    /// Denom of the limit
+    pub denom: String,
+    /// Limit in base units
+    pub limit: crate::amount::CoinAmount,
+    /// What the transaction would bring the total to
+    pub total: crate::amount::CoinAmount,
+    /// Already spent within the day, `None` for the per-transaction limit
+    pub spent: Option<crate::amount::CoinAmount>,
+}
+
+impl std::fmt::Display for SpendLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.spent {
+            None => write!(
+                f,
+                "Policy violation: the transaction spends {}{}, the limit per transaction is {}{}",
+                self.total, self.denom, self.limit, self.denom
+            ),
+            Some(spent) => write!(
+                f,
+                "Policy violation: the sender would spend {}{} within 24h, the daily limit is {}{} (already spent {}{})",
+                self.total, self.denom, self.limit, self.denom, spent, self.denom
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpendLimitExceeded {}
@@ -0,0 +1,529 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Resolved amounts and denoms
+use std::collections::HashMap;
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Human amounts and checked arithmetic
+use crate::amount::{self, CoinAmount, HumanAmount};
+/// Live chain connections
+use crate::chain::Chain;
+/// Fee estimates
+use crate::signing;
+/// Bank send messages
+use crate::tx;
+/// Spendable balances
+use crate::vesting;
+
+/// Decimal places kept for plain numbers, e.g. 0.25
+const SCALE: u32 = 18;
+
+/// `10^SCALE`, a plain number of 1
+const ONE: u128 = 10u128.pow(SCALE);
+
+/// An operator of an expression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+}
+
+/// An amount expression, e.g. `balance * 0.25` or `100osmo - fee`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    /// A plain number, with [`SCALE`] decimal places
+    Number(u128),
+    /// An amount, e.g. 100osmo
+    Amount(HumanAmount),
+    /// Balance of the sender, in a denom as typed, or the denom of the expression if missing
+    Balance(Option<String>),
+    /// Estimated fee of the transaction, in the gas denom
+    Fee,
+    /// An operation on two operands
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+/// A token of an expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// A number, possibly followed by a denom
+    Literal(String),
+    /// A name, e.g. balance
+    Name(String),
+    /// An operator
+    Op(Op),
+    /// `(`
+    Open,
+    /// `)`
+    Close,
+}
+
+/// Whether `c` continues a denom or name, `/` only in ibc/... and factory/... denoms so it divides otherwise
+fn continues_denom(so_far: &str, c: char) -> bool {
+    match c {
+        '/' => so_far.starts_with("ibc") || so_far.starts_with("factory"),
+        c => c.is_ascii_alphanumeric() || matches!(c, '_' | ':'),
+    }
+}
+
+/// Splits an expression into tokens
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '+' => Token::Op(Op::Add),
+            '-' => Token::Op(Op::Sub),
+            '*' => Token::Op(Op::Mul),
+            '/' => Token::Op(Op::Div),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            c if c.is_ascii_digit() || c == '.' => {
+                // Digits and separators, then the denom if any, e.g. 1.5osmo or 1.234,5osmo
+                let mut end = start;
+                let mut denom = None;
+                while let Some(&(index, c)) = chars.peek() {
+                    let accepted = if let Some(denom) = denom {
+                        continues_denom(&input[denom..end], c)
+                    } else if c.is_ascii_alphabetic() {
+                        denom = Some(index);
+                        true
+                    } else {
+                        c.is_ascii_digit() || c == '.' || c == ','
+                    };
+                    if !accepted {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Literal(input[start..end].to_owned()));
+                continue;
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut end = start;
+                while let Some(&(index, c)) = chars.peek() {
+                    if !continues_denom(&input[start..end], c) {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Name(input[start..end].to_owned()));
+                continue;
+            }
+            c => return Err(anyhow!("Unexpected {} in amount {}", c, input)),
+        };
+        tokens.push(token);
+        chars.next();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens of an expression
+struct Parser {
+    /// Tokens of the expression
+    tokens: Vec<Token>,
+    /// Index of the next token
+    next: usize,
+}
+
+impl Parser {
+    /// Next token, without consuming it
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    /// Consumes the next token
+    fn take(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.term()?;
+        while let Some(Token::Op(op @ (Op::Add | Op::Sub))) = self.peek() {
+            let op = *op;
+            self.next += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    /// `factor (('*' | '/') factor)*`
+    fn term(&mut self) -> Result<Expr> {
+        let mut left = self.factor()?;
+        while let Some(Token::Op(op @ (Op::Mul | Op::Div))) = self.peek() {
+            let op = *op;
+            self.next += 1;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.factor()?));
+        }
+        Ok(left)
+    }
+
+    /// A number, an amount, `balance`, `balance(denom)`, `fee` or a parenthesized expression
+    fn factor(&mut self) -> Result<Expr> {
+        match self.take() {
+            Some(Token::Literal(literal)) => {
+                if literal.ends_with(|c: char| c.is_ascii_digit() || c == '.' || c == ',') {
+                    amount::number_locale()
+                        .normalize(&literal)
+                        .and_then(|number| amount::to_base_units(&number, SCALE))
+                        .map(Expr::Number)
+                        .with_context(|| format!("Invalid number {}", literal))
+                } else {
+                    literal.parse().map(Expr::Amount)
+                }
+            }
+            Some(Token::Name(name)) if name == "fee" => Ok(Expr::Fee),
+            Some(Token::Name(name)) if name == "balance" => {
+                if self.peek() != Some(&Token::Open) {
+                    return Ok(Expr::Balance(None));
+                }
+                self.next += 1;
+                match (self.take(), self.take()) {
+                    (Some(Token::Name(denom)), Some(Token::Close)) => {
+                        Ok(Expr::Balance(Some(denom)))
+                    }
+                    _ => Err(anyhow!(
+                        "Expected a denom in balance(...), e.g. balance(osmo)"
+                    )),
+                }
+            }
+            Some(Token::Name(name)) => Err(anyhow!(
+                "Unknown name {} in amount, expected balance or fee",
+                name
+            )),
+            Some(Token::Open) => {
+                let inner = self.expr()?;
+                match self.take() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(anyhow!("Missing ) in amount")),
+                }
+            }
+            Some(Token::Op(_) | Token::Close) | None => {
+                Err(anyhow!("Incomplete amount expression"))
+            }
+        }
+    }
+}
+
+/// Parses an amount expression.
+///
+/// Amounts such as `1.5osmo` combine with `+`, `-`, `*`, `/` and parentheses
+/// with plain numbers, `balance` (the sender's balance in the denom of the
+/// expression, or `balance(denom)`) and `fee` (the estimated fee).
+///
+/// ### Errors
+/// Fails on a malformed expression.
+pub fn parse(input: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        next: 0,
+    };
+    let expr = parser.expr()?;
+    if parser.next < parser.tokens.len() {
+        return Err(anyhow!("Unexpected trailing input in amount {}", input));
+    }
+    Ok(expr)
+}
+
+/// Live values an expression is evaluated with
+#[derive(Clone, Debug, Default)]
+pub struct Values {
+    /// Denom of a bare `balance`, the denom of the expression's amounts or the gas denom
+    pub denom: String,
+    /// Amounts of the expression in base units, by amount as typed
+    pub amounts: HashMap<String, cosmos::Coin>,
+    /// Base denoms of `balance(denom)`, by denom as typed
+    pub denoms: HashMap<String, String>,
+    /// Balances of the sender
+    pub balances: Vec<cosmos::Coin>,
+    /// Estimated fee, if the expression uses it
+    pub fee: Option<cosmos::Coin>,
+}
+
+/// Result of evaluating part of an expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    /// An amount of a denom
+    Coin(String, CoinAmount),
+    /// A plain number, with [`SCALE`] decimal places
+    Number(u128),
+}
+
+/// `amount * numerator / denominator` rounded down, without overflowing on large amounts
+fn scale(amount: u128, numerator: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let whole = (amount / denominator).checked_mul(numerator)?;
+    let part = (amount % denominator).checked_mul(numerator)? / denominator;
+    whole.checked_add(part)
+}
+
+impl Expr {
+    /// Whether the expression is a single amount, e.g. 1.5osmo
+    pub fn is_literal(&self) -> bool {
+        matches!(self, Expr::Amount(_))
+    }
+
+    /// Every amount and `balance(denom)` of the expression
+    fn visit<'a>(&'a self, amounts: &mut Vec<&'a HumanAmount>, denoms: &mut Vec<&'a str>) {
+        match self {
+            Expr::Amount(amount) => amounts.push(amount),
+            Expr::Balance(Some(denom)) => denoms.push(denom),
+            Expr::Binary(left, _, right) => {
+                left.visit(amounts, denoms);
+                right.visit(amounts, denoms);
+            }
+            Expr::Number(_) | Expr::Balance(None) | Expr::Fee => {}
+        }
+    }
+
+    /// Whether evaluating needs the sender's balances
+    fn uses_balance(&self) -> bool {
+        match self {
+            Expr::Balance(_) => true,
+            Expr::Binary(left, _, right) => left.uses_balance() || right.uses_balance(),
+            _ => false,
+        }
+    }
+
+    /// Whether evaluating needs a fee estimate
+    fn uses_fee(&self) -> bool {
+        match self {
+            Expr::Fee => true,
+            Expr::Binary(left, _, right) => left.uses_fee() || right.uses_fee(),
+            _ => false,
+        }
+    }
+
+    /// Evaluates a part of the expression
+    fn value(&self, values: &Values) -> Result<Value> {
+        Ok(match self {
+            Expr::Number(number) => Value::Number(*number),
+            Expr::Amount(human) => {
+                let coin = values
+                    .amounts
+                    .get(&human.to_string())
+                    .ok_or_else(|| anyhow!("Amount {} wasn't resolved", human))?;
+                Value::Coin(coin.denom.clone(), CoinAmount::of(coin, &coin.denom)?)
+            }
+            Expr::Balance(denom) => {
+                let denom = match denom {
+                    Some(denom) => values
+                        .denoms
+                        .get(denom)
+                        .ok_or_else(|| anyhow!("Denom {} wasn't resolved", denom))?,
+                    None => &values.denom,
+                };
+                let held = match values.balances.iter().find(|coin| &coin.denom == denom) {
+                    Some(coin) => CoinAmount::of(coin, denom)?,
+                    None => CoinAmount::ZERO,
+                };
+                Value::Coin(denom.clone(), held)
+            }
+            Expr::Fee => {
+                let fee = values
+                    .fee
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("No fee estimate"))?;
+                Value::Coin(fee.denom.clone(), CoinAmount::of(fee, &fee.denom)?)
+            }
+            Expr::Binary(left, op, right) => {
+                let overflow = || anyhow!("Amount overflow or division by zero");
+                match (left.value(values)?, *op, right.value(values)?) {
+                    (Value::Coin(a, _), Op::Add | Op::Sub, Value::Coin(b, _)) if a != b => {
+                        return Err(anyhow!("Can't combine amounts of {} and {}", a, b));
+                    }
+                    (Value::Coin(denom, x), Op::Add, Value::Coin(_, y)) => {
+                        Value::Coin(denom, x.checked_add(y)?)
+                    }
+                    (Value::Coin(denom, x), Op::Sub, Value::Coin(_, y)) => Value::Coin(
+                        denom.clone(),
+                        x.checked_sub(y).map_err(|_| {
+                            anyhow!(
+                                "Amount is negative, {}{} is less than {}{}",
+                                x,
+                                denom,
+                                y,
+                                denom
+                            )
+                        })?,
+                    ),
+                    (Value::Coin(denom, x), Op::Mul, Value::Number(n))
+                    | (Value::Number(n), Op::Mul, Value::Coin(denom, x)) => {
+                        Value::Coin(denom, CoinAmount(scale(x.0, n, ONE).ok_or_else(overflow)?))
+                    }
+                    (Value::Coin(denom, x), Op::Div, Value::Number(n)) => {
+                        Value::Coin(denom, CoinAmount(scale(x.0, ONE, n).ok_or_else(overflow)?))
+                    }
+                    (Value::Number(a), Op::Add, Value::Number(b)) => {
+                        Value::Number(a.checked_add(b).ok_or_else(overflow)?)
+                    }
+                    (Value::Number(a), Op::Sub, Value::Number(b)) => Value::Number(
+                        a.checked_sub(b)
+                            .ok_or_else(|| anyhow!("Negative number in amount"))?,
+                    ),
+                    (Value::Number(a), Op::Mul, Value::Number(b)) => {
+                        Value::Number(scale(a, b, ONE).ok_or_else(overflow)?)
+                    }
+                    (Value::Number(a), Op::Div, Value::Number(b)) => {
+                        Value::Number(scale(a, ONE, b).ok_or_else(overflow)?)
+                    }
+                    (_, op, _) => {
+                        return Err(anyhow!(
+                            "Can't {} an amount and a plain number, e.g. use balance * 0.25",
+                            match op {
+                                Op::Add => "add",
+                                Op::Sub => "subtract",
+                                Op::Mul => "multiply two amounts",
+                                Op::Div => "divide by an amount",
+                            }
+                        ))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Evaluates the expression into a coin in base units.
+    ///
+    /// ### Errors
+    /// Fails if the result isn't an amount, is zero or negative, if amounts
+    /// of different denoms are combined, or on overflow.
+    pub fn evaluate(&self, values: &Values) -> Result<cosmos::Coin> {
+        match self.value(values)? {
+            Value::Coin(denom, amount) if amount > CoinAmount::ZERO => Ok(amount.coin(&denom)),
+            Value::Coin(denom, _) => Err(anyhow!("Amount resolves to 0{}", denom)),
+            Value::Number(_) => Err(anyhow!(
+                "Amount is a plain number, it needs a denom, e.g. 100osmo or balance * 0.25"
+            )),
+        }
+    }
+}
+
+/// Evaluates an amount expression for a bank send from `wallet` to `destination`.
+///
+/// Amounts are resolved like any other, see [`amount::resolve`]. Balances
+/// are spendable balances, excluding locked vesting tokens, and are only queried, and the send only simulated for `fee`, when the
+/// expression uses them. With a fee granter the wallet pays no fee, so
+/// `fee` is zero.
+///
+/// ### Errors
+/// Fails if an amount can't be resolved, a query fails, or evaluating fails, see [`Expr::evaluate`].
+pub async fn resolve(
+    chain: &Chain,
+    wallet: &cosmos::Wallet,
+    destination: cosmos::Address,
+    expr: &Expr,
+    fee_granter: Option<cosmos::Address>,
+) -> Result<cosmos::Coin> {
+    let cosmos = chain.cosmos();
+    let gas_denom = cosmos.get_cosmos_builder().gas_coin().to_owned();
+
+    let (mut amounts, mut denoms) = (Vec::new(), Vec::new());
+    expr.visit(&mut amounts, &mut denoms);
+
+    let mut values = Values::default();
+    for human in amounts {
+        let coin = amount::resolve(cosmos, human).await?;
+        values.amounts.insert(human.to_string(), coin);
+    }
+    for denom in denoms {
+        let unit = HumanAmount {
+            amount: "0".to_owned(),
+            denom: denom.to_owned(),
+        };
+        let base = amount::resolve(cosmos, &unit).await?.denom;
+        values.denoms.insert(denom.to_owned(), base);
+    }
+
+    // A bare balance is in the denom of the expression's amounts
+    let mut expression_denoms = values.amounts.values().map(|coin| coin.denom.clone());
+    values.denom = match expression_denoms.next() {
+        Some(denom) if expression_denoms.all(|other| other == denom) => denom,
+        _ => gas_denom.clone(),
+    };
+
+    if expr.uses_balance() {
+        values.balances = vesting::spendable(chain.grpc().await?, wallet.get_address()).await?;
+    }
+    if expr.uses_fee() {
+        values.fee = Some(match fee_granter {
+            Some(_) => CoinAmount::ZERO.coin(&gas_denom),
+            None => {
+                // The gas of a bank send doesn't depend on the amount
+                let probe = tx::msg_send(
+                    wallet.get_address(),
+                    destination,
+                    vec![CoinAmount(1).coin(&gas_denom)],
+                );
+                signing::estimate_fee(cosmos, chain.grpc().await?, wallet, &[probe])
+                    .await
+                    .context("Error estimating the fee")?
+            }
+        });
+    }
+    expr.evaluate(&values)
+}
+
+/// Evaluates the largest send of `denom` from `wallet`, for `send --all`.
+///
+/// That's the spendable balance, minus the estimated fee when it's paid in
+/// the same denom, minus `leave` if given, e.g. to keep gas for later.
+///
+/// ### Errors
+/// Fails if the balance doesn't cover the fee and `leave`, or as [`resolve`] does.
+pub async fn max_send(
+    chain: &Chain,
+    wallet: &cosmos::Wallet,
+    destination: cosmos::Address,
+    denom: &str,
+    leave: Option<&HumanAmount>,
+    fee_granter: Option<cosmos::Address>,
+) -> Result<cosmos::Coin> {
+    let gas_denom = chain.cosmos().get_cosmos_builder().gas_coin().to_owned();
+    let unit = HumanAmount {
+        amount: "0".to_owned(),
+        denom: denom.to_owned(),
+    };
+    let base = amount::resolve(chain.cosmos(), &unit).await?.denom;
+
+    let expr = all(denom, base == gas_denom && fee_granter.is_none(), leave);
+    resolve(chain, wallet, destination, &expr, fee_granter)
+        .await
+        .with_context(|| format!("Nothing to send with --all {}", denom))
+}
+
+/// Expression of a send of everything held in `denom`, less the fee if `pays_fee` and less `leave`
+pub fn all(denom: &str, pays_fee: bool, leave: Option<&HumanAmount>) -> Expr {
+    let mut expr = Expr::Balance(Some(denom.to_owned()));
+    if pays_fee {
+        expr = Expr::Binary(Box::new(expr), Op::Sub, Box::new(Expr::Fee));
+    }
+    if let Some(leave) = leave {
+        expr = Expr::Binary(
+            Box::new(expr),
+            Op::Sub,
+            Box::new(Expr::Amount(leave.clone())),
+        );
+    }
+    expr
+}
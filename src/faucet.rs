@@ -0,0 +1,459 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Rate limit windows and client addresses
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// HTTP server
+use axum::extract::{ConnectInfo, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+/// Requests and responses
+use serde::{Deserialize, Serialize};
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Chain operations
+use crate::chain::ChainClient;
+/// Stop serving on Ctrl-C
+use crate::tasks::CancellationToken;
+/// Transaction building and broadcasting
+use crate::tx;
+/// Address checks
+use crate::validate;
+
+/// Keys tracked by a [`Limiter`] before expired ones are swept
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// A rate limit, e.g. `1/day` or `5/2h`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rate {
+    /// Requests allowed within the window
+    pub count: u32,
+    /// Length of the window
+    pub per: Duration,
+}
+
+impl std::str::FromStr for Rate {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let (count, per) = input
+            .split_once('/')
+            .ok_or_else(|| anyhow!("Invalid rate {}, expected e.g. 1/day or 5/2h", input))?;
+        let count = count
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&count| count > 0)
+            .ok_or_else(|| anyhow!("Invalid rate {}, the count must be at least 1", input))?;
+        let per = match per.trim() {
+            "second" | "s" => Duration::from_secs(1),
+            "minute" | "min" | "m" => Duration::from_secs(60),
+            "hour" | "h" => Duration::from_secs(60 * 60),
+            "day" | "d" => Duration::from_secs(24 * 60 * 60),
+            "week" | "w" => Duration::from_secs(7 * 24 * 60 * 60),
+            other => humantime::parse_duration(other)
+                .with_context(|| format!("Invalid rate window {}", other))?,
+        };
+        Ok(Rate { count, per })
+    }
+}
+
+/// An allowed client network, e.g. `10.0.0.0/8`, or a single address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpNet {
+    /// First address of the network
+    addr: IpAddr,
+    /// Leading bits that must match
+    prefix: u8,
+}
+
+impl std::str::FromStr for IpNet {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let (addr, prefix) = match input.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (input, None),
+        };
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid IP address {}", addr))?;
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&prefix| prefix <= bits)
+                .ok_or_else(|| anyhow!("Invalid prefix length in {}", input))?,
+            None => bits,
+        };
+        Ok(IpNet { addr, prefix })
+    }
+}
+
+impl IpNet {
+    /// Whether `ip` is in the network, IPv4-mapped IPv6 addresses count as IPv4
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let bits = |addr: IpAddr| match addr.to_canonical() {
+            IpAddr::V4(v4) => (u128::from(u32::from(v4)) << 96, true),
+            IpAddr::V6(v6) => (u128::from(v6), false),
+        };
+        let ((network, v4), (ip, ip_v4)) = (bits(self.addr), bits(ip));
+        if v4 != ip_v4 {
+            return false;
+        }
+        // IPv4 bits are the leading ones, so the prefix applies to both families alike
+        let mask = u128::MAX
+            .checked_shl(128 - u32::from(self.prefix))
+            .unwrap_or(0);
+        network & mask == ip & mask
+    }
+}
+
+/// Counts requests per key within a sliding window
+pub struct Limiter {
+    /// Requests allowed per window
+    rate: Rate,
+    /// Times of the requests of each key within the window, oldest first
+    hits: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl Limiter {
+    /// Limiter allowing `rate` per key
+    pub fn new(rate: Rate) -> Self {
+        Limiter {
+            rate,
+            hits: Mutex::default(),
+        }
+    }
+
+    /// Counts a request of `key` made at `now`.
+    ///
+    /// ### Errors
+    /// Returns how long until `key` may request again if it's over the limit, the request then doesn't count.
+    pub fn acquire(&self, key: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let mut hits = self
+            .hits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let expired = |time: &Instant| now.saturating_duration_since(*time) >= self.rate.per;
+        if hits.len() > SWEEP_THRESHOLD {
+            hits.retain(|_, times| !times.back().is_some_and(expired));
+        }
+
+        let times = hits.entry(key.to_owned()).or_default();
+        while times.front().is_some_and(expired) {
+            times.pop_front();
+        }
+        if times.len() >= self.rate.count as usize {
+            let oldest = times.front().copied().unwrap_or(now);
+            return Err(self.rate.per - now.saturating_duration_since(oldest));
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    /// Forgets the last request of `key`, for a request that failed
+    pub fn release(&self, key: &str) {
+        let mut hits = self
+            .hits
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(times) = hits.get_mut(key) {
+            times.pop_back();
+        }
+    }
+}
+
+/// Tokens sent by the faucet
+#[derive(Serialize, Clone, Debug)]
+pub struct Dispensed {
+    /// Address receiving the tokens
+    pub address: String,
+    /// Amount sent, e.g. 1000000uosmo
+    pub amount: String,
+    /// Txhash of the send
+    pub txhash: String,
+}
+
+/// Why the faucet didn't send
+#[derive(Debug, PartialEq, Eq)]
+pub enum Refusal {
+    /// The client IP isn't on the allowlist
+    NotAllowed,
+    /// The address is malformed or on another chain
+    InvalidAddress(String),
+    /// The client IP or the address is over its limit, with the time until the next request
+    Limited(Duration),
+    /// The send failed
+    Failed(String),
+}
+
+impl Refusal {
+    /// HTTP status of the refusal
+    fn status(&self) -> StatusCode {
+        match self {
+            Refusal::NotAllowed => StatusCode::FORBIDDEN,
+            Refusal::InvalidAddress(_) => StatusCode::BAD_REQUEST,
+            Refusal::Limited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Refusal::Failed(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Refusal::NotAllowed => write!(f, "This faucet doesn't serve your network"),
+            Refusal::InvalidAddress(error) => write!(f, "Invalid address: {}", error),
+            Refusal::Limited(wait) => write!(
+                f,
+                "Rate limited, try again in {}",
+                humantime::format_duration(Duration::from_secs(wait.as_secs().max(1)))
+            ),
+            Refusal::Failed(error) => write!(f, "Send failed: {}", error),
+        }
+    }
+}
+
+/// Hands out a fixed amount from a wallet, within rate limits
+pub struct Faucet<C> {
+    /// Chain to send on
+    chain: C,
+    /// Wallet paying out
+    wallet: cosmos::Wallet,
+    /// Amount sent per request
+    coin: cosmos::Coin,
+    /// Limit per client IP
+    per_ip: Limiter,
+    /// Limit per receiving address, so one client can't fill many addresses from many IPs
+    per_address: Limiter,
+    /// Client networks served, everyone if empty
+    allow: Vec<IpNet>,
+    /// Sends one at a time, they share the wallet's sequence
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl<C: ChainClient> Faucet<C> {
+    /// Faucet sending `coin` from `wallet`, `per_ip` and `per_address` times per window
+    pub fn new(
+        chain: C,
+        wallet: cosmos::Wallet,
+        coin: cosmos::Coin,
+        per_ip: Rate,
+        per_address: Rate,
+        allow: Vec<IpNet>,
+    ) -> Self {
+        Faucet {
+            chain,
+            wallet,
+            coin,
+            per_ip: Limiter::new(per_ip),
+            per_address: Limiter::new(per_address),
+            allow,
+            sending: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Chain the faucet sends on
+    pub fn chain(&self) -> &C {
+        &self.chain
+    }
+
+    /// Sends the faucet amount to `address` for a client at `ip`.
+    ///
+    /// Refused requests and failed sends don't count towards the limits.
+    ///
+    /// ### Errors
+    /// Returns why nothing was sent, see [`Refusal`].
+    pub async fn dispense(&self, ip: IpAddr, address: &str) -> Result<Dispensed, Refusal> {
+        if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(ip)) {
+            return Err(Refusal::NotAllowed);
+        }
+        let destination = validate::parse_address(address)
+            .and_then(|destination| {
+                validate::check_prefix(destination, self.wallet.get_address())?;
+                Ok(destination)
+            })
+            .map_err(|error| Refusal::InvalidAddress(format!("{:#}", error)))?;
+
+        let now = Instant::now();
+        let (ip_key, address_key) = (ip.to_canonical().to_string(), destination.to_string());
+        self.per_ip
+            .acquire(&ip_key, now)
+            .map_err(Refusal::Limited)?;
+        if let Err(wait) = self.per_address.acquire(&address_key, now) {
+            self.per_ip.release(&ip_key);
+            return Err(Refusal::Limited(wait));
+        }
+
+        let amount = tx::format_coins(std::slice::from_ref(&self.coin));
+        let result = {
+            let _sending = self.sending.lock().await;
+            let message = tx::msg_send(
+                self.wallet.get_address(),
+                destination,
+                vec![self.coin.clone()],
+            );
+            tx::broadcast(
+                &self.chain,
+                &self.wallet,
+                vec![message],
+                &address_key,
+                &amount,
+            )
+            .await
+        };
+        let failure = match result {
+            Ok(response) if response.code == 0 => {
+                tracing::info!(
+                    "Sent {} to {} for {}, txhash {}",
+                    amount,
+                    address_key,
+                    ip_key,
+                    response.txhash
+                );
+                return Ok(Dispensed {
+                    address: address_key,
+                    amount,
+                    txhash: response.txhash,
+                });
+            }
+            Ok(response) => format!("code {}: {}", response.code, response.raw_log),
+            Err(error) => format!("{:#}", error),
+        };
+        tracing::warn!("Send to {} failed: {}", address_key, failure);
+        self.per_ip.release(&ip_key);
+        self.per_address.release(&address_key);
+        Err(Refusal::Failed(failure))
+    }
+}
+
+/// Whether a chain id looks like a testnet or local chain, e.g. osmo-test-5 or localosmosis
+pub fn looks_like_testnet(chain_id: &str) -> bool {
+    let chain_id = chain_id.to_ascii_lowercase();
+    ["test", "devnet", "local"]
+        .iter()
+        .any(|marker| chain_id.contains(marker))
+}
+
+/// Body of a token request
+#[derive(Deserialize)]
+struct TokenRequest {
+    /// Address to send to
+    address: String,
+}
+
+/// State of the HTTP handlers
+struct Server<C> {
+    /// Faucet handing out the tokens
+    faucet: Faucet<C>,
+    /// Reverse proxies in front of the faucet appending to X-Forwarded-For, none to ignore it
+    trusted_proxies: usize,
+}
+
+/// `GET /`: what the faucet sends, and from which address
+async fn info<C: ChainClient + Send + 'static>(State(server): State<Arc<Server<C>>>) -> Response {
+    let faucet = &server.faucet;
+    Json(serde_json::json!({
+        "chain_id": faucet.chain.chain_id(),
+        "address": faucet.wallet.to_string(),
+        "amount": tx::format_coins(std::slice::from_ref(&faucet.coin)),
+        "usage": "POST /request with {\"address\": \"<address>\"}",
+    }))
+    .into_response()
+}
+
+/// `POST /request`: sends the faucet amount to the address of the body
+async fn request<C: ChainClient + Send + 'static>(
+    State(server): State<Arc<Server<C>>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(body): Json<TokenRequest>,
+) -> Response {
+    // Behind a reverse proxy the peer is the proxy, the client is in the forwarded addresses
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok());
+    let ip = client_ip(peer.ip(), forwarded, server.trusted_proxies);
+
+    match server.faucet.dispense(ip, &body.address).await {
+        Ok(dispensed) => Json(dispensed).into_response(),
+        Err(refusal) => {
+            let json = Json(serde_json::json!({ "error": refusal.to_string() }));
+            match refusal {
+                Refusal::Limited(wait) => (
+                    refusal.status(),
+                    [(header::RETRY_AFTER, wait.as_secs().max(1).to_string())],
+                    json,
+                )
+                    .into_response(),
+                _ => (refusal.status(), json).into_response(),
+            }
+        }
+    }
+}
+
+/// IP of the client of a request from `peer` with the X-Forwarded-For header `forwarded`.
+///
+/// Each of the `trusted_proxies` reverse proxies in front of the faucet
+/// appends the address it was reached from, so the client is that many
+/// entries from the right; entries further left are sent by the client and
+/// can be anything. Without trusted proxies, or with fewer entries than
+/// proxies, the peer address is used.
+pub fn client_ip(peer: IpAddr, forwarded: Option<&str>, trusted_proxies: usize) -> IpAddr {
+    if trusted_proxies == 0 {
+        return peer;
+    }
+    forwarded
+        .and_then(|forwarded| forwarded.rsplit(',').nth(trusted_proxies - 1))
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Serves `faucet` over HTTP on `listen` until `cancel` is cancelled.
+///
+/// `GET /` describes the faucet and `POST /request` with `{"address": ...}`
+/// sends to the address. Behind `trusted_proxies` reverse proxies, clients are
+/// told apart by the X-Forwarded-For header rather than the peer address, see
+/// [`client_ip`].
+///
+/// ### Errors
+/// Fails if the address can't be bound or the server stops with an error.
+pub async fn serve<C: ChainClient + Send + 'static>(
+    faucet: Faucet<C>,
+    listen: SocketAddr,
+    trusted_proxies: usize,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let app = Router::new()
+        .route("/", get(info::<C>))
+        .route("/request", post(request::<C>))
+        .with_state(Arc::new(Server {
+            faucet,
+            trusted_proxies,
+        }));
+
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Error listening on {}", listen))?;
+    tracing::info!("Faucet listening on http://{}", listen);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move { cancel.cancelled().await })
+    .await
+    .context("Faucet server failed")
+}
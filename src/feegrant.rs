@@ -0,0 +1,154 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Decode allowances
+use prost::Message;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Feegrant protobuf types
+use cosmos::proto::cosmos::feegrant::v1beta1 as feegrant;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Transaction messages
+use crate::tx::{self, Msg};
+
+/// Type url of the basic allowance
+const BASIC_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.BasicAllowance";
+
+/// A feegrant allowance
+#[derive(Serialize, Clone, Debug)]
+pub struct Allowance {
+    /// Address paying the fees
+    pub granter: String,
+    /// Address whose fees are paid
+    pub grantee: String,
+    /// Allowance type, e.g. BasicAllowance
+    pub kind: String,
+    /// Maximum fees the grantee may spend, unlimited if missing
+    pub spend_limit: Option<String>,
+    /// When the allowance expires, RFC 3339
+    pub expiration: Option<String>,
+}
+
+/// Converts a protobuf grant into an [`Allowance`]
+fn allowance(grant: feegrant::Grant) -> Allowance {
+    let any = grant.allowance.unwrap_or_default();
+    let kind = any
+        .type_url
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+
+    // Only basic allowances are decoded, others are shown by type
+    let basic = (any.type_url == BASIC_ALLOWANCE)
+        .then(|| feegrant::BasicAllowance::decode(any.value.as_slice()).ok())
+        .flatten();
+    let spend_limit = basic
+        .as_ref()
+        .filter(|basic| !basic.spend_limit.is_empty())
+        .map(|basic| tx::format_coins(&basic.spend_limit));
+    let expiration = basic
+        .and_then(|basic| basic.expiration)
+        .and_then(|time| chrono::DateTime::from_timestamp(time.seconds, time.nanos as u32))
+        .map(|time| time.to_rfc3339());
+
+    Allowance {
+        granter: grant.granter,
+        grantee: grant.grantee,
+        kind,
+        spend_limit,
+        expiration,
+    }
+}
+
+/// Allowances granted to and by an address
+pub async fn list(client: &grpc::Client, address: cosmos::Address) -> Result<Vec<Allowance>> {
+    let received: feegrant::QueryAllowancesResponse = client
+        .query(
+            "/cosmos.feegrant.v1beta1.Query/Allowances",
+            feegrant::QueryAllowancesRequest {
+                grantee: address.to_string(),
+                pagination: None,
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying allowances granted to {}", address))?;
+    let granted: feegrant::QueryAllowancesByGranterResponse = client
+        .query(
+            "/cosmos.feegrant.v1beta1.Query/AllowancesByGranter",
+            feegrant::QueryAllowancesByGranterRequest {
+                granter: address.to_string(),
+                pagination: None,
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying allowances granted by {}", address))?;
+
+    Ok(received
+        .allowances
+        .into_iter()
+        .chain(granted.allowances)
+        .map(allowance)
+        .collect())
+}
+
+/// Grants a basic allowance paying the fees of `grantee`.
+///
+/// An empty `spend_limit` allows unlimited fees, a missing `expiration` never expires.
+pub fn grant(
+    granter: cosmos::Address,
+    grantee: cosmos::Address,
+    spend_limit: Vec<cosmos::Coin>,
+    expiration: Option<chrono::DateTime<chrono::Utc>>,
+) -> Msg {
+    let description = format!("Grant fee allowance from {} to {}", granter, grantee);
+
+    let mut amino_allowance = serde_json::json!({ "spend_limit": tx::amino_coins(&spend_limit) });
+    if let Some(expiration) = expiration {
+        amino_allowance["expiration"] = tx::amino_time(expiration);
+    }
+    let amino = serde_json::json!({
+        "allowance": { "type": "cosmos-sdk/BasicAllowance", "value": amino_allowance },
+        "grantee": grantee.to_string(),
+        "granter": granter.to_string(),
+    });
+
+    let allowance = feegrant::BasicAllowance {
+        spend_limit,
+        expiration: expiration.map(tx::timestamp),
+    };
+    tx::message(
+        "/cosmos.feegrant.v1beta1.MsgGrantAllowance",
+        &feegrant::MsgGrantAllowance {
+            granter: granter.to_string(),
+            grantee: grantee.to_string(),
+            allowance: Some(prost_types::Any {
+                type_url: BASIC_ALLOWANCE.to_owned(),
+                value: allowance.encode_to_vec(),
+            }),
+        },
+        description,
+    )
+    .with_amino("cosmos-sdk/MsgGrantAllowance", amino)
+}
+
+/// Revokes the allowance granted to `grantee`
+pub fn revoke(granter: cosmos::Address, grantee: cosmos::Address) -> Msg {
+    let amino = serde_json::json!({
+        "grantee": grantee.to_string(),
+        "granter": granter.to_string(),
+    });
+    tx::message(
+        "/cosmos.feegrant.v1beta1.MsgRevokeAllowance",
+        &feegrant::MsgRevokeAllowance {
+            granter: granter.to_string(),
+            grantee: grantee.to_string(),
+        },
+        format!("Revoke fee allowance from {} to {}", granter, grantee),
+    )
+    .with_amino("cosmos-sdk/MsgRevokeAllowance", amino)
+}
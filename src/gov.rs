@@ -0,0 +1,176 @@
+/// Error handling
+use anyhow::{anyhow, Result};
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Governance protobuf types
+use cosmos::proto::cosmos::gov::v1 as gov;
+
+/// Raw gRPC queries
+use crate::grpc;
+
+/// Vote options accepted by `gov vote`
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum VoteOption {
+    /// In favour of the proposal
+    Yes,
+    /// Against the proposal
+    No,
+    /// Counts towards quorum without taking a side
+    Abstain,
+    /// Against the proposal, burning the deposit if it reaches the veto threshold
+    #[value(name = "no_with_veto", alias = "no-with-veto")]
+    NoWithVeto,
+}
+
+impl From<VoteOption> for gov::VoteOption {
+    fn from(option: VoteOption) -> Self {
+        match option {
+            VoteOption::Yes => gov::VoteOption::Yes,
+            VoteOption::No => gov::VoteOption::No,
+            VoteOption::Abstain => gov::VoteOption::Abstain,
+            VoteOption::NoWithVeto => gov::VoteOption::NoWithVeto,
+        }
+    }
+}
+
+/// Vote counts of a proposal
+#[derive(Serialize, Default)]
+pub struct Tally {
+    /// Voting power for yes
+    pub yes: String,
+    /// Voting power for no
+    pub no: String,
+    /// Voting power for abstain
+    pub abstain: String,
+    /// Voting power for no with veto
+    pub no_with_veto: String,
+}
+
+/// A governance proposal
+#[derive(Serialize)]
+pub struct Proposal {
+    /// Proposal id
+    pub id: u64,
+    /// Proposal title
+    pub title: String,
+    /// Proposal summary
+    pub summary: String,
+    /// Status, e.g. VOTING_PERIOD
+    pub status: String,
+    /// End of the voting period, RFC 3339
+    pub voting_end: Option<String>,
+    /// Current tally, or the final one once voting ended
+    pub tally: Tally,
+}
+
+/// Converts a protobuf tally into a [`Tally`]
+fn tally(result: gov::TallyResult) -> Tally {
+    Tally {
+        yes: result.yes_count,
+        no: result.no_count,
+        abstain: result.abstain_count,
+        no_with_veto: result.no_with_veto_count,
+    }
+}
+
+/// Converts a protobuf proposal into a [`Proposal`]
+fn proposal(proposal: gov::Proposal) -> Proposal {
+    let status = gov::ProposalStatus::try_from(proposal.status)
+        .map(|status| {
+            status
+                .as_str_name()
+                .trim_start_matches("PROPOSAL_STATUS_")
+                .to_owned()
+        })
+        .unwrap_or_else(|_| proposal.status.to_string());
+    let voting_end = proposal.voting_end_time.and_then(|time| {
+        chrono::DateTime::from_timestamp(time.seconds, time.nanos as u32)
+            .map(|time| time.to_rfc3339())
+    });
+
+    Proposal {
+        id: proposal.id,
+        title: proposal.title,
+        summary: proposal.summary,
+        status,
+        voting_end,
+        tally: proposal.final_tally_result.map(tally).unwrap_or_default(),
+    }
+}
+
+/// Live tally of a proposal in its voting period
+async fn current_tally(client: &grpc::Client, id: u64) -> Result<Tally> {
+    let response: gov::QueryTallyResultResponse = client
+        .query(
+            "/cosmos.gov.v1.Query/TallyResult",
+            gov::QueryTallyResultRequest { proposal_id: id },
+        )
+        .await?;
+    Ok(response.tally.map(tally).unwrap_or_default())
+}
+
+/// Lists the proposals currently in their voting period, with their live tally
+pub async fn active_proposals(client: &grpc::Client) -> Result<Vec<Proposal>> {
+    let response: gov::QueryProposalsResponse = client
+        .query(
+            "/cosmos.gov.v1.Query/Proposals",
+            gov::QueryProposalsRequest {
+                proposal_status: gov::ProposalStatus::VotingPeriod as i32,
+                voter: String::new(),
+                depositor: String::new(),
+                pagination: None,
+            },
+        )
+        .await?;
+
+    let mut proposals = Vec::new();
+    for item in response.proposals {
+        let mut item = proposal(item);
+        item.tally = current_tally(client, item.id).await?;
+        proposals.push(item);
+    }
+    Ok(proposals)
+}
+
+/// Fetches a single proposal
+pub async fn show(client: &grpc::Client, id: u64) -> Result<Proposal> {
+    let response: gov::QueryProposalResponse = client
+        .query(
+            "/cosmos.gov.v1.Query/Proposal",
+            gov::QueryProposalRequest { proposal_id: id },
+        )
+        .await?;
+    let raw = response
+        .proposal
+        .ok_or_else(|| anyhow!("Proposal {} not found", id))?;
+    let voting = raw.status == gov::ProposalStatus::VotingPeriod as i32;
+
+    let mut item = proposal(raw);
+    if voting {
+        item.tally = current_tally(client, id).await?;
+    }
+    Ok(item)
+}
+
+/// Message casting a vote on a proposal
+pub fn vote(voter: cosmos::Address, id: u64, option: VoteOption) -> crate::tx::Msg {
+    let option = gov::VoteOption::from(option);
+    let amino = serde_json::json!({
+        "option": option as i32,
+        "proposal_id": id.to_string(),
+        "voter": voter.to_string(),
+    });
+    crate::tx::message(
+        "/cosmos.gov.v1.MsgVote",
+        &gov::MsgVote {
+            proposal_id: id,
+            voter: voter.to_string(),
+            option: option as i32,
+            metadata: String::new(),
+        },
+        format!("Vote {} on proposal {}", option.as_str_name(), id),
+    )
+    .with_amino("cosmos-sdk/v1/MsgVote", amino)
+}
@@ -0,0 +1,88 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// gRPC transport
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+/// Deadlines of chain calls
+use crate::deadline::{self, Phase};
+/// Metrics
+use crate::telemetry;
+
+/// Raw gRPC client for queries the cosmos crate doesn't expose.
+///
+/// Connects to the same endpoint as the [`cosmos::Cosmos`] it was created from
+/// and performs unary calls by method path, e.g.
+/// `/cosmos.gov.v1.Query/Proposals`.
+#[derive(Clone)]
+pub struct Client {
+    /// Connection to the gRPC endpoint
+    channel: Channel,
+}
+
+impl Client {
+    /// Connects to the gRPC endpoint used by the given connection
+    pub async fn connect(cosmos: &cosmos::Cosmos) -> Result<Self> {
+        let url = cosmos.get_cosmos_builder().grpc_url().to_owned();
+        Self::connect_url(&url).await
+    }
+
+    /// Connects to a gRPC endpoint by URL
+    pub async fn connect_url(url: &str) -> Result<Self> {
+        let mut endpoint = Endpoint::from_shared(url.to_owned())
+            .with_context(|| format!("Invalid gRPC endpoint {}", url))?;
+        if url.starts_with("https://") {
+            endpoint = endpoint
+                .tls_config(ClientTlsConfig::new().with_native_roots())
+                .context("Error configuring TLS")?;
+        }
+        let channel =
+            deadline::within(Phase::Connect, url, async { Ok(endpoint.connect().await?) })
+                .await
+                .with_context(|| format!("Error connecting to gRPC endpoint {}", url))?;
+        Ok(Client { channel })
+    }
+
+    /// Performs a unary gRPC call
+    pub async fn query<Req, Resp>(&self, path: &'static str, request: Req) -> Result<Resp>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let started = std::time::Instant::now();
+        let response = deadline::within(Phase::of(path), path, async {
+            let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+            grpc.ready()
+                .await
+                .with_context(|| format!("gRPC endpoint not ready for {}", path))?;
+
+            let codec = tonic::codec::ProstCodec::<Req, Resp>::default();
+            grpc.unary(
+                tonic::Request::new(request),
+                tonic::codegen::http::uri::PathAndQuery::from_static(path),
+                codec,
+            )
+            .await
+            .with_context(|| format!("gRPC query {} failed", path))
+        })
+        .await;
+        telemetry::record_rpc(path, response.is_ok(), started.elapsed());
+        Ok(response?.into_inner())
+    }
+}
+
+/// Whether a query failed because the queried item doesn't exist.
+///
+/// Chains without the queried module answer `Unimplemented`, which is
+/// treated the same way.
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<tonic::Status>())
+        .is_some_and(|status| {
+            matches!(
+                status.code(),
+                tonic::Code::NotFound | tonic::Code::Unimplemented
+            ) || status.message().contains("not found")
+        })
+}
@@ -0,0 +1,432 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Decode client states
+use prost::Message;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Event attributes
+use std::collections::HashMap;
+
+/// IBC protobuf types
+use ibc_proto::ibc::core::channel::v1 as channel;
+use ibc_proto::ibc::core::client::v1 as client;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Transaction messages
+use crate::tx::{self, Msg, TxResponse};
+
+/// Port used by ICS-20 token transfers
+pub const TRANSFER_PORT: &str = "transfer";
+
+/// Health of an IBC channel, as checked before a transfer
+#[derive(Serialize, Debug)]
+pub struct ChannelHealth {
+    /// Channel id, e.g. channel-0
+    pub channel: String,
+    /// Channel state, e.g. STATE_OPEN
+    pub state: String,
+    /// Light client backing the channel
+    pub client_id: String,
+    /// Client status, e.g. Active, Expired or Frozen
+    pub client_status: String,
+    /// Chain id of the counterparty, when the client is a tendermint client
+    pub counterparty_chain_id: Option<String>,
+    /// Packets sent on the channel that haven't been acknowledged or timed out
+    pub pending_packets: u64,
+}
+
+/// Queries the state, client status and pending packets of a channel
+pub async fn channel_health(
+    client: &grpc::Client,
+    port: &str,
+    channel_id: &str,
+) -> Result<ChannelHealth> {
+    let response: channel::QueryChannelResponse = client
+        .query(
+            "/ibc.core.channel.v1.Query/Channel",
+            channel::QueryChannelRequest {
+                port_id: port.to_owned(),
+                channel_id: channel_id.to_owned(),
+            },
+        )
+        .await
+        .with_context(|| format!("Channel {} not found on port {}", channel_id, port))?;
+    let state = response
+        .channel
+        .ok_or_else(|| anyhow!("Channel {} not found on port {}", channel_id, port))?
+        .state;
+    let state = channel::State::try_from(state)
+        .map(|state| state.as_str_name().to_owned())
+        .unwrap_or_else(|_| state.to_string());
+
+    // Light client the channel's connection is built on
+    let response: channel::QueryChannelClientStateResponse = client
+        .query(
+            "/ibc.core.channel.v1.Query/ChannelClientState",
+            channel::QueryChannelClientStateRequest {
+                port_id: port.to_owned(),
+                channel_id: channel_id.to_owned(),
+            },
+        )
+        .await?;
+    let identified = response
+        .identified_client_state
+        .ok_or_else(|| anyhow!("No client state for channel {}", channel_id))?;
+    let counterparty_chain_id = identified
+        .client_state
+        .as_ref()
+        .filter(|any| any.type_url == "/ibc.lightclients.tendermint.v1.ClientState")
+        .and_then(|any| {
+            ibc_proto::ibc::lightclients::tendermint::v1::ClientState::decode(any.value.as_slice())
+                .ok()
+        })
+        .map(|state| state.chain_id);
+
+    let status: client::QueryClientStatusResponse = client
+        .query(
+            "/ibc.core.client.v1.Query/ClientStatus",
+            client::QueryClientStatusRequest {
+                client_id: identified.client_id.clone(),
+            },
+        )
+        .await?;
+
+    // Outstanding commitments are packets relayers haven't finished yet
+    let response: channel::QueryPacketCommitmentsResponse = client
+        .query(
+            "/ibc.core.channel.v1.Query/PacketCommitments",
+            channel::QueryPacketCommitmentsRequest {
+                port_id: port.to_owned(),
+                channel_id: channel_id.to_owned(),
+                pagination: Some(ibc_proto::cosmos::base::query::v1beta1::PageRequest {
+                    key: vec![],
+                    offset: 0,
+                    limit: 1,
+                    count_total: true,
+                    reverse: false,
+                }),
+            },
+        )
+        .await?;
+    let pending_packets = response.pagination.map_or(0, |page| page.total);
+
+    Ok(ChannelHealth {
+        channel: channel_id.to_owned(),
+        state,
+        client_id: identified.client_id,
+        client_status: status.status,
+        counterparty_chain_id,
+        pending_packets,
+    })
+}
+
+/// Fails with guidance when a channel isn't safe to transfer over.
+///
+/// Funds sent over a closed channel or an expired client sit in a packet
+/// that can only time out, and a large backlog of pending packets means
+/// relayers aren't keeping up.
+pub fn ensure_healthy(health: &ChannelHealth, max_pending: u64) -> Result<()> {
+    if health.state != channel::State::Open.as_str_name() {
+        return Err(anyhow!(
+            "Channel {} is {}, transfers over it can't be relayed. Use an open channel to the same chain",
+            health.channel,
+            health.state
+        ));
+    }
+    if health.client_status != "Active" {
+        return Err(anyhow!(
+            "Client {} of channel {} is {}, packets can't be relayed until it is recovered by governance. Use another channel or wait for the client to be restored",
+            health.client_id,
+            health.channel,
+            health.client_status
+        ));
+    }
+    if health.pending_packets > max_pending {
+        return Err(anyhow!(
+            "Channel {} has {} pending packets, relayers may be inactive and the transfer could time out. Retry later, use another channel or raise --max-pending",
+            health.channel,
+            health.pending_packets
+        ));
+    }
+    Ok(())
+}
+
+/// ICS-20 transfer message
+pub fn transfer(
+    sender: cosmos::Address,
+    receiver: &str,
+    channel_id: &str,
+    coin: &cosmos::Coin,
+    timeout: std::time::Duration,
+) -> Result<Msg> {
+    let timeout_timestamp = (chrono::Utc::now()
+        + chrono::Duration::from_std(timeout).context("Invalid timeout")?)
+    .timestamp_nanos_opt()
+    .ok_or_else(|| anyhow!("Timeout out of range"))? as u64;
+
+    let amino = serde_json::json!({
+        "receiver": receiver,
+        "sender": sender.to_string(),
+        "source_channel": channel_id,
+        "source_port": TRANSFER_PORT,
+        "timeout_height": {},
+        "timeout_timestamp": timeout_timestamp.to_string(),
+        "token": { "amount": coin.amount, "denom": coin.denom },
+    });
+
+    Ok(tx::message(
+        "/ibc.applications.transfer.v1.MsgTransfer",
+        &ibc_proto::ibc::applications::transfer::v1::MsgTransfer {
+            source_port: TRANSFER_PORT.to_owned(),
+            source_channel: channel_id.to_owned(),
+            token: Some(ibc_proto::cosmos::base::v1beta1::Coin {
+                denom: coin.denom.clone(),
+                amount: coin.amount.clone(),
+            }),
+            sender: sender.to_string(),
+            receiver: receiver.to_owned(),
+            timeout_height: None,
+            timeout_timestamp,
+            memo: String::new(),
+        },
+        format!(
+            "IBC transfer {}{} to {} over {}",
+            coin.amount, coin.denom, receiver, channel_id
+        ),
+    )
+    .with_amino("cosmos-sdk/MsgTransfer", amino))
+}
+
+/// An ICS-20 packet sent by a transaction
+#[derive(Serialize, Clone, Debug)]
+pub struct Packet {
+    /// Packet sequence on the source channel
+    pub sequence: u64,
+    /// Source port, usually `transfer`
+    pub src_port: String,
+    /// Source channel
+    pub src_channel: String,
+    /// Destination channel on the counterparty
+    pub dst_channel: String,
+    /// Timeout as a unix timestamp in nanoseconds, 0 if none
+    pub timeout_timestamp: u64,
+    /// Timeout height on the counterparty, e.g. 1-1000, 0-0 if none
+    pub timeout_height: String,
+    /// Sender of the tokens
+    pub sender: String,
+    /// Receiver on the counterparty
+    pub receiver: String,
+    /// Tokens sent, e.g. 110uosmo
+    pub amount: String,
+}
+
+/// Where a sent packet stands, as seen from the source chain
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PacketStatus {
+    /// Received by the counterparty, nothing to refund
+    Delivered {
+        /// Transaction relaying the acknowledgement
+        txhash: String,
+    },
+    /// Rejected by the counterparty, the tokens were refunded
+    RefundedAfterError {
+        /// Transaction relaying the acknowledgement
+        txhash: String,
+        /// Error acknowledged by the counterparty
+        error: String,
+    },
+    /// Timed out, the tokens were refunded
+    RefundedAfterTimeout {
+        /// Transaction relaying the timeout
+        txhash: String,
+    },
+    /// Past its timeout, the refund lands once a relayer submits the timeout
+    AwaitingTimeoutRelay,
+    /// Not yet received or timed out
+    InFlight,
+    /// The commitment is gone but no acknowledgement or timeout was found,
+    /// usually because the node doesn't index old transactions
+    Unknown,
+}
+
+impl PacketStatus {
+    /// Explains the status, with the next step when the refund is stuck
+    pub fn describe(&self, packet: &Packet, chain_id: &str) -> String {
+        match self {
+            PacketStatus::Delivered { txhash } => format!(
+                "Delivered to {}, acknowledged in {}, nothing to refund",
+                packet.receiver, txhash
+            ),
+            PacketStatus::RefundedAfterError { txhash, error } => format!(
+                "Rejected by the counterparty ({}), {} refunded in {}",
+                error, packet.amount, txhash
+            ),
+            PacketStatus::RefundedAfterTimeout { txhash } => {
+                format!("Timed out, {} refunded in {}", packet.amount, txhash)
+            }
+            PacketStatus::AwaitingTimeoutRelay => format!(
+                "Timed out but not refunded yet: no relayer has submitted the timeout. \
+                 The refund lands once one does, e.g. hermes clear packets --chain {} --port {} --channel {}",
+                chain_id,
+                packet.src_port,
+                packet.src_channel
+            ),
+            PacketStatus::InFlight => format!(
+                "Still in flight, waiting to be relayed to {}. It times out at {}",
+                packet.dst_channel,
+                match packet.timeout_timestamp {
+                    0 => format!("height {}", packet.timeout_height),
+                    timestamp => chrono::DateTime::from_timestamp_nanos(timestamp as i64)
+                        .to_rfc3339(),
+                }
+            ),
+            PacketStatus::Unknown => "Packet is no longer pending but no acknowledgement or timeout was found, \
+                 the node may not index transactions that old"
+                .to_owned(),
+        }
+    }
+}
+
+/// Attributes of every event of a type emitted by a transaction
+fn events(response: &TxResponse, kind: &str) -> Vec<HashMap<String, String>> {
+    response
+        .events
+        .iter()
+        .filter(|event| event.r#type == kind)
+        .map(|event| {
+            event
+                .attributes
+                .iter()
+                .map(|attribute| (attribute.key.clone(), attribute.value.clone()))
+                .collect()
+        })
+        .collect()
+}
+
+/// Extracts the ICS-20 packet sent by a transaction.
+///
+/// ### Errors
+/// Fails if the transaction didn't send a transfer packet.
+pub fn sent_packet(response: &TxResponse) -> Result<Packet> {
+    let send = events(response, "send_packet")
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Transaction {} didn't send an IBC packet", response.txhash))?;
+    let field = |key: &str| {
+        send.get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("send_packet event without {}", key))
+    };
+
+    // The token data is JSON in the packet itself
+    let data: serde_json::Value = serde_json::from_str(&field("packet_data")?)
+        .context("Packet data isn't an ICS-20 transfer")?;
+    let data_field = |key: &str| data[key].as_str().unwrap_or_default().to_owned();
+
+    Ok(Packet {
+        sequence: field("packet_sequence")?
+            .parse()
+            .context("Invalid packet sequence")?,
+        src_port: field("packet_src_port")?,
+        src_channel: field("packet_src_channel")?,
+        dst_channel: field("packet_dst_channel")?,
+        timeout_timestamp: field("packet_timeout_timestamp")?
+            .parse()
+            .context("Invalid packet timeout")?,
+        timeout_height: field("packet_timeout_height")?,
+        sender: data_field("sender"),
+        receiver: data_field("receiver"),
+        amount: format!("{}{}", data_field("amount"), data_field("denom")),
+    })
+}
+
+/// Transactions matching an event query, e.g. `timeout_packet.packet_sequence='5'`
+async fn search(client: &grpc::Client, conditions: &[String]) -> Result<Vec<TxResponse>> {
+    use cosmos::proto::cosmos::tx::v1beta1::{GetTxsEventRequest, GetTxsEventResponse};
+
+    // Nodes before SDK 0.50 read `events`, later ones `query`
+    #[allow(deprecated)]
+    let request = GetTxsEventRequest {
+        events: conditions.to_vec(),
+        query: conditions.join(" AND "),
+        limit: 10,
+        ..Default::default()
+    };
+    let response: GetTxsEventResponse = client
+        .query("/cosmos.tx.v1beta1.Service/GetTxsEvent", request)
+        .await
+        .context("Error searching transactions")?;
+    Ok(response.tx_responses)
+}
+
+/// Works out what happened to a packet sent from this chain.
+///
+/// A packet commitment stays on the source chain until the packet is either
+/// acknowledged or timed out, so its presence tells whether the packet is
+/// still pending; once it's gone the acknowledgement or timeout transaction
+/// tells whether the tokens were delivered or refunded.
+pub async fn packet_status(client: &grpc::Client, packet: &Packet) -> Result<PacketStatus> {
+    let commitment: Result<channel::QueryPacketCommitmentResponse> = client
+        .query(
+            "/ibc.core.channel.v1.Query/PacketCommitment",
+            channel::QueryPacketCommitmentRequest {
+                port_id: packet.src_port.clone(),
+                channel_id: packet.src_channel.clone(),
+                sequence: packet.sequence,
+            },
+        )
+        .await;
+
+    // Still committed, check whether the timeout has passed
+    if let Ok(commitment) = commitment {
+        if !commitment.commitment.is_empty() {
+            let now = chrono::Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX) as u64;
+            if packet.timeout_timestamp != 0 && packet.timeout_timestamp <= now {
+                return Ok(PacketStatus::AwaitingTimeoutRelay);
+            }
+            return Ok(PacketStatus::InFlight);
+        }
+    }
+
+    let conditions = |event: &str| {
+        vec![
+            format!("{}.packet_src_channel='{}'", event, packet.src_channel),
+            format!("{}.packet_sequence='{}'", event, packet.sequence),
+        ]
+    };
+
+    if let Some(timeout) = search(client, &conditions("timeout_packet"))
+        .await?
+        .into_iter()
+        .find(|response| response.code == 0)
+    {
+        return Ok(PacketStatus::RefundedAfterTimeout {
+            txhash: timeout.txhash,
+        });
+    }
+
+    let Some(ack) = search(client, &conditions("acknowledge_packet"))
+        .await?
+        .into_iter()
+        .find(|response| response.code == 0)
+    else {
+        return Ok(PacketStatus::Unknown);
+    };
+
+    // The transfer module reports the acknowledgement outcome
+    let error = events(&ack, "fungible_token_packet")
+        .into_iter()
+        .find_map(|mut attributes| attributes.remove("error"));
+    Ok(match error {
+        Some(error) => PacketStatus::RefundedAfterError {
+            txhash: ack.txhash,
+            error,
+        },
+        None => PacketStatus::Delivered { txhash: ack.txhash },
+    })
+}
@@ -0,0 +1,125 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Serialize journal entries
+use serde::{Deserialize, Serialize};
+
+/// Filesystem access
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// Environment variable which overrides the data directory
+pub const DATA_DIR_ENV: &str = "CLI_TOOL_DATA_DIR";
+
+/// Journal file name inside the data directory
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// A single broadcast attempt recorded in the journal
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+    /// When the attempt was made
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Chain id where the transaction was broadcast
+    pub chain: String,
+    /// Sender address
+    pub from: String,
+    /// Destination address
+    pub to: String,
+    /// Amount sent, e.g. 110uosmo
+    pub amount: String,
+    /// Fee paid, when it could be read from the response
+    pub fee: Option<String>,
+    /// Transaction txhash, missing if the broadcast never happened
+    pub txhash: Option<String>,
+    /// Transaction response code, missing if the broadcast never happened
+    pub code: Option<u32>,
+    /// Error message when the attempt failed before getting a response
+    pub error: Option<String>,
+}
+
+/// Returns the directory where the tool keeps its local state.
+///
+/// Uses `CLI_TOOL_DATA_DIR` when set, otherwise the platform data directory,
+/// e.g. `~/.local/share/cli-tool` on Linux.
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = match std::env::var_os(DATA_DIR_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::data_dir()
+            .context("Unable to determine the data directory for this platform")?
+            .join("cli-tool"),
+    };
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Error creating data directory {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Path of the journal file
+pub fn path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(JOURNAL_FILE))
+}
+
+/// Appends an entry to the journal.
+///
+/// The journal is append-only: entries are never rewritten or removed,
+/// so it can serve as an audit trail independent of the chain.
+pub fn append(entry: &Entry) -> Result<()> {
+    let path = path()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Error opening journal {}", path.display()))?;
+
+    // One JSON document per line
+    let mut line = serde_json::to_string(entry).context("Error serializing journal entry")?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Error writing to journal {}", path.display()))?;
+    Ok(())
+}
+
+/// Reads every entry in the journal, oldest first
+pub fn read_all() -> Result<Vec<Entry>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = fs::File::open(&path)
+        .with_context(|| format!("Error opening journal {}", path.display()))?;
+
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Error reading journal {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid journal entry at line {}", index + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Finds the entries for a given txhash
+pub fn find(txhash: &str) -> Result<Vec<Entry>> {
+    Ok(read_all()?
+        .into_iter()
+        .filter(|entry| {
+            entry
+                .txhash
+                .as_deref()
+                .is_some_and(|hash| hash.eq_ignore_ascii_case(txhash))
+        })
+        .collect())
+}
+
+/// Extracts the fee from a transaction response, e.g. 3500uosmo
+pub fn fee_from_response(response: &crate::tx::TxResponse) -> Option<String> {
+    use prost::Message;
+
+    let tx = response.tx.as_ref()?;
+    let tx = cosmos::proto::cosmos::tx::v1beta1::Tx::decode(tx.value.as_slice()).ok()?;
+    let fee = tx.auth_info?.fee?;
+    Some(crate::tx::format_coins(&fee.amount))
+}
@@ -0,0 +1,200 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Deserialize key settings
+use serde::Deserialize;
+
+/// Encode public keys
+use base64::Engine;
+
+/// Addresses of public keys
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// Config file location
+use std::path::Path;
+
+/// Configuration file
+use crate::config::Config;
+/// Addresses of watch-only keys
+use crate::validate;
+
+/// Name of the key backed by `COSMOS_WALLET` when not configured otherwise
+pub const DEFAULT_KEY: &str = "default";
+
+/// Environment variable holding the mnemonic of the default key
+pub const DEFAULT_MNEMONIC_ENV: &str = "COSMOS_WALLET";
+
+/// Amino prefix of a secp256k1 public key
+const AMINO_SECP256K1_PREFIX: [u8; 5] = [0xeb, 0x5a, 0xe9, 0x87, 0x21];
+
+/// A named key, a `[keys.<name>]` section of the config file.
+///
+/// Mnemonics are never stored in the config file, only the name of the
+/// environment variable holding them. A watch-only key has an address
+/// instead: it can be queried and watched but never signs.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KeyConfig {
+    /// Environment variable holding the mnemonic
+    pub mnemonic_env: Option<String>,
+    /// Address of a watch-only key, which has no mnemonic
+    pub address: Option<String>,
+}
+
+impl KeyConfig {
+    /// Whether the key only has an address and can't sign
+    pub fn watch_only(&self) -> bool {
+        self.mnemonic_env.is_none() && self.address.is_some()
+    }
+}
+
+/// Output formats for public keys
+#[derive(clap::ValueEnum, Clone, Copy, Default, Debug)]
+pub enum PubkeyFormat {
+    /// `{"@type":"/cosmos.crypto.secp256k1.PubKey","key":"<base64>"}`, as used by the chain CLIs
+    #[default]
+    Json,
+    /// Amino encoded bech32, e.g. osmopub1...
+    Bech32,
+    /// Compressed key as hex
+    Hex,
+}
+
+/// Loads the seed phrase of a named key.
+///
+/// ### Errors
+/// Fails if the key isn't configured, is watch-only, or its mnemonic is missing or invalid.
+pub fn seed_phrase(config: &Config, name: &str) -> Result<cosmos::SeedPhrase> {
+    let env = match config.keys.get(name) {
+        Some(key) => match (&key.mnemonic_env, &key.address) {
+            (Some(env), None) => env.as_str(),
+            (None, Some(address)) => {
+                return Err(anyhow!(
+                    "Key {} is watch-only, it has the address {} but no mnemonic and can't sign",
+                    name,
+                    address
+                ))
+            }
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "Key {} has both mnemonic_env and address, a key either signs or is watch-only",
+                    name
+                ))
+            }
+            (None, None) => {
+                return Err(anyhow!(
+                    "Key {} needs mnemonic_env, or address for a watch-only key",
+                    name
+                ))
+            }
+        },
+        None if name == DEFAULT_KEY => DEFAULT_MNEMONIC_ENV,
+        None => return Err(anyhow!("Key {} not found in the config file", name)),
+    };
+    let mnemonic = std::env::var(env)
+        .with_context(|| format!("Environment variable {} for key {} is not set", env, name))?;
+    mnemonic
+        .parse()
+        .map_err(|error| anyhow!("Invalid mnemonic in {} for key {}: {}", env, name, error))
+}
+
+/// Resolves an argument naming an address or a key, e.g. osmo1... or treasury.
+///
+/// ### Returns
+/// The address given or of a watch-only key, `None` for a key with a
+/// mnemonic, whose address depends on the bech32 prefix of the chain.
+///
+/// ### Errors
+/// Fails if the argument is neither an address nor a configured key.
+pub fn address(config: &Config, input: &str) -> Result<Option<cosmos::Address>> {
+    match config.keys.get(input) {
+        Some(key) if key.watch_only() => {
+            let address = key.address.as_deref().unwrap_or_default();
+            validate::parse_address(address)
+                .map(Some)
+                .with_context(|| format!("Invalid address of key {}", input))
+        }
+        Some(_) => Ok(None),
+        None if input == DEFAULT_KEY => Ok(None),
+        None => validate::parse_address(input)
+            .map(Some)
+            .with_context(|| format!("{} is neither an address nor a key", input)),
+    }
+}
+
+/// Adds a watch-only key to the config file at `path`, creating it if needed.
+///
+/// The section is appended, so the rest of the file, comments included, is left as it is.
+///
+/// ### Errors
+/// Fails if the name isn't a plain TOML key, is already taken, or the file can't be written.
+pub fn import_watch_only(path: &Path, name: &str, address: cosmos::Address) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow!(
+            "Invalid key name {}, use letters, digits, - and _",
+            name
+        ));
+    }
+
+    // Parse what's there so a broken file isn't made worse
+    let mut contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("Error reading config file {}", path.display()))
+        }
+    };
+    let config: Config = toml::from_str(&contents)
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+    if config.keys.contains_key(name) || name == DEFAULT_KEY {
+        return Err(anyhow!("Key {} already exists", name));
+    }
+
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+    contents.push_str(&format!("[keys.{}]\naddress = \"{}\"\n", name, address));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Error creating {}", parent.display()))?;
+    }
+    std::fs::write(path, contents)
+        .with_context(|| format!("Error writing config file {}", path.display()))
+}
+
+/// Address of a compressed secp256k1 public key on a chain with bech32 prefix `hrp`.
+///
+/// ### Errors
+/// Fails if `hrp` isn't a valid bech32 prefix.
+pub fn address_of(key: &[u8], hrp: &str) -> Result<String> {
+    let hrp = bech32::Hrp::parse(hrp).context("Invalid bech32 prefix")?;
+    let raw = Ripemd160::digest(Sha256::digest(key));
+    bech32::encode::<bech32::Bech32>(hrp, &raw).context("Error encoding address")
+}
+
+/// Formats a compressed secp256k1 public key
+pub fn format_pubkey(key: &[u8], hrp: &str, format: PubkeyFormat) -> Result<String> {
+    match format {
+        PubkeyFormat::Json => Ok(serde_json::json!({
+            "@type": "/cosmos.crypto.secp256k1.PubKey",
+            "key": base64::engine::general_purpose::STANDARD.encode(key),
+        })
+        .to_string()),
+        PubkeyFormat::Bech32 => {
+            let hrp = bech32::Hrp::parse(&format!("{}pub", hrp))
+                .context("Invalid bech32 prefix for public key")?;
+            let data = [AMINO_SECP256K1_PREFIX.as_slice(), key].concat();
+            bech32::encode::<bech32::Bech32>(hrp, &data).context("Error encoding public key")
+        }
+        PubkeyFormat::Hex => Ok(hex::encode(key)),
+    }
+}
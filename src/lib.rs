@@ -0,0 +1,94 @@
+/// User-defined command aliases
+pub mod alias;
+/// Human-unit amount parsing
+pub mod amount;
+/// Signing requests approved on a companion wallet
+pub mod approval;
+/// Account queries
+pub mod auth;
+/// Authz grants and delegated execution
+pub mod authz;
+/// Balance helpers
+pub mod balances;
+/// Batch sends from a file
+pub mod batch;
+/// Chain operations and live connections
+pub mod chain;
+/// Command line interface
+pub mod cli;
+/// Configuration file
+pub mod config;
+/// Global options and shared state
+pub mod context;
+/// Messages of types the tool doesn't model
+pub mod custom;
+/// CW20 token contracts
+pub mod cw20;
+/// Long-running jobs for several profiles
+pub mod daemon;
+/// Per-phase deadlines of chain calls
+pub mod deadline;
+/// Denom display preferences and amount formatting
+pub mod display;
+/// Error types
+pub mod error;
+/// Amount expressions
+pub mod expr;
+/// Rate-limited testnet faucet
+pub mod faucet;
+/// Feegrant allowances
+pub mod feegrant;
+/// Governance proposals and votes
+pub mod gov;
+/// Raw gRPC queries
+pub mod grpc;
+/// IBC transfers and channel health
+pub mod ibc;
+/// Local transaction log
+pub mod journal;
+/// Named keys
+pub mod keys;
+/// Sends on several chains from a manifest
+pub mod manifest;
+/// Spend policies
+pub mod policy;
+/// Fiat prices of amounts
+pub mod prices;
+/// Spinners and progress lines
+pub mod progress;
+/// Sequential transaction queue
+pub mod queue;
+/// Chain registry used to resolve networks
+pub mod registry;
+/// Automation rules run by the daemon
+pub mod rules;
+/// Scheduled and recurring jobs
+pub mod schedule;
+/// Sending funds
+pub mod send;
+/// Custom signing modes
+pub mod signing;
+/// Status reports of long runs
+pub mod status;
+/// Osmosis pool swaps
+pub mod swap;
+/// Task groups and cancellation
+pub mod tasks;
+/// Prometheus metrics and trace export
+pub mod telemetry;
+/// Multi-message transaction templates
+pub mod templates;
+/// Transaction building and broadcasting
+pub mod tx;
+/// Exported transaction files for review
+pub mod txfile;
+/// Address validation before broadcast
+pub mod validate;
+/// Checking blocks against a second endpoint
+pub mod verify;
+/// Vesting accounts
+pub mod vesting;
+/// CosmWasm contract execution and code verification
+pub mod wasm;
+/// Monitor addresses for transfers
+pub mod watch;
@@ -0,0 +1,249 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Sends grouped by chain and wallet
+use std::collections::BTreeMap;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Parse and add up amounts
+use crate::amount::{HumanAmount, Totals};
+/// Sends of one wallet
+use crate::batch::{self, Progress};
+/// Chain operations
+use crate::chain::ChainClient;
+/// Spend policies
+use crate::policy::{self, PolicyConfig};
+/// Sending funds
+use crate::send::SendRequest;
+/// Stop starting sends when cancelled
+use crate::tasks::CancellationToken;
+/// Address checks
+use crate::validate;
+
+/// A line of a manifest
+#[derive(Clone, Debug)]
+pub struct Row {
+    /// Line number in the file, from 1
+    pub line: usize,
+    /// Profile or network the funds are sent on, e.g. prod or juno
+    pub chain: String,
+    /// Named key sending the funds, see `[keys]` in the config file
+    pub from: String,
+    /// Address receiving the funds
+    pub destination: cosmos::Address,
+    /// Amount as written, e.g. 1.5osmo
+    pub amount: HumanAmount,
+}
+
+/// Parses a manifest of `chain,from,to,amount` lines.
+///
+/// Blank lines, `#` comments and a `chain,from,to,amount` header are
+/// skipped. Destinations are only checked to be addresses here, their prefix
+/// is checked once the chain is known.
+///
+/// ### Errors
+/// Fails on the first malformed line, naming it, so nothing is sent from a bad file.
+pub fn parse(input: &str) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.eq_ignore_ascii_case("chain,from,to,amount")
+        {
+            continue;
+        }
+        // The amount comes last, it may have commas with --number-locale
+        let fields: Vec<_> = line.splitn(4, ',').map(str::trim).collect();
+        let [chain, from, destination, amount] = fields[..] else {
+            return Err(anyhow!(
+                "Line {}: expected chain,from,to,amount, got {}",
+                line_number,
+                line
+            ));
+        };
+        if chain.is_empty() || from.is_empty() {
+            return Err(anyhow!(
+                "Line {}: the chain and the sending key can't be empty",
+                line_number
+            ));
+        }
+        rows.push(Row {
+            line: line_number,
+            chain: chain.to_owned(),
+            from: from.to_owned(),
+            destination: validate::parse_address(destination)
+                .with_context(|| format!("Line {}", line_number))?,
+            amount: amount
+                .parse()
+                .with_context(|| format!("Line {}: invalid amount", line_number))?,
+        });
+    }
+    if rows.is_empty() {
+        return Err(anyhow!("No sends found in the manifest"));
+    }
+    Ok(rows)
+}
+
+/// Groups rows by chain, then by sending key, keeping their order within a group
+pub fn group(rows: Vec<Row>) -> BTreeMap<String, BTreeMap<String, Vec<Row>>> {
+    let mut groups: BTreeMap<String, BTreeMap<String, Vec<Row>>> = BTreeMap::new();
+    for row in rows {
+        groups
+            .entry(row.chain.clone())
+            .or_default()
+            .entry(row.from.clone())
+            .or_default()
+            .push(row);
+    }
+    groups
+}
+
+/// Result of one send of a manifest
+#[derive(Serialize, Clone, Debug)]
+pub struct Outcome {
+    /// Profile or network of the send
+    pub chain: String,
+    /// Named key that sent it
+    pub from: String,
+    /// What happened to the send
+    #[serde(flatten)]
+    pub send: batch::Outcome,
+}
+
+impl Outcome {
+    /// Outcome of a row that failed before anything was broadcast, e.g. because its chain was unreachable
+    pub fn failed(row: &Row, error: &anyhow::Error) -> Outcome {
+        Outcome {
+            chain: row.chain.clone(),
+            from: row.from.clone(),
+            send: batch::Outcome {
+                line: row.line,
+                destination: row.destination.to_string(),
+                amount: row.amount.to_string(),
+                txhash: None,
+                code: None,
+                error: Some(format!("{:#}", error)),
+            },
+        }
+    }
+}
+
+/// Sends of one key on one chain, ready to be signed
+#[derive(Clone, Debug)]
+pub struct WalletSends {
+    /// Profile or network the funds are sent on
+    pub chain: String,
+    /// Named key sending the funds
+    pub from: String,
+    /// Sends by line number
+    pub requests: Vec<(usize, SendRequest)>,
+}
+
+/// Sends the requests of one key on one chain, like [`batch::run`].
+///
+/// Nothing is sent unless the wallet covers all of them, see [`batch::plan`].
+/// Failures are reported as outcomes rather than errors, so one wallet or
+/// chain doesn't stop the others. Once `cancel` is cancelled no further
+/// send starts.
+///
+/// ### Returns
+/// An outcome per request, in input order.
+pub async fn run_wallet(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    sends: WalletSends,
+    policy: &PolicyConfig,
+    concurrency: usize,
+    progress: &Progress,
+    cancel: &CancellationToken,
+) -> Vec<Outcome> {
+    let WalletSends {
+        chain: chain_name,
+        from,
+        requests,
+    } = sends;
+    let outcome = |send| Outcome {
+        chain: chain_name.clone(),
+        from: from.clone(),
+        send,
+    };
+    let failed = |requests: &[(usize, SendRequest)], error: anyhow::Error| {
+        requests
+            .iter()
+            .map(|(line, request)| {
+                outcome(batch::Outcome {
+                    line: *line,
+                    destination: request.destination.to_string(),
+                    amount: format!("{}{}", request.coin.amount, request.coin.denom),
+                    txhash: None,
+                    code: None,
+                    error: Some(format!("{:#}", error)),
+                })
+            })
+            .collect()
+    };
+
+    if let Err(error) = batch::plan(chain, wallet, &requests).await {
+        return failed(&requests, error);
+    }
+    let pending = requests.clone();
+    match batch::run(
+        chain,
+        wallet,
+        requests,
+        policy,
+        concurrency,
+        progress,
+        cancel,
+    )
+    .await
+    {
+        Ok(outcomes) => outcomes.into_iter().map(outcome).collect(),
+        Err(error) => failed(&pending, error),
+    }
+}
+
+/// What a manifest did on one chain
+#[derive(Serialize, Clone, Debug)]
+pub struct ChainSummary {
+    /// Profile or network
+    pub chain: String,
+    /// Sends that succeeded
+    pub succeeded: usize,
+    /// Sends that failed
+    pub failed: usize,
+    /// Totals by denom of the sends that succeeded
+    pub sent: Totals,
+}
+
+/// Adds up outcomes by chain, in chain order.
+///
+/// ### Errors
+/// Fails if a total overflows.
+pub fn summarize(outcomes: &[Outcome]) -> Result<Vec<ChainSummary>> {
+    let mut summaries: BTreeMap<&str, ChainSummary> = BTreeMap::new();
+    for outcome in outcomes {
+        let summary = summaries
+            .entry(&outcome.chain)
+            .or_insert_with(|| ChainSummary {
+                chain: outcome.chain.clone(),
+                succeeded: 0,
+                failed: 0,
+                sent: Totals::default(),
+            });
+        if outcome.send.succeeded() {
+            summary.succeeded += 1;
+            summary
+                .sent
+                .add(&policy::parse_coin(&outcome.send.amount)?)
+                .with_context(|| format!("Total sent on {}", outcome.chain))?;
+        } else {
+            summary.failed += 1;
+        }
+    }
+    Ok(summaries.into_values().collect())
+}
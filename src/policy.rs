@@ -0,0 +1,249 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Deserialize policy settings
+use serde::Deserialize;
+
+/// Time windows
+use std::time::Duration;
+
+/// Checked sums of amounts
+use crate::amount::{CoinAmount, Totals};
+/// Sends over a spend limit
+use crate::error::SpendLimitExceeded;
+/// Journal entries used to track past sends
+use crate::journal::Entry;
+
+/// Window of the daily spend limits
+pub const SPEND_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Spend policies, the `[policy]` section of the config file
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Maximum amount any single destination may receive within `destination_window`,
+    /// one coin per denom, e.g. `["100000000uosmo"]`
+    pub destination_limits: Vec<String>,
+    /// Window used for the per-destination limits, e.g. "24h"
+    #[serde(with = "humantime_serde")]
+    pub destination_window: Duration,
+    /// Maximum amount a single transaction may spend, one coin per denom, e.g. `["500000000uosmo"]`
+    pub tx_limits: Vec<String>,
+    /// Maximum amount a sender may spend on a chain within 24 hours, one coin per denom
+    pub daily_limits: Vec<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig {
+            destination_limits: Vec::new(),
+            destination_window: Duration::from_secs(24 * 60 * 60),
+            tx_limits: Vec::new(),
+            daily_limits: Vec::new(),
+        }
+    }
+}
+
+impl PolicyConfig {
+    /// The same policies without the spend limits, for a send confirmed with `--override-limit`
+    pub fn without_spend_limits(&self) -> PolicyConfig {
+        PolicyConfig {
+            tx_limits: Vec::new(),
+            daily_limits: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Parses a coin string such as 110uosmo
+pub fn parse_coin(coin: &str) -> Result<cosmos::Coin> {
+    let parsed: cosmos::ParsedCoin = coin
+        .parse()
+        .with_context(|| format!("Invalid coin {}", coin))?;
+    Ok(parsed.into())
+}
+
+/// Parses the amount of a coin
+pub fn parse_amount(coin: &cosmos::Coin) -> Result<u128> {
+    coin.amount
+        .parse()
+        .with_context(|| format!("Invalid amount {} for denom {}", coin.amount, coin.denom))
+}
+
+/// Limit of `denom` among `limits`, `field` naming them in errors
+fn limit_of(limits: &[String], field: &str, denom: &str) -> Result<Option<CoinAmount>> {
+    for limit in limits {
+        let limit = parse_coin(limit).with_context(|| format!("Invalid policy.{}", field))?;
+        if limit.denom == denom {
+            return CoinAmount::of(&limit, denom).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Checks a send against every policy: the destination's limits, then the sender's spend limits.
+///
+/// ### Errors
+/// Returns an error if any check fails, see [`check_destination`] and [`check_spend`].
+pub fn check_send(
+    config: &PolicyConfig,
+    journal: &[Entry],
+    chain: &str,
+    from: &str,
+    destination: &str,
+    coin: &cosmos::Coin,
+) -> Result<()> {
+    check_destination(config, journal, chain, destination, coin)?;
+    check_spend(config, journal, chain, from, std::slice::from_ref(coin))
+}
+
+/// Checks what a transaction spends against the per-transaction and daily limits.
+///
+/// Coins of one denom are added up first, so a transaction of several sends
+/// is held to the limit as a whole. The daily limit counts what `from`
+/// spent on `chain` within [`SPEND_WINDOW`], read from the journal like
+/// [`check_destination`].
+///
+/// ### Errors
+/// Fails with [`SpendLimitExceeded`] if a limit would be exceeded, or if the
+/// limits in the configuration can't be parsed.
+pub fn check_spend(
+    config: &PolicyConfig,
+    journal: &[Entry],
+    chain: &str,
+    from: &str,
+    coins: &[cosmos::Coin],
+) -> Result<()> {
+    let mut spending = Totals::default();
+    for coin in coins {
+        spending.add(coin)?;
+    }
+
+    for (denom, amount) in spending.iter() {
+        if let Some(limit) = limit_of(&config.tx_limits, "tx_limits", denom)? {
+            if amount > limit {
+                return Err(SpendLimitExceeded {
+                    denom: denom.to_owned(),
+                    limit,
+                    total: amount,
+                    spent: None,
+                }
+                .into());
+            }
+        }
+
+        let Some(limit) = limit_of(&config.daily_limits, "daily_limits", denom)? else {
+            continue;
+        };
+        let since = chrono::Utc::now()
+            - chrono::Duration::from_std(SPEND_WINDOW).context("Invalid spend window")?;
+
+        // Sum what the sender already spent within the window, a transaction may have moved several coins
+        let mut spent = CoinAmount::ZERO;
+        for entry in journal {
+            if entry.code != Some(0)
+                || entry.timestamp < since
+                || entry.chain != chain
+                || entry.from != from
+            {
+                continue;
+            }
+            for previous in entry
+                .amount
+                .split(',')
+                .filter_map(|coin| parse_coin(coin).ok())
+            {
+                if previous.denom == denom {
+                    spent = spent.checked_add(CoinAmount::of(&previous, denom)?)?;
+                }
+            }
+        }
+
+        let total = spent
+            .checked_add(amount)
+            .with_context(|| format!("Total spent by {} overflows", from))?;
+        if total > limit {
+            return Err(SpendLimitExceeded {
+                denom: denom.to_owned(),
+                limit,
+                total,
+                spent: Some(spent),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks a send against the per-destination limits.
+///
+/// Past sends are read from the journal: only attempts on `chain` accepted
+/// by the chain (code 0) within the configured window count towards the limit.
+///
+/// ### Errors
+/// Returns an error if the send would push the destination over its limit,
+/// or if the limits in the configuration can't be parsed.
+pub fn check_destination(
+    config: &PolicyConfig,
+    journal: &[Entry],
+    chain: &str,
+    destination: &str,
+    coin: &cosmos::Coin,
+) -> Result<()> {
+    // Find the limit for this denom, no limit means nothing to check
+    let limit = config
+        .destination_limits
+        .iter()
+        .map(|limit| parse_coin(limit))
+        .collect::<Result<Vec<_>>>()
+        .context("Invalid policy.destination_limits")?
+        .into_iter()
+        .find(|limit| limit.denom == coin.denom);
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    let limit_amount = CoinAmount::of(&limit, &coin.denom)?;
+
+    let since = chrono::Utc::now()
+        - chrono::Duration::from_std(config.destination_window)
+            .context("Invalid policy.destination_window")?;
+
+    // Sum what this destination already received within the window, a transaction may have moved several coins
+    let mut sent = CoinAmount::ZERO;
+    for entry in journal {
+        if entry.code != Some(0)
+            || entry.timestamp < since
+            || entry.chain != chain
+            || entry.to != destination
+        {
+            continue;
+        }
+        for previous in entry
+            .amount
+            .split(',')
+            .filter_map(|coin| parse_coin(coin).ok())
+        {
+            if previous.denom == coin.denom {
+                sent = sent.checked_add(CoinAmount::of(&previous, &coin.denom)?)?;
+            }
+        }
+    }
+
+    let total = sent
+        .checked_add(CoinAmount::of(coin, &coin.denom)?)
+        .with_context(|| format!("Total sent to {} overflows", destination))?;
+    if total > limit_amount {
+        return Err(anyhow!(
+            "Policy violation: {} would receive {}{} within {}, limit is {}{} (already sent {}{})",
+            destination,
+            total,
+            coin.denom,
+            humantime::format_duration(config.destination_window),
+            limit_amount,
+            limit.denom,
+            sent,
+            coin.denom
+        ));
+    }
+    Ok(())
+}
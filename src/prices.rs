@@ -0,0 +1,173 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Price cache
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Data directory
+use crate::journal;
+
+/// How long cached prices are considered fresh
+const CACHE_TTL: chrono::Duration = chrono::Duration::minutes(10);
+
+/// CoinGecko simple price endpoint
+const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/// Known denoms: base denom, CoinGecko id and display exponent
+const KNOWN_DENOMS: &[(&str, &str, u32)] = &[
+    ("uosmo", "osmosis", 6),
+    ("uion", "ion", 6),
+    ("uatom", "cosmos", 6),
+    ("ujuno", "juno-network", 6),
+    // ATOM on Osmosis
+    (
+        "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2",
+        "cosmos",
+        6,
+    ),
+    // USDC on Osmosis
+    (
+        "ibc/498A0751C798A0D9A389AA3691123DADA57DAA4FE165D5C75894505B876BA6E4",
+        "usd-coin",
+        6,
+    ),
+];
+
+/// Fiat currency used to annotate amounts
+#[derive(clap::ValueEnum, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Fiat {
+    /// US dollar
+    Usd,
+    /// Euro
+    Eur,
+}
+
+impl Fiat {
+    /// Currency code used by CoinGecko
+    fn code(self) -> &'static str {
+        match self {
+            Fiat::Usd => "usd",
+            Fiat::Eur => "eur",
+        }
+    }
+}
+
+/// Prices cached in the data directory
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    /// When the prices were fetched
+    fetched_at: chrono::DateTime<chrono::Utc>,
+    /// Currency of the prices
+    currency: Fiat,
+    /// Price per CoinGecko id
+    prices: HashMap<String, f64>,
+}
+
+/// Prices of the known denoms in a fiat currency
+pub struct Prices {
+    /// Currency of the prices
+    currency: Fiat,
+    /// Price per CoinGecko id
+    prices: HashMap<String, f64>,
+}
+
+impl Prices {
+    /// Approximate fiat value of a coin, if its price is known
+    pub fn value(&self, coin: &cosmos::Coin) -> Option<f64> {
+        let (_, id, exponent) = KNOWN_DENOMS
+            .iter()
+            .find(|(denom, _, _)| *denom == coin.denom)?;
+        let price = self.prices.get(*id)?;
+        let amount: f64 = coin.amount.parse().ok()?;
+        Some(amount / 10f64.powi(*exponent as i32) * price)
+    }
+
+    /// Annotation for a coin, e.g. " (≈ 1.23 USD)", empty if the price is unknown
+    pub fn annotate(&self, coin: &cosmos::Coin) -> String {
+        self.value(coin)
+            .map(|value| format!(" (≈ {:.2} {})", value, self.currency.code().to_uppercase()))
+            .unwrap_or_default()
+    }
+}
+
+/// Path of the price cache
+fn cache_path() -> Result<std::path::PathBuf> {
+    Ok(journal::data_dir()?.join("prices.json"))
+}
+
+/// Reads the cached prices for a currency, if any
+fn read_cache(currency: Fiat) -> Option<Cache> {
+    let contents = std::fs::read_to_string(cache_path().ok()?).ok()?;
+    let cache: Cache = serde_json::from_str(&contents).ok()?;
+    (cache.currency == currency).then_some(cache)
+}
+
+/// Fetches the prices of all known denoms from CoinGecko and caches them
+async fn fetch(currency: Fiat) -> Result<HashMap<String, f64>> {
+    let mut ids: Vec<&str> = KNOWN_DENOMS.iter().map(|(_, id, _)| *id).collect();
+    ids.dedup();
+
+    // Response looks like {"osmosis": {"usd": 0.5}}
+    let response: HashMap<String, HashMap<String, f64>> = reqwest::Client::new()
+        .get(COINGECKO_URL)
+        .query(&[
+            ("ids", ids.join(",")),
+            ("vs_currencies", currency.code().to_owned()),
+        ])
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .context("Error requesting prices")?
+        .error_for_status()
+        .context("Price service returned an error")?
+        .json()
+        .await
+        .context("Invalid price response")?;
+
+    let prices: HashMap<String, f64> = response
+        .into_iter()
+        .filter_map(|(id, quotes)| Some((id, *quotes.get(currency.code())?)))
+        .collect();
+
+    let cache = Cache {
+        fetched_at: chrono::Utc::now(),
+        currency,
+        prices: prices.clone(),
+    };
+    std::fs::write(cache_path()?, serde_json::to_vec(&cache)?)
+        .context("Error writing price cache")?;
+    Ok(prices)
+}
+
+/// Loads prices for a currency.
+///
+/// Uses the local cache while it is fresh, otherwise fetches from CoinGecko.
+/// When offline a stale cache is used instead, and without any cache `None`
+/// is returned: fiat display is best-effort and never fails a command.
+pub async fn load(currency: Fiat) -> Option<Prices> {
+    let cached = read_cache(currency);
+    if let Some(cache) = &cached {
+        if chrono::Utc::now() - cache.fetched_at < CACHE_TTL {
+            return Some(Prices {
+                currency,
+                prices: cache.prices.clone(),
+            });
+        }
+    }
+
+    match fetch(currency).await {
+        Ok(prices) => Some(Prices { currency, prices }),
+        Err(error) => {
+            tracing::warn!("Unable to fetch prices: {:#}", error);
+            cached.map(|cache| {
+                tracing::warn!("Using cached prices from {}", cache.fetched_at.to_rfc3339());
+                Prices {
+                    currency,
+                    prices: cache.prices,
+                }
+            })
+        }
+    }
+}
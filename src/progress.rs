@@ -0,0 +1,105 @@
+/// Terminal detection
+use std::io::IsTerminal;
+
+/// Mode chosen once at startup
+use std::sync::OnceLock;
+
+/// Spinners
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// How progress is reported
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Spinners on stderr, used on a terminal without `--verbose`
+    Spinner,
+    /// Plain log lines, used when piped or with `--verbose`
+    Plain,
+    /// Nothing, used with `--quiet`
+    Quiet,
+}
+
+/// Mode and spinners shared by every stage, including concurrent ones
+struct State {
+    /// Reporting mode
+    mode: Mode,
+    /// Spinners drawn together, e.g. by the daemon's concurrent jobs
+    bars: MultiProgress,
+}
+
+/// Set by [`init`], plain log lines until then
+static STATE: OnceLock<State> = OnceLock::new();
+
+/// Picks the reporting mode from the global options and whether stderr is a terminal
+pub fn init(quiet: bool, verbose: u8) -> Mode {
+    let mode = if quiet {
+        Mode::Quiet
+    } else if verbose == 0 && std::io::stderr().is_terminal() {
+        Mode::Spinner
+    } else {
+        Mode::Plain
+    };
+    STATE.get_or_init(|| State {
+        mode,
+        bars: MultiProgress::new(),
+    });
+    mode
+}
+
+/// Current reporting mode
+pub fn mode() -> Mode {
+    STATE.get().map_or(Mode::Plain, |state| state.mode)
+}
+
+/// A step in progress, e.g. connecting or waiting for inclusion.
+///
+/// Dropping a stage without finishing it clears its spinner, as happens
+/// when the step fails and the error is reported instead.
+pub struct Stage {
+    /// Spinner, in spinner mode only
+    bar: Option<ProgressBar>,
+}
+
+/// Starts a stage, shown as a spinner or logged as `message...`
+pub fn stage(message: impl Into<String>) -> Stage {
+    let message = message.into();
+    let bar = match STATE.get() {
+        Some(state) if state.mode == Mode::Spinner => {
+            let bar = state.bars.add(ProgressBar::new_spinner());
+            bar.set_style(
+                ProgressStyle::with_template("{spinner} {msg} {elapsed:.dim}")
+                    .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+            );
+            bar.set_message(message);
+            bar.enable_steady_tick(std::time::Duration::from_millis(100));
+            Some(bar)
+        }
+        Some(state) if state.mode == Mode::Quiet => None,
+        _ => {
+            tracing::info!("{}...", message);
+            None
+        }
+    };
+    Stage { bar }
+}
+
+impl Stage {
+    /// Ends the stage, leaving `message` in place of the spinner or logging it
+    pub fn finish(self, message: impl Into<String>) {
+        let message = message.into();
+        match &self.bar {
+            Some(bar) => bar.finish_with_message(format!("✔ {}", message)),
+            None if mode() == Mode::Quiet => {}
+            None => tracing::info!("{}", message),
+        }
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        if let Some(bar) = &self.bar {
+            if !bar.is_finished() {
+                bar.finish_and_clear();
+            }
+        }
+    }
+}
@@ -0,0 +1,435 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize queue items
+use serde::{Deserialize, Serialize};
+
+/// Where the queue is kept
+use std::path::{Path, PathBuf};
+
+/// Delay between retries
+use std::time::Duration;
+
+/// Chain operations
+use crate::chain::ChainClient;
+/// Broadcasts without a known result, and sends the spend limits refuse
+use crate::error::{SpendLimitExceeded, Unconfirmed};
+/// Raw gRPC queries
+use crate::grpc;
+/// Data directory
+use crate::journal;
+/// Spend policies
+use crate::policy::{self, PolicyConfig};
+/// Sending funds
+use crate::send::{self, SendRequest};
+/// Gas limits too low get another try
+use crate::signing::OUT_OF_GAS;
+/// Stop between items when cancelled
+use crate::tasks::{self, CancellationToken};
+/// Inclusion of earlier broadcasts
+use crate::txfile;
+/// Address checks
+use crate::validate;
+
+/// Directory of the queue inside the data directory
+pub const QUEUE_DIR: &str = "queue";
+
+/// File held by the running `queue run`
+const LOCK_FILE: &str = "run.lock";
+
+/// Where an item of the queue stands
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Waiting to be sent
+    Pending,
+    /// Broadcast, but its inclusion couldn't be confirmed; never sent again automatically
+    Broadcast,
+    /// Included with code 0
+    Confirmed,
+    /// Rejected by the chain, or failed every attempt before being broadcast
+    Failed,
+}
+
+/// What an item of the queue does
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Intent {
+    /// Bank send
+    Send {
+        /// Address receiving the funds
+        destination: String,
+        /// Amount in base units, e.g. 1500000uosmo
+        amount: String,
+    },
+}
+
+impl Intent {
+    /// The send the intent stands for
+    pub fn request(&self) -> Result<SendRequest> {
+        match self {
+            Intent::Send {
+                destination,
+                amount,
+            } => Ok(SendRequest {
+                coin: policy::parse_coin(amount)?,
+                destination: validate::parse_address(destination)?,
+                cw20: None,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Intent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Intent::Send {
+                destination,
+                amount,
+            } => write!(f, "send {} to {}", amount, destination),
+        }
+    }
+}
+
+/// A transaction intent waiting in, or processed by, the queue
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    /// Unique id, ordered by the time the item was added
+    pub id: String,
+    /// When the item was added
+    pub created: chrono::DateTime<chrono::Utc>,
+    /// Profile or network the item is sent on
+    pub chain: String,
+    /// Named key signing the item, see `[keys]` in the config file
+    pub key: String,
+    /// What to send
+    pub intent: Intent,
+    /// Where the item stands
+    pub status: Status,
+    /// Times sending was attempted
+    pub attempts: u32,
+    /// Txhash once broadcast
+    pub txhash: Option<String>,
+    /// Block the transaction was included in
+    pub height: Option<i64>,
+    /// Response code of the transaction
+    pub code: Option<u32>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    /// When the item last changed
+    pub updated: chrono::DateTime<chrono::Utc>,
+}
+
+/// Items of the queue, one JSON file each so adding never races with running
+pub struct Store {
+    /// Directory of the item files
+    dir: PathBuf,
+}
+
+/// Held while a `queue run` processes the store, removed when dropped
+pub struct RunLock {
+    /// Lock file
+    path: PathBuf,
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.path) {
+            tracing::warn!("Error removing {}: {}", self.path.display(), error);
+        }
+    }
+}
+
+impl Store {
+    /// The queue of the data directory
+    pub fn open() -> Result<Store> {
+        Ok(Store::at(journal::data_dir()?.join(QUEUE_DIR)))
+    }
+
+    /// A queue kept in `dir`
+    pub fn at(dir: PathBuf) -> Store {
+        Store { dir }
+    }
+
+    /// Directory of the item files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Adds a pending item.
+    ///
+    /// Ids start with the time in nanoseconds and end with the process id, so
+    /// scripts adding items at the same time don't collide and items are
+    /// sent in the order they were added.
+    ///
+    /// ### Errors
+    /// Fails if the item can't be written.
+    pub fn add(&self, chain: &str, key: &str, intent: Intent) -> Result<Item> {
+        let now = chrono::Utc::now();
+        let nanos = now
+            .timestamp_nanos_opt()
+            .ok_or_else(|| anyhow!("The clock is out of range"))?;
+        let item = Item {
+            id: format!("{:020}-{}", nanos, std::process::id()),
+            created: now,
+            chain: chain.to_owned(),
+            key: key.to_owned(),
+            intent,
+            status: Status::Pending,
+            attempts: 0,
+            txhash: None,
+            height: None,
+            code: None,
+            error: None,
+            updated: now,
+        };
+        self.save(&item)?;
+        Ok(item)
+    }
+
+    /// Every item, oldest first
+    pub fn list(&self) -> Result<Vec<Item>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Error reading the queue {}", self.dir.display()))
+            }
+        };
+        let mut items = Vec::new();
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("Error reading the queue {}", self.dir.display()))?
+                .path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("Error reading queue item {}", path.display()))?;
+            let item: Item = serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid queue item {}", path.display()))?;
+            items.push(item);
+        }
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(items)
+    }
+
+    /// Writes an item to a temporary file renamed over its file, so it's never half written
+    pub fn save(&self, item: &Item) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Error creating {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.json", item.id));
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(item)?)
+            .with_context(|| format!("Error writing queue item {}", partial.display()))?;
+        std::fs::rename(&partial, &path)
+            .with_context(|| format!("Error writing queue item {}", path.display()))
+    }
+
+    /// Takes the run lock, so two runners never sign for the same account.
+    ///
+    /// ### Errors
+    /// Fails if another `queue run` holds it. A runner that was killed
+    /// leaves the file behind, it's named in the error so it can be removed.
+    pub fn lock(&self) -> Result<RunLock> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Error creating {}", self.dir.display()))?;
+        let path = self.dir.join(LOCK_FILE);
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                use std::io::Write;
+                write!(file, "{}", std::process::id())
+                    .with_context(|| format!("Error writing {}", path.display()))?;
+                Ok(RunLock { path })
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                let pid = std::fs::read_to_string(&path).unwrap_or_default();
+                Err(anyhow!(
+                    "Another queue run (process {}) is processing the queue. \
+                     If it's no longer running, remove {}",
+                    pid.trim(),
+                    path.display()
+                ))
+            }
+            Err(error) => Err(error).with_context(|| format!("Error creating {}", path.display())),
+        }
+    }
+}
+
+/// How often a send failing before broadcast is tried again
+#[derive(Clone, Copy, Debug)]
+pub struct Retry {
+    /// Tries after the first one
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub delay: Duration,
+}
+
+/// Looks up items a previous run broadcast without confirming their inclusion.
+///
+/// ### Errors
+/// Fails if the chain can't be queried or an item can't be saved.
+pub async fn reconcile(client: &grpc::Client, store: &Store, items: &mut [Item]) -> Result<()> {
+    for item in items
+        .iter_mut()
+        .filter(|item| item.status == Status::Broadcast)
+    {
+        let Some(txhash) = item.txhash.clone() else {
+            continue;
+        };
+        let Some(inclusion) = txfile::inclusion(client, &txhash).await? else {
+            continue;
+        };
+        item.height = Some(inclusion.height);
+        item.code = Some(inclusion.code);
+        if inclusion.code == 0 {
+            item.status = Status::Confirmed;
+            item.error = None;
+        } else {
+            item.status = Status::Failed;
+            item.error = Some(inclusion.raw_log);
+        }
+        item.updated = chrono::Utc::now();
+        store.save(item)?;
+    }
+    Ok(())
+}
+
+/// Fails the pending items of a key that can't sign, e.g. because its mnemonic isn't set.
+///
+/// ### Errors
+/// Fails if an item can't be saved.
+pub fn fail_pending(store: &Store, items: &mut [Item], error: &anyhow::Error) -> Result<()> {
+    for item in items
+        .iter_mut()
+        .filter(|item| item.status == Status::Pending)
+    {
+        item.status = Status::Failed;
+        item.error = Some(format!("{:#}", error));
+        item.updated = chrono::Utc::now();
+        store.save(item)?;
+    }
+    Ok(())
+}
+
+/// Sends the pending items of one key on one chain, strictly one after the other in queue order.
+///
+/// Each send goes through [`send::execute_transaction`], so it's held to the
+/// policies and recorded in the journal, and the next one only starts once
+/// it's included. Failures before broadcast are retried with `retry`, unless
+/// the policies refuse the send, and a run out of gas is retried since the
+/// gas is simulated again. A
+/// broadcast whose inclusion isn't confirmed is marked [`Status::Broadcast`]
+/// and never resent. Every change is saved before the next item starts.
+/// Once `cancel` is cancelled no further item starts.
+///
+/// ### Returns
+/// The items as they were left.
+///
+/// ### Errors
+/// Fails if an item can't be saved.
+pub async fn run_account(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    store: &Store,
+    items: Vec<Item>,
+    policy: &PolicyConfig,
+    retry: Retry,
+    cancel: &CancellationToken,
+) -> Result<Vec<Item>> {
+    let mut processed = Vec::new();
+    for mut item in items {
+        if item.status != Status::Pending || cancel.is_cancelled() {
+            processed.push(item);
+            continue;
+        }
+        let request = match item.intent.request() {
+            Ok(request) => request,
+            Err(error) => {
+                item.status = Status::Failed;
+                item.error = Some(format!("{:#}", error));
+                item.updated = chrono::Utc::now();
+                store.save(&item)?;
+                processed.push(item);
+                continue;
+            }
+        };
+
+        let mut retries = 0;
+        loop {
+            item.attempts += 1;
+            let result = send::execute_transaction(chain, wallet, &request, policy).await;
+            let retry_after = match result {
+                Ok(outcome) => {
+                    item.txhash = Some(outcome.txhash.clone());
+                    item.height = Some(outcome.height);
+                    item.code = Some(outcome.code);
+                    match outcome.check() {
+                        Ok(()) => {
+                            item.status = Status::Confirmed;
+                            item.error = None;
+                            None
+                        }
+                        Err(failed) => {
+                            item.status = Status::Failed;
+                            item.error = Some(failed.to_string());
+                            (outcome.code == OUT_OF_GAS).then_some(failed.to_string())
+                        }
+                    }
+                }
+                Err(error) => match error.downcast_ref::<Unconfirmed>() {
+                    // It may still be included, sending it again could send twice
+                    Some(unconfirmed) => {
+                        item.status = Status::Broadcast;
+                        item.txhash = Some(unconfirmed.txhash.clone());
+                        item.error = Some(format!("{:#}", error));
+                        None
+                    }
+                    None => {
+                        item.status = Status::Failed;
+                        item.error = Some(format!("{:#}", error));
+                        // A refused send fails the same way every time
+                        (!is_refusal(&error)).then(|| format!("{:#}", error))
+                    }
+                },
+            };
+            item.updated = chrono::Utc::now();
+
+            let Some(reason) = retry_after.filter(|_| retries < retry.retries) else {
+                break;
+            };
+            let delay = retry.delay * 2u32.saturating_pow(retries);
+            retries += 1;
+            tracing::warn!(
+                "Queue item {} failed, retrying in {:?}: {}",
+                item.id,
+                delay,
+                reason
+            );
+            // Left pending when cancelled, the next run tries again
+            item.status = Status::Pending;
+            store.save(&item)?;
+            if !tasks::sleep(cancel, delay).await {
+                break;
+            }
+        }
+        store.save(&item)?;
+        processed.push(item);
+    }
+    Ok(processed)
+}
+
+/// Whether an error is a policy or validation refusal of the send rather than a failure talking to the chain
+fn is_refusal(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string();
+        cause.is::<SpendLimitExceeded>()
+            || message.starts_with("Policy violation")
+            || message.starts_with("Invalid policy.")
+    })
+}
@@ -0,0 +1,331 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize the cache and command output
+use serde::{Deserialize, Serialize};
+
+/// Chains by name
+use std::collections::BTreeMap;
+
+/// Filesystem access
+use std::path::PathBuf;
+
+/// Node info of gRPC endpoints
+use cosmos::proto::cosmos::base::tendermint::v1beta1 as tendermint;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Data directory
+use crate::journal;
+
+/// Raw files of the cosmos/chain-registry repository
+const REGISTRY_URL: &str = "https://raw.githubusercontent.com/cosmos/chain-registry/master";
+
+/// Chains known without fetching the registry
+const SNAPSHOT: &str = include_str!("registry/chains.json");
+
+/// Cache of fetched chains, in the data directory
+const CACHE_FILE: &str = "chain-registry.json";
+
+/// How long to wait for an endpoint before trying the next one
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A chain from the registry, the subset of its `chain.json` the tool uses
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChainInfo {
+    /// Registry name, e.g. juno
+    pub chain_name: String,
+    /// Chain id, e.g. juno-1
+    pub chain_id: String,
+    /// Display name, e.g. Juno
+    #[serde(default)]
+    pub pretty_name: Option<String>,
+    /// mainnet or testnet
+    #[serde(default)]
+    pub network_type: Option<String>,
+    /// Address prefix, e.g. juno
+    pub bech32_prefix: String,
+    /// Tokens accepted for fees
+    #[serde(default)]
+    pub fees: Fees,
+    /// Public endpoints
+    #[serde(default)]
+    pub apis: Apis,
+}
+
+/// Fee settings of a chain
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Fees {
+    /// Tokens accepted for fees, the first is used
+    #[serde(default)]
+    pub fee_tokens: Vec<FeeToken>,
+}
+
+/// A token accepted for fees
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FeeToken {
+    /// Base denom, e.g. ujuno
+    pub denom: String,
+    /// Lowest suggested gas price
+    #[serde(default)]
+    pub low_gas_price: Option<f64>,
+    /// Average suggested gas price
+    #[serde(default)]
+    pub average_gas_price: Option<f64>,
+    /// Highest suggested gas price
+    #[serde(default)]
+    pub high_gas_price: Option<f64>,
+}
+
+/// Public endpoints of a chain
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Apis {
+    /// gRPC endpoints
+    #[serde(default)]
+    pub grpc: Vec<Endpoint>,
+}
+
+/// A public endpoint
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Endpoint {
+    /// Address, with or without a scheme, e.g. grpc.osmosis.zone:9090
+    pub address: String,
+    /// Who runs the endpoint
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+impl Endpoint {
+    /// URL to connect to, registry addresses often leave out the scheme
+    pub fn url(&self) -> String {
+        if self.address.contains("://") {
+            self.address.clone()
+        } else if self.address.ends_with(":443") {
+            format!("https://{}", self.address)
+        } else {
+            format!("http://{}", self.address)
+        }
+    }
+}
+
+/// Chains fetched with `chains update`
+#[derive(Serialize, Deserialize, Default)]
+struct Cache {
+    /// When the cache was last written
+    updated: Option<chrono::DateTime<chrono::Utc>>,
+    /// Fetched chains by name
+    chains: BTreeMap<String, ChainInfo>,
+}
+
+/// Where a known chain comes from
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Snapshot embedded in the tool
+    Embedded,
+    /// Fetched from the registry
+    Cached,
+}
+
+/// A known chain and where it comes from
+#[derive(Serialize, Clone, Debug)]
+pub struct Entry {
+    /// The chain
+    #[serde(flatten)]
+    pub chain: ChainInfo,
+    /// Where it comes from
+    pub source: Source,
+}
+
+/// Path of the registry cache
+pub fn cache_path() -> Result<PathBuf> {
+    Ok(journal::data_dir()?.join(CACHE_FILE))
+}
+
+/// Reads the registry cache, empty when it doesn't exist
+fn read_cache() -> Result<Cache> {
+    let path = cache_path()?;
+    if !path.exists() {
+        return Ok(Cache::default());
+    }
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Error reading chain registry cache {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid chain registry cache {}", path.display()))
+}
+
+/// Every known chain by name, fetched chains replacing the embedded snapshot
+pub fn all() -> Result<BTreeMap<String, Entry>> {
+    let snapshot: Vec<ChainInfo> =
+        serde_json::from_str(SNAPSHOT).context("Invalid embedded chain registry")?;
+    let mut chains: BTreeMap<String, Entry> = snapshot
+        .into_iter()
+        .map(|chain| {
+            let entry = Entry {
+                chain,
+                source: Source::Embedded,
+            };
+            (entry.chain.chain_name.clone(), entry)
+        })
+        .collect();
+
+    for (name, chain) in read_cache()?.chains {
+        chains.insert(
+            name,
+            Entry {
+                chain,
+                source: Source::Cached,
+            },
+        );
+    }
+    Ok(chains)
+}
+
+/// Finds a known chain by registry name or chain id, ignoring case
+pub fn find(name: &str) -> Result<Option<ChainInfo>> {
+    Ok(all()?.into_values().map(|entry| entry.chain).find(|chain| {
+        chain.chain_name.eq_ignore_ascii_case(name) || chain.chain_id.eq_ignore_ascii_case(name)
+    }))
+}
+
+/// Downloads the `chain.json` of a chain, looking under `testnets/` when it isn't a mainnet
+async fn fetch(client: &reqwest::Client, name: &str) -> Result<ChainInfo> {
+    for url in [
+        format!("{}/{}/chain.json", REGISTRY_URL, name),
+        format!("{}/testnets/{}/chain.json", REGISTRY_URL, name),
+    ] {
+        let response = client
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(20))
+            .send()
+            .await
+            .with_context(|| format!("Error requesting {}", url))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            continue;
+        }
+        return response
+            .error_for_status()
+            .with_context(|| format!("Chain registry returned an error for {}", name))?
+            .json()
+            .await
+            .with_context(|| format!("Invalid chain.json for {}", name));
+    }
+    Err(anyhow!("Chain {} not found in the chain registry", name))
+}
+
+/// Fetches chains from the registry into the cache.
+///
+/// Every known chain is refreshed when `names` is empty. Chains that fail
+/// to download keep their previous entry.
+///
+/// ### Returns
+/// The names of the chains updated.
+///
+/// ### Errors
+/// Fails if no chain could be fetched or the cache can't be written.
+pub async fn update(names: &[String]) -> Result<Vec<String>> {
+    let names = if names.is_empty() {
+        all()?.into_keys().collect()
+    } else {
+        names.to_vec()
+    };
+
+    let client = reqwest::Client::new();
+    let results = futures::future::join_all(names.iter().map(|name| fetch(&client, name))).await;
+
+    let mut cache = read_cache()?;
+    let mut updated = Vec::new();
+    for (name, result) in names.iter().zip(results) {
+        match result {
+            Ok(chain) => {
+                cache.chains.insert(chain.chain_name.clone(), chain);
+                updated.push(name.clone());
+            }
+            Err(error) => tracing::warn!("Unable to update {}: {:#}", name, error),
+        }
+    }
+    if updated.is_empty() {
+        return Err(anyhow!("No chain could be fetched from the chain registry"));
+    }
+
+    cache.updated = Some(chrono::Utc::now());
+    let path = cache_path()?;
+    let contents = serde_json::to_string_pretty(&cache).context("Error serializing the cache")?;
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Error writing chain registry cache {}", path.display()))?;
+    Ok(updated)
+}
+
+/// Connects to an endpoint and checks it serves the expected chain
+async fn probe(url: String, chain_id: &str) -> Result<String> {
+    let check = async {
+        let client = grpc::Client::connect_url(&url).await?;
+        let info: tendermint::GetNodeInfoResponse = client
+            .query(
+                "/cosmos.base.tendermint.v1beta1.Service/GetNodeInfo",
+                tendermint::GetNodeInfoRequest {},
+            )
+            .await?;
+        let network = info
+            .default_node_info
+            .map(|info| info.network)
+            .unwrap_or_default();
+        if network != chain_id {
+            return Err(anyhow!("{} serves {}, not {}", url, network, chain_id));
+        }
+        Ok(())
+    };
+    tokio::time::timeout(PROBE_TIMEOUT, check)
+        .await
+        .map_err(|_| anyhow!("{} didn't answer within {:?}", url, PROBE_TIMEOUT))??;
+    Ok(url)
+}
+
+/// Picks the first gRPC endpoint of a chain that answers for the right chain id
+pub async fn healthy_endpoint(chain: &ChainInfo) -> Result<String> {
+    if chain.apis.grpc.is_empty() {
+        return Err(anyhow!(
+            "The chain registry lists no gRPC endpoint for {}, pass --grpc",
+            chain.chain_name
+        ));
+    }
+    let probes = chain
+        .apis
+        .grpc
+        .iter()
+        .map(|endpoint| Box::pin(probe(endpoint.url(), &chain.chain_id)));
+    let (url, _) = futures::future::select_ok(probes).await.with_context(|| {
+        format!(
+            "No healthy gRPC endpoint for {}, pass --grpc",
+            chain.chain_name
+        )
+    })?;
+    Ok(url)
+}
+
+/// Builder connecting to a registry chain, through `grpc` or the first healthy endpoint
+pub async fn builder(chain: &ChainInfo, grpc: Option<&str>) -> Result<cosmos::CosmosBuilder> {
+    let fee_token = chain.fees.fee_tokens.first().ok_or_else(|| {
+        anyhow!(
+            "The chain registry lists no fee token for {}",
+            chain.chain_name
+        )
+    })?;
+    let grpc_url = match grpc {
+        Some(grpc) => grpc.to_owned(),
+        None => healthy_endpoint(chain).await?,
+    };
+    tracing::info!("Using gRPC endpoint {} for {}", grpc_url, chain.chain_name);
+
+    let hrp = cosmos::AddressHrp::from_string(chain.bech32_prefix.clone())
+        .with_context(|| format!("Invalid bech32 prefix {}", chain.bech32_prefix))?;
+    let mut builder = cosmos::CosmosBuilder::new(&chain.chain_id, &fee_token.denom, hrp, grpc_url);
+
+    // The registry suggests a range of gas prices, fall back to the average at both ends
+    let low = fee_token.low_gas_price.or(fee_token.average_gas_price);
+    let high = fee_token.high_gas_price.or(fee_token.average_gas_price);
+    if let (Some(low), Some(high)) = (low, high) {
+        builder.set_gas_price(low, high);
+    }
+    Ok(builder)
+}
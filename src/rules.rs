@@ -0,0 +1,223 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Deserialize rules
+use serde::Deserialize;
+
+/// Daemon counters and the last block processed
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Poll interval
+use std::time::Duration;
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Amounts compared with the threshold
+use crate::amount::CoinAmount;
+/// Shared state passed to every command
+use crate::context::AppContext;
+/// Per-profile metrics
+use crate::daemon::Metrics;
+/// Core logic
+use crate::send::{self, SendRequest};
+/// Stop the rule when cancelled
+use crate::tasks::CancellationToken;
+/// Transfers of the watched address
+use crate::watch::{self, Direction, TransferEvent};
+/// Thresholds, signing keys and coin parsing
+use crate::{amount, keys, policy};
+
+/// An automation rule run by the daemon, a `[[daemon.rules]]` entry.
+///
+/// When `address` receives (or sends, with `when = "sent"`) more than
+/// `above` in a single transfer, `forward_percent` of that transfer is sent
+/// to `to`, signed with `key`.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Name used in logs
+    pub name: String,
+    /// Profile providing the network and endpoint
+    pub profile: String,
+    /// Address to monitor, must be the address of `key`
+    pub address: String,
+    /// Direction of the transfers that trigger the rule
+    #[serde(default = "default_when")]
+    pub when: Direction,
+    /// Transfers must be larger than this amount, e.g. 100osmo; also selects the denom
+    pub above: String,
+    /// Percentage of the transfer to forward, 1 to 100
+    pub forward_percent: u8,
+    /// Address receiving the forwarded funds
+    pub to: String,
+    /// Named key signing the forwards
+    #[serde(default = "default_key")]
+    pub key: String,
+    /// How often to poll for new blocks
+    #[serde(default = "default_interval", with = "humantime_serde")]
+    pub interval: Duration,
+}
+
+/// Rules trigger on received funds unless configured otherwise
+fn default_when() -> Direction {
+    Direction::Received
+}
+
+/// Rules sign with the default key unless configured otherwise
+fn default_key() -> String {
+    keys::DEFAULT_KEY.to_owned()
+}
+
+/// Default poll interval for rules
+fn default_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Amount to forward for an event, if the rule triggers.
+///
+/// `threshold` is the rule's `above` amount resolved to base units.
+pub fn evaluate(
+    threshold: &cosmos::Coin,
+    when: Direction,
+    forward_percent: u8,
+    event: &TransferEvent,
+) -> Result<Option<cosmos::Coin>> {
+    if event.direction != when {
+        return Ok(None);
+    }
+    let threshold_amount = CoinAmount::of(threshold, &threshold.denom)?;
+
+    // A transfer can move several denoms, only the rule's denom counts
+    for coin in event.amount.split(',') {
+        let coin = policy::parse_coin(coin)?;
+        if coin.denom != threshold.denom {
+            continue;
+        }
+        let transferred = CoinAmount::of(&coin, &threshold.denom)?;
+        if transferred <= threshold_amount {
+            return Ok(None);
+        }
+        let forward = transferred.percent(forward_percent.into())?;
+        return Ok(Some(forward.coin(&coin.denom)));
+    }
+    Ok(None)
+}
+
+/// Runs a rule until it fails or `cancel` is cancelled.
+///
+/// Forwards go through [`send::execute_transaction`], so they are held to the
+/// configured policies and recorded in the journal. A forward that is refused
+/// or fails is logged and counted, the rule keeps running. The rule's own
+/// forwards, transfers sent to `to`, never trigger it.
+///
+/// `progress` holds the last block the rule processed, a rule run again with
+/// it after a failure continues there, see [`watch::watch`].
+pub async fn run(
+    ctx: &AppContext,
+    rule: &Rule,
+    metrics: &Metrics,
+    progress: &AtomicI64,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if !(1..=100).contains(&rule.forward_percent) {
+        return Err(anyhow!(
+            "Rule {}: forward_percent must be between 1 and 100",
+            rule.name
+        ));
+    }
+    let address: cosmos::Address = rule
+        .address
+        .parse()
+        .with_context(|| format!("Rule {}: invalid address {}", rule.name, rule.address))?;
+    let to: cosmos::Address = rule
+        .to
+        .parse()
+        .with_context(|| format!("Rule {}: invalid address {}", rule.name, rule.to))?;
+
+    // A watch-only key can't forward anything, no need to connect
+    let seed =
+        keys::seed_phrase(&ctx.config, &rule.key).with_context(|| format!("Rule {}", rule.name))?;
+
+    let chain = ctx.connect().await?;
+    let cosmos_addr = chain.cosmos();
+    let threshold = amount::resolve(cosmos_addr, &rule.above.parse()?).await?;
+
+    // Only the owner of the watched address can forward its funds
+    let wallet = ctx.wallet(cosmos_addr, &seed)?;
+    if wallet.get_address() != address {
+        return Err(anyhow!(
+            "Rule {}: key {} is {}, not the watched address {}",
+            rule.name,
+            rule.key,
+            wallet,
+            address
+        ));
+    }
+    tracing::info!(
+        "[{}] Rule {}: forwarding {}% of transfers {:?} above {}{} to {}",
+        rule.profile,
+        rule.name,
+        rule.forward_percent,
+        rule.when,
+        threshold.amount,
+        threshold.denom,
+        to
+    );
+
+    // Funds are only forwarded for transfers in blocks the second endpoint has too
+    let verifier = ctx.verifier(&chain).await?;
+    let (chain, wallet, threshold) = (&chain, &wallet, &threshold);
+    watch::watch(
+        cosmos_addr,
+        address,
+        rule.interval,
+        verifier.as_ref(),
+        progress,
+        cancel,
+        |event| async move {
+            metrics.events.fetch_add(1, Ordering::Relaxed);
+            // Forwards are sent transfers too, a `when = "sent"` rule would trigger itself
+            if event.direction == Direction::Sent && event.counterparty == to.to_string() {
+                return Ok(());
+            }
+            let Some(coin) = evaluate(threshold, rule.when, rule.forward_percent, &event)? else {
+                return Ok(());
+            };
+            tracing::info!(
+                "[{}] Rule {} triggered by {}: forwarding {}{} to {}",
+                rule.profile,
+                rule.name,
+                event.txhash,
+                coin.amount,
+                coin.denom,
+                to
+            );
+
+            let request = SendRequest {
+                coin,
+                destination: to,
+                cw20: None,
+            };
+            let result =
+                send::execute_transaction(chain, wallet, &request, &ctx.config.policy).await;
+            match result.and_then(|response| Ok(response.check()?)) {
+                Ok(()) => {
+                    metrics.forwards.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(error) => {
+                    metrics.errors.fetch_add(1, Ordering::Relaxed);
+                    tracing::error!(
+                        "[{}] Rule {}: forward for {} failed: {:#}",
+                        rule.profile,
+                        rule.name,
+                        event.txhash,
+                        error
+                    );
+                }
+            }
+            Ok(())
+        },
+    )
+    .await
+}
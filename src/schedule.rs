@@ -0,0 +1,93 @@
+/// Error handling
+use anyhow::{anyhow, Result};
+
+/// Time between runs
+use std::time::Duration;
+
+/// Stop the schedule when cancelled
+use crate::tasks::{self, CancellationToken};
+
+/// When and how often to run a job
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Schedule {
+    /// Wait until this time before the first run
+    pub at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Time between the start of consecutive runs, a single run if missing
+    pub every: Option<Duration>,
+    /// Number of runs, unlimited if missing and `every` is set
+    pub count: Option<u32>,
+}
+
+impl Schedule {
+    /// A schedule running once, right away
+    pub fn is_immediate(&self) -> bool {
+        self.at.is_none() && self.every.is_none()
+    }
+}
+
+/// Runs `job` on a schedule.
+///
+/// Runs start at fixed intervals from the first one, so a slow run doesn't
+/// delay the ones after it. A failing run is logged and the schedule goes on;
+/// the failures are reported once every run is done. Cancelling `cancel`
+/// stops the schedule between runs, a run in progress is completed.
+///
+/// ### Errors
+/// Returns an error if any run failed, if `at` is in the past, or if
+/// cancelled before the first run.
+pub async fn run<F, Fut>(schedule: Schedule, cancel: &CancellationToken, mut job: F) -> Result<()>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    if let Some(at) = schedule.at {
+        let wait = (at - chrono::Utc::now())
+            .to_std()
+            .map_err(|_| anyhow!("Scheduled time {} is in the past", at.to_rfc3339()))?;
+        tracing::info!("Waiting until {} for the first run", at.to_rfc3339());
+        if !tasks::sleep(cancel, wait).await {
+            return Err(anyhow!("Interrupted before the first run"));
+        }
+    }
+
+    let runs = match schedule.every {
+        Some(_) => schedule.count,
+        None => Some(1),
+    };
+    let start = tokio::time::Instant::now();
+    let mut failed = 0;
+
+    let mut run = 1;
+    while runs.map_or(true, |runs| run <= runs) {
+        if let Err(error) = job(run).await {
+            failed += 1;
+            tracing::error!("Run {} failed: {:#}", run, error);
+        }
+        if runs == Some(run) {
+            break;
+        }
+
+        // Fixed rate from the first run
+        if let Some(every) = schedule.every {
+            let next = start + every * run;
+            tracing::info!(
+                "Next run in {}",
+                humantime::format_duration(Duration::from_secs(
+                    next.saturating_duration_since(tokio::time::Instant::now())
+                        .as_secs()
+                ))
+            );
+            let wait = next.saturating_duration_since(tokio::time::Instant::now());
+            if !tasks::sleep(cancel, wait).await {
+                tracing::info!("Schedule interrupted after {} runs", run);
+                break;
+            }
+        }
+        run += 1;
+    }
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(anyhow!("{} of {} runs failed", failed, run)),
+    }
+}
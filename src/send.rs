@@ -0,0 +1,252 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Chain operations
+use crate::chain::ChainClient;
+/// CW20 token contracts
+use crate::cw20;
+/// Rejected transactions
+use crate::error::TxFailed;
+/// Local transaction log
+use crate::journal;
+/// Spend policies
+use crate::policy::{self, PolicyConfig};
+/// Transaction building and broadcasting
+use crate::tx;
+
+/// A bank transfer emitted by a transaction, fees included
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    /// Address the funds left
+    pub sender: String,
+    /// Address the funds went to
+    pub recipient: String,
+    /// Amount moved, e.g. 110uosmo or a comma separated list of coins
+    pub amount: String,
+}
+
+/// Outcome of a broadcast transaction, with what it cost and what it moved
+#[derive(Serialize, Clone, Debug)]
+pub struct TxOutcome {
+    /// Transaction response code, 0 on success
+    pub code: u32,
+    /// Block the transaction was included in
+    pub height: i64,
+    /// Transaction txhash
+    pub txhash: String,
+    /// Gas limit of the transaction
+    pub gas_wanted: i64,
+    /// Gas the transaction actually used
+    pub gas_used: i64,
+    /// Fee paid, e.g. 3500uosmo, if the response carries the transaction
+    pub fee: Option<String>,
+    /// Bank transfers emitted by the transaction, in order
+    pub transfers: Vec<Transfer>,
+    /// Time of the block, RFC 3339, if the node reported it
+    pub timestamp: Option<String>,
+}
+
+impl TxOutcome {
+    /// Turns a non-zero response code into a [`TxFailed`] error
+    pub fn check(&self) -> Result<(), TxFailed> {
+        if self.code == 0 {
+            Ok(())
+        } else {
+            Err(TxFailed {
+                code: self.code,
+                height: self.height,
+                txhash: self.txhash.clone(),
+            })
+        }
+    }
+}
+
+impl From<tx::TxResponse> for TxOutcome {
+    fn from(result: tx::TxResponse) -> Self {
+        TxOutcome {
+            fee: journal::fee_from_response(&result),
+            transfers: transfers(&result),
+            code: result.code,
+            height: result.height,
+            txhash: result.txhash,
+            gas_wanted: result.gas_wanted,
+            gas_used: result.gas_used,
+            timestamp: Some(result.timestamp).filter(|timestamp| !timestamp.is_empty()),
+        }
+    }
+}
+
+impl std::fmt::Display for TxOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "txhash {} height {}", self.txhash, self.height)?;
+        if let Some(timestamp) = &self.timestamp {
+            write!(f, " at {}", timestamp)?;
+        }
+        write!(
+            f,
+            " code {}\ngas used {} of {}, fee {}",
+            self.code,
+            self.gas_used,
+            self.gas_wanted,
+            self.fee.as_deref().unwrap_or("unknown")
+        )?;
+        for transfer in &self.transfers {
+            write!(
+                f,
+                "\ntransfer {} from {} to {}",
+                transfer.amount, transfer.sender, transfer.recipient
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Bank `transfer` events of a transaction response, skipping incomplete ones
+pub fn transfers(response: &tx::TxResponse) -> Vec<Transfer> {
+    response
+        .events
+        .iter()
+        .filter(|event| event.r#type == "transfer")
+        .filter_map(|event| {
+            let attribute = |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key == key)
+                    .map(|attribute| attribute.value.clone())
+            };
+            Some(Transfer {
+                sender: attribute("sender")?,
+                recipient: attribute("recipient")?,
+                amount: attribute("amount")?,
+            })
+        })
+        .collect()
+}
+
+/// Funds to send, already resolved to base units
+#[derive(Clone, Debug)]
+pub struct SendRequest {
+    /// Coin to send, for CW20 tokens the denom is [`cw20::denom`]
+    pub coin: cosmos::Coin,
+    /// Destination address to receive the funds
+    pub destination: cosmos::Address,
+    /// CW20 contract to transfer tokens from, if any
+    pub cw20: Option<cosmos::Address>,
+}
+
+/// Executes a transaction.
+///
+/// This function performs the following steps:
+/// 1. Retrieves the balances from the given address.
+/// 2. Checks the send against the configured policies.
+/// 3. Sends the specified token amount to the destination address using the provided transaction details.
+/// 4. Records the attempt, successful or not, in the local transaction log, best-effort: a failed write is only logged.
+///
+/// ### Arguments
+/// * `chain` - The [`ChainClient`] to query and broadcast with, a live [`crate::chain::Chain`] or a mock.
+/// * `wallet` - The wallet from which the funds will be sent.
+/// * `request` - A reference to a [`SendRequest`] struct containing the transaction details:
+///   - `coin`: The amount to transfer in base units, e.g., "110uosmo".
+///   - `destination`: The wallet address that will receive the funds.
+///   - `cw20`: The CW20 contract to transfer tokens from, if any.
+/// * `policy` - Spend policies from the configuration file, checked against the local transaction log.
+///
+/// ### Returns
+/// Returns a [`TxOutcome`] struct containing:
+/// * `code` - A `u32` representing the transaction response code (0 indicates success, non-zero indicates failure).
+/// * `height` - An `i64` representing the block height where the transaction was included.
+/// * `txhash` - A `String` representing the transaction hash, useful for tracking the transaction on the blockchain.
+/// * `gas_wanted`, `gas_used` and `fee` - What the transaction cost.
+/// * `transfers` - The bank transfers it emitted, for reconciliation.
+/// * `timestamp` - The time of its block.
+///
+/// ### Errors
+/// This function may return an error in the following cases:
+/// - If the balance retrieval for the provided address fails
+/// - If the send violates a configured policy
+/// - If the transaction execution fails
+pub async fn execute_transaction(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    request: &SendRequest,
+    policy: &PolicyConfig,
+) -> Result<TxOutcome> {
+    // Get the address
+    let address = request.destination;
+
+    // Get balance
+    tracing::info!("Getting balances for address {}", address);
+
+    // Get all balances
+    let balances = chain
+        .all_balances(address)
+        .await
+        .context("Failed to retrieve all balances for the Cosmos address")?;
+
+    // Iterate over all Coins and for each one get the balance
+    // A Cosmos Address can contains several Coins
+    let mut addr_balances = String::new();
+
+    balances.iter().for_each(|balance| {
+        addr_balances += &format!("\nDenom: {}, Balance: {}", balance.denom, balance.amount);
+    });
+
+    tracing::info!("Balances: {}", addr_balances);
+
+    tracing::info!("Executing transaction...");
+
+    // Vec which contains the Coin to send => 100 uosmo
+    let amount: Vec<cosmos::Coin> = vec![request.coin.clone()];
+
+    // Show and record wallet which should match with your
+    // Wallet addr in https://testnet-trade.levana.finance/
+    tracing::info!("Sender Wallet address: {}", wallet);
+
+    // Destination Wallet
+    tracing::info!("Destination Wallet address: {}", address);
+
+    // Refuse sends over the configured limits
+    let history = journal::read_all()?;
+    policy::check_send(
+        policy,
+        &history,
+        &chain.chain_id(),
+        &wallet.to_string(),
+        &address.to_string(),
+        &amount[0],
+    )?;
+
+    // Build the message moving the funds
+    let journal_amount = tx::format_coins(&amount);
+    let message = match request.cw20 {
+        Some(contract) => {
+            let tokens = policy::parse_amount(&amount[0])?;
+            cw20::transfer(wallet.get_address(), contract, address, tokens)?
+        }
+        None => tx::msg_send(wallet.get_address(), address, amount),
+    };
+
+    // Execute transaction, the attempt is recorded in the journal
+    let result = tx::broadcast(
+        chain,
+        wallet,
+        vec![message],
+        &address.to_string(),
+        &journal_amount,
+    )
+    .await
+    .context(format!(
+        "Error executing the transaction at address {}",
+        address
+    ))?;
+
+    // Send Response
+    Ok(result.into())
+}
@@ -0,0 +1,650 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Encode protobuf messages
+use prost::Message;
+
+/// Txhash of a broadcast that timed out
+use sha2::{Digest, Sha256};
+
+/// Sequences shared between transactions
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Transaction protobuf types
+use cosmos::proto::cosmos::tx::signing::v1beta1::SignMode as ProtoSignMode;
+use cosmos::proto::cosmos::tx::v1beta1::{
+    mode_info, AuthInfo, BroadcastMode, BroadcastTxRequest, BroadcastTxResponse, Fee, ModeInfo,
+    SignDoc, SignerInfo, SimulateRequest, SimulateResponse, TxBody, TxRaw,
+};
+/// Contract executions, told apart by contract in the gas cache
+use cosmos::proto::cosmwasm::wasm::v1::MsgExecuteContract;
+
+/// Deadlines of chain calls
+use crate::deadline::{self, Phase};
+/// Broadcasts without a known result
+use crate::error::{TimedOut, Unconfirmed};
+/// Raw gRPC queries
+use crate::grpc;
+/// Progress reporting
+use crate::progress;
+/// Transaction messages
+use crate::tx::{Msg, TxResponse};
+
+/// Multiplier applied to the simulated gas
+const GAS_MULTIPLIER: f64 = 1.3;
+
+/// Code of transactions signed with a stale sequence
+pub const WRONG_SEQUENCE: u32 = 32;
+
+/// Code of transactions which ran out of gas
+pub const OUT_OF_GAS: u32 = 11;
+
+/// Signing modes selectable with `--sign-mode`
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SignMode {
+    /// SIGN_MODE_DIRECT, signs the protobuf encoded transaction
+    Direct,
+    /// SIGN_MODE_LEGACY_AMINO_JSON, signs the canonical amino JSON document
+    AminoJson,
+}
+
+impl From<SignMode> for ProtoSignMode {
+    fn from(mode: SignMode) -> Self {
+        match mode {
+            SignMode::Direct => ProtoSignMode::Direct,
+            SignMode::AminoJson => ProtoSignMode::LegacyAminoJson,
+        }
+    }
+}
+
+/// Everything needed to sign a transaction for an account
+pub struct Signer<'a> {
+    /// Wallet holding the private key
+    pub wallet: &'a cosmos::Wallet,
+    /// Chain id the signature is valid for
+    pub chain_id: String,
+    /// Account number of the wallet
+    pub account_number: u64,
+    /// Sequence to sign with
+    pub sequence: u64,
+}
+
+/// Protobuf Any of the wallet's secp256k1 public key
+pub fn public_key_any(wallet: &cosmos::Wallet) -> prost_types::Any {
+    secp256k1_key_any(wallet.public_key_bytes())
+}
+
+/// Protobuf Any of a compressed secp256k1 public key, e.g. of a wallet signing elsewhere
+pub fn secp256k1_key_any(key: &[u8]) -> prost_types::Any {
+    prost_types::Any {
+        type_url: "/cosmos.crypto.secp256k1.PubKey".to_owned(),
+        value: cosmos::proto::cosmos::crypto::secp256k1::PubKey { key: key.to_vec() }
+            .encode_to_vec(),
+    }
+}
+
+/// Builds the body of a transaction
+pub fn body(messages: &[Msg], memo: &str) -> TxBody {
+    TxBody {
+        messages: messages.iter().map(Msg::to_any).collect(),
+        memo: memo.to_owned(),
+        timeout_height: 0,
+        extension_options: vec![],
+        non_critical_extension_options: vec![],
+    }
+}
+
+/// Builds the auth info of a transaction signed by a single key
+pub fn auth_info(
+    public_key: prost_types::Any,
+    mode: SignMode,
+    sequence: u64,
+    fee: Fee,
+) -> AuthInfo {
+    AuthInfo {
+        signer_infos: vec![SignerInfo {
+            public_key: Some(public_key),
+            mode_info: Some(ModeInfo {
+                sum: Some(mode_info::Sum::Single(mode_info::Single {
+                    mode: ProtoSignMode::from(mode) as i32,
+                })),
+            }),
+            sequence,
+        }],
+        fee: Some(fee),
+        tip: None,
+    }
+}
+
+/// Canonical amino JSON sign document.
+///
+/// Keys are sorted explicitly so the result doesn't depend on how `serde_json`
+/// orders maps, and the output is compact, as required by the legacy amino
+/// signing rules.
+pub fn amino_sign_doc(
+    messages: &[Msg],
+    memo: &str,
+    fee: &Fee,
+    chain_id: &str,
+    account_number: u64,
+    sequence: u64,
+) -> Result<Vec<u8>> {
+    let msgs = messages
+        .iter()
+        .map(|msg| {
+            msg.amino.clone().ok_or_else(|| {
+                anyhow!(
+                    "{} has no amino JSON encoding, use --sign-mode direct",
+                    msg.type_url
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut fee_json = serde_json::json!({
+        "amount": crate::tx::amino_coins(&fee.amount),
+        "gas": fee.gas_limit.to_string(),
+    });
+    if !fee.granter.is_empty() {
+        fee_json["granter"] = fee.granter.clone().into();
+    }
+
+    let doc = serde_json::json!({
+        "account_number": account_number.to_string(),
+        "chain_id": chain_id,
+        "fee": fee_json,
+        "memo": memo,
+        "msgs": msgs,
+        "sequence": sequence.to_string(),
+    });
+    serde_json::to_vec(&sort(doc)).context("Error serializing amino sign document")
+}
+
+/// Recursively sorts the keys of a JSON value
+fn sort(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let sorted: std::collections::BTreeMap<_, _> = map
+                .into_iter()
+                .map(|(key, value)| (key, sort(value)))
+                .collect();
+            serde_json::Value::Object(sorted.into_iter().collect())
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(sort).collect())
+        }
+        value => value,
+    }
+}
+
+/// Signs a transaction with the given mode, returning the raw signed transaction
+#[tracing::instrument(name = "sign", level = "debug", skip_all)]
+pub fn sign(
+    signer: &Signer,
+    mode: SignMode,
+    messages: &[Msg],
+    memo: &str,
+    fee: Fee,
+) -> Result<TxRaw> {
+    let body = body(messages, memo);
+    let body_bytes = body.encode_to_vec();
+    let auth_info = auth_info(
+        public_key_any(signer.wallet),
+        mode,
+        signer.sequence,
+        fee.clone(),
+    );
+    let auth_info_bytes = auth_info.encode_to_vec();
+
+    // Bytes covered by the signature depend on the mode
+    let sign_bytes = match mode {
+        SignMode::Direct => SignDoc {
+            body_bytes: body_bytes.clone(),
+            auth_info_bytes: auth_info_bytes.clone(),
+            chain_id: signer.chain_id.clone(),
+            account_number: signer.account_number,
+        }
+        .encode_to_vec(),
+        SignMode::AminoJson => amino_sign_doc(
+            messages,
+            memo,
+            &fee,
+            &signer.chain_id,
+            signer.account_number,
+            signer.sequence,
+        )?,
+    };
+    let signature = signer.wallet.sign_bytes(&sign_bytes);
+
+    Ok(TxRaw {
+        body_bytes,
+        auth_info_bytes,
+        signatures: vec![signature.serialize_compact().to_vec()],
+    })
+}
+
+/// Simulates a transaction and returns the gas limit to use.
+///
+/// Signatures aren't verified during simulation, so an empty one is sent.
+#[tracing::instrument(name = "simulate", level = "debug", skip_all)]
+pub async fn estimate_gas(
+    client: &grpc::Client,
+    public_key: prost_types::Any,
+    messages: &[Msg],
+    memo: &str,
+    sequence: u64,
+) -> Result<u64> {
+    let tx = TxRaw {
+        body_bytes: body(messages, memo).encode_to_vec(),
+        auth_info_bytes: auth_info(public_key, SignMode::Direct, sequence, Fee::default())
+            .encode_to_vec(),
+        signatures: vec![vec![]],
+    };
+    #[allow(deprecated)]
+    let response: SimulateResponse = client
+        .query(
+            "/cosmos.tx.v1beta1.Service/Simulate",
+            SimulateRequest {
+                tx: None,
+                tx_bytes: tx.encode_to_vec(),
+            },
+        )
+        .await
+        .context("Error simulating transaction")?;
+    let gas_used = response
+        .gas_info
+        .ok_or_else(|| anyhow!("Simulation returned no gas info"))?
+        .gas_used;
+    Ok((gas_used as f64 * GAS_MULTIPLIER) as u64)
+}
+
+/// Fee paying for `gas_limit` in the network's gas denom.
+///
+/// Priced at the low end of the network's gas prices, like the first attempt
+/// of a transaction signed by the cosmos crate; the registry sets the range.
+pub fn fee(cosmos: &cosmos::Cosmos, gas_limit: u64) -> Fee {
+    let builder = cosmos.get_cosmos_builder();
+    let (low, _high) = builder.gas_price();
+    let amount = (gas_limit as f64 * low).ceil() as u128;
+    Fee {
+        amount: vec![cosmos::Coin {
+            denom: builder.gas_coin().to_owned(),
+            amount: amount.to_string(),
+        }],
+        gas_limit,
+        payer: String::new(),
+        granter: String::new(),
+    }
+}
+
+/// Estimated fee of a transaction from `wallet`, simulated with its current sequence
+pub async fn estimate_fee(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    wallet: &cosmos::Wallet,
+    messages: &[Msg],
+) -> Result<cosmos::Coin> {
+    let account = query_account(cosmos, wallet).await?;
+    let gas_limit = estimate_gas(
+        client,
+        public_key_any(wallet),
+        messages,
+        "",
+        account.sequence,
+    )
+    .await?;
+    fee(cosmos, gas_limit)
+        .amount
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No fee for gas limit {}", gas_limit))
+}
+
+/// Broadcasts a signed transaction without waiting for it to be included.
+///
+/// A transaction rejected by CheckTx is returned with its non-zero code.
+#[tracing::instrument(name = "broadcast", level = "debug", skip_all)]
+pub async fn broadcast_sync(client: &grpc::Client, tx: TxRaw) -> Result<TxResponse> {
+    let stage = progress::stage("Broadcasting");
+    let tx_bytes = tx.encode_to_vec();
+    let txhash = hex::encode_upper(Sha256::digest(&tx_bytes));
+    let response: BroadcastTxResponse = match client
+        .query(
+            "/cosmos.tx.v1beta1.Service/BroadcastTx",
+            BroadcastTxRequest {
+                tx_bytes,
+                mode: BroadcastMode::Sync as i32,
+            },
+        )
+        .await
+    {
+        Ok(response) => response,
+        // The node may have the transaction, so it's followed up like a dropped wait
+        Err(error) if error.is::<TimedOut>() => {
+            return Err(error.context(Unconfirmed { txhash }));
+        }
+        Err(error) => return Err(error.context("Error broadcasting transaction")),
+    };
+    let response = response
+        .tx_response
+        .ok_or_else(|| anyhow!("Broadcast returned no response"))?;
+
+    if response.code == 0 {
+        stage.finish(format!("Broadcast {}", response.txhash));
+    } else {
+        stage.finish(format!("Rejected with code {}", response.code));
+    }
+    Ok(response)
+}
+
+/// Waits for a broadcast transaction to be included in a block
+#[tracing::instrument(name = "confirm", level = "debug", skip_all)]
+pub async fn wait_for_inclusion(
+    cosmos: &cosmos::Cosmos,
+    response: TxResponse,
+) -> Result<TxResponse> {
+    // Rejected by CheckTx, it will never be included
+    if response.code != 0 {
+        return Ok(response);
+    }
+
+    // Declared before the stage so the spinner is cleared before the guard prints
+    let pending = PendingInclusion::new(Some(&response.txhash));
+    let stage = progress::stage(format!("Waiting for inclusion of {}", response.txhash));
+    let included = deadline::within(Phase::Confirm, &response.txhash, async {
+        Ok(cosmos.wait_for_transaction(&response.txhash).await?)
+    })
+    .await;
+    pending.finish();
+    let included = included.with_context(|| Unconfirmed {
+        txhash: response.txhash.clone(),
+    })?;
+    stage.finish(format!("Included at height {}", included.height));
+    Ok(included)
+}
+
+/// Tells how to follow up on a transaction when the wait for its inclusion is dropped, e.g. on Ctrl-C.
+///
+/// [`PendingInclusion::finish`] is called once the wait is over, with or
+/// without inclusion, anything else dropping the guard prints what to check.
+pub struct PendingInclusion<'a> {
+    /// Txhash of the broadcast transaction, `None` while it isn't known
+    txhash: Option<&'a str>,
+    /// Whether the wait is over
+    done: bool,
+}
+
+impl<'a> PendingInclusion<'a> {
+    /// Guards the wait for `txhash`, `None` if the transaction is signed and broadcast in the same call
+    pub fn new(txhash: Option<&'a str>) -> Self {
+        PendingInclusion {
+            txhash,
+            done: false,
+        }
+    }
+
+    /// Marks the wait as over
+    pub fn finish(mut self) {
+        self.done = true;
+    }
+}
+
+impl Drop for PendingInclusion<'_> {
+    fn drop(&mut self) {
+        if self.done || std::thread::panicking() {
+            return;
+        }
+        match self.txhash {
+            Some(txhash) => eprintln!(
+                "Stopped waiting for inclusion of {}. It was broadcast and may still be included, \
+                 check with `cli-tool tx status {}` before sending again",
+                txhash, txhash
+            ),
+            None => eprintln!(
+                "Stopped before the transaction was confirmed. It may have been broadcast and still be \
+                 included, check the recent transactions of the sender before sending again"
+            ),
+        }
+    }
+}
+
+/// Broadcasts a signed transaction and waits for it to be included in a block
+pub async fn broadcast_raw(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    tx: TxRaw,
+) -> Result<TxResponse> {
+    let response = broadcast_sync(client, tx).await?;
+    wait_for_inclusion(cosmos, response).await
+}
+
+/// Account number and next sequence of a wallet
+#[derive(Clone, Copy, Debug)]
+struct AccountState {
+    /// Account number
+    account_number: u64,
+    /// Sequence the next transaction signs with
+    sequence: u64,
+}
+
+/// What a [`SequenceCache`] remembers between transactions
+#[derive(Default)]
+struct CacheState {
+    /// Accounts by address
+    accounts: HashMap<String, AccountState>,
+    /// Gas limits by message shape, see [`shape`]
+    gas_limits: HashMap<String, u64>,
+}
+
+/// Sequences of the wallets signing through a [`crate::chain::Chain`], incremented locally.
+///
+/// Signing and broadcasting hold the cache so transactions reach the node in
+/// sequence order, waiting for inclusion doesn't. A transaction rejected
+/// before inclusion drops the wallet's entry, so the next one queries the
+/// chain again.
+///
+/// The simulated gas limit is remembered per message shape too, so a run of
+/// similar transactions, e.g. the bank sends of a batch, is simulated once.
+#[derive(Clone, Default)]
+pub struct SequenceCache {
+    /// Shared state
+    state: Arc<tokio::sync::Mutex<CacheState>>,
+}
+
+/// Message types, contracts and sizes of a transaction, transactions of the same shape use about the same gas.
+///
+/// The type alone isn't enough: executing another contract, or the same one
+/// with a much larger payload, can use far more gas than the remembered
+/// limit. Sizes are rounded up to a power of two, so sends whose amounts
+/// differ by a few digits still share the limit.
+fn shape(messages: &[Msg]) -> String {
+    messages
+        .iter()
+        .map(|msg| {
+            let contract = match msg.type_url.as_str() {
+                "/cosmwasm.wasm.v1.MsgExecuteContract" => {
+                    MsgExecuteContract::decode(msg.value.as_slice())
+                        .map(|execute| execute.contract)
+                        .unwrap_or_default()
+                }
+                _ => String::new(),
+            };
+            format!(
+                "{}@{}:{}",
+                msg.type_url,
+                contract,
+                msg.value.len().next_power_of_two()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Queries the account number and sequence of a wallet
+#[tracing::instrument(name = "account", level = "debug", skip_all)]
+async fn query_account(cosmos: &cosmos::Cosmos, wallet: &cosmos::Wallet) -> Result<AccountState> {
+    let account = deadline::within(Phase::Query, "account", async {
+        Ok(cosmos.get_base_account(wallet.get_address()).await?)
+    })
+    .await
+    .with_context(|| format!("Error getting account info for {}", wallet))?;
+    Ok(AccountState {
+        account_number: account.account_number,
+        sequence: account.sequence,
+    })
+}
+
+/// Whether a transaction failed because it was signed with a stale sequence
+fn is_sequence_mismatch(result: &Result<TxResponse>) -> bool {
+    match result {
+        Ok(response) => response.code == WRONG_SEQUENCE,
+        Err(error) => format!("{:#}", error).contains("account sequence mismatch"),
+    }
+}
+
+/// Signs and broadcasts a transaction for an account, without waiting for inclusion.
+///
+/// The transaction is simulated first unless `gas_limit` is known.
+async fn sign_and_submit(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    signer: &Signer<'_>,
+    messages: &[Msg],
+    mode: SignMode,
+    fee_granter: Option<cosmos::Address>,
+    gas_limit: Option<u64>,
+) -> Result<(TxResponse, u64)> {
+    let gas_limit = match gas_limit {
+        Some(gas_limit) => gas_limit,
+        None => {
+            let stage = progress::stage("Simulating");
+            let gas_limit = estimate_gas(
+                client,
+                public_key_any(signer.wallet),
+                messages,
+                "",
+                signer.sequence,
+            )
+            .await?;
+            stage.finish(format!("Simulated, gas limit {}", gas_limit));
+            gas_limit
+        }
+    };
+    tracing::debug!(
+        "Signing with {:?}, sequence {}, gas limit {}",
+        mode,
+        signer.sequence,
+        gas_limit
+    );
+
+    let mut fee = fee(cosmos, gas_limit);
+    if let Some(granter) = fee_granter {
+        tracing::info!("Fees paid by granter {}", granter);
+        fee.granter = granter.to_string();
+    }
+    let tx = sign(signer, mode, messages, "", fee)?;
+    Ok((broadcast_sync(client, tx).await?, gas_limit))
+}
+
+/// Builds, signs and broadcasts a transaction with an explicit signing mode.
+///
+/// When `fee_granter` is given the fee is paid from its feegrant allowance
+/// to the wallet instead of the wallet's own balance. With a [`SequenceCache`]
+/// the sequence and gas limit are taken from the cache instead of queried
+/// and simulated, and a sequence mismatch resyncs from the chain and retries once.
+pub async fn sign_and_broadcast(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    wallet: &cosmos::Wallet,
+    messages: &[Msg],
+    mode: SignMode,
+    fee_granter: Option<cosmos::Address>,
+    sequences: Option<&SequenceCache>,
+) -> Result<TxResponse> {
+    // Signers differ only in their sequence
+    let chain_id = cosmos.get_cosmos_builder().chain_id().to_owned();
+    let signer = |account: AccountState| Signer {
+        wallet,
+        chain_id: chain_id.clone(),
+        account_number: account.account_number,
+        sequence: account.sequence,
+    };
+
+    let Some(sequences) = sequences else {
+        let account = query_account(cosmos, wallet).await?;
+        let (response, _) = sign_and_submit(
+            cosmos,
+            client,
+            &signer(account),
+            messages,
+            mode,
+            fee_granter,
+            None,
+        )
+        .await?;
+        return wait_for_inclusion(cosmos, response).await;
+    };
+
+    let address = wallet.get_address().to_string();
+    let shape = shape(messages);
+    let mut resynced = false;
+    let response = loop {
+        let mut state = sequences.state.lock().await;
+        let account = match state.accounts.get(&address) {
+            Some(account) => *account,
+            None => query_account(cosmos, wallet).await?,
+        };
+        let gas_limit = state.gas_limits.get(&shape).copied();
+
+        let result = sign_and_submit(
+            cosmos,
+            client,
+            &signer(account),
+            messages,
+            mode,
+            fee_granter,
+            gas_limit,
+        )
+        .await;
+        match result {
+            Ok((response, gas_limit)) if response.code == 0 => {
+                state.accounts.insert(
+                    address.clone(),
+                    AccountState {
+                        sequence: account.sequence + 1,
+                        ..account
+                    },
+                );
+                state.gas_limits.insert(shape.clone(), gas_limit);
+                break response;
+            }
+            result => {
+                // Rejected, stale, or broadcast without confirmation and maybe included after all:
+                // the sequence isn't known any more, the next transaction queries it
+                state.accounts.remove(&address);
+                let result = result.map(|(response, _)| response);
+                if is_sequence_mismatch(&result) && !resynced {
+                    tracing::warn!(
+                        "Sequence {} of {} is stale, resyncing from the chain",
+                        account.sequence,
+                        address
+                    );
+                    resynced = true;
+                    continue;
+                }
+                return result;
+            }
+        }
+    };
+
+    let included = wait_for_inclusion(cosmos, response).await?;
+    // A remembered gas limit too low for this transaction is simulated again next time
+    if included.code == OUT_OF_GAS {
+        sequences.state.lock().await.gas_limits.remove(&shape);
+    }
+    Ok(included)
+}
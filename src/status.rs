@@ -0,0 +1,86 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Where reports are written
+use std::path::{Path, PathBuf};
+
+/// Stop reporting with the run
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Writes a status report, replacing `file` in one step or as a line on stderr.
+///
+/// The report is written to a temporary file renamed over `file`, so a
+/// reader never sees half of it.
+///
+/// ### Errors
+/// Fails if the file can't be written.
+pub fn write(file: Option<&Path>, report: &serde_json::Value) -> Result<()> {
+    let report = serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "pid": std::process::id(),
+        "status": report,
+    });
+    match file {
+        Some(file) => {
+            let partial = file.with_extension("partial");
+            std::fs::write(&partial, serde_json::to_vec_pretty(&report)?)
+                .with_context(|| format!("Error writing status report {}", partial.display()))?;
+            std::fs::rename(&partial, file)
+                .with_context(|| format!("Error writing status report {}", file.display()))
+        }
+        None => {
+            eprintln!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Writes `report()` with [`write`] every time the process receives SIGUSR1, without interrupting the run.
+///
+/// Reporting stops when the returned guard is dropped or `parent` is
+/// cancelled. Only Unix has SIGUSR1, elsewhere nothing is reported.
+pub fn report_on_sigusr1<F>(
+    parent: &CancellationToken,
+    file: Option<PathBuf>,
+    report: F,
+) -> DropGuard
+where
+    F: Fn() -> serde_json::Value + Send + 'static,
+{
+    let token = parent.child_token();
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let cancel = token.clone();
+        match signal(SignalKind::user_defined1()) {
+            Ok(mut signals) => {
+                tokio::spawn(async move {
+                    loop {
+                        tokio::select! {
+                            received = signals.recv() => {
+                                if received.is_none() {
+                                    return;
+                                }
+                                if let Err(error) = write(file.as_deref(), &report()) {
+                                    tracing::warn!("{:#}", error);
+                                }
+                            }
+                            _ = cancel.cancelled() => return,
+                        }
+                    }
+                });
+                tracing::debug!(
+                    "Send SIGUSR1 to process {} for a status report",
+                    std::process::id()
+                );
+            }
+            Err(error) => tracing::warn!("Status reports on SIGUSR1 unavailable: {}", error),
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (file, report);
+
+    token.drop_guard()
+}
@@ -0,0 +1,227 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Parse amounts
+use crate::amount;
+/// Raw gRPC queries
+use crate::grpc;
+/// Transaction messages
+use crate::tx::{self, Msg};
+
+/// Osmosis poolmanager types, not part of the cosmos protobuf crate
+pub mod proto {
+    /// A hop of a swap through a pool
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SwapAmountInRoute {
+        /// Pool to swap through
+        #[prost(uint64, tag = "1")]
+        pub pool_id: u64,
+        /// Denom received from the pool
+        #[prost(string, tag = "2")]
+        pub token_out_denom: String,
+    }
+
+    /// Swaps an exact amount in for at least a minimum amount out
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct MsgSwapExactAmountIn {
+        /// Address swapping
+        #[prost(string, tag = "1")]
+        pub sender: String,
+        /// Pools to swap through, in order
+        #[prost(message, repeated, tag = "2")]
+        pub routes: Vec<SwapAmountInRoute>,
+        /// Tokens given to the first pool
+        #[prost(message, optional, tag = "3")]
+        pub token_in: Option<cosmos::Coin>,
+        /// The swap fails if less than this is received
+        #[prost(string, tag = "4")]
+        pub token_out_min_amount: String,
+    }
+
+    /// Estimates the output of a swap without executing it
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EstimateSwapExactAmountInRequest {
+        /// First pool of the routes
+        #[prost(uint64, tag = "2")]
+        pub pool_id: u64,
+        /// Tokens in, e.g. 100uosmo
+        #[prost(string, tag = "3")]
+        pub token_in: String,
+        /// Pools to swap through, in order
+        #[prost(message, repeated, tag = "4")]
+        pub routes: Vec<SwapAmountInRoute>,
+    }
+
+    /// Estimated output of a swap
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EstimateSwapExactAmountInResponse {
+        /// Amount of the output denom received
+        #[prost(string, tag = "1")]
+        pub token_out_amount: String,
+    }
+
+    /// Spot price of a pool
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SpotPriceRequest {
+        /// Pool to price
+        #[prost(uint64, tag = "1")]
+        pub pool_id: u64,
+        /// Denom being priced
+        #[prost(string, tag = "2")]
+        pub base_asset_denom: String,
+        /// Denom the price is expressed in
+        #[prost(string, tag = "3")]
+        pub quote_asset_denom: String,
+    }
+
+    /// Spot price, in quote denom per base denom
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SpotPriceResponse {
+        /// Decimal price
+        #[prost(string, tag = "1")]
+        pub spot_price: String,
+    }
+}
+
+/// Expected result of a swap, shown before executing it
+#[derive(Serialize, Clone, Debug)]
+pub struct Quote {
+    /// Pool swapped through
+    pub pool_id: u64,
+    /// Tokens given, e.g. 100uosmo
+    pub token_in: String,
+    /// Denom received
+    pub token_out_denom: String,
+    /// Amount expected at the current pool state
+    pub expected_out: u128,
+    /// Minimum accepted after slippage, the swap fails below it
+    pub min_out: u128,
+    /// Spot price before the swap, output denom per input denom
+    pub spot_price: String,
+    /// How much worse the swap is than the spot price, in percent
+    pub price_impact: f64,
+}
+
+/// Parses a slippage tolerance such as 1% or 0.5%, in basis points
+pub fn parse_slippage(input: &str) -> Result<u128> {
+    let percent = input.trim().trim_end_matches('%');
+    let basis_points = amount::to_base_units(percent, 2)
+        .with_context(|| format!("Invalid slippage {}, e.g. 1% or 0.5%", input))?;
+    if basis_points >= 10_000 {
+        return Err(anyhow!("Slippage {} must be below 100%", input));
+    }
+    Ok(basis_points)
+}
+
+/// Routes of a single pool swap
+fn routes(pool_id: u64, token_out_denom: &str) -> Vec<proto::SwapAmountInRoute> {
+    vec![proto::SwapAmountInRoute {
+        pool_id,
+        token_out_denom: token_out_denom.to_owned(),
+    }]
+}
+
+/// Quotes a swap through one pool: the estimated output, the minimum accepted
+/// with the slippage tolerance and the price impact against the spot price.
+///
+/// ### Errors
+/// Fails if the pool doesn't exist or doesn't hold both denoms.
+pub async fn quote(
+    client: &grpc::Client,
+    pool_id: u64,
+    token_in: &cosmos::Coin,
+    token_out_denom: &str,
+    slippage_bps: u128,
+) -> Result<Quote> {
+    let token_in_text = tx::format_coins(std::slice::from_ref(token_in));
+
+    let estimate: proto::EstimateSwapExactAmountInResponse = client
+        .query(
+            "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn",
+            proto::EstimateSwapExactAmountInRequest {
+                pool_id,
+                token_in: token_in_text.clone(),
+                routes: routes(pool_id, token_out_denom),
+            },
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Error estimating swap of {} in pool {}",
+                token_in_text, pool_id
+            )
+        })?;
+    let expected_out: u128 = estimate
+        .token_out_amount
+        .parse()
+        .with_context(|| format!("Invalid estimated amount {}", estimate.token_out_amount))?;
+
+    let spot: proto::SpotPriceResponse = client
+        .query(
+            "/osmosis.poolmanager.v1beta1.Query/SpotPrice",
+            proto::SpotPriceRequest {
+                pool_id,
+                base_asset_denom: token_in.denom.clone(),
+                quote_asset_denom: token_out_denom.to_owned(),
+            },
+        )
+        .await
+        .with_context(|| format!("Error getting the spot price of pool {}", pool_id))?;
+
+    // Output at the spot price, as if the swap didn't move the pool
+    let amount_in: f64 = token_in
+        .amount
+        .parse()
+        .with_context(|| format!("Invalid amount {}", token_in.amount))?;
+    let spot_price: f64 = spot
+        .spot_price
+        .parse()
+        .with_context(|| format!("Invalid spot price {}", spot.spot_price))?;
+    let ideal_out = amount_in * spot_price;
+    let price_impact = if ideal_out > 0.0 {
+        (1.0 - expected_out as f64 / ideal_out) * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(Quote {
+        pool_id,
+        token_in: token_in_text,
+        token_out_denom: token_out_denom.to_owned(),
+        expected_out,
+        min_out: expected_out * (10_000 - slippage_bps) / 10_000,
+        spot_price: spot.spot_price,
+        price_impact,
+    })
+}
+
+/// Message swapping the tokens of a quote, failing if less than its minimum is received
+pub fn swap(sender: cosmos::Address, token_in: cosmos::Coin, quote: &Quote) -> Msg {
+    let description = format!(
+        "Swap {} for at least {}{} in pool {}",
+        quote.token_in, quote.min_out, quote.token_out_denom, quote.pool_id
+    );
+    let amino = serde_json::json!({
+        "routes": [{
+            "pool_id": quote.pool_id.to_string(),
+            "token_out_denom": quote.token_out_denom,
+        }],
+        "sender": sender.to_string(),
+        "token_in": { "amount": token_in.amount, "denom": token_in.denom },
+        "token_out_min_amount": quote.min_out.to_string(),
+    });
+    tx::message(
+        "/osmosis.poolmanager.v1beta1.MsgSwapExactAmountIn",
+        &proto::MsgSwapExactAmountIn {
+            sender: sender.to_string(),
+            routes: routes(quote.pool_id, &quote.token_out_denom),
+            token_in: Some(token_in),
+            token_out_min_amount: quote.min_out.to_string(),
+        },
+        description,
+    )
+    .with_amino("osmosis/poolmanager/swap-exact-amount-in", amino)
+}
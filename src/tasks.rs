@@ -0,0 +1,197 @@
+/// Error handling
+use anyhow::{anyhow, Result};
+
+/// Grace period after cancellation
+use std::time::Duration;
+
+/// Spawned tasks
+use tokio::task::JoinSet;
+
+/// Cancellation shared by a tree of tasks
+pub use tokio_util::sync::CancellationToken;
+
+/// How long cancelled tasks get to finish what they're doing, e.g. a broadcast, before they're aborted
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// Cancels `token` on the first Ctrl-C or SIGTERM, and exits right away on the second.
+///
+/// Installing the handler replaces the default of killing the process, so
+/// whatever runs must stop once the token is cancelled, see [`until_cancelled`].
+pub fn cancel_on_signals(token: CancellationToken) {
+    tokio::spawn(async move {
+        let Some(signal) = next_signal().await else {
+            return;
+        };
+        tracing::warn!("{}, stopping. Press Ctrl-C again to exit now", signal);
+        token.cancel();
+
+        if next_signal().await.is_some() {
+            std::process::exit(130);
+        }
+    });
+}
+
+/// Waits for the next Ctrl-C or SIGTERM, `None` if no handler could be installed
+async fn next_signal() -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(error) => {
+                tracing::warn!("SIGTERM can't be handled: {}", error);
+                return tokio::signal::ctrl_c().await.ok().map(|_| "Interrupted");
+            }
+        };
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.ok().map(|_| "Interrupted"),
+            _ = terminate.recv() => Some("Terminated"),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.ok().map(|_| "Interrupted")
+}
+
+/// Runs `future` until it completes or `token` is cancelled, dropping it in that case.
+///
+/// For work that doesn't watch the token itself and can stop at any await point.
+///
+/// ### Errors
+/// Returns an error if the token was cancelled first, or the error of `future`.
+pub async fn until_cancelled<T>(
+    token: &CancellationToken,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        result = future => result,
+        _ = token.cancelled() => Err(anyhow!("Interrupted")),
+    }
+}
+
+/// Sleeps for `duration`, or until `token` is cancelled.
+///
+/// ### Returns
+/// Whether the full duration elapsed, false if the token was cancelled.
+pub async fn sleep(token: &CancellationToken, duration: Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => true,
+        _ = token.cancelled() => false,
+    }
+}
+
+/// Tasks started together and stopped together.
+///
+/// Every task gets a child of the group's token and is expected to return
+/// once it's cancelled. The first task to fail, or panic, cancels its
+/// siblings; cancelling the parent token, e.g. on Ctrl-C, cancels them all.
+/// Tasks still running [`SHUTDOWN_GRACE`] after the cancellation are aborted,
+/// so no future outlives the group.
+pub struct TaskGroup {
+    /// Cancelled when the group stops
+    token: CancellationToken,
+    /// Running tasks, each returning its name and result
+    tasks: JoinSet<(String, Result<()>)>,
+}
+
+impl TaskGroup {
+    /// Empty group, cancelled along with `parent`
+    pub fn new(parent: &CancellationToken) -> Self {
+        TaskGroup {
+            token: parent.child_token(),
+            tasks: JoinSet::new(),
+        }
+    }
+
+    /// Token cancelled when the group stops, for tasks to watch
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Number of running tasks
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether no task is running
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Starts a task, named in the logs if it fails
+    pub fn spawn<F>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: std::future::Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = name.into();
+        self.tasks.spawn(async move { (name, task.await) });
+    }
+
+    /// Cancels every task and waits for them to stop.
+    ///
+    /// ### Errors
+    /// Returns the first error of a task, see [`TaskGroup::join`].
+    pub async fn shutdown(self) -> Result<()> {
+        self.token.cancel();
+        self.join().await
+    }
+
+    /// Waits for every task to finish, cancelling the others when one fails.
+    ///
+    /// ### Errors
+    /// Returns the first error of a task, or an error if one panicked.
+    pub async fn join(mut self) -> Result<()> {
+        let mut first_error = None;
+
+        // Until cancelled, tasks run as long as they need
+        while !self.token.is_cancelled() {
+            tokio::select! {
+                joined = self.tasks.join_next() => match joined {
+                    Some(joined) => {
+                        if let Err(error) = finished(joined) {
+                            first_error.get_or_insert(error);
+                            self.token.cancel();
+                        }
+                    }
+                    None => return Ok(()),
+                },
+                _ = self.token.cancelled() => {}
+            }
+        }
+
+        // Then they get a grace period to wrap up
+        let drain = async {
+            while let Some(joined) = self.tasks.join_next().await {
+                if let Err(error) = finished(joined) {
+                    first_error.get_or_insert(error);
+                }
+            }
+        };
+        if tokio::time::timeout(SHUTDOWN_GRACE, drain).await.is_err() {
+            tracing::warn!(
+                "{} tasks still running after {:?}, aborting them",
+                self.tasks.len(),
+                SHUTDOWN_GRACE
+            );
+            self.tasks.shutdown().await;
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Result of a finished task, logging failures with the task's name
+fn finished(joined: Result<(String, Result<()>), tokio::task::JoinError>) -> Result<()> {
+    match joined {
+        Ok((_, Ok(()))) => Ok(()),
+        Ok((name, Err(error))) => {
+            tracing::error!("Task {} failed: {:#}", name, error);
+            Err(error.context(format!("Task {} failed", name)))
+        }
+        Err(error) if error.is_cancelled() => Ok(()),
+        Err(error) => Err(anyhow!("Task panicked: {}", error)),
+    }
+}
@@ -0,0 +1,248 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Listening address of the metrics endpoint
+use std::net::SocketAddr;
+
+/// Durations of RPC calls and phases
+use std::time::{Duration, Instant};
+
+/// Phase timings collected for `--timings`
+use std::sync::Mutex;
+
+/// Prometheus exporter
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+
+/// Layered tracing subscriber
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Transactions broadcast, labelled by chain and response code
+pub const BROADCASTS: &str = "cli_tool_broadcasts_total";
+
+/// Broadcasts that errored or were rejected, labelled by chain and code
+pub const FAILURES: &str = "cli_tool_broadcast_failures_total";
+
+/// Duration of gRPC calls in seconds, labelled by method and outcome
+pub const RPC_DURATION: &str = "cli_tool_rpc_duration_seconds";
+
+/// Duration of broadcasts until inclusion in seconds, labelled by chain
+pub const BROADCAST_DURATION: &str = "cli_tool_broadcast_duration_seconds";
+
+/// Spans timed by `--timings`, in the order a transaction goes through them
+pub const PHASES: &[&str] = &[
+    "connect",
+    "account",
+    "simulate",
+    "sign",
+    "broadcast",
+    "confirm",
+];
+
+/// Histogram buckets in seconds, from a fast query to a slow inclusion
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Flushes exported traces when dropped, keep it alive until the program ends
+pub struct Guard {
+    /// Provider of the OTLP exporter, if enabled
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            for result in provider.force_flush() {
+                if let Err(error) = result {
+                    eprintln!("Error flushing traces: {}", error);
+                }
+            }
+        }
+    }
+}
+
+/// Installs the tracing subscriber logging at `level`.
+///
+/// With the `otlp` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` set, spans are
+/// also exported over OTLP, configured with the standard `OTEL_*` variables.
+/// With `timings`, the [`PHASES`] spans are timed for [`timing_report`].
+///
+/// ### Errors
+/// Returns an error if the OTLP exporter can't be created.
+pub fn init_tracing(level: tracing::Level, timings: bool) -> Result<Guard> {
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_filter(tracing_subscriber::filter::LevelFilter::from_level(level));
+
+    // Phase spans are debug spans, timed whatever the log level
+    let timings = timings.then(|| {
+        TimingLayer.with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
+            metadata.is_span() && PHASES.contains(&metadata.name())
+        }))
+    });
+
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some() {
+            let provider = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+                .install_batch(opentelemetry_sdk::runtime::Tokio)
+                .context("Error creating the OTLP trace exporter")?;
+            let otel = tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer("cli-tool"))
+                .with_filter(tracing_subscriber::filter::LevelFilter::from_level(level));
+            tracing_subscriber::registry()
+                .with(fmt)
+                .with(timings)
+                .with(otel)
+                .init();
+            return Ok(Guard {
+                provider: Some(provider),
+            });
+        }
+    }
+
+    tracing_subscriber::registry()
+        .with(fmt)
+        .with(timings)
+        .init();
+    Ok(Guard {
+        #[cfg(feature = "otlp")]
+        provider: None,
+    })
+}
+
+/// Serves Prometheus metrics on `addr`, e.g. 0.0.0.0:9100, for as long as the program runs.
+///
+/// ### Errors
+/// Returns an error if the address can't be bound.
+pub fn serve_metrics(addr: SocketAddr) -> Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_owned()), BUCKETS)
+        .context("Invalid histogram buckets")?
+        .install()
+        .with_context(|| format!("Error serving metrics on {}", addr))?;
+    tracing::info!("Serving Prometheus metrics on http://{}/metrics", addr);
+    Ok(())
+}
+
+/// Records a broadcast attempt, `code` is missing when it errored before a response
+pub fn record_broadcast(chain: &str, code: Option<u32>, duration: Duration) {
+    let code = code.map_or_else(|| "error".to_owned(), |code| code.to_string());
+    metrics::counter!(BROADCASTS, "chain" => chain.to_owned(), "code" => code.clone()).increment(1);
+    if code != "0" {
+        metrics::counter!(FAILURES, "chain" => chain.to_owned(), "code" => code).increment(1);
+    }
+    metrics::histogram!(BROADCAST_DURATION, "chain" => chain.to_owned())
+        .record(duration.as_secs_f64());
+}
+
+/// Records the duration of a gRPC call
+pub fn record_rpc(method: &'static str, ok: bool, duration: Duration) {
+    let outcome = if ok { "ok" } else { "error" };
+    metrics::histogram!(RPC_DURATION, "method" => method, "outcome" => outcome)
+        .record(duration.as_secs_f64());
+}
+
+/// Totals of a phase over a command
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PhaseTiming {
+    /// Times the phase ran
+    pub count: u32,
+    /// Time spent in it, adding up concurrent runs
+    pub total: Duration,
+    /// Longest run
+    pub max: Duration,
+}
+
+/// Timings by index in [`PHASES`]
+static TIMINGS: Mutex<[PhaseTiming; PHASES.len()]> = Mutex::new(
+    [PhaseTiming {
+        count: 0,
+        total: Duration::ZERO,
+        max: Duration::ZERO,
+    }; PHASES.len()],
+);
+
+/// Records a run of a phase of [`PHASES`], other names are ignored
+pub fn record_phase(name: &str, elapsed: Duration) {
+    let Some(index) = PHASES.iter().position(|phase| *phase == name) else {
+        return;
+    };
+    let mut timings = TIMINGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let timing = &mut timings[index];
+    timing.count += 1;
+    timing.total += elapsed;
+    timing.max = timing.max.max(elapsed);
+}
+
+/// Phases that ran so far with their timings, in [`PHASES`] order
+pub fn timings() -> Vec<(&'static str, PhaseTiming)> {
+    let timings = TIMINGS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    PHASES
+        .iter()
+        .zip(timings.iter())
+        .filter(|(_, timing)| timing.count > 0)
+        .map(|(phase, timing)| (*phase, *timing))
+        .collect()
+}
+
+/// Per-phase latency breakdown, e.g. to tell a slow endpoint from a slow chain
+pub fn timing_report() -> String {
+    let timings = timings();
+    if timings.is_empty() {
+        return "Timings: no phases ran".to_owned();
+    }
+    let mut lines = vec!["Timings:".to_owned()];
+    for (phase, timing) in timings {
+        let average = timing.total / timing.count;
+        lines.push(format!(
+            "  {:<10} {:>4}x  total {:>9.1?}  avg {:>9.1?}  max {:>9.1?}",
+            phase, timing.count, timing.total, average, timing.max
+        ));
+    }
+    lines.join("\n")
+}
+
+/// When a timed span was created
+struct Started(Instant);
+
+/// Layer timing [`PHASES`] spans from creation to close, awaits included
+struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        _attrs: &tracing::span::Attributes<'_>,
+        id: &tracing::span::Id,
+        ctx: LayerContext<'_, S>,
+    ) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: tracing::span::Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(Started(started)) = span.extensions().get::<Started>() {
+            record_phase(span.name(), started.elapsed());
+        }
+    }
+}
@@ -0,0 +1,397 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Deserialize templates
+use serde::{Deserialize, Serialize};
+
+/// Parameters and template files
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Staking and distribution protobuf types
+use cosmos::proto::cosmos::distribution::v1beta1 as distribution;
+use cosmos::proto::cosmos::staking::v1beta1 as staking;
+
+/// Core logic
+use crate::amount::CoinAmount;
+use crate::tx::{self, Msg};
+use crate::{amount, config, grpc};
+
+/// Templates shipped with the tool
+const BUILT_IN: &[&str] = &[
+    include_str!("templates/monthly-ops.yaml"),
+    include_str!("templates/restake.yaml"),
+];
+
+/// A parameterized multi-message flow, read from YAML
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    /// Name used with `run-template`
+    pub name: String,
+    /// What the template does
+    #[serde(default)]
+    pub description: String,
+    /// Parameters referenced as `{{name}}` in the steps
+    #[serde(default)]
+    pub params: BTreeMap<String, Param>,
+    /// Messages to send, in order, in a single transaction
+    pub steps: Vec<Step>,
+}
+
+/// A template parameter
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Param {
+    /// What the parameter is for
+    #[serde(default)]
+    pub description: String,
+    /// Value used when `--param` doesn't set it, required if missing
+    pub default: Option<String>,
+}
+
+/// A step of a template.
+///
+/// Amounts are either a coin such as `10osmo`, `N% of rewards` or
+/// `rest of rewards`, where rewards are the staking rewards pending on the
+/// validators of the `claim_rewards` steps.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Withdraw the staking rewards from a validator
+    ClaimRewards {
+        /// Validator operator address
+        validator: String,
+    },
+    /// Delegate to a validator
+    Delegate {
+        /// Validator operator address
+        validator: String,
+        /// Amount to delegate
+        amount: String,
+    },
+    /// Bank send
+    Send {
+        /// Destination address
+        to: String,
+        /// Amount to send
+        amount: String,
+    },
+}
+
+/// Where user-defined templates are read from, `templates/` next to the config file
+pub fn user_dir() -> Result<PathBuf> {
+    let path = config::path()?;
+    Ok(path
+        .parent()
+        .map(|dir| dir.join("templates"))
+        .unwrap_or_else(|| PathBuf::from("templates")))
+}
+
+/// Parses a template
+pub fn parse(yaml: &str) -> Result<Template> {
+    serde_yaml::from_str(yaml).context("Invalid template")
+}
+
+/// Built-in and user-defined templates by name, user templates replacing built-in ones
+pub fn all() -> Result<BTreeMap<String, Template>> {
+    let mut templates = BTreeMap::new();
+    for yaml in BUILT_IN {
+        let template = parse(yaml)?;
+        templates.insert(template.name.clone(), template);
+    }
+
+    let dir = user_dir()?;
+    if dir.is_dir() {
+        let entries =
+            std::fs::read_dir(&dir).with_context(|| format!("Error reading {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if !path
+                .extension()
+                .is_some_and(|extension| extension == "yaml" || extension == "yml")
+            {
+                continue;
+            }
+            let yaml = std::fs::read_to_string(&path)
+                .with_context(|| format!("Error reading {}", path.display()))?;
+            let template = parse(&yaml).with_context(|| format!("In {}", path.display()))?;
+            templates.insert(template.name.clone(), template);
+        }
+    }
+    Ok(templates)
+}
+
+/// Replaces every `{{name}}` with its parameter value
+fn substitute(text: &str, values: &BTreeMap<String, String>) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| anyhow!("Unclosed {{{{ in {}", text))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = values
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown parameter {} in {}", name, text))?;
+        result.push_str(&rest[..start]);
+        result.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Amount of a step once parameters are substituted
+enum Amount {
+    /// A fixed amount
+    Fixed(amount::HumanAmount),
+    /// A percentage of the rewards
+    Percent(u128),
+    /// Rewards left after every percentage
+    Rest,
+}
+
+/// A step with its parameters substituted
+enum Resolved {
+    /// Withdraw rewards from a validator
+    ClaimRewards(String),
+    /// Delegate to a validator
+    Delegate(String, Amount),
+    /// Bank send to an address
+    Send(cosmos::Address, Amount),
+}
+
+/// Parses the amount of a step
+fn parse_amount(text: &str) -> Result<Amount> {
+    let text = text.trim();
+    if text == "rest of rewards" {
+        return Ok(Amount::Rest);
+    }
+    if let Some(percent) = text.strip_suffix("% of rewards") {
+        let percent: u128 = percent
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid percentage in {}", text))?;
+        return Ok(Amount::Percent(percent));
+    }
+    text.parse().map(Amount::Fixed)
+}
+
+/// Staking rewards pending on a validator, in the bond denom, truncated to whole base units
+async fn pending_rewards(
+    client: &grpc::Client,
+    delegator: cosmos::Address,
+    validator: &str,
+    bond_denom: &str,
+) -> Result<CoinAmount> {
+    let response: distribution::QueryDelegationRewardsResponse = client
+        .query(
+            "/cosmos.distribution.v1beta1.Query/DelegationRewards",
+            distribution::QueryDelegationRewardsRequest {
+                delegator_address: delegator.to_string(),
+                validator_address: validator.to_owned(),
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying rewards from {}", validator))?;
+    let Some(reward) = response
+        .rewards
+        .iter()
+        .find(|reward| reward.denom == bond_denom)
+    else {
+        return Ok(CoinAmount::ZERO);
+    };
+
+    // Decimal coins carry 18 decimal places, either written out or implied
+    let whole = match reward.amount.split_once('.') {
+        Some((whole, _)) => whole.parse(),
+        None => reward
+            .amount
+            .parse::<u128>()
+            .map(|amount| amount / 10u128.pow(18)),
+    };
+    whole
+        .map(CoinAmount)
+        .with_context(|| format!("Invalid reward amount {}", reward.amount))
+}
+
+/// Builds the messages of a template for `sender`.
+///
+/// Parameters missing from `values` fall back to their defaults. Returns the
+/// messages and the bank sends among them as (destination, coin), so they can
+/// be checked against the spend policies.
+///
+/// ### Errors
+/// Fails if a parameter without default is missing, an unknown parameter is
+/// given, or the percentages of the rewards add up to more than 100.
+pub async fn build(
+    cosmos: &cosmos::Cosmos,
+    template: &Template,
+    sender: cosmos::Address,
+    mut values: BTreeMap<String, String>,
+) -> Result<(Vec<Msg>, Vec<(cosmos::Address, cosmos::Coin)>)> {
+    if let Some(unknown) = values
+        .keys()
+        .find(|name| !template.params.contains_key(*name))
+    {
+        return Err(anyhow!(
+            "Template {} has no parameter {}",
+            template.name,
+            unknown
+        ));
+    }
+    for (name, param) in &template.params {
+        if !values.contains_key(name) {
+            let default = param.default.clone().ok_or_else(|| {
+                anyhow!(
+                    "Template {} requires --param {}=<value>",
+                    template.name,
+                    name
+                )
+            })?;
+            values.insert(name.clone(), default);
+        }
+    }
+
+    // Substitute first so amounts and addresses can be checked up front
+    let mut steps = Vec::new();
+    let mut validators = Vec::new();
+    let mut allocated: u128 = 0;
+    for step in &template.steps {
+        let step = match step {
+            Step::ClaimRewards { validator } => {
+                let validator = substitute(validator, &values)?;
+                validators.push(validator.clone());
+                Resolved::ClaimRewards(validator)
+            }
+            Step::Delegate { validator, amount } => Resolved::Delegate(
+                substitute(validator, &values)?,
+                parse_amount(&substitute(amount, &values)?)?,
+            ),
+            Step::Send { to, amount } => {
+                let to = substitute(to, &values)?;
+                let to = to
+                    .parse()
+                    .map_err(|error| anyhow!("Invalid address {}: {}", to, error))?;
+                Resolved::Send(to, parse_amount(&substitute(amount, &values)?)?)
+            }
+        };
+        if let Resolved::Delegate(_, Amount::Percent(percent))
+        | Resolved::Send(_, Amount::Percent(percent)) = &step
+        {
+            allocated = allocated.saturating_add(*percent);
+        }
+        steps.push(step);
+    }
+    if allocated > 100 {
+        return Err(anyhow!(
+            "Template {} allocates {}% of the rewards",
+            template.name,
+            allocated
+        ));
+    }
+
+    // Rewards are only queried when a step shares them out
+    let shares_rewards = steps.iter().any(|step| {
+        matches!(
+            step,
+            Resolved::Delegate(_, Amount::Percent(_) | Amount::Rest)
+                | Resolved::Send(_, Amount::Percent(_) | Amount::Rest)
+        )
+    });
+    let (rewards, bond_denom) = if shares_rewards {
+        if validators.is_empty() {
+            return Err(anyhow!(
+                "Template {} shares rewards without a claim_rewards step",
+                template.name
+            ));
+        }
+        let client = grpc::Client::connect(cosmos).await?;
+        let params: staking::QueryParamsResponse = client
+            .query(
+                "/cosmos.staking.v1beta1.Query/Params",
+                staking::QueryParamsRequest {},
+            )
+            .await
+            .context("Error querying staking params")?;
+        let bond_denom = params.params.unwrap_or_default().bond_denom;
+        let mut rewards = CoinAmount::ZERO;
+        for validator in &validators {
+            rewards = rewards
+                .checked_add(pending_rewards(&client, sender, validator, &bond_denom).await?)?;
+        }
+        tracing::info!("Pending rewards: {}{}", rewards, bond_denom);
+        (rewards, bond_denom)
+    } else {
+        (CoinAmount::ZERO, String::new())
+    };
+    let rest = rewards.checked_sub(rewards.percent(allocated)?)?;
+
+    // Amounts in base units
+    let bond_denom = &bond_denom;
+    let coin = |amount: Amount| async move {
+        match amount {
+            Amount::Fixed(human) => amount::resolve(cosmos, &human).await,
+            Amount::Percent(percent) => Ok(rewards.percent(percent)?.coin(bond_denom)),
+            Amount::Rest => Ok(rest.coin(bond_denom)),
+        }
+    };
+
+    let mut messages = Vec::new();
+    let mut sends = Vec::new();
+    for step in steps {
+        match step {
+            Resolved::ClaimRewards(validator) => messages.push(claim_rewards(sender, &validator)),
+            Resolved::Delegate(validator, amount) => {
+                messages.push(delegate(sender, &validator, coin(amount).await?))
+            }
+            Resolved::Send(to, amount) => {
+                let coin = coin(amount).await?;
+                sends.push((to, coin.clone()));
+                messages.push(tx::msg_send(sender, to, vec![coin]));
+            }
+        }
+    }
+    Ok((messages, sends))
+}
+
+/// Message withdrawing the staking rewards of `delegator` from a validator
+pub fn claim_rewards(delegator: cosmos::Address, validator: &str) -> Msg {
+    let amino = serde_json::json!({
+        "delegator_address": delegator.to_string(),
+        "validator_address": validator,
+    });
+    tx::message(
+        "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward",
+        &distribution::MsgWithdrawDelegatorReward {
+            delegator_address: delegator.to_string(),
+            validator_address: validator.to_owned(),
+        },
+        format!("Claim rewards from {}", validator),
+    )
+    .with_amino("cosmos-sdk/MsgWithdrawDelegationReward", amino)
+}
+
+/// Message delegating `amount` to a validator
+pub fn delegate(delegator: cosmos::Address, validator: &str, amount: cosmos::Coin) -> Msg {
+    let description = format!(
+        "Delegate {}{} to {}",
+        amount.amount, amount.denom, validator
+    );
+    let amino = serde_json::json!({
+        "amount": { "amount": amount.amount, "denom": amount.denom },
+        "delegator_address": delegator.to_string(),
+        "validator_address": validator,
+    });
+    tx::message(
+        "/cosmos.staking.v1beta1.MsgDelegate",
+        &staking::MsgDelegate {
+            delegator_address: delegator.to_string(),
+            validator_address: validator.to_owned(),
+            amount: Some(amount),
+        },
+        description,
+    )
+    .with_amino("cosmos-sdk/MsgDelegate", amino)
+}
@@ -0,0 +1,227 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Encode protobuf messages
+use prost::Message;
+
+/// Signed transactions
+use cosmos::proto::cosmos::tx::v1beta1::TxRaw;
+
+/// Chain operations
+use crate::chain::ChainClient;
+/// Raw gRPC queries
+use crate::grpc;
+/// Local transaction log
+use crate::journal;
+/// Broadcasting transactions signed elsewhere
+use crate::signing;
+/// Metrics
+use crate::telemetry;
+
+/// Transaction response returned by the chain
+pub type TxResponse = cosmos::proto::cosmos::base::abci::v1beta1::TxResponse;
+
+/// A message to include in a transaction
+#[derive(Clone, Debug)]
+pub struct Msg {
+    /// Protobuf type url, e.g. /cosmos.bank.v1beta1.MsgSend
+    pub type_url: String,
+    /// Protobuf encoded message
+    pub value: Vec<u8>,
+    /// Human readable description used in logs
+    pub description: String,
+    /// Legacy amino JSON form of the message, required by `--sign-mode amino-json`
+    pub amino: Option<serde_json::Value>,
+}
+
+impl Msg {
+    /// Attaches the amino JSON form of the message, e.g. `cosmos-sdk/MsgSend`
+    pub fn with_amino(mut self, amino_type: &str, value: serde_json::Value) -> Self {
+        self.amino = Some(serde_json::json!({ "type": amino_type, "value": value }));
+        self
+    }
+
+    /// Protobuf Any wrapping the message
+    pub fn to_any(&self) -> prost_types::Any {
+        prost_types::Any {
+            type_url: self.type_url.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl From<Msg> for cosmos::TxMessage {
+    fn from(msg: Msg) -> Self {
+        cosmos::TxMessage::new(msg.type_url, msg.value, msg.description)
+    }
+}
+
+/// Wraps a protobuf message so it can be added to a transaction
+pub fn message<M: Message>(type_url: &str, msg: &M, description: impl Into<String>) -> Msg {
+    Msg {
+        type_url: type_url.to_owned(),
+        value: msg.encode_to_vec(),
+        description: description.into(),
+        amino: None,
+    }
+}
+
+/// Amino JSON form of a list of coins
+pub fn amino_coins(coins: &[cosmos::Coin]) -> serde_json::Value {
+    coins
+        .iter()
+        .map(|coin| serde_json::json!({ "amount": coin.amount, "denom": coin.denom }))
+        .collect()
+}
+
+/// Protobuf timestamp of a time
+pub fn timestamp(time: chrono::DateTime<chrono::Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+/// Amino JSON form of a time, RFC 3339 in UTC
+pub fn amino_time(time: chrono::DateTime<chrono::Utc>) -> serde_json::Value {
+    time.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+        .into()
+}
+
+/// Bank send message
+pub fn msg_send(from: cosmos::Address, to: cosmos::Address, amount: Vec<cosmos::Coin>) -> Msg {
+    let description = format!("Send {} from {} to {}", format_coins(&amount), from, to);
+    let amino = serde_json::json!({
+        "amount": amino_coins(&amount),
+        "from_address": from.to_string(),
+        "to_address": to.to_string(),
+    });
+    message(
+        "/cosmos.bank.v1beta1.MsgSend",
+        &cosmos::proto::cosmos::bank::v1beta1::MsgSend {
+            from_address: from.to_string(),
+            to_address: to.to_string(),
+            amount,
+        },
+        description,
+    )
+    .with_amino("cosmos-sdk/MsgSend", amino)
+}
+
+/// Formats coins as a comma separated list, e.g. 110uosmo,5uion
+pub fn format_coins(coins: &[cosmos::Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| format!("{}{}", coin.amount, coin.denom))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Signs and broadcasts a transaction, recording the attempt in the journal.
+///
+/// `to` and `amount` describe the transaction in the journal: the destination
+/// or target of the messages, and the funds moved (may be empty).
+///
+/// ### Errors
+/// Returns an error if the broadcast fails. A journal that can't be written
+/// is only logged, the transaction having been sent. A transaction rejected
+/// with a non-zero code is returned as `Ok`, check `code` on the response.
+pub async fn broadcast(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    messages: Vec<Msg>,
+    to: &str,
+    amount: &str,
+) -> Result<TxResponse> {
+    broadcast_for(chain, wallet, wallet.get_address(), messages, to, amount).await
+}
+
+/// Signs and broadcasts a transaction moving the funds of `from`, e.g. an authz
+/// exec on behalf of a granter, journaling it as sent by `from` like [`broadcast`].
+///
+/// ### Errors
+/// Same as [`broadcast`].
+pub async fn broadcast_for(
+    chain: &impl ChainClient,
+    wallet: &cosmos::Wallet,
+    from: cosmos::Address,
+    messages: Vec<Msg>,
+    to: &str,
+    amount: &str,
+) -> Result<TxResponse> {
+    // Record details for the journal before anything moves
+    let entry = journal_entry(chain.chain_id(), from.to_string(), to, amount);
+
+    let started = std::time::Instant::now();
+    let result = chain.sign_and_broadcast(wallet, &messages).await;
+    record(entry, &result, started);
+
+    result.with_context(|| format!("Error broadcasting transaction to {}", to))
+}
+
+/// Broadcasts a transaction signed elsewhere, e.g. approved on a companion wallet, recording it like [`broadcast`].
+///
+/// ### Errors
+/// Returns an error if the broadcast fails, a journal that can't be written is only logged.
+pub async fn broadcast_signed(
+    cosmos: &cosmos::Cosmos,
+    client: &grpc::Client,
+    from: cosmos::Address,
+    signed: TxRaw,
+    to: &str,
+    amount: &str,
+) -> Result<TxResponse> {
+    let chain_id = cosmos.get_cosmos_builder().chain_id().to_owned();
+    let entry = journal_entry(chain_id, from.to_string(), to, amount);
+
+    let started = std::time::Instant::now();
+    let result = signing::broadcast_raw(cosmos, client, signed).await;
+    record(entry, &result, started);
+
+    result.with_context(|| format!("Error broadcasting transaction to {}", to))
+}
+
+/// Journal entry of a transaction about to be broadcast
+fn journal_entry(chain: String, from: String, to: &str, amount: &str) -> journal::Entry {
+    journal::Entry {
+        timestamp: chrono::Utc::now(),
+        chain,
+        from,
+        to: to.to_owned(),
+        amount: amount.to_owned(),
+        fee: None,
+        txhash: None,
+        code: None,
+        error: None,
+    }
+}
+
+/// Records the result of a broadcast in the metrics and the journal.
+///
+/// A journal that can't be written is logged rather than returned: the
+/// broadcast already happened, and callers treating an error as "not sent"
+/// (queue retries, the faucet rate limit) would send it again.
+fn record(mut entry: journal::Entry, result: &Result<TxResponse>, started: std::time::Instant) {
+    telemetry::record_broadcast(
+        &entry.chain,
+        result.as_ref().ok().map(|response| response.code),
+        started.elapsed(),
+    );
+
+    // Every attempt ends up in the journal, failed ones included
+    match result {
+        Ok(response) => {
+            entry.fee = journal::fee_from_response(response);
+            entry.txhash = Some(response.txhash.clone());
+            entry.code = Some(response.code);
+        }
+        Err(error) => entry.error = Some(format!("{:#}", error)),
+    }
+    if let Err(error) = journal::append(&entry) {
+        tracing::warn!(
+            "Error recording transaction {} in the journal: {:#}",
+            entry.txhash.as_deref().unwrap_or("(not sent)"),
+            error
+        );
+    }
+}
@@ -0,0 +1,412 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Decode transactions and messages
+use prost::Message;
+
+/// Serialize exports and reviews
+use serde::{Deserialize, Serialize};
+
+/// Transaction hashes
+use sha2::{Digest, Sha256};
+
+/// Binary fields in JSON
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+/// Encryption of exported files
+use argon2::Argon2;
+use chacha20poly1305::aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+/// Protobuf types of decoded messages
+use cosmos::proto::cosmos::authz::v1beta1 as authz;
+use cosmos::proto::cosmos::bank::v1beta1 as bank;
+use cosmos::proto::cosmos::gov::v1 as gov;
+use cosmos::proto::cosmos::tx::signing::v1beta1::SignMode;
+use cosmos::proto::cosmos::tx::v1beta1::{
+    mode_info, AuthInfo, GetTxRequest, GetTxResponse, TxBody, TxRaw,
+};
+use cosmos::proto::cosmwasm::wasm::v1 as wasm;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Coin formatting
+use crate::tx;
+
+/// Environment variable holding the passphrase of encrypted files
+pub const PASSPHRASE_ENV: &str = "CLI_TOOL_EXPORT_PASSPHRASE";
+
+/// First bytes of an encrypted file, followed by the salt, the nonce and the ciphertext
+const MAGIC: &[u8] = b"cli-tool encrypted tx v1\n";
+
+/// Length of the passphrase salt
+const SALT_LEN: usize = 16;
+
+/// Length of an XChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 24;
+
+/// Format of an exported transaction
+#[derive(clap::ValueEnum, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// The signed bytes with the chain, height and time of inclusion
+    #[default]
+    Json,
+    /// The raw `TxRaw` bytes alone, as broadcast
+    Proto,
+}
+
+/// A transaction exported for review
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    /// Chain the transaction was included on
+    pub chain_id: String,
+    /// Txhash, uppercase hex
+    pub txhash: String,
+    /// Block height of inclusion
+    pub height: i64,
+    /// Block time of inclusion
+    pub timestamp: String,
+    /// Signed `TxRaw` bytes, base64
+    pub tx: String,
+}
+
+/// Fetches an included transaction by txhash.
+///
+/// ### Errors
+/// Fails if the node doesn't know the transaction.
+pub async fn fetch(client: &grpc::Client, chain_id: &str, txhash: &str) -> Result<Export> {
+    let response: GetTxResponse = client
+        .query(
+            "/cosmos.tx.v1beta1.Service/GetTx",
+            GetTxRequest {
+                hash: txhash.to_owned(),
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying transaction {}", txhash))?;
+    let response = response
+        .tx_response
+        .ok_or_else(|| anyhow!("Transaction {} not found", txhash))?;
+    let tx = response
+        .tx
+        .ok_or_else(|| anyhow!("The node didn't return the bytes of {}", txhash))?;
+
+    // Tx and TxRaw share their field numbers, the bytes decode as either
+    let raw = TxRaw::decode(tx.value.as_slice()).context("Invalid transaction bytes")?;
+    Ok(Export {
+        chain_id: chain_id.to_owned(),
+        txhash: response.txhash,
+        height: response.height,
+        timestamp: response.timestamp,
+        tx: BASE64.encode(raw.encode_to_vec()),
+    })
+}
+
+/// Where a transaction landed, as far as a node knows
+#[derive(Serialize, Clone, Debug)]
+pub struct Inclusion {
+    /// Txhash, uppercase hex
+    pub txhash: String,
+    /// Block height of inclusion
+    pub height: i64,
+    /// Block time of inclusion
+    pub timestamp: String,
+    /// Response code, 0 on success
+    pub code: u32,
+    /// Log of a failed transaction
+    pub raw_log: String,
+}
+
+/// Looks up whether a transaction was included.
+///
+/// ### Returns
+/// `None` if the node doesn't know the transaction: it's not included yet, or was dropped from the mempool.
+///
+/// ### Errors
+/// Fails if the node can't be queried.
+pub async fn inclusion(client: &grpc::Client, txhash: &str) -> Result<Option<Inclusion>> {
+    let response: Result<GetTxResponse> = client
+        .query(
+            "/cosmos.tx.v1beta1.Service/GetTx",
+            GetTxRequest {
+                hash: txhash.to_owned(),
+            },
+        )
+        .await;
+    let response = match response {
+        Ok(response) => response.tx_response,
+        Err(error) if grpc::is_not_found(&error) => None,
+        Err(error) => return Err(error.context(format!("Error querying transaction {}", txhash))),
+    };
+    Ok(response.map(|response| Inclusion {
+        txhash: response.txhash,
+        height: response.height,
+        timestamp: response.timestamp,
+        code: response.code,
+        raw_log: response.raw_log,
+    }))
+}
+
+/// Encodes an export in `format`
+pub fn encode(export: &Export, format: Format) -> Result<Vec<u8>> {
+    match format {
+        Format::Json => {
+            let mut json = serde_json::to_vec_pretty(export)?;
+            json.push(b'\n');
+            Ok(json)
+        }
+        Format::Proto => BASE64
+            .decode(&export.tx)
+            .context("Invalid transaction bytes"),
+    }
+}
+
+/// Derives the key of an encrypted file from the passphrase
+fn key(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| anyhow!("Error deriving the key: {}", error))?;
+    XChaCha20Poly1305::new_from_slice(&key).map_err(|_| anyhow!("Invalid key length"))
+}
+
+/// Encrypts a file with a passphrase, using Argon2id and XChaCha20-Poly1305
+pub fn encrypt(plain: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = key(passphrase, &salt)?
+        .encrypt(&nonce, plain)
+        .map_err(|_| anyhow!("Error encrypting the export"))?;
+    Ok([MAGIC, &salt, &nonce, &ciphertext].concat())
+}
+
+/// Whether a file was written by [`encrypt`]
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Decrypts a file written by [`encrypt`].
+///
+/// ### Errors
+/// Fails if the file is truncated, the passphrase is wrong or the file was tampered with.
+pub fn decrypt(bytes: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| anyhow!("Not an encrypted transaction file"))?;
+    if rest.len() < SALT_LEN + NONCE_LEN {
+        return Err(anyhow!("Encrypted file is truncated"));
+    }
+    let (salt, rest) = rest.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    key(passphrase, salt)?
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong passphrase, or the file was modified"))
+}
+
+/// A message of a reviewed transaction
+#[derive(Serialize, Clone, Debug)]
+pub struct MessageReview {
+    /// Message type url
+    pub type_url: String,
+    /// Fields of known messages, or the base64 `value` of others
+    pub value: serde_json::Value,
+}
+
+/// A signature of a reviewed transaction
+#[derive(Serialize, Clone, Debug)]
+pub struct SignerReview {
+    /// Public key type url, missing if the account's key was already known on chain
+    pub key_type: Option<String>,
+    /// Public key, base64
+    pub public_key: Option<String>,
+    /// Sign mode, e.g. SIGN_MODE_DIRECT, or multisig
+    pub sign_mode: String,
+    /// Account sequence signed over
+    pub sequence: u64,
+    /// Signature, base64
+    pub signature: String,
+}
+
+/// Fee of a reviewed transaction
+#[derive(Serialize, Clone, Debug)]
+pub struct FeeReview {
+    /// Fee amount, e.g. 3500uosmo
+    pub amount: String,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// Account paying instead of the first signer, if any
+    pub payer: Option<String>,
+    /// Feegrant granter paying the fee, if any
+    pub granter: Option<String>,
+}
+
+/// Everything a transaction file says was signed
+#[derive(Serialize, Clone, Debug)]
+pub struct Review {
+    /// Hash of the signed bytes
+    pub txhash: String,
+    /// Details of the export, missing for a raw proto file
+    pub export: Option<Export>,
+    /// Whether the bytes hash to the exported txhash, i.e. they're exactly what was broadcast
+    pub hash_matches: Option<bool>,
+    /// Memo
+    pub memo: String,
+    /// Height after which the transaction is invalid, 0 for none
+    pub timeout_height: u64,
+    /// Messages, in execution order
+    pub messages: Vec<MessageReview>,
+    /// Fee and gas
+    pub fee: FeeReview,
+    /// Signatures, in signer order
+    pub signers: Vec<SignerReview>,
+}
+
+/// Decodes a transaction file: a JSON or proto export, decrypted beforehand if needed, or base64 `TxRaw` bytes.
+///
+/// ### Errors
+/// Fails if the file is neither, or the bytes don't decode as a transaction.
+pub fn review(bytes: &[u8]) -> Result<Review> {
+    let trimmed = bytes.trim_ascii_start();
+    let (export, raw) = if trimmed.starts_with(b"{") {
+        let export: Export = serde_json::from_slice(trimmed).context("Invalid JSON export")?;
+        let raw = BASE64
+            .decode(&export.tx)
+            .context("Invalid transaction bytes in the export")?;
+        (Some(export), raw)
+    } else {
+        // Base64 text, as in the tx_bytes of a broadcast request, or raw bytes
+        let text = std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| BASE64.decode(text.trim()).ok());
+        (None, text.unwrap_or_else(|| bytes.to_vec()))
+    };
+
+    let txhash = hex::encode_upper(Sha256::digest(&raw));
+    let hash_matches = export
+        .as_ref()
+        .map(|export| export.txhash.eq_ignore_ascii_case(&txhash));
+
+    let tx = TxRaw::decode(raw.as_slice()).context("Not a transaction file")?;
+    let body = TxBody::decode(tx.body_bytes.as_slice()).context("Invalid transaction body")?;
+    let auth_info =
+        AuthInfo::decode(tx.auth_info_bytes.as_slice()).context("Invalid transaction auth info")?;
+
+    let fee = auth_info.fee.unwrap_or_default();
+    let signers = auth_info
+        .signer_infos
+        .into_iter()
+        .zip(tx.signatures)
+        .map(|(signer, signature)| SignerReview {
+            key_type: signer.public_key.as_ref().map(|key| key.type_url.clone()),
+            public_key: signer
+                .public_key
+                .as_ref()
+                .map(|key| public_key(&key.type_url, &key.value)),
+            sign_mode: match signer.mode_info.and_then(|mode| mode.sum) {
+                Some(mode_info::Sum::Single(single)) => SignMode::try_from(single.mode)
+                    .map_or_else(
+                        |_| single.mode.to_string(),
+                        |mode| mode.as_str_name().to_owned(),
+                    ),
+                Some(mode_info::Sum::Multi(_)) => "multisig".to_owned(),
+                None => "unknown".to_owned(),
+            },
+            sequence: signer.sequence,
+            signature: BASE64.encode(signature),
+        })
+        .collect();
+
+    Ok(Review {
+        txhash,
+        export,
+        hash_matches,
+        memo: body.memo,
+        timeout_height: body.timeout_height,
+        messages: body
+            .messages
+            .iter()
+            .map(|any| message(&any.type_url, &any.value))
+            .collect(),
+        fee: FeeReview {
+            amount: tx::format_coins(&fee.amount),
+            gas_limit: fee.gas_limit,
+            payer: Some(fee.payer).filter(|payer| !payer.is_empty()),
+            granter: Some(fee.granter).filter(|granter| !granter.is_empty()),
+        },
+        signers,
+    })
+}
+
+/// Public key as base64, the plain key for secp256k1 and the encoded `Any` value otherwise
+fn public_key(type_url: &str, value: &[u8]) -> String {
+    match type_url {
+        "/cosmos.crypto.secp256k1.PubKey" => {
+            cosmos::proto::cosmos::crypto::secp256k1::PubKey::decode(value)
+                .map_or_else(|_| BASE64.encode(value), |key| BASE64.encode(key.key))
+        }
+        _ => BASE64.encode(value),
+    }
+}
+
+/// Decodes the messages the tool sends, others are shown as base64
+fn message(type_url: &str, value: &[u8]) -> MessageReview {
+    let decoded = match type_url {
+        "/cosmos.bank.v1beta1.MsgSend" => bank::MsgSend::decode(value).ok().map(|msg| {
+            serde_json::json!({
+                "from_address": msg.from_address,
+                "to_address": msg.to_address,
+                "amount": tx::format_coins(&msg.amount),
+            })
+        }),
+        "/ibc.applications.transfer.v1.MsgTransfer" => {
+            ibc_proto::ibc::applications::transfer::v1::MsgTransfer::decode(value)
+                .ok()
+                .map(|msg| {
+                    serde_json::json!({
+                        "source_port": msg.source_port,
+                        "source_channel": msg.source_channel,
+                        "token": msg.token.map(|token| format!("{}{}", token.amount, token.denom)),
+                        "sender": msg.sender,
+                        "receiver": msg.receiver,
+                        "timeout_timestamp": msg.timeout_timestamp,
+                        "memo": msg.memo,
+                    })
+                })
+        }
+        "/cosmwasm.wasm.v1.MsgExecuteContract" => {
+            wasm::MsgExecuteContract::decode(value).ok().map(|msg| {
+                serde_json::json!({
+                    "sender": msg.sender,
+                    "contract": msg.contract,
+                    "msg": serde_json::from_slice::<serde_json::Value>(&msg.msg)
+                        .unwrap_or_else(|_| BASE64.encode(&msg.msg).into()),
+                    "funds": tx::format_coins(&msg.funds),
+                })
+            })
+        }
+        "/cosmos.gov.v1.MsgVote" => gov::MsgVote::decode(value).ok().map(|msg| {
+            serde_json::json!({
+                "proposal_id": msg.proposal_id,
+                "voter": msg.voter,
+                "option": gov::VoteOption::try_from(msg.option)
+                    .map_or_else(|_| msg.option.to_string(), |option| option.as_str_name().to_owned()),
+            })
+        }),
+        "/cosmos.authz.v1beta1.MsgExec" => authz::MsgExec::decode(value).ok().map(|msg| {
+            serde_json::json!({
+                "grantee": msg.grantee,
+                "msgs": msg
+                    .msgs
+                    .iter()
+                    .map(|any| message(&any.type_url, &any.value))
+                    .collect::<Vec<_>>(),
+            })
+        }),
+        _ => None,
+    };
+    MessageReview {
+        type_url: type_url.to_owned(),
+        value: decoded.unwrap_or_else(|| serde_json::json!({ "value": BASE64.encode(value) })),
+    }
+}
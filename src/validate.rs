@@ -0,0 +1,144 @@
+/// Error handling
+use anyhow::{anyhow, Result};
+
+/// Account queries
+use crate::auth::{self, Activity};
+/// Raw gRPC queries
+use crate::grpc;
+/// Local transaction log
+use crate::journal;
+
+/// Parses an address, explaining what's wrong with a mistyped one.
+///
+/// Used as the parser of destination arguments, so a bad address is refused
+/// before connecting to the chain.
+///
+/// ### Errors
+/// Fails if the address isn't bech32, its checksum doesn't match, or it's not 20 or 32 bytes long.
+pub fn parse_address(input: &str) -> Result<cosmos::Address> {
+    let input = input.trim();
+    let Some((hrp, _)) = input.rsplit_once('1') else {
+        return Err(anyhow!(
+            "{} isn't an address, addresses look like osmo1...",
+            input
+        ));
+    };
+    if input.chars().any(|c| c.is_ascii_uppercase())
+        && input.chars().any(|c| c.is_ascii_lowercase())
+    {
+        return Err(anyhow!(
+            "{} mixes upper and lower case, addresses are all lowercase",
+            input
+        ));
+    }
+
+    let (_, data) = match bech32::decode(input) {
+        Ok(decoded) => decoded,
+        Err(bech32::DecodeError::Checksum(_)) => {
+            return Err(anyhow!(
+                "{} has an invalid checksum, it's most likely mistyped. Copy the address again instead of typing it",
+                input
+            ))
+        }
+        Err(error) => return Err(anyhow!("{} isn't a valid {} address: {}", input, hrp, error)),
+    };
+    // Accounts are 20 bytes, contracts and module-derived accounts 32
+    if data.len() != 20 && data.len() != 32 {
+        return Err(anyhow!(
+            "{} decodes to {} bytes, addresses are 20 or 32 bytes, it may be truncated",
+            input,
+            data.len()
+        ));
+    }
+    input
+        .to_lowercase()
+        .parse()
+        .map_err(|error| anyhow!("Invalid address {}: {}", input, error))
+}
+
+/// Human-readable part of an address, e.g. osmo
+pub fn prefix(address: cosmos::Address) -> String {
+    let address = address.to_string();
+    address
+        .rsplit_once('1')
+        .map(|(hrp, _)| hrp.to_owned())
+        .unwrap_or_default()
+}
+
+/// Checks a destination belongs to the chain the funds are sent on.
+///
+/// ### Errors
+/// Fails if the prefix of `destination` differs from the one of `sender`.
+pub fn check_prefix(destination: cosmos::Address, sender: cosmos::Address) -> Result<()> {
+    let (expected, found) = (prefix(sender), prefix(destination));
+    if expected != found {
+        return Err(anyhow!(
+            "{} is a {} address, but this chain uses {} addresses. To send to another chain use `ibc transfer`",
+            destination,
+            found,
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// Checks a destination before anything is signed: its prefix, and with `require_existing` that its account exists.
+///
+/// ### Errors
+/// Fails with what to do about it if a check fails.
+pub async fn check_destination(
+    client: &grpc::Client,
+    destination: cosmos::Address,
+    sender: cosmos::Address,
+    require_existing: bool,
+) -> Result<()> {
+    check_prefix(destination, sender)?;
+    if require_existing && !auth::account_exists(client, destination).await? {
+        return Err(anyhow!(
+            "{} has no account on chain, it never received funds or signed a transaction. \
+             Check the address, or leave out --require-existing to send to a new account",
+            destination
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the journal shows a successful send to or from `destination`
+pub fn known_destination(history: &[journal::Entry], destination: cosmos::Address) -> bool {
+    let destination = destination.to_string();
+    history.iter().any(|entry| {
+        (entry.to == destination && entry.code == Some(0)) || entry.from == destination
+    })
+}
+
+/// Warns about a first-time destination that looks brand-new or unused on chain.
+///
+/// Destinations found in `history` aren't queried, see [`known_destination`].
+///
+/// ### Returns
+/// What to tell the user before confirming the send, `None` if the destination was seen before or has signed transactions.
+///
+/// ### Errors
+/// Fails if the account can't be queried.
+pub async fn first_time_warning(
+    client: &grpc::Client,
+    history: &[journal::Entry],
+    destination: cosmos::Address,
+) -> Result<Option<String>> {
+    if known_destination(history, destination) {
+        return Ok(None);
+    }
+    Ok(match auth::activity(client, destination).await? {
+        Activity::Unused => Some(format!(
+            "{} was never sent to from here and looks like a brand-new, unused address: \
+             it has no account on chain. Check it was copied correctly",
+            destination
+        )),
+        Activity::ReceivedOnly => Some(format!(
+            "{} was never sent to from here and looks like an unused address: \
+             it received funds but never signed a transaction",
+            destination
+        )),
+        Activity::Active => None,
+    })
+}
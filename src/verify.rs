@@ -0,0 +1,173 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Waiting for the second endpoint to catch up
+use std::time::Duration;
+
+/// Block protobuf types
+use cosmos::proto::cosmos::base::tendermint::v1beta1 as tendermint;
+
+/// Raw gRPC queries
+use crate::grpc;
+
+/// How long the second endpoint gets to reach a height it is behind
+const CATCH_UP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the second endpoint is polled while catching up
+const CATCH_UP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Blocks the primary endpoint may lag behind the second one
+pub const MAX_LAG: i64 = 20;
+
+/// A second, independent gRPC endpoint that blocks at pinned heights are checked against.
+///
+/// A node on a fork, or one serving stale state, answers with blocks the
+/// rest of the network doesn't have, which a single endpoint can't tell.
+#[derive(Clone)]
+pub struct Verifier {
+    /// Endpoint URL, for messages
+    url: String,
+    /// Connection to the endpoint
+    client: grpc::Client,
+}
+
+impl Verifier {
+    /// Connects to `url` and checks it serves `chain_id`.
+    ///
+    /// ### Errors
+    /// Fails if the endpoint can't be reached or serves another chain.
+    pub async fn connect(url: &str, chain_id: &str) -> Result<Verifier> {
+        let client = grpc::Client::connect_url(url).await?;
+        let info: tendermint::GetNodeInfoResponse = client
+            .query(
+                "/cosmos.base.tendermint.v1beta1.Service/GetNodeInfo",
+                tendermint::GetNodeInfoRequest {},
+            )
+            .await
+            .with_context(|| format!("Error querying the verification endpoint {}", url))?;
+        let network = info
+            .default_node_info
+            .map(|info| info.network)
+            .unwrap_or_default();
+        if network != chain_id {
+            return Err(anyhow!(
+                "The verification endpoint {} serves {}, not {}",
+                url,
+                network,
+                chain_id
+            ));
+        }
+        Ok(Verifier {
+            url: url.to_owned(),
+            client,
+        })
+    }
+
+    /// Connection to the second endpoint, for queries to repeat on it
+    pub fn client(&self) -> &grpc::Client {
+        &self.client
+    }
+
+    /// Endpoint URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Checks the block at `height` has the same hash on `primary` and on the second endpoint.
+    ///
+    /// Waits up to [`CATCH_UP_TIMEOUT`] for the second endpoint to reach
+    /// `height` if it's behind.
+    ///
+    /// ### Errors
+    /// Fails if the hashes differ, or if the second endpoint doesn't reach `height` in time.
+    pub async fn check_block(&self, primary: &grpc::Client, height: i64) -> Result<()> {
+        let expected = block_hash(primary, height).await?;
+
+        let started = tokio::time::Instant::now();
+        while latest_height(&self.client).await? < height {
+            if started.elapsed() >= CATCH_UP_TIMEOUT {
+                return Err(anyhow!(
+                    "The verification endpoint {} didn't reach block {} within {:?}, \
+                     the primary endpoint may be on a fork",
+                    self.url,
+                    height,
+                    CATCH_UP_TIMEOUT
+                ));
+            }
+            tokio::time::sleep(CATCH_UP_INTERVAL).await;
+        }
+        let found = block_hash(&self.client, height).await?;
+        compare(height, &expected, &found, &self.url)
+    }
+
+    /// Checks `primary` isn't more than [`MAX_LAG`] blocks behind the second endpoint.
+    ///
+    /// ### Returns
+    /// The latest height of `primary`.
+    ///
+    /// ### Errors
+    /// Fails if `primary` lags further behind, which means it serves stale state.
+    pub async fn check_lag(&self, primary: &grpc::Client) -> Result<i64> {
+        let (latest, reference) =
+            tokio::try_join!(latest_height(primary), latest_height(&self.client))?;
+        if reference - latest > MAX_LAG {
+            return Err(anyhow!(
+                "The endpoint is at block {}, {} blocks behind the verification endpoint {}",
+                latest,
+                reference - latest,
+                self.url
+            ));
+        }
+        Ok(latest)
+    }
+}
+
+/// Compares the hashes of one block from two endpoints.
+///
+/// ### Errors
+/// Fails if they differ, naming both.
+pub fn compare(height: i64, expected: &[u8], found: &[u8], url: &str) -> Result<()> {
+    if expected != found {
+        return Err(anyhow!(
+            "Block {} has hash {} on the endpoint but {} on the verification endpoint {}, \
+             one of them is on a fork. Nothing pinned to this height is trusted",
+            height,
+            hex::encode_upper(expected),
+            hex::encode_upper(found),
+            url
+        ));
+    }
+    Ok(())
+}
+
+/// Latest block height of an endpoint
+pub async fn latest_height(client: &grpc::Client) -> Result<i64> {
+    let response: tendermint::GetLatestBlockResponse = client
+        .query(
+            "/cosmos.base.tendermint.v1beta1.Service/GetLatestBlock",
+            tendermint::GetLatestBlockRequest {},
+        )
+        .await
+        .context("Error getting the latest block")?;
+    response
+        .block
+        .and_then(|block| block.header)
+        .map(|header| header.height)
+        .ok_or_else(|| anyhow!("The latest block has no header"))
+}
+
+/// Hash of the block at `height`
+pub async fn block_hash(client: &grpc::Client, height: i64) -> Result<Vec<u8>> {
+    let response: tendermint::GetBlockByHeightResponse = client
+        .query(
+            "/cosmos.base.tendermint.v1beta1.Service/GetBlockByHeight",
+            tendermint::GetBlockByHeightRequest { height },
+        )
+        .await
+        .with_context(|| format!("Error getting block {}", height))?;
+    response
+        .block_id
+        .map(|id| id.hash)
+        .filter(|hash| !hash.is_empty())
+        .ok_or_else(|| anyhow!("Block {} has no hash", height))
+}
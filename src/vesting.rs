@@ -0,0 +1,146 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Decode account types
+use prost::Message;
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Account and vesting protobuf types
+use cosmos::proto::cosmos::auth::v1beta1 as auth;
+use cosmos::proto::cosmos::bank::v1beta1 as bank;
+use cosmos::proto::cosmos::vesting::v1beta1 as vesting;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Format coin lists
+use crate::tx;
+
+/// A vesting period of a periodic vesting account
+#[derive(Serialize, Clone, Debug)]
+pub struct Period {
+    /// When the period's tokens vest, RFC 3339
+    pub end: String,
+    /// Tokens vesting at the end of the period
+    pub amount: String,
+}
+
+/// Vesting schedule of an account
+#[derive(Serialize, Clone, Debug)]
+pub struct Schedule {
+    /// Account type, e.g. continuous or periodic
+    pub kind: String,
+    /// Tokens locked when the account was created
+    pub original_vesting: String,
+    /// When vesting starts, RFC 3339, missing for delayed and permanently locked accounts
+    pub start: Option<String>,
+    /// When every token has vested, RFC 3339, missing for permanently locked accounts
+    pub end: Option<String>,
+    /// Vesting periods of a periodic account
+    pub periods: Vec<Period>,
+}
+
+/// RFC 3339 form of a unix timestamp
+fn time(seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(seconds, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| seconds.to_string())
+}
+
+/// Schedule from the common part of every vesting account
+fn base(kind: &str, account: Option<vesting::BaseVestingAccount>) -> Schedule {
+    let account = account.unwrap_or_default();
+    Schedule {
+        kind: kind.to_owned(),
+        original_vesting: tx::format_coins(&account.original_vesting),
+        start: None,
+        end: Some(time(account.end_time)),
+        periods: Vec::new(),
+    }
+}
+
+/// Queries the vesting schedule of an account, `None` for regular accounts
+pub async fn schedule(client: &grpc::Client, address: cosmos::Address) -> Result<Option<Schedule>> {
+    let response: Result<auth::QueryAccountResponse> = client
+        .query(
+            "/cosmos.auth.v1beta1.Query/Account",
+            auth::QueryAccountRequest {
+                address: address.to_string(),
+            },
+        )
+        .await;
+
+    // Accounts which never received funds don't exist yet
+    let Some(account) = response.ok().and_then(|response| response.account) else {
+        return Ok(None);
+    };
+    let value = account.value.as_slice();
+
+    let schedule = match account.type_url.as_str() {
+        "/cosmos.vesting.v1beta1.ContinuousVestingAccount" => {
+            let account = vesting::ContinuousVestingAccount::decode(value)
+                .context("Invalid continuous vesting account")?;
+            Schedule {
+                start: Some(time(account.start_time)),
+                ..base("continuous", account.base_vesting_account)
+            }
+        }
+        "/cosmos.vesting.v1beta1.DelayedVestingAccount" => {
+            let account = vesting::DelayedVestingAccount::decode(value)
+                .context("Invalid delayed vesting account")?;
+            base("delayed", account.base_vesting_account)
+        }
+        "/cosmos.vesting.v1beta1.PeriodicVestingAccount" => {
+            let account = vesting::PeriodicVestingAccount::decode(value)
+                .context("Invalid periodic vesting account")?;
+
+            // Period lengths are relative to the previous period
+            let mut end = account.start_time;
+            let periods = account
+                .vesting_periods
+                .iter()
+                .map(|period| {
+                    end += period.length;
+                    Period {
+                        end: time(end),
+                        amount: tx::format_coins(&period.amount),
+                    }
+                })
+                .collect();
+            Schedule {
+                start: Some(time(account.start_time)),
+                periods,
+                ..base("periodic", account.base_vesting_account)
+            }
+        }
+        "/cosmos.vesting.v1beta1.PermanentLockedAccount" => {
+            let account = vesting::PermanentLockedAccount::decode(value)
+                .context("Invalid permanently locked account")?;
+            Schedule {
+                end: None,
+                ..base("permanent_locked", account.base_vesting_account)
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(schedule))
+}
+
+/// Balances an address can spend right now, excluding locked vesting tokens
+pub async fn spendable(
+    client: &grpc::Client,
+    address: cosmos::Address,
+) -> Result<Vec<cosmos::Coin>> {
+    let response: bank::QuerySpendableBalancesResponse = client
+        .query(
+            "/cosmos.bank.v1beta1.Query/SpendableBalances",
+            bank::QuerySpendableBalancesRequest {
+                address: address.to_string(),
+                pagination: None,
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying spendable balances of {}", address))?;
+    Ok(response.balances)
+}
@@ -0,0 +1,338 @@
+/// Error handling
+use anyhow::{anyhow, Context, Result};
+
+/// Serialize command output
+use serde::Serialize;
+
+/// Hash contract code
+use sha2::{Digest, Sha256};
+
+/// Local artifacts and builds
+use std::path::{Path, PathBuf};
+
+/// CosmWasm protobuf types
+use cosmos::proto::cosmwasm::wasm::v1 as wasm;
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Transaction messages
+use crate::tx::{self, Msg};
+
+/// Image used to build contracts reproducibly from a git repository
+pub const OPTIMIZER_IMAGE: &str = "cosmwasm/optimizer:0.16.1";
+
+/// Where the code to compare against comes from
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// A local .wasm file
+    File(PathBuf),
+    /// A .wasm artifact to download, e.g. from a release page
+    Url(String),
+    /// A git repository to build with [`OPTIMIZER_IMAGE`], at an optional revision
+    Git {
+        /// Repository url
+        url: String,
+        /// Branch, tag or commit
+        rev: Option<String>,
+    },
+}
+
+impl std::str::FromStr for Source {
+    type Err = anyhow::Error;
+
+    /// Parses `git+<url>[#rev]`, an http(s) url or a file path
+    fn from_str(input: &str) -> Result<Self> {
+        if let Some(repository) = input.strip_prefix("git+") {
+            let (url, rev) = match repository.split_once('#') {
+                Some((url, rev)) => (url, Some(rev.to_owned())),
+                None => (repository, None),
+            };
+            return Ok(Source::Git {
+                url: url.to_owned(),
+                rev,
+            });
+        }
+        if input.starts_with("https://") || input.starts_with("http://") {
+            if input.ends_with(".git") {
+                return Ok(Source::Git {
+                    url: input.to_owned(),
+                    rev: None,
+                });
+            }
+            return Ok(Source::Url(input.to_owned()));
+        }
+        Ok(Source::File(PathBuf::from(input)))
+    }
+}
+
+/// A candidate artifact and its hash
+#[derive(Serialize, Clone, Debug)]
+pub struct Artifact {
+    /// File name or url of the artifact
+    pub name: String,
+    /// Hex SHA-256 of the code
+    pub hash: String,
+}
+
+/// Outcome of comparing on-chain code with its source
+#[derive(Serialize, Clone, Debug)]
+pub struct Verification {
+    /// Code id checked
+    pub code_id: u64,
+    /// Address which uploaded the code
+    pub creator: String,
+    /// Hex SHA-256 of the code stored on chain
+    pub on_chain_hash: String,
+    /// Artifacts compared against
+    pub artifacts: Vec<Artifact>,
+    /// Artifact matching the on-chain code, if any
+    pub matched: Option<String>,
+}
+
+/// Hex SHA-256 of some code
+pub fn hash(code: &[u8]) -> String {
+    hex::encode(Sha256::digest(code))
+}
+
+/// Queries the hash of the code stored under a code id
+pub async fn code_info(client: &grpc::Client, code_id: u64) -> Result<wasm::CodeInfoResponse> {
+    let response: wasm::QueryCodeResponse = client
+        .query(
+            "/cosmwasm.wasm.v1.Query/Code",
+            wasm::QueryCodeRequest { code_id },
+        )
+        .await
+        .with_context(|| format!("Error querying code {}", code_id))?;
+    response
+        .code_info
+        .ok_or_else(|| anyhow!("Code {} not found", code_id))
+}
+
+/// Reads, downloads or builds the artifacts of a source
+pub async fn artifacts(source: &Source) -> Result<Vec<Artifact>> {
+    match source {
+        Source::File(path) => {
+            let code =
+                std::fs::read(path).with_context(|| format!("Error reading {}", path.display()))?;
+            Ok(vec![Artifact {
+                name: path.display().to_string(),
+                hash: hash(&code),
+            }])
+        }
+        Source::Url(url) => {
+            tracing::info!("Downloading {}", url);
+            let code = reqwest::get(url)
+                .await
+                .and_then(|response| response.error_for_status())
+                .with_context(|| format!("Error downloading {}", url))?
+                .bytes()
+                .await
+                .with_context(|| format!("Error downloading {}", url))?;
+            Ok(vec![Artifact {
+                name: url.clone(),
+                hash: hash(&code),
+            }])
+        }
+        Source::Git { url, rev } => build(url, rev.as_deref()).await,
+    }
+}
+
+/// Clones a repository and builds it with the CosmWasm optimizer
+async fn build(url: &str, rev: Option<&str>) -> Result<Vec<Artifact>> {
+    let dir = crate::journal::data_dir()?
+        .join("wasm-builds")
+        .join(&hash(url.as_bytes())[..16]);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("Error cleaning {}", dir.display()))?;
+    }
+
+    tracing::info!("Cloning {}", url);
+    run(
+        "git",
+        &["clone", "--quiet", url, &dir.display().to_string()],
+        None,
+    )
+    .await?;
+    if let Some(rev) = rev {
+        run("git", &["checkout", "--quiet", rev], Some(&dir)).await?;
+    }
+
+    // The optimizer writes the reproducible builds to artifacts/
+    tracing::info!(
+        "Building with {}, this can take a few minutes",
+        OPTIMIZER_IMAGE
+    );
+    let volume = format!("{}:/code", dir.display());
+    run(
+        "docker",
+        &["run", "--rm", "-v", &volume, OPTIMIZER_IMAGE],
+        Some(&dir),
+    )
+    .await?;
+
+    let mut artifacts = Vec::new();
+    let entries = std::fs::read_dir(dir.join("artifacts"))
+        .context("The optimizer produced no artifacts directory")?;
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "wasm")
+        {
+            let code = std::fs::read(&path)
+                .with_context(|| format!("Error reading {}", path.display()))?;
+            artifacts.push(Artifact {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                hash: hash(&code),
+            });
+        }
+    }
+    if artifacts.is_empty() {
+        return Err(anyhow!("The optimizer built no .wasm artifacts"));
+    }
+    Ok(artifacts)
+}
+
+/// Runs a command, failing on a non-zero exit status
+async fn run(program: &str, args: &[&str], dir: Option<&Path>) -> Result<()> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let status = command
+        .status()
+        .await
+        .with_context(|| format!("Error running {}, is it installed?", program))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "{} {} exited with {}",
+            program,
+            args.join(" "),
+            status
+        ));
+    }
+    Ok(())
+}
+
+/// Compares the code stored under a code id with the artifacts of a source.
+///
+/// ### Errors
+/// Returns an error if the code or the source can't be fetched. A mismatch is
+/// not an error, check `matched` on the result.
+pub async fn verify(client: &grpc::Client, code_id: u64, source: &Source) -> Result<Verification> {
+    let info = code_info(client, code_id).await?;
+    let on_chain_hash = hex::encode(&info.data_hash);
+
+    let artifacts = artifacts(source).await?;
+    let matched = artifacts
+        .iter()
+        .find(|artifact| artifact.hash == on_chain_hash)
+        .map(|artifact| artifact.name.clone());
+
+    Ok(Verification {
+        code_id,
+        creator: info.creator,
+        on_chain_hash,
+        artifacts,
+        matched,
+    })
+}
+
+/// Code id of the contract at an address
+pub async fn contract_code_id(client: &grpc::Client, contract: cosmos::Address) -> Result<u64> {
+    let response: wasm::QueryContractInfoResponse = client
+        .query(
+            "/cosmwasm.wasm.v1.Query/ContractInfo",
+            wasm::QueryContractInfoRequest {
+                address: contract.to_string(),
+            },
+        )
+        .await
+        .with_context(|| format!("Error querying contract {}", contract))?;
+    response
+        .contract_info
+        .map(|info| info.code_id)
+        .ok_or_else(|| anyhow!("{} is not a contract", contract))
+}
+
+/// Whether an address is a contract, checked before plain bank sends to it
+pub async fn is_contract(client: &grpc::Client, address: cosmos::Address) -> Result<bool> {
+    let response: Result<wasm::QueryContractInfoResponse> = client
+        .query(
+            "/cosmwasm.wasm.v1.Query/ContractInfo",
+            wasm::QueryContractInfoRequest {
+                address: address.to_string(),
+            },
+        )
+        .await;
+    match response {
+        Ok(response) => Ok(response.contract_info.is_some()),
+        Err(error) if grpc::is_not_found(&error) => Ok(false),
+        Err(error) => {
+            Err(error.context(format!("Error checking whether {} is a contract", address)))
+        }
+    }
+}
+
+/// Message executing a contract with a JSON message and optional funds
+pub fn execute(
+    sender: cosmos::Address,
+    contract: cosmos::Address,
+    json: serde_json::Value,
+    funds: Vec<cosmos::Coin>,
+) -> Result<Msg> {
+    let msg = serde_json::to_vec(&json).context("Error serializing contract message")?;
+    let description = format!("Execute {} with {}", contract, json);
+    let amino = serde_json::json!({
+        "contract": contract.to_string(),
+        "funds": tx::amino_coins(&funds),
+        "msg": json,
+        "sender": sender.to_string(),
+    });
+
+    Ok(tx::message(
+        "/cosmwasm.wasm.v1.MsgExecuteContract",
+        &wasm::MsgExecuteContract {
+            sender: sender.to_string(),
+            contract: contract.to_string(),
+            msg,
+            funds,
+        },
+        description,
+    )
+    .with_amino("wasm/MsgExecuteContract", amino))
+}
+
+/// Gas statistics of one message over repeated runs
+#[derive(Serialize, Clone, Debug)]
+pub struct GasStats {
+    /// Message profiled, as given on the command line
+    pub message: String,
+    /// Number of runs
+    pub runs: usize,
+    /// Lowest gas used
+    pub min: u64,
+    /// Average gas used
+    pub avg: u64,
+    /// Highest gas used
+    pub max: u64,
+}
+
+impl GasStats {
+    /// Statistics of the gas used by each run of a message
+    pub fn new(message: String, samples: &[u64]) -> Self {
+        let runs = samples.len();
+        GasStats {
+            message,
+            runs,
+            min: samples.iter().copied().min().unwrap_or_default(),
+            avg: samples.iter().sum::<u64>() / runs.max(1) as u64,
+            max: samples.iter().copied().max().unwrap_or_default(),
+        }
+    }
+}
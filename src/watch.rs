@@ -0,0 +1,279 @@
+/// Error handling
+use anyhow::{Context, Result};
+
+/// Emit events as JSON
+use serde::{Deserialize, Serialize};
+
+/// Poll interval
+use std::time::Duration;
+
+/// Last block processed, kept across restarts
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Raw gRPC queries
+use crate::grpc;
+/// Stop watching when cancelled
+use crate::tasks::{self, CancellationToken};
+/// Transactions searched by event
+use crate::tx::TxResponse;
+/// Checking blocks against a second endpoint
+use crate::verify::Verifier;
+
+/// Direction of a transfer relative to the watched address
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// The watched address received funds
+    Received,
+    /// The watched address sent funds
+    Sent,
+}
+
+/// A transfer involving the watched address
+#[derive(Serialize, Clone, Debug)]
+pub struct TransferEvent {
+    /// Block height where the transfer happened
+    pub height: i64,
+    /// Transaction txhash
+    pub txhash: String,
+    /// Whether the address received or sent the funds
+    pub direction: Direction,
+    /// The other side of the transfer
+    pub counterparty: String,
+    /// Amount transferred, e.g. 110uosmo
+    pub amount: String,
+}
+
+/// Extracts the transfers involving `address` from a transaction response.
+///
+/// Uses the bank `transfer` events emitted by the chain, so funds moved by
+/// contracts or other modules are detected as well.
+pub fn transfers(
+    address: &str,
+    response: &cosmos::proto::cosmos::base::abci::v1beta1::TxResponse,
+) -> Vec<TransferEvent> {
+    let mut found = Vec::new();
+
+    for event in response
+        .events
+        .iter()
+        .filter(|event| event.r#type == "transfer")
+    {
+        // Collect the attributes of this transfer
+        let mut sender = None;
+        let mut recipient = None;
+        let mut amount = None;
+        for attribute in &event.attributes {
+            match attribute.key.as_str() {
+                "sender" => sender = Some(attribute.value.clone()),
+                "recipient" => recipient = Some(attribute.value.clone()),
+                "amount" => amount = Some(attribute.value.clone()),
+                _ => (),
+            }
+        }
+        let (Some(sender), Some(recipient), Some(amount)) = (sender, recipient, amount) else {
+            continue;
+        };
+
+        let (direction, counterparty) = if recipient == address {
+            (Direction::Received, sender)
+        } else if sender == address {
+            (Direction::Sent, recipient)
+        } else {
+            continue;
+        };
+
+        found.push(TransferEvent {
+            height: response.height,
+            txhash: response.txhash.clone(),
+            direction,
+            counterparty,
+            amount,
+        });
+    }
+    found
+}
+
+/// Most recent transfers involving `address`, newest first.
+///
+/// Searches the `limit` latest transactions with a bank transfer from the
+/// address and those with one to it, so funds moved by contracts or other
+/// modules are found too. Only what the node's transaction index still holds
+/// can be found.
+///
+/// ### Errors
+/// Returns an error if the node can't be searched.
+pub async fn history(
+    client: &grpc::Client,
+    address: &str,
+    limit: usize,
+) -> Result<Vec<TransferEvent>> {
+    use cosmos::proto::cosmos::tx::v1beta1::{GetTxsEventRequest, GetTxsEventResponse, OrderBy};
+
+    let mut responses = Vec::new();
+    for attribute in ["transfer.sender", "transfer.recipient"] {
+        let condition = format!("{}='{}'", attribute, address);
+        // Nodes before SDK 0.50 read `events`, later ones `query`
+        #[allow(deprecated)]
+        let request = GetTxsEventRequest {
+            events: vec![condition.clone()],
+            query: condition,
+            order_by: OrderBy::Desc as i32,
+            limit: limit as u64,
+            ..Default::default()
+        };
+        let response: GetTxsEventResponse = client
+            .query("/cosmos.tx.v1beta1.Service/GetTxsEvent", request)
+            .await
+            .context("Error searching transactions")?;
+        responses.extend(response.tx_responses);
+    }
+    Ok(recent(address, responses, limit))
+}
+
+/// Transfers involving `address` in the `limit` latest successful transactions of `responses`, newest first
+pub fn recent(address: &str, mut responses: Vec<TxResponse>, limit: usize) -> Vec<TransferEvent> {
+    // A transaction both sending and receiving is found by both searches
+    responses.retain(|response| response.code == 0);
+    responses.sort_by(|a, b| {
+        b.height
+            .cmp(&a.height)
+            .then_with(|| a.txhash.cmp(&b.txhash))
+    });
+    responses.dedup_by(|a, b| a.txhash == b.txhash);
+    responses.truncate(limit);
+    responses
+        .iter()
+        .flat_map(|response| transfers(address, response))
+        .collect()
+}
+
+/// Watches an address, calling `on_event` for every transfer it receives or sends.
+///
+/// Polls the chain every `interval` for new blocks and fetches each
+/// transaction in them. Returns once `cancel` is cancelled, after the blocks
+/// being processed.
+///
+/// `progress` holds the last block processed. At 0 the watch starts after the
+/// latest block at the time of the call, otherwise right after it, so a watch
+/// restarted with the same `progress` after a failure misses no block. A
+/// block's transactions are all fetched before any of its transfers is
+/// reported, so a block is only reported again if `on_event` failed in it.
+///
+/// With a `verifier`, each poll checks the endpoint isn't lagging and each
+/// block is checked against the second endpoint before its transfers are
+/// reported, so a forked or stale node can't report transfers that didn't
+/// happen.
+///
+/// ### Errors
+/// Returns an error if the chain can't be queried, a block fails verification, or `on_event` fails.
+pub async fn watch<F, Fut>(
+    cosmos: &cosmos::Cosmos,
+    address: cosmos::Address,
+    interval: Duration,
+    verifier: Option<&Verifier>,
+    progress: &AtomicI64,
+    cancel: &CancellationToken,
+    mut on_event: F,
+) -> Result<()>
+where
+    F: FnMut(TransferEvent) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let address = address.to_string();
+    let primary = match verifier {
+        Some(_) => Some(grpc::Client::connect(cosmos).await?),
+        None => None,
+    };
+
+    // Continue after the last block processed, or start from the current tip
+    let mut last_height = progress.load(Ordering::Relaxed);
+    if last_height == 0 {
+        last_height = cosmos
+            .get_latest_block_info()
+            .await
+            .context("Error getting the latest block")?
+            .height;
+        progress.store(last_height, Ordering::Relaxed);
+        tracing::info!("Watching {} from block {}", address, last_height);
+    } else {
+        tracing::info!("Watching {} again after block {}", address, last_height);
+    }
+
+    loop {
+        if !tasks::sleep(cancel, interval).await {
+            tracing::info!("Stopped watching {} at block {}", address, last_height);
+            return Ok(());
+        }
+
+        let latest = cosmos
+            .get_latest_block_info()
+            .await
+            .context("Error getting the latest block")?
+            .height;
+        if let (Some(verifier), Some(primary)) = (verifier, &primary) {
+            verifier.check_lag(primary).await?;
+        }
+
+        // Process every block we haven't seen yet
+        for height in last_height + 1..=latest {
+            if let (Some(verifier), Some(primary)) = (verifier, &primary) {
+                verifier.check_block(primary, height).await?;
+            }
+            let block = cosmos
+                .get_block_info(height)
+                .await
+                .with_context(|| format!("Error getting block {}", height))?;
+
+            let mut responses = Vec::new();
+            for txhash in block.txhashes {
+                let (_, _, response) = cosmos
+                    .get_transaction_body(txhash.clone())
+                    .await
+                    .with_context(|| format!("Error getting transaction {}", txhash))?;
+                responses.push(response);
+            }
+
+            for event in responses
+                .iter()
+                .flat_map(|response| transfers(&address, response))
+            {
+                on_event(event).await?;
+            }
+            last_height = height;
+            progress.store(last_height, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Runs a shell command for an event.
+///
+/// The event is passed as JSON both on stdin and in the `CLI_TOOL_EVENT`
+/// environment variable, so the command can be a script or a `curl` webhook.
+pub async fn exec(command: &str, event: &TransferEvent) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let json = serde_json::to_string(event).context("Error serializing event")?;
+
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("CLI_TOOL_EVENT", &json)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Error running {}", command))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(json.as_bytes())
+            .await
+            .context("Error writing event to command stdin")?;
+    }
+
+    let status = child.wait().await.context("Error waiting for command")?;
+    if !status.success() {
+        // A failing hook shouldn't stop the watcher
+        tracing::warn!("Command {} exited with {}", command, status);
+    }
+    Ok(())
+}
@@ -0,0 +1,69 @@
+/// Command tree
+use clap::CommandFactory;
+
+/// Configured aliases
+use std::collections::HashMap;
+
+/// Raw command line arguments
+use std::ffi::OsString;
+
+/// Library under test
+use cli_tool::alias;
+use cli_tool::cli::Cli;
+
+/// Expands `args` against a `payroll` alias and an alias loop
+fn expand(args: &[&str]) -> anyhow::Result<Vec<String>> {
+    let aliases = HashMap::from([
+        (
+            "payroll".to_owned(),
+            "send-batch '/srv/pay roll.csv' --profile prod".to_owned(),
+        ),
+        ("weekly".to_owned(), "payroll --concurrency 4".to_owned()),
+        ("ping".to_owned(), "pong".to_owned()),
+        ("pong".to_owned(), "ping".to_owned()),
+    ]);
+    let args = args.iter().map(OsString::from).collect();
+    let expanded = alias::expand(args, &Cli::command(), || Ok(aliases))?;
+    Ok(expanded
+        .into_iter()
+        .map(|arg| arg.into_string().unwrap())
+        .collect())
+}
+
+#[test]
+fn aliases_split_without_a_shell() {
+    assert_eq!(
+        alias::split(r#"send 'balance * 0.25' "a \"b\" $HOME" c\ d"#).unwrap(),
+        ["send", "balance * 0.25", "a \"b\" $HOME", "c d"]
+    );
+    assert_eq!(alias::split("a '' b").unwrap(), ["a", "", "b"]);
+    assert!(alias::split("send 'osmo").is_err());
+    assert!(alias::split("send \\").is_err());
+}
+
+#[test]
+fn aliases_expand_in_place_of_the_command() {
+    assert_eq!(
+        expand(&["cli-tool", "--network", "juno", "weekly", "--dry-run"]).unwrap(),
+        [
+            "cli-tool",
+            "--network",
+            "juno",
+            "send-batch",
+            "/srv/pay roll.csv",
+            "--profile",
+            "prod",
+            "--concurrency",
+            "4",
+            "--dry-run"
+        ]
+    );
+
+    // Commands and built-in aliases are left alone, without loading the config
+    let untouched = ["cli-tool", "-q", "b", "osmo1..."];
+    let args = untouched.iter().map(OsString::from).collect();
+    let expanded = alias::expand(args, &Cli::command(), || panic!("config loaded")).unwrap();
+    assert_eq!(expanded.len(), untouched.len());
+
+    assert!(expand(&["cli-tool", "ping"]).is_err());
+}
@@ -0,0 +1,56 @@
+/// Library under test
+use cli_tool::amount::{HumanAmount, NumberLocale};
+
+/// Raw arguments
+use std::ffi::OsString;
+
+#[test]
+fn locales_read_their_own_separators() {
+    let normalize = |locale: NumberLocale, number| locale.normalize(number).unwrap();
+    assert_eq!(normalize(NumberLocale::Plain, "1234.56"), "1234.56");
+    assert_eq!(normalize(NumberLocale::En, "1,234.56"), "1234.56");
+    assert_eq!(normalize(NumberLocale::De, "1.234,56"), "1234.56");
+    assert_eq!(normalize(NumberLocale::En, "12,345,678"), "12345678");
+    assert_eq!(normalize(NumberLocale::De, "0,5"), "0.5");
+    assert_eq!(normalize(NumberLocale::En, "0.123"), "0.123");
+
+    let amount = HumanAmount::parse_in("1.234,5osmo", NumberLocale::De).unwrap();
+    assert_eq!(
+        (amount.amount.as_str(), amount.denom.as_str()),
+        ("1234.5", "osmo")
+    );
+}
+
+#[test]
+fn ambiguous_and_misplaced_separators_are_refused() {
+    let refused = |locale: NumberLocale, number| locale.normalize(number).unwrap_err().to_string();
+
+    // A thousand times off in one locale or the other
+    assert!(refused(NumberLocale::En, "1,234").contains("ambiguous"));
+    assert!(refused(NumberLocale::De, "1.234").contains("ambiguous"));
+    assert!(refused(NumberLocale::En, "123.456").contains("ambiguous"));
+
+    assert!(refused(NumberLocale::Plain, "1,5").contains("--number-locale"));
+    NumberLocale::En.normalize("1,23,456").unwrap_err();
+    NumberLocale::En.normalize("1.5.5").unwrap_err();
+    NumberLocale::De.normalize("1.5").unwrap_err();
+    NumberLocale::De.normalize("1,").unwrap_err();
+    NumberLocale::En.normalize("0,123").unwrap_err();
+    HumanAmount::parse_in("1,000,5osmo", NumberLocale::En).unwrap_err();
+}
+
+#[test]
+fn locale_is_found_in_raw_arguments() {
+    let args = |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).collect() };
+    let locale = |raw: &[&str]| NumberLocale::from_args(&args(raw)).unwrap();
+
+    assert_eq!(
+        locale(&["cli-tool", "--number-locale", "de", "send", "1,5osmo"]),
+        NumberLocale::De
+    );
+    assert_eq!(
+        locale(&["cli-tool", "send", "1,234.5osmo", "--number-locale=en"]),
+        NumberLocale::En
+    );
+    NumberLocale::from_args(&args(&["cli-tool", "--number-locale", "fr"])).unwrap_err();
+}
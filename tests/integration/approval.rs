@@ -0,0 +1,113 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Encode signatures the way a wallet hands them back
+use base64::Engine;
+
+/// Library under test
+use cli_tool::approval::{self, Pending, RemoteSigner, SignRequest};
+use cli_tool::tx;
+
+/// Fixtures
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Send of 110uosmo from the key of `TEST1_MNEMONIC`, waiting for its signature
+fn pending() -> Pending {
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let signer = RemoteSigner {
+        address: from.get_address(),
+        public_key: from.public_key_bytes().to_vec(),
+        chain_id: "osmo-test-5".to_owned(),
+        account_number: 42,
+        sequence: 7,
+    };
+    let messages = vec![tx::msg_send(
+        from.get_address(),
+        to.get_address(),
+        vec![coin(110, "uosmo")],
+    )];
+    Pending::new(&signer, &messages, Default::default(), "Send 110uosmo")
+}
+
+/// Signature a wallet with `mnemonic` returns for a request
+fn approve(mnemonic: &str, request: &SignRequest) -> Vec<u8> {
+    let sign_doc = base64::engine::general_purpose::STANDARD
+        .decode(&request.sign_doc)
+        .unwrap();
+    wallet(mnemonic)
+        .sign_bytes(&sign_doc)
+        .serialize_compact()
+        .to_vec()
+}
+
+#[test]
+fn request_survives_the_link() {
+    let pending = pending();
+    let link = pending.request.link().unwrap();
+    assert!(link.starts_with("cosmos-sign://sign?request="));
+    assert_eq!(SignRequest::from_link(&link).unwrap(), pending.request);
+    assert_eq!(pending.request.sequence, 7);
+    SignRequest::from_link("https://example.com").unwrap_err();
+}
+
+#[test]
+fn only_the_signers_approval_is_assembled() {
+    let pending = pending();
+    let signature = approve(TEST1_MNEMONIC, &pending.request);
+    let signed = pending.clone().complete(&signature).unwrap();
+    assert_eq!(signed.signatures, vec![signature]);
+
+    // Another key, e.g. the wrong account picked on the wallet
+    let error = pending
+        .complete(&approve(TEST2_MNEMONIC, &pending().request))
+        .unwrap_err();
+    assert!(error.to_string().contains("isn't from"));
+}
+
+#[test]
+fn signatures_are_read_as_hex_base64_or_link() {
+    let signature = vec![7u8; 64];
+    let base64 = base64::engine::general_purpose::STANDARD.encode(&signature);
+    assert_eq!(
+        approval::parse_signature(&hex::encode(&signature)).unwrap(),
+        signature
+    );
+    assert_eq!(
+        approval::parse_signature(&format!(" {base64}\n")).unwrap(),
+        signature
+    );
+    assert_eq!(
+        approval::parse_signature(&format!("cosmos-sign://signature?value={base64}")).unwrap(),
+        signature
+    );
+    approval::parse_signature(&hex::encode([7u8; 65])).unwrap_err();
+    approval::parse_signature("not a signature").unwrap_err();
+    approval::parse_public_key(&hex::encode([2u8; 33])).unwrap();
+    approval::parse_public_key(&hex::encode([2u8; 32])).unwrap_err();
+}
+
+#[test]
+fn only_the_accounts_own_public_key_is_used() {
+    let (from, other) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let address = from.get_address().to_string();
+    let key = from.public_key_bytes().to_vec();
+    let other_key = other.public_key_bytes().to_vec();
+    let check = |given: Option<&Vec<u8>>, on_chain: Option<&Vec<u8>>| {
+        approval::signer_public_key(&address, "osmo", given.cloned(), on_chain.cloned())
+    };
+
+    assert_eq!(check(None, Some(&key)).unwrap(), key);
+    assert_eq!(check(Some(&key), None).unwrap(), key);
+    assert_eq!(check(Some(&key), Some(&key)).unwrap(), key);
+
+    // A key of another account, whether or not the account has one on chain
+    let error = check(Some(&other_key), None).unwrap_err();
+    assert!(
+        error.to_string().contains(&other.get_address().to_string()),
+        "{}",
+        error
+    );
+    let error = check(Some(&other_key), Some(&key)).unwrap_err();
+    assert!(error.to_string().contains("on chain"), "{}", error);
+    check(None, None).unwrap_err();
+}
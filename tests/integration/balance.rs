@@ -0,0 +1,65 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::amount::CoinAmount;
+use cli_tool::balances;
+use cli_tool::chain::ChainClient;
+
+/// Fixtures
+use crate::mock::MockChain;
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+#[tokio::test]
+async fn all_balances_of_unknown_address_is_empty() {
+    let chain = MockChain::new();
+    let balances = chain
+        .all_balances(wallet(TEST1_MNEMONIC).get_address())
+        .await
+        .unwrap();
+    assert!(balances.is_empty());
+}
+
+#[tokio::test]
+async fn diff_covers_denoms_held_by_either_address() {
+    let (a, b) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new()
+        .fund(a.get_address(), vec![coin(1_000, "uosmo"), coin(5, "uion")])
+        .fund(b.get_address(), vec![coin(400, "uosmo")]);
+
+    let (balances_a, balances_b) = tokio::try_join!(
+        chain.all_balances(a.get_address()),
+        chain.all_balances(b.get_address())
+    )
+    .unwrap();
+    let diff = balances::diff(&balances_a, &balances_b).unwrap();
+
+    let denoms: Vec<_> = diff.iter().map(|denom| denom.denom.as_str()).collect();
+    assert_eq!(denoms, ["uion", "uosmo"]);
+    assert_eq!(diff[0].difference, "-5");
+    assert_eq!(diff[1].difference, "-600");
+}
+
+#[tokio::test]
+async fn scan_totals_every_address_in_order() {
+    let (a, b) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new()
+        .fund(a.get_address(), vec![coin(1_000, "uosmo")])
+        .fund(b.get_address(), vec![coin(400, "uosmo"), coin(5, "uion")]);
+
+    let scan = balances::scan(&chain, vec![b.get_address(), a.get_address()], 1)
+        .await
+        .unwrap();
+
+    let addresses: Vec<_> = scan
+        .addresses
+        .iter()
+        .map(|address| &address.address)
+        .collect();
+    assert_eq!(
+        addresses,
+        [&b.get_address().to_string(), &a.get_address().to_string()]
+    );
+    assert_eq!(scan.totals.get("uosmo"), CoinAmount(1_400));
+    assert_eq!(scan.totals.get("uion"), CoinAmount(5));
+}
@@ -0,0 +1,261 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::amount::CoinAmount;
+use cli_tool::batch;
+use cli_tool::policy::PolicyConfig;
+use cli_tool::send::SendRequest;
+use cli_tool::tasks::CancellationToken;
+
+/// Fixtures
+use crate::mock::{self, MockChain};
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+#[test]
+fn batch_file_skips_comments_and_names_bad_lines() {
+    let to = wallet(TEST2_MNEMONIC).get_address();
+    let input = format!(
+        "address,amount\n# payroll\n\n{},1.5osmo\n{} , 110uosmo\n",
+        to, to
+    );
+    let rows = batch::parse(&input).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!((rows[0].line, rows[1].line), (4, 5));
+    assert_eq!(rows[1].amount.to_string(), "110uosmo");
+
+    let error = batch::parse(&format!("{},1osmo\n{}\n", to, to)).unwrap_err();
+    assert!(error.to_string().starts_with("Line 2"), "{}", error);
+    assert!(batch::parse("# nothing\n").is_err());
+}
+
+#[tokio::test]
+async fn batch_reports_every_send_in_order_and_continues_after_failures() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(250, "uosmo")]);
+
+    let requests = [100, 200, 100]
+        .into_iter()
+        .enumerate()
+        .map(|(line, amount)| {
+            (
+                line + 1,
+                SendRequest {
+                    coin: coin(amount, "uosmo"),
+                    destination: to.get_address(),
+                    cw20: None,
+                },
+            )
+        })
+        .collect();
+    let path = crate::data_dir().join("batch-in-order.json");
+    let progress = batch::Progress::with_checkpoint(path.clone(), batch::Checkpoint::default());
+    let outcomes = batch::run(
+        &chain,
+        &from,
+        requests,
+        &PolicyConfig::default(),
+        1,
+        &progress,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let lines: Vec<_> = outcomes.iter().map(|outcome| outcome.line).collect();
+    assert_eq!(lines, [1, 2, 3]);
+    assert!(outcomes[0].succeeded());
+    assert_eq!(outcomes[1].code, Some(mock::INSUFFICIENT_FUNDS));
+    assert!(outcomes[2].succeeded());
+    assert_eq!(chain.broadcast_count(), 3);
+    assert_eq!(chain.balance(to.get_address(), "uosmo"), 200);
+
+    // The status report counts what's done
+    let status = progress.snapshot();
+    assert_eq!((status.total, status.succeeded, status.failed), (3, 2, 1));
+    assert!(status.in_flight.is_empty());
+    assert_eq!(status.sent.get("uosmo"), CoinAmount(200));
+
+    // Every broadcast is in the checkpoint file, a resumed run only sends the rejected line again
+    let checkpoint = batch::Checkpoint::load(&path).unwrap().unwrap();
+    assert_eq!(Some(&checkpoint), progress.checkpoint().as_ref());
+    assert_eq!(checkpoint.lines.len(), 3);
+    assert_eq!(
+        checkpoint.completed().into_iter().collect::<Vec<_>>(),
+        [1, 3]
+    );
+    assert!(checkpoint.unconfirmed().is_empty());
+}
+
+#[tokio::test]
+async fn cancelled_batches_start_no_further_sends() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(1_000, "uosmo")]);
+    let requests = (1..=2)
+        .map(|line| {
+            (
+                line,
+                SendRequest {
+                    coin: coin(100, "uosmo"),
+                    destination: to.get_address(),
+                    cw20: None,
+                },
+            )
+        })
+        .collect();
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let progress = batch::Progress::default();
+    let outcomes = batch::run(
+        &chain,
+        &from,
+        requests,
+        &PolicyConfig::default(),
+        1,
+        &progress,
+        &cancel,
+    )
+    .await
+    .unwrap();
+    assert!(outcomes
+        .iter()
+        .all(|outcome| outcome.error.as_deref() == Some(batch::NOT_SENT)));
+    assert_eq!(chain.broadcast_count(), 0);
+    assert_eq!(progress.snapshot().failed, 0);
+}
+
+#[test]
+fn checkpoints_keep_unconfirmed_broadcasts_apart() {
+    crate::data_dir();
+    let mut checkpoint = batch::Checkpoint::default();
+    let outcome = |line, txhash: Option<&str>, code| batch::Outcome {
+        line,
+        destination: "osmo1...".to_owned(),
+        amount: "1uosmo".to_owned(),
+        txhash: txhash.map(str::to_owned),
+        code,
+        error: None,
+    };
+    checkpoint.record(&outcome(1, Some("AA"), Some(0)));
+    checkpoint.record(&outcome(2, Some("BB"), None));
+    checkpoint.record(&outcome(3, None, None));
+    assert_eq!(checkpoint.completed().into_iter().collect::<Vec<_>>(), [1]);
+    assert_eq!(checkpoint.unconfirmed(), [(2, "BB".to_owned())]);
+
+    // Checkpoints are named after the content of the file
+    let path = batch::Checkpoint::path("a,1osmo\n").unwrap();
+    assert_eq!(path, batch::Checkpoint::path("a,1osmo\n").unwrap());
+    assert_ne!(path, batch::Checkpoint::path("a,2osmo\n").unwrap());
+}
+
+#[tokio::test]
+async fn batch_sends_count_towards_destination_limits_as_they_go() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(1_000, "ubatch")]);
+    let policy = PolicyConfig {
+        destination_limits: vec!["150ubatch".to_owned()],
+        ..PolicyConfig::default()
+    };
+
+    // Concurrent sends to one destination must not all pass the check before any is recorded
+    let requests = (1..=3)
+        .map(|line| {
+            (
+                line,
+                SendRequest {
+                    coin: coin(100, "ubatch"),
+                    destination: to.get_address(),
+                    cw20: None,
+                },
+            )
+        })
+        .collect();
+    let progress = batch::Progress::default();
+    let outcomes = batch::run(
+        &chain,
+        &from,
+        requests,
+        &policy,
+        3,
+        &progress,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let sent = outcomes
+        .iter()
+        .filter(|outcome| outcome.succeeded())
+        .count();
+    assert_eq!(sent, 1);
+    assert!(outcomes
+        .iter()
+        .filter(|outcome| !outcome.succeeded())
+        .all(|outcome| outcome.txhash.is_none()));
+    assert_eq!(chain.balance(to.get_address(), "ubatch"), 100);
+}
+
+#[tokio::test]
+async fn batch_plan_refuses_an_underfunded_wallet() {
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(250, "uosmo")]);
+    let request = |amount: u128, denom: &str| {
+        (
+            1,
+            SendRequest {
+                coin: coin(amount, denom),
+                destination: to.get_address(),
+                cw20: None,
+            },
+        )
+    };
+
+    let totals = batch::plan(
+        &chain,
+        &from,
+        &[request(100, "uosmo"), request(150, "uosmo")],
+    )
+    .await
+    .unwrap();
+    assert_eq!(totals.get("uosmo"), CoinAmount(250));
+
+    let error = batch::plan(
+        &chain,
+        &from,
+        &[request(200, "uosmo"), request(100, "uosmo")],
+    )
+    .await
+    .unwrap_err();
+    assert!(error.to_string().contains("300uosmo"), "{}", error);
+    assert!(batch::plan(&chain, &from, &[request(1, "uion")])
+        .await
+        .is_err());
+
+    // Totals overflow instead of wrapping
+    let error = batch::plan(
+        &chain,
+        &from,
+        &[request(u128::MAX, "uosmo"), request(1, "uosmo")],
+    )
+    .await
+    .unwrap_err();
+    assert!(format!("{:#}", error).contains("overflow"), "{:#}", error);
+}
+
+#[test]
+fn coin_amounts_are_checked() {
+    let osmo = CoinAmount::of(&coin(1_000, "uosmo"), "uosmo").unwrap();
+    assert!(CoinAmount::of(&coin(1_000, "uion"), "uosmo").is_err());
+    assert_eq!(osmo.percent(25).unwrap(), CoinAmount(250));
+    assert_eq!(
+        CoinAmount(u128::MAX).percent(100).unwrap(),
+        CoinAmount(u128::MAX)
+    );
+    assert!(CoinAmount(u128::MAX).checked_add(CoinAmount(1)).is_err());
+    assert!(osmo.checked_sub(CoinAmount(1_001)).is_err());
+    assert!(osmo.checked_mul(u128::MAX).is_err());
+}
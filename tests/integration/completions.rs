@@ -0,0 +1,31 @@
+/// Command tree
+use clap::CommandFactory;
+
+/// Library under test
+use cli_tool::cli::Cli;
+
+#[test]
+fn command_tree_is_consistent() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn completions_and_man_page_cover_every_command() {
+    let mut tree = Cli::command();
+    let mut script = Vec::new();
+    clap_complete::generate(
+        clap_complete::Shell::Bash,
+        &mut tree,
+        "cli-tool",
+        &mut script,
+    );
+    let script = String::from_utf8(script).unwrap();
+    assert!(script.contains("send-batch"));
+    assert!(script.contains("completions"));
+
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Cli::command())
+        .render(&mut page)
+        .unwrap();
+    assert!(String::from_utf8(page).unwrap().contains("balance\\-all"));
+}
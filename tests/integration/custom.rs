@@ -0,0 +1,132 @@
+/// Encode protobuf messages
+use prost::Message;
+
+/// Descriptor set fixtures
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+};
+
+/// Library under test
+use cli_tool::custom::{funds, Schemas};
+
+/// Descriptor set of `example.tokens.v1.MsgMint { string sender = 1; uint64 amount = 2; }`, written to `dir`
+fn descriptor_set(dir: &std::path::Path) -> std::path::PathBuf {
+    let field = |name: &str, number, kind: Type| FieldDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        label: Some(Label::Optional as i32),
+        r#type: Some(kind as i32),
+        json_name: Some(name.to_owned()),
+        ..Default::default()
+    };
+    let set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("example/tokens/v1/tx.proto".to_owned()),
+            package: Some("example.tokens.v1".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("MsgMint".to_owned()),
+                field: vec![
+                    field("sender", 1, Type::String),
+                    field("amount", 2, Type::Uint64),
+                ],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        }],
+    };
+    let path = dir.join("tokens.binpb");
+    std::fs::write(&path, set.encode_to_vec()).unwrap();
+    path
+}
+
+#[test]
+fn json_messages_are_encoded_with_their_descriptor() {
+    let dir = tempfile::tempdir().unwrap();
+    let schemas = Schemas::load(&[descriptor_set(dir.path())]).unwrap();
+
+    let messages = schemas
+        .messages(r#"{"@type": "/example.tokens.v1.MsgMint", "sender": "osmo1abc", "amount": "5"}"#)
+        .unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].type_url, "/example.tokens.v1.MsgMint");
+    // Field 1 "osmo1abc", field 2 varint 5
+    let mut expected = vec![0x0a, 8];
+    expected.extend(b"osmo1abc");
+    expected.extend([0x10, 5]);
+    assert_eq!(messages[0].value, expected);
+    assert!(messages[0].amino.is_none());
+
+    // Unknown fields are refused rather than dropped
+    let error = schemas
+        .messages(r#"[{"@type": "/example.tokens.v1.MsgMint", "recipient": "osmo1abc"}]"#)
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).starts_with("Message 1"),
+        "{:#}",
+        error
+    );
+}
+
+#[test]
+fn encoded_messages_need_no_descriptor() {
+    let schemas = Schemas::load(&[] as &[&str]).unwrap();
+    let messages = schemas
+        .messages(
+            r#"[{"@type": "/example.tokens.v1.MsgBurn", "@value": "0a02",
+                 "@amino": {"type": "tokens/MsgBurn", "value": {"amount": "1"}}}]"#,
+        )
+        .unwrap();
+    assert_eq!(messages[0].value, [0x0a, 0x02]);
+    assert_eq!(
+        messages[0].amino,
+        Some(serde_json::json!({ "type": "tokens/MsgBurn", "value": { "amount": "1" } }))
+    );
+
+    // Without a descriptor the fields can't be encoded
+    let error = schemas
+        .messages(r#"{"@type": "/example.tokens.v1.MsgBurn", "amount": "1"}"#)
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("No descriptor for /example.tokens.v1.MsgBurn"),
+        "{:#}",
+        error
+    );
+    assert!(schemas.messages(r#"{"amount": "1"}"#).is_err());
+    assert!(schemas.messages("[]").is_err());
+}
+
+#[test]
+fn funds_are_known_only_for_messages_that_move_them() {
+    let coin = |amount: &str, denom: &str| cosmos::Coin {
+        denom: denom.to_owned(),
+        amount: amount.to_owned(),
+    };
+    let send = cli_tool::tx::message(
+        "/cosmos.bank.v1beta1.MsgSend",
+        &cosmos::proto::cosmos::bank::v1beta1::MsgSend {
+            from_address: "osmo1from".to_owned(),
+            to_address: "osmo1to".to_owned(),
+            amount: vec![coin("5", "uosmo"), coin("2", "uion")],
+        },
+        "send",
+    );
+    assert_eq!(
+        funds(&send).unwrap(),
+        Some(vec![coin("5", "uosmo"), coin("2", "uion")])
+    );
+
+    // A type whose spending isn't known is told apart from one spending nothing
+    let schemas = Schemas::load(&[] as &[&str]).unwrap();
+    let mint = schemas
+        .message(&serde_json::json!({ "@type": "/example.tokens.v1.MsgMint", "@value": "0a02" }))
+        .unwrap();
+    assert_eq!(funds(&mint).unwrap(), None);
+
+    // Bytes that don't decode as the known type are refused
+    let broken = schemas
+        .message(&serde_json::json!({ "@type": "/cosmos.bank.v1beta1.MsgSend", "@value": "0aff" }))
+        .unwrap();
+    assert!(funds(&broken).is_err());
+}
@@ -0,0 +1,64 @@
+/// Timeouts
+use std::time::Duration;
+
+/// Library under test
+use cli_tool::deadline::{self, Phase};
+use cli_tool::error::TimedOut;
+
+#[tokio::test]
+async fn stalled_phase_fails_with_its_own_message() {
+    let stalled = std::future::pending::<anyhow::Result<()>>();
+    let error = deadline::within_timeout(
+        Some(Duration::from_millis(10)),
+        Phase::Simulate,
+        "/cosmos.tx.v1beta1.Service/Simulate",
+        stalled,
+    )
+    .await
+    .unwrap_err();
+
+    let timed_out = error.downcast_ref::<TimedOut>().unwrap();
+    assert_eq!(timed_out.phase, Phase::Simulate);
+    assert_eq!(
+        error.to_string(),
+        "Timed out after 10ms simulating the transaction, nothing was broadcast \
+         (/cosmos.tx.v1beta1.Service/Simulate)"
+    );
+}
+
+#[tokio::test]
+async fn calls_within_the_timeout_keep_their_result() {
+    let answer = deadline::within_timeout(
+        Some(Duration::from_secs(5)),
+        Phase::Query,
+        "balance",
+        async { anyhow::Ok(42) },
+    )
+    .await
+    .unwrap();
+    assert_eq!(answer, 42);
+
+    // Errors of the call itself aren't timeouts
+    let error = deadline::within_timeout(None, Phase::Connect, "node", async {
+        Err::<(), _>(anyhow::anyhow!("refused"))
+    })
+    .await
+    .unwrap_err();
+    assert!(error.downcast_ref::<TimedOut>().is_none());
+}
+
+#[test]
+fn grpc_calls_are_timed_by_phase() {
+    assert_eq!(
+        Phase::of("/cosmos.tx.v1beta1.Service/BroadcastTx"),
+        Phase::Broadcast
+    );
+    assert_eq!(
+        Phase::of("/cosmos.tx.v1beta1.Service/Simulate"),
+        Phase::Simulate
+    );
+    assert_eq!(
+        Phase::of("/cosmos.bank.v1beta1.Query/Balance"),
+        Phase::Query
+    );
+}
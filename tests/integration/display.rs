@@ -0,0 +1,61 @@
+/// Library under test
+use cli_tool::amount;
+use cli_tool::display::{DenomDisplay, DisplayConfig, Formatter, IbcStyle};
+
+/// Preferences by chain id
+use std::collections::HashMap;
+
+/// Fixtures
+use crate::coin;
+
+/// IBC denom of ATOM on Osmosis
+const ATOM: &str = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+#[test]
+fn base_units_convert_back_to_decimals() {
+    assert_eq!(amount::from_base_units(1_500_000, 6), "1.5");
+    assert_eq!(amount::from_base_units(5, 6), "0.000005");
+    assert_eq!(amount::from_base_units(2_000_000, 6), "2");
+    assert_eq!(amount::from_base_units(110, 0), "110");
+}
+
+#[test]
+fn built_in_units_and_abbreviated_ibc_denoms() {
+    let display = Formatter::new(&HashMap::new(), "osmosis-1");
+    assert_eq!(display.coin(&coin(1_500_000, "uosmo")), "1.5 OSMO");
+    assert_eq!(display.amount("-250000", "uosmo"), "-0.25 OSMO");
+    assert_eq!(display.denom("ibc/ABCDEF0123456789"), "ibc/ABCD…6789");
+    assert_eq!(
+        display.coin_list("110factory/x/y,5uion"),
+        "110factory/x/y, 0.000005 ION"
+    );
+    assert_eq!(display.coin_list(""), "-");
+}
+
+#[test]
+fn configured_symbols_replace_built_in_ones() {
+    let mut config = DisplayConfig {
+        ibc: IbcStyle::Full,
+        ..DisplayConfig::default()
+    };
+    config.denoms.insert(
+        ATOM.to_owned(),
+        DenomDisplay {
+            symbol: "hATOM".to_owned(),
+            exponent: 6,
+        },
+    );
+    let display = Formatter::new(
+        &HashMap::from([("osmosis-1".to_owned(), config)]),
+        "osmosis-1",
+    );
+    assert_eq!(display.amount("3000000", ATOM), "3 hATOM");
+    assert_eq!(
+        display.denom("ibc/ABCDEF0123456789"),
+        "ibc/ABCDEF0123456789"
+    );
+
+    // Preferences of one chain don't leak into another
+    let other = Formatter::new(&HashMap::new(), "juno-1");
+    assert_eq!(other.coin(&coin(7, "uosmo")), "7uosmo");
+}
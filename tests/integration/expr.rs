@@ -0,0 +1,88 @@
+/// Library under test
+use cli_tool::expr::{self, Expr, Values};
+
+/// Fixtures
+use crate::coin;
+
+/// Values of a wallet holding 10 OSMO and 3 ION, with a 5000uosmo fee
+fn values() -> Values {
+    let mut values = Values {
+        denom: "uosmo".to_owned(),
+        balances: vec![coin(10_000_000, "uosmo"), coin(3_000_000, "uion")],
+        fee: Some(coin(5_000, "uosmo")),
+        ..Values::default()
+    };
+    values
+        .amounts
+        .insert("100osmo".to_owned(), coin(100_000_000, "uosmo"));
+    values.amounts.insert("1uion".to_owned(), coin(1, "uion"));
+    values.denoms.insert("ion".to_owned(), "uion".to_owned());
+    values
+}
+
+/// Evaluates an expression against [`values`]
+fn evaluate(input: &str) -> anyhow::Result<cosmos::Coin> {
+    expr::parse(input)?.evaluate(&values())
+}
+
+#[test]
+fn plain_amounts_are_literals() {
+    assert!(expr::parse("1.5osmo").unwrap().is_literal());
+    assert!(!expr::parse("balance * 0.25").unwrap().is_literal());
+    assert_eq!(expr::parse("(balance)").unwrap(), Expr::Balance(None));
+}
+
+#[test]
+fn expressions_use_balances_and_fees() {
+    assert_eq!(
+        evaluate("(balance * 0.25)").unwrap(),
+        coin(2_500_000, "uosmo")
+    );
+    assert_eq!(evaluate("balance - fee").unwrap(), coin(9_995_000, "uosmo"));
+    assert_eq!(
+        evaluate("100osmo - fee").unwrap(),
+        coin(99_995_000, "uosmo")
+    );
+    assert_eq!(
+        evaluate("balance(ion) / 3").unwrap(),
+        coin(1_000_000, "uion")
+    );
+    assert_eq!(evaluate("1uion * (1 + 1) * 2").unwrap(), coin(4, "uion"));
+}
+
+#[test]
+fn invalid_expressions_are_refused() {
+    // Different denoms, negative results, no denom and malformed input
+    assert!(evaluate("100osmo + 1uion").is_err());
+    assert!(evaluate("fee - balance").is_err());
+    assert!(evaluate("0.5 * 2").is_err());
+    assert!(evaluate("balance * balance").is_err());
+    assert!(evaluate("balance * 0").is_err());
+    assert!(evaluate("balance / 0").is_err());
+    assert!(expr::parse("balance *").is_err());
+    assert!(expr::parse("(balance").is_err());
+    assert!(expr::parse("total * 2").is_err());
+}
+
+#[test]
+fn all_sends_the_balance_less_fee_and_leave() {
+    let mut values = values();
+    values.denoms.insert("osmo".to_owned(), "uosmo".to_owned());
+    values
+        .amounts
+        .insert("1osmo".to_owned(), coin(1_000_000, "uosmo"));
+    let leave = "1osmo".parse().unwrap();
+
+    let all = |denom, pays_fee, leave| expr::all(denom, pays_fee, leave).evaluate(&values);
+    assert_eq!(all("osmo", true, None).unwrap(), coin(9_995_000, "uosmo"));
+    assert_eq!(
+        all("osmo", true, Some(&leave)).unwrap(),
+        coin(8_995_000, "uosmo")
+    );
+    assert_eq!(all("ion", false, None).unwrap(), coin(3_000_000, "uion"));
+
+    // Leaving more than the balance, or in another denom, is refused
+    let too_much = "100osmo".parse().unwrap();
+    assert!(all("osmo", true, Some(&too_much)).is_err());
+    assert!(all("ion", false, Some(&leave)).is_err());
+}
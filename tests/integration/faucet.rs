@@ -0,0 +1,152 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Client addresses and rate windows
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Library under test
+use cli_tool::faucet::{self, Faucet, IpNet, Limiter, Rate, Refusal};
+
+/// Fixtures
+use crate::mock::MockChain;
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Parses an IP address
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+/// Faucet sending 10ufaucet once a day per IP and address to clients in 10.0.0.0/8
+fn faucet(chain: MockChain) -> Faucet<MockChain> {
+    let from = wallet(TEST1_MNEMONIC);
+    let chain = chain.fund(from.get_address(), vec![coin(1_000, "ufaucet")]);
+    let daily = "1/day".parse().unwrap();
+    Faucet::new(
+        chain,
+        from,
+        coin(10, "ufaucet"),
+        daily,
+        daily,
+        vec!["10.0.0.0/8".parse().unwrap()],
+    )
+}
+
+#[test]
+fn rates_and_networks_parse() {
+    assert_eq!(
+        "5/2h".parse::<Rate>().unwrap(),
+        Rate {
+            count: 5,
+            per: Duration::from_secs(2 * 60 * 60)
+        }
+    );
+    assert_eq!(
+        "1/day".parse::<Rate>().unwrap().per,
+        Duration::from_secs(86_400)
+    );
+    assert!("0/day".parse::<Rate>().is_err());
+    assert!("daily".parse::<Rate>().is_err());
+
+    let net: IpNet = "192.168.1.0/24".parse().unwrap();
+    assert!(net.contains(ip("192.168.1.77")));
+    assert!(net.contains(ip("::ffff:192.168.1.77")));
+    assert!(!net.contains(ip("192.168.2.1")));
+    assert!("2001:db8::/32"
+        .parse::<IpNet>()
+        .unwrap()
+        .contains(ip("2001:db8::1")));
+    assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+}
+
+#[test]
+fn limiter_windows_slide() {
+    let limiter = Limiter::new("2/1m".parse().unwrap());
+    let start = Instant::now();
+    assert!(limiter.acquire("a", start).is_ok());
+    assert!(limiter
+        .acquire("a", start + Duration::from_secs(10))
+        .is_ok());
+    assert_eq!(
+        limiter.acquire("a", start + Duration::from_secs(20)),
+        Err(Duration::from_secs(40))
+    );
+    assert!(limiter.acquire("b", start).is_ok());
+    assert!(limiter
+        .acquire("a", start + Duration::from_secs(60))
+        .is_ok());
+
+    // A released request doesn't count
+    limiter.release("b");
+    assert!(limiter.acquire("b", start).is_ok());
+}
+
+#[test]
+fn client_ip_comes_from_the_trusted_proxies() {
+    let proxy = ip("10.0.0.2");
+    // The client forged the leading entry, the proxy appended the real one
+    let spoofed = Some("198.51.100.1, 203.0.113.7");
+
+    assert_eq!(faucet::client_ip(proxy, spoofed, 1), ip("203.0.113.7"));
+    assert_eq!(
+        faucet::client_ip(proxy, Some("198.51.100.1, 203.0.113.7, 10.0.0.1"), 2),
+        ip("203.0.113.7")
+    );
+    // Not behind a proxy, or not behind as many as configured, the header is ignored
+    assert_eq!(faucet::client_ip(proxy, spoofed, 0), proxy);
+    assert_eq!(faucet::client_ip(proxy, spoofed, 3), proxy);
+    assert_eq!(faucet::client_ip(proxy, None, 1), proxy);
+    assert_eq!(faucet::client_ip(proxy, Some("garbage"), 1), proxy);
+}
+
+#[tokio::test]
+async fn faucet_limits_ips_and_addresses() {
+    crate::data_dir();
+    let faucet = faucet(MockChain::new());
+    let to = wallet(TEST2_MNEMONIC).get_address().to_string();
+    let other = wallet("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about")
+        .get_address()
+        .to_string();
+
+    let dispensed = faucet.dispense(ip("10.0.0.1"), &to).await.unwrap();
+    assert_eq!(dispensed.amount, "10ufaucet");
+    assert_eq!(faucet.chain().balance(to.parse().unwrap(), "ufaucet"), 10);
+
+    // Same IP, or same address from another IP, is limited
+    assert!(matches!(
+        faucet.dispense(ip("10.0.0.1"), &other).await,
+        Err(Refusal::Limited(_))
+    ));
+    assert!(matches!(
+        faucet.dispense(ip("10.0.0.2"), &to).await,
+        Err(Refusal::Limited(_))
+    ));
+    assert!(faucet.dispense(ip("10.0.0.2"), &other).await.is_ok());
+
+    // Outside the allowlist, or with a bad address, nothing is sent
+    assert_eq!(
+        faucet.dispense(ip("203.0.113.7"), &to).await.unwrap_err(),
+        Refusal::NotAllowed
+    );
+    assert!(matches!(
+        faucet.dispense(ip("10.0.0.3"), "osmo1nope").await,
+        Err(Refusal::InvalidAddress(_))
+    ));
+    assert_eq!(faucet.chain().broadcast_count(), 2);
+}
+
+#[tokio::test]
+async fn failed_sends_dont_count() {
+    crate::data_dir();
+    let faucet = faucet(MockChain::reject_with(13));
+    let to = wallet(TEST2_MNEMONIC).get_address().to_string();
+
+    for _ in 0..2 {
+        assert!(matches!(
+            faucet.dispense(ip("10.0.0.1"), &to).await,
+            Err(Refusal::Failed(_))
+        ));
+    }
+    assert!(faucet::looks_like_testnet("osmo-test-5"));
+    assert!(!faucet::looks_like_testnet("osmosis-1"));
+}
@@ -0,0 +1,105 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Parse command lines
+use clap::Parser;
+
+/// Library under test
+use cli_tool::cli::Cli;
+use cli_tool::config::Config;
+use cli_tool::keys::{self, KeyConfig};
+
+/// Keys by name
+use std::collections::HashMap;
+
+/// Fixtures
+use crate::{wallet, TEST1_MNEMONIC};
+
+/// Config with a signing key `ops` and a watch-only key `treasury` for `address`
+fn config(address: &cosmos::Address) -> Config {
+    Config {
+        keys: HashMap::from([
+            (
+                "ops".to_owned(),
+                KeyConfig {
+                    mnemonic_env: Some("CLI_TOOL_TEST_OPS_MNEMONIC".to_owned()),
+                    address: None,
+                },
+            ),
+            (
+                "treasury".to_owned(),
+                KeyConfig {
+                    mnemonic_env: None,
+                    address: Some(address.to_string()),
+                },
+            ),
+        ]),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn watch_only_keys_resolve_but_never_sign() {
+    let address = wallet(TEST1_MNEMONIC).get_address();
+    let config = config(&address);
+
+    assert_eq!(keys::address(&config, "treasury").unwrap(), Some(address));
+    assert_eq!(
+        keys::address(&config, &address.to_string()).unwrap(),
+        Some(address)
+    );
+    assert_eq!(keys::address(&config, "ops").unwrap(), None);
+    keys::address(&config, "unknown").unwrap_err();
+
+    let error = keys::seed_phrase(&config, "treasury").unwrap_err();
+    assert!(error.to_string().contains("watch-only"));
+}
+
+#[test]
+fn import_appends_a_watch_only_key_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cli-tool").join("config.toml");
+    let address = wallet(TEST1_MNEMONIC).get_address();
+
+    keys::import_watch_only(&path, "treasury", address).unwrap();
+    keys::import_watch_only(&path, "audit", address).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        contents,
+        format!(
+            "[keys.treasury]\naddress = \"{address}\"\n\n[keys.audit]\naddress = \"{address}\"\n"
+        )
+    );
+
+    let error = keys::import_watch_only(&path, "treasury", address).unwrap_err();
+    assert!(error.to_string().contains("already exists"));
+    keys::import_watch_only(&path, "bad name", address).unwrap_err();
+}
+
+#[test]
+fn signing_commands_take_a_key_instead_of_the_mnemonic() {
+    let address = wallet(TEST1_MNEMONIC).get_address().to_string();
+    // With --key, COSMOS_WALLET isn't needed to parse any signing command
+    for args in [
+        vec!["send", "1osmo", address.as_str()],
+        vec!["gov", "vote", "1", "yes"],
+        vec!["swap", "1osmo", "--to", "uion", "--pool", "1"],
+        vec![
+            "ibc",
+            "transfer",
+            "1osmo",
+            "cosmos1receiver",
+            "--channel",
+            "channel-0",
+        ],
+        vec!["wasm", "execute", address.as_str(), "--msg", "{}"],
+    ] {
+        let cli = Cli::try_parse_from(
+            ["cli-tool", "--key", "treasury"]
+                .into_iter()
+                .chain(args.clone()),
+        )
+        .unwrap_or_else(|error| panic!("{:?}: {}", args, error));
+        assert_eq!(cli.global.key.as_deref(), Some("treasury"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Flows against a running [localosmosis](https://github.com/osmosis-labs/osmosis/tree/main/tests/localosmosis)
+//! chain, enabled with the `localosmosis` feature.
+//!
+//! The chain's gRPC endpoint defaults to `http://localhost:9090` and can be
+//! overridden with `LOCALOSMOSIS_GRPC`. The `lo-test1` and `lo-test2` genesis
+//! accounts must be funded.
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::chain::{Chain, ChainClient};
+use cli_tool::policy::PolicyConfig;
+use cli_tool::send::{self, SendRequest};
+
+/// Fixtures
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Connects to the local chain
+async fn connect() -> Chain {
+    let grpc =
+        std::env::var("LOCALOSMOSIS_GRPC").unwrap_or_else(|_| "http://localhost:9090".to_owned());
+    let mut builder = cosmos::CosmosNetwork::OsmosisLocal
+        .builder()
+        .await
+        .expect("Error loading localosmosis settings");
+    builder.set_grpc_url(&grpc);
+    Chain::new(
+        builder.build().expect("Error connecting to localosmosis"),
+        None,
+    )
+}
+
+#[tokio::test]
+async fn genesis_account_has_funds() {
+    let chain = connect().await;
+    let balances = chain
+        .all_balances(wallet(TEST1_MNEMONIC).get_address())
+        .await
+        .unwrap();
+    assert!(balances.iter().any(|coin| coin.denom == "uosmo"));
+}
+
+#[tokio::test]
+async fn send_is_included() {
+    crate::data_dir();
+    let chain = connect().await;
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+
+    let request = SendRequest {
+        coin: coin(1, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    let response = send::execute_transaction(&chain, &from, &request, &PolicyConfig::default())
+        .await
+        .unwrap();
+
+    assert!(response.check().is_ok());
+    assert!(response.height > 0);
+}
+
+#[tokio::test]
+async fn send_over_balance_is_refused() {
+    crate::data_dir();
+    let chain = connect().await;
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+
+    let request = SendRequest {
+        coin: coin(u64::MAX as u128, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    // Depending on the signing path the node refuses during simulation or with a code
+    match send::execute_transaction(&chain, &from, &request, &PolicyConfig::default()).await {
+        Ok(response) => assert_eq!(response.check().unwrap_err().code, 5),
+        Err(error) => assert!(format!("{:#}", error).contains("insufficient funds")),
+    }
+}
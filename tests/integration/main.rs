@@ -0,0 +1,100 @@
+//! Integration tests, run with `cargo test --features integration`.
+//!
+//! The send and balance flows run against [`mock::MockChain`], an in-memory
+//! [`cli_tool::chain::ChainClient`] with fixtures for successful, underfunded
+//! and rejected transactions. With the `localosmosis` feature the same flows
+//! also run against a local chain, see [`localosmosis`].
+
+/// Command aliases
+mod alias;
+/// Typed amounts and number locales
+mod amount;
+/// Signing requests approved on a companion wallet
+mod approval;
+/// Balance queries and diffs
+mod balance;
+/// Batch sends
+mod batch;
+/// Shell completions and man page
+mod completions;
+/// Messages of types the tool doesn't model
+mod custom;
+/// Per-phase timeouts
+mod deadline;
+/// Denom display formatting
+mod display;
+/// Amount expressions
+mod expr;
+/// Rate-limited faucet
+mod faucet;
+/// Watch-only keys
+mod keys;
+/// Flows against a running localosmosis chain
+#[cfg(feature = "localosmosis")]
+mod localosmosis;
+/// Sends on several chains from a manifest
+mod manifest;
+/// In-memory chain client
+mod mock;
+/// Per-transaction and daily spend limits
+mod policy;
+/// Sequential transaction queue
+mod queue;
+/// Network resolution through the chain registry
+mod registry;
+/// Daemon automation rules
+mod rules;
+/// Sends and their journal entries
+mod send;
+/// Task groups, cancellation and status reports
+mod tasks;
+/// Phase timings
+mod telemetry;
+/// Transaction export files
+mod txfile;
+/// Address validation
+mod validate;
+/// Block checks against a second endpoint
+mod verify;
+/// Transfer history of an address
+mod watch;
+
+/// Isolated data directory
+use std::sync::OnceLock;
+
+/// Mnemonic of the localosmosis `lo-test1` account
+pub const TEST1_MNEMONIC: &str = "notice oak worry limit wrap speak medal online prefer cluster roof addict wrist behave treat actual wasp year salad speed social layer crew genius";
+
+/// Mnemonic of the localosmosis `lo-test2` account
+pub const TEST2_MNEMONIC: &str = "quality vacuum heart guard buzz spike sight swarm shove special gym robust assume sudden deposit grid alcohol choice devote leader tilt noodle tide penalty";
+
+/// Points the journal and caches at a temporary directory shared by all tests.
+///
+/// Tests run in parallel in one process, so the directory is created once and
+/// tests tell their entries apart by txhash.
+pub fn data_dir() -> &'static std::path::Path {
+    static DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let dir = tempfile::tempdir().expect("Error creating temporary data directory");
+        std::env::set_var(cli_tool::journal::DATA_DIR_ENV, dir.path());
+        dir
+    })
+    .path()
+}
+
+/// Wallet for a mnemonic on an osmo chain
+pub fn wallet(mnemonic: &str) -> cosmos::Wallet {
+    mnemonic
+        .parse::<cosmos::SeedPhrase>()
+        .expect("Invalid test mnemonic")
+        .with_hrp(cosmos::AddressHrp::from_static("osmo"))
+        .expect("Error deriving test wallet")
+}
+
+/// Coin with an amount in base units
+pub fn coin(amount: u128, denom: &str) -> cosmos::Coin {
+    cosmos::Coin {
+        denom: denom.to_owned(),
+        amount: amount.to_string(),
+    }
+}
@@ -0,0 +1,106 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::amount::CoinAmount;
+use cli_tool::batch::Progress;
+use cli_tool::manifest::{self, WalletSends};
+use cli_tool::policy::PolicyConfig;
+use cli_tool::send::SendRequest;
+use cli_tool::tasks::CancellationToken;
+
+/// Fixtures
+use crate::mock::MockChain;
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Sends of `amounts` to `destination`, numbered from `first_line`
+fn sends(
+    chain: &str,
+    destination: cosmos::Address,
+    first_line: usize,
+    amounts: &[u128],
+) -> WalletSends {
+    WalletSends {
+        chain: chain.to_owned(),
+        from: "treasury".to_owned(),
+        requests: amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| {
+                (
+                    first_line + index,
+                    SendRequest {
+                        coin: coin(*amount, "uosmo"),
+                        destination,
+                        cw20: None,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn manifest_is_grouped_by_chain_then_key() {
+    let to = wallet(TEST2_MNEMONIC).get_address();
+    let input = format!(
+        "chain,from,to,amount\n# rebalance\nprod,treasury,{to},1osmo\njuno,ops,{to},2osmo\nprod, ops ,{to},3osmo\nprod,treasury,{to},4osmo\n"
+    );
+    let groups = manifest::group(manifest::parse(&input).unwrap());
+    assert_eq!(groups.keys().collect::<Vec<_>>(), ["juno", "prod"]);
+    let prod = &groups["prod"];
+    assert_eq!(prod.keys().collect::<Vec<_>>(), ["ops", "treasury"]);
+    let lines: Vec<_> = prod["treasury"].iter().map(|row| row.line).collect();
+    assert_eq!(lines, [3, 6]);
+
+    let error = manifest::parse(&format!("prod,{to},1osmo\n")).unwrap_err();
+    assert!(error.to_string().starts_with("Line 1"), "{}", error);
+    assert!(manifest::parse(&format!(",treasury,{to},1osmo\n")).is_err());
+    assert!(manifest::parse("# nothing\n").is_err());
+}
+
+#[tokio::test]
+async fn chains_are_sent_on_concurrently_and_summarized_apart() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let funded = MockChain::new().fund(from.get_address(), vec![coin(1_000, "uosmo")]);
+    let short = MockChain::new().fund(from.get_address(), vec![coin(50, "uosmo")]);
+    let policy = PolicyConfig::default();
+    let (progress, short_progress) = (Progress::default(), Progress::default());
+    let cancel = CancellationToken::new();
+
+    let (sent, refused) = tokio::join!(
+        manifest::run_wallet(
+            &funded,
+            &from,
+            sends("prod", to.get_address(), 1, &[100, 200]),
+            &policy,
+            4,
+            &progress,
+            &cancel,
+        ),
+        manifest::run_wallet(
+            &short,
+            &from,
+            sends("juno", to.get_address(), 3, &[100]),
+            &policy,
+            4,
+            &short_progress,
+            &cancel,
+        ),
+    );
+    assert!(sent.iter().all(|outcome| outcome.send.succeeded()));
+    assert_eq!(funded.balance(to.get_address(), "uosmo"), 300);
+
+    // A wallet short of the total sends nothing
+    assert_eq!(refused.len(), 1);
+    assert!(refused[0].send.error.is_some());
+    assert_eq!(short.broadcast_count(), 0);
+
+    let outcomes: Vec<_> = sent.into_iter().chain(refused).collect();
+    let chains = manifest::summarize(&outcomes).unwrap();
+    assert_eq!(chains.len(), 2);
+    assert_eq!((chains[0].chain.as_str(), chains[0].failed), ("juno", 1));
+    assert_eq!((chains[1].chain.as_str(), chains[1].succeeded), ("prod", 2));
+    assert_eq!(chains[1].sent.get("uosmo"), CoinAmount(300));
+}
@@ -0,0 +1,179 @@
+/// Error handling
+use anyhow::{anyhow, Result};
+
+/// Decode broadcast messages
+use prost::Message;
+
+/// Shared state behind the client
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Library under test
+use cli_tool::chain::ChainClient;
+use cli_tool::tx::{Msg, TxResponse};
+
+/// Chain id reported by the mock
+pub const CHAIN_ID: &str = "mock-1";
+
+/// Code returned by the SDK when an account can't cover a send
+pub const INSUFFICIENT_FUNDS: u32 = 5;
+
+/// In-memory chain holding bank balances.
+///
+/// Bank sends move funds between accounts, or fail with
+/// [`INSUFFICIENT_FUNDS`] like the SDK does. [`MockChain::reject_with`]
+/// makes every broadcast fail with a given code instead.
+#[derive(Default)]
+pub struct MockChain {
+    /// Balances by address
+    balances: Mutex<HashMap<String, Vec<cosmos::Coin>>>,
+    /// Code to reject every transaction with, if any
+    reject: Option<u32>,
+    /// Messages of every broadcast, in order
+    broadcasts: Mutex<Vec<Vec<Msg>>>,
+}
+
+impl MockChain {
+    /// Chain where nothing is rejected up front
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Chain rejecting every transaction with `code`, e.g. 13 for insufficient fee
+    pub fn reject_with(code: u32) -> Self {
+        MockChain {
+            reject: Some(code),
+            ..Self::default()
+        }
+    }
+
+    /// Funds an account
+    pub fn fund(self, address: cosmos::Address, coins: Vec<cosmos::Coin>) -> Self {
+        self.balances
+            .lock()
+            .unwrap()
+            .insert(address.to_string(), coins);
+        self
+    }
+
+    /// Amount of `denom` held by an address
+    pub fn balance(&self, address: cosmos::Address, denom: &str) -> u128 {
+        self.balances
+            .lock()
+            .unwrap()
+            .get(&address.to_string())
+            .and_then(|coins| coins.iter().find(|coin| coin.denom == denom))
+            .map_or(0, |coin| coin.amount.parse().unwrap())
+    }
+
+    /// Number of transactions broadcast so far
+    pub fn broadcast_count(&self) -> usize {
+        self.broadcasts.lock().unwrap().len()
+    }
+
+    /// Applies a bank send, returning the response code
+    fn apply_send(&self, msg: &Msg) -> Result<u32> {
+        let send = cosmos::proto::cosmos::bank::v1beta1::MsgSend::decode(msg.value.as_slice())?;
+        let mut balances = self.balances.lock().unwrap();
+
+        // Check every coin before moving anything
+        for coin in &send.amount {
+            let held = balances
+                .get(&send.from_address)
+                .and_then(|coins| coins.iter().find(|held| held.denom == coin.denom))
+                .map_or(0, |held| held.amount.parse::<u128>().unwrap());
+            if held < coin.amount.parse()? {
+                return Ok(INSUFFICIENT_FUNDS);
+            }
+        }
+
+        for coin in &send.amount {
+            let amount: u128 = coin.amount.parse()?;
+            add(
+                balances.entry(send.from_address.clone()).or_default(),
+                &coin.denom,
+                -(amount as i128),
+            );
+            add(
+                balances.entry(send.to_address.clone()).or_default(),
+                &coin.denom,
+                amount as i128,
+            );
+        }
+        Ok(0)
+    }
+}
+
+/// Adds a signed amount to a denom in a list of coins
+fn add(coins: &mut Vec<cosmos::Coin>, denom: &str, amount: i128) {
+    match coins.iter_mut().find(|coin| coin.denom == denom) {
+        Some(coin) => {
+            let held: i128 = coin.amount.parse().unwrap();
+            coin.amount = (held + amount).to_string();
+        }
+        None => coins.push(crate::coin(amount as u128, denom)),
+    }
+}
+
+impl ChainClient for MockChain {
+    fn chain_id(&self) -> String {
+        CHAIN_ID.to_owned()
+    }
+
+    async fn all_balances(&self, address: cosmos::Address) -> Result<Vec<cosmos::Coin>> {
+        Ok(self
+            .balances
+            .lock()
+            .unwrap()
+            .get(&address.to_string())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn sign_and_broadcast(
+        &self,
+        _wallet: &cosmos::Wallet,
+        messages: &[Msg],
+    ) -> Result<TxResponse> {
+        let mut broadcasts = self.broadcasts.lock().unwrap();
+        broadcasts.push(messages.to_vec());
+        let height = broadcasts.len() as i64;
+        drop(broadcasts);
+
+        let code = match self.reject {
+            Some(code) => code,
+            None => {
+                let mut code = 0;
+                for msg in messages {
+                    if msg.type_url != "/cosmos.bank.v1beta1.MsgSend" {
+                        return Err(anyhow!("MockChain can't execute {}", msg.type_url));
+                    }
+                    code = self.apply_send(msg)?;
+                    if code != 0 {
+                        break;
+                    }
+                }
+                code
+            }
+        };
+
+        Ok(TxResponse {
+            height,
+            txhash: next_txhash(),
+            code,
+            codespace: if code == 0 {
+                String::new()
+            } else {
+                "sdk".to_owned()
+            },
+            ..Default::default()
+        })
+    }
+}
+
+/// Unique txhash, also across mocks of tests running in parallel
+fn next_txhash() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    format!("{:064X}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
@@ -0,0 +1,117 @@
+/// Library under test
+use cli_tool::error::SpendLimitExceeded;
+use cli_tool::journal::Entry;
+use cli_tool::policy::{self, PolicyConfig};
+
+/// Fixtures
+use crate::coin;
+
+/// Journal entry of a send accepted `hours_ago`
+fn sent(from: &str, amount: &str, hours_ago: i64) -> Entry {
+    Entry {
+        timestamp: chrono::Utc::now() - chrono::Duration::hours(hours_ago),
+        chain: "osmosis-1".to_owned(),
+        from: from.to_owned(),
+        to: "osmo1destination".to_owned(),
+        amount: amount.to_owned(),
+        fee: None,
+        txhash: None,
+        code: Some(0),
+        error: None,
+    }
+}
+
+#[test]
+fn transactions_over_the_per_transaction_limit_are_refused() {
+    let config = PolicyConfig {
+        tx_limits: vec!["500uosmo".to_owned()],
+        ..PolicyConfig::default()
+    };
+    let check = |coins: &[cosmos::Coin]| {
+        policy::check_spend(&config, &[], "osmosis-1", "osmo1sender", coins)
+    };
+
+    check(&[coin(500, "uosmo"), coin(10_000, "uion")]).unwrap();
+    // Sends of one transaction count together
+    let error = check(&[coin(300, "uosmo"), coin(300, "uosmo")]).unwrap_err();
+    let exceeded = error.downcast_ref::<SpendLimitExceeded>().unwrap();
+    assert_eq!((exceeded.total.0, exceeded.limit.0), (600, 500));
+    assert!(exceeded.spent.is_none());
+}
+
+#[test]
+fn daily_limit_counts_what_the_sender_spent_on_the_chain_in_the_last_day() {
+    let config = PolicyConfig {
+        daily_limits: vec!["1000uosmo".to_owned()],
+        ..PolicyConfig::default()
+    };
+    let mut rejected = sent("osmo1sender", "400uosmo", 1);
+    rejected.code = Some(5);
+    let mut testnet = sent("osmo1sender", "400uosmo", 1);
+    testnet.chain = "osmo-test-5".to_owned();
+    let journal = [
+        sent("osmo1sender", "300uosmo", 2),
+        // Several coins in one transaction, e.g. a template
+        sent("osmo1sender", "200uosmo,5uion", 3),
+        // None of these count
+        sent("osmo1sender", "900uosmo", 25),
+        sent("osmo1other", "900uosmo", 1),
+        rejected,
+        testnet,
+    ];
+    let check = |amount| {
+        policy::check_spend(
+            &config,
+            &journal,
+            "osmosis-1",
+            "osmo1sender",
+            &[coin(amount, "uosmo")],
+        )
+    };
+
+    check(500).unwrap();
+    let error = check(501).unwrap_err();
+    let exceeded = error.downcast_ref::<SpendLimitExceeded>().unwrap();
+    assert_eq!(exceeded.spent.map(|spent| spent.0), Some(500));
+    assert_eq!(exceeded.total.0, 1001);
+
+    // Overridden, only the spend limits are lifted
+    let overridden = config.without_spend_limits();
+    policy::check_spend(
+        &overridden,
+        &journal,
+        "osmosis-1",
+        "osmo1sender",
+        &[coin(501, "uosmo")],
+    )
+    .unwrap();
+}
+
+#[test]
+fn destination_limit_counts_what_the_destination_received_on_the_chain() {
+    let config = PolicyConfig {
+        destination_limits: vec!["1000uosmo".to_owned()],
+        ..PolicyConfig::default()
+    };
+    let mut testnet = sent("osmo1sender", "900uosmo", 1);
+    testnet.chain = "osmo-test-5".to_owned();
+    let journal = [
+        sent("osmo1sender", "300uosmo", 2),
+        // Several coins in one transaction, e.g. the funds of a contract execution
+        sent("osmo1sender", "5uion,200uosmo", 3),
+        // Sent on another chain to the same address
+        testnet,
+    ];
+    let check = |amount| {
+        policy::check_destination(
+            &config,
+            &journal,
+            "osmosis-1",
+            "osmo1destination",
+            &coin(amount, "uosmo"),
+        )
+    };
+
+    check(500).unwrap();
+    assert!(check(501).is_err());
+}
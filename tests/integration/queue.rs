@@ -0,0 +1,166 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::policy::PolicyConfig;
+use cli_tool::queue::{self, Intent, Retry, Status, Store};
+use cli_tool::tasks::CancellationToken;
+
+/// Fixtures
+use crate::mock::{self, MockChain};
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Bank send intent of `amount` to the test2 account
+fn send(amount: &str) -> Intent {
+    Intent::Send {
+        destination: wallet(TEST2_MNEMONIC).get_address().to_string(),
+        amount: amount.to_owned(),
+    }
+}
+
+/// No retries, so failures show at once
+const NO_RETRY: Retry = Retry {
+    retries: 0,
+    delay: std::time::Duration::ZERO,
+};
+
+#[test]
+fn queue_lists_items_in_the_order_they_were_added() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::at(dir.path().to_owned());
+    assert!(store.list().unwrap().is_empty());
+
+    let first = store.add("osmosis", "payroll", send("100uosmo")).unwrap();
+    let second = store.add("juno", "default", send("5ujuno")).unwrap();
+    assert_eq!(first.status, Status::Pending);
+
+    let items = store.list().unwrap();
+    assert_eq!(items, [first, second]);
+    assert_eq!(
+        items[0].intent.to_string(),
+        format!("send 100uosmo to {}", wallet(TEST2_MNEMONIC).get_address())
+    );
+}
+
+#[tokio::test]
+async fn queue_sends_one_item_after_the_other_and_saves_each_status() {
+    crate::data_dir();
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::at(dir.path().to_owned());
+    let from = wallet(TEST1_MNEMONIC);
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(250, "uosmo")]);
+    for amount in ["100uosmo", "200uosmo", "100uosmo"] {
+        store.add("osmosis", "default", send(amount)).unwrap();
+    }
+
+    let items = queue::run_account(
+        &chain,
+        &from,
+        &store,
+        store.list().unwrap(),
+        &PolicyConfig::default(),
+        NO_RETRY,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    let statuses: Vec<_> = items.iter().map(|item| item.status).collect();
+    assert_eq!(
+        statuses,
+        [Status::Confirmed, Status::Failed, Status::Confirmed]
+    );
+    assert_eq!(items[1].code, Some(mock::INSUFFICIENT_FUNDS));
+    assert!(items[0].txhash.is_some());
+    assert_eq!(
+        chain.balance(wallet(TEST2_MNEMONIC).get_address(), "uosmo"),
+        200
+    );
+
+    // The store has what the run did, a second run sends nothing
+    assert_eq!(store.list().unwrap(), items);
+    queue::run_account(
+        &chain,
+        &from,
+        &store,
+        store.list().unwrap(),
+        &PolicyConfig::default(),
+        NO_RETRY,
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(chain.broadcast_count(), 3);
+}
+
+#[tokio::test]
+async fn cancelled_queue_leaves_items_pending() {
+    crate::data_dir();
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::at(dir.path().to_owned());
+    let from = wallet(TEST1_MNEMONIC);
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(250, "uosmo")]);
+    store.add("osmosis", "default", send("100uosmo")).unwrap();
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let items = queue::run_account(
+        &chain,
+        &from,
+        &store,
+        store.list().unwrap(),
+        &PolicyConfig::default(),
+        NO_RETRY,
+        &cancel,
+    )
+    .await
+    .unwrap();
+    assert_eq!(items[0].status, Status::Pending);
+    assert_eq!(chain.broadcast_count(), 0);
+}
+
+#[test]
+fn only_one_queue_run_holds_the_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::at(dir.path().to_owned());
+
+    let lock = store.lock().unwrap();
+    let error = store.lock().unwrap_err();
+    assert!(error.to_string().contains("Another queue run"), "{}", error);
+
+    // Released when dropped
+    drop(lock);
+    store.lock().unwrap();
+}
+
+#[tokio::test]
+async fn sends_refused_by_the_policies_are_not_retried() {
+    crate::data_dir();
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::at(dir.path().to_owned());
+    let from = wallet(TEST1_MNEMONIC);
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(250, "uosmo")]);
+    store.add("osmosis", "default", send("100uosmo")).unwrap();
+    let policy = PolicyConfig {
+        tx_limits: vec!["50uosmo".to_owned()],
+        ..PolicyConfig::default()
+    };
+
+    let items = queue::run_account(
+        &chain,
+        &from,
+        &store,
+        store.list().unwrap(),
+        &policy,
+        Retry {
+            retries: 3,
+            delay: std::time::Duration::from_millis(1),
+        },
+        &CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(items[0].status, Status::Failed);
+    assert_eq!(items[0].attempts, 1);
+    assert_eq!(chain.broadcast_count(), 0);
+}
@@ -0,0 +1,49 @@
+/// Library under test
+use cli_tool::context::Network;
+use cli_tool::registry;
+
+/// Fixtures
+use crate::data_dir;
+
+#[test]
+fn registry_names_and_chain_ids_resolve_to_the_embedded_snapshot() {
+    data_dir();
+    for name in ["juno", "JUNO", "juno-1"] {
+        let Network::Registry(chain) = Network::resolve(name).unwrap() else {
+            panic!("{} resolved to a built-in network", name);
+        };
+        assert_eq!(chain.chain_id, "juno-1");
+        assert_eq!(chain.bech32_prefix, "juno");
+        assert_eq!(chain.fees.fee_tokens[0].denom, "ujuno");
+    }
+}
+
+#[test]
+fn built_in_networks_take_precedence_over_the_registry() {
+    data_dir();
+    assert!(matches!(
+        Network::resolve("osmosis-testnet").unwrap(),
+        Network::Builtin(cosmos::CosmosNetwork::OsmosisTestnet)
+    ));
+    assert!(Network::resolve("not-a-chain").is_err());
+}
+
+#[test]
+fn endpoint_urls_get_a_scheme() {
+    let url = |address: &str| {
+        registry::Endpoint {
+            address: address.to_owned(),
+            provider: None,
+        }
+        .url()
+    };
+    assert_eq!(
+        url("grpc.osmosis.zone:9090"),
+        "http://grpc.osmosis.zone:9090"
+    );
+    assert_eq!(
+        url("juno-grpc.example.com:443"),
+        "https://juno-grpc.example.com:443"
+    );
+    assert_eq!(url("https://grpc.example.com"), "https://grpc.example.com");
+}
@@ -0,0 +1,43 @@
+/// Library under test
+use cli_tool::rules;
+use cli_tool::watch::{Direction, TransferEvent};
+
+/// Fixtures
+use crate::coin;
+
+/// Transfer event moving `amount`
+fn event(direction: Direction, amount: &str) -> TransferEvent {
+    TransferEvent {
+        height: 1,
+        txhash: "A".repeat(64),
+        direction,
+        counterparty: "osmo1counterparty".to_owned(),
+        amount: amount.to_owned(),
+    }
+}
+
+#[test]
+fn forwards_a_share_of_transfers_above_the_threshold() {
+    let threshold = coin(100_000_000, "uosmo");
+    let forward = rules::evaluate(
+        &threshold,
+        Direction::Received,
+        90,
+        &event(Direction::Received, "5uion,150000000uosmo"),
+    )
+    .unwrap();
+    assert_eq!(forward.unwrap().amount, "135000000");
+}
+
+#[test]
+fn ignores_small_transfers_other_denoms_and_directions() {
+    let threshold = coin(100_000_000, "uosmo");
+    for event in [
+        event(Direction::Received, "100000000uosmo"),
+        event(Direction::Received, "500000000uion"),
+        event(Direction::Sent, "500000000uosmo"),
+    ] {
+        let forward = rules::evaluate(&threshold, Direction::Received, 90, &event).unwrap();
+        assert!(forward.is_none());
+    }
+}
@@ -0,0 +1,159 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Library under test
+use cli_tool::journal;
+use cli_tool::policy::PolicyConfig;
+use cli_tool::send::{self, SendRequest, Transfer, TxOutcome};
+use cli_tool::tx::TxResponse;
+
+/// Fixtures
+use crate::mock::{self, MockChain};
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Journal entry of a transaction
+fn journal_entry(txhash: &str) -> journal::Entry {
+    journal::find(txhash)
+        .expect("Error reading the journal")
+        .expect("Transaction missing from the journal")
+}
+
+#[tokio::test]
+async fn send_moves_funds_and_is_journaled() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(1_000, "uosmo")]);
+
+    let request = SendRequest {
+        coin: coin(110, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    let response = send::execute_transaction(&chain, &from, &request, &PolicyConfig::default())
+        .await
+        .unwrap();
+
+    assert!(response.check().is_ok());
+    assert_eq!(chain.balance(from.get_address(), "uosmo"), 890);
+    assert_eq!(chain.balance(to.get_address(), "uosmo"), 110);
+
+    let entry = journal_entry(&response.txhash);
+    assert_eq!(entry.chain, mock::CHAIN_ID);
+    assert_eq!(entry.to, to.get_address().to_string());
+    assert_eq!(entry.amount, "110uosmo");
+    assert_eq!(entry.code, Some(0));
+}
+
+#[tokio::test]
+async fn send_without_funds_fails_with_insufficient_funds() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(100, "uosmo")]);
+
+    let request = SendRequest {
+        coin: coin(110, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    let response = send::execute_transaction(&chain, &from, &request, &PolicyConfig::default())
+        .await
+        .unwrap();
+
+    let failed = response.check().unwrap_err();
+    assert_eq!(failed.code, mock::INSUFFICIENT_FUNDS);
+    assert_eq!(chain.balance(from.get_address(), "uosmo"), 100);
+    assert_eq!(chain.balance(to.get_address(), "uosmo"), 0);
+    assert_eq!(
+        journal_entry(&response.txhash).code,
+        Some(mock::INSUFFICIENT_FUNDS)
+    );
+}
+
+#[tokio::test]
+async fn rejected_send_reports_the_code() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    // 13 is ErrInsufficientFee
+    let chain = MockChain::reject_with(13).fund(from.get_address(), vec![coin(1_000, "uosmo")]);
+
+    let request = SendRequest {
+        coin: coin(110, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    let response = send::execute_transaction(&chain, &from, &request, &PolicyConfig::default())
+        .await
+        .unwrap();
+
+    assert_eq!(response.check().unwrap_err().code, 13);
+    assert_eq!(chain.broadcast_count(), 1);
+    assert_eq!(chain.balance(from.get_address(), "uosmo"), 1_000);
+    assert_eq!(journal_entry(&response.txhash).code, Some(13));
+}
+
+#[tokio::test]
+async fn policy_violation_is_refused_before_broadcast() {
+    crate::data_dir();
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let chain = MockChain::new().fund(from.get_address(), vec![coin(1_000, "uosmo")]);
+    let policy = PolicyConfig {
+        destination_limits: vec!["100uosmo".to_owned()],
+        ..PolicyConfig::default()
+    };
+
+    let request = SendRequest {
+        coin: coin(110, "uosmo"),
+        destination: to.get_address(),
+        cw20: None,
+    };
+    let result = send::execute_transaction(&chain, &from, &request, &policy).await;
+
+    assert!(result.is_err());
+    assert_eq!(chain.broadcast_count(), 0);
+}
+
+#[test]
+fn outcome_has_gas_and_transfers_of_the_response() {
+    let mut response = TxResponse {
+        height: 7,
+        txhash: "A1B2".to_owned(),
+        gas_wanted: 100_000,
+        gas_used: 81_234,
+        timestamp: "2026-01-02T03:04:05Z".to_owned(),
+        ..Default::default()
+    };
+    for kind in ["message", "transfer"] {
+        response.events.push(Default::default());
+        let event = response.events.last_mut().unwrap();
+        event.r#type = kind.to_owned();
+        for (key, value) in [
+            ("sender", "osmo1from"),
+            ("recipient", "osmo1to"),
+            ("amount", "110uosmo"),
+        ] {
+            event.attributes.push(Default::default());
+            let attribute = event.attributes.last_mut().unwrap();
+            attribute.key = key.to_owned();
+            attribute.value = value.to_owned();
+        }
+    }
+
+    let outcome = TxOutcome::from(response);
+    assert_eq!((outcome.gas_wanted, outcome.gas_used), (100_000, 81_234));
+    assert_eq!(outcome.timestamp.as_deref(), Some("2026-01-02T03:04:05Z"));
+    assert_eq!(outcome.fee, None);
+    assert_eq!(
+        outcome.transfers,
+        vec![Transfer {
+            sender: "osmo1from".to_owned(),
+            recipient: "osmo1to".to_owned(),
+            amount: "110uosmo".to_owned(),
+        }]
+    );
+    assert_eq!(
+        outcome.to_string(),
+        "txhash A1B2 height 7 at 2026-01-02T03:04:05Z code 0\n\
+         gas used 81234 of 100000, fee unknown\n\
+         transfer 110uosmo from osmo1from to osmo1to"
+    );
+}
@@ -0,0 +1,87 @@
+/// Library under test
+use cli_tool::status;
+use cli_tool::tasks::{self, CancellationToken, TaskGroup};
+
+/// Task timings
+use std::time::Duration;
+
+/// Whether the sibling stopped
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A task that waits for cancellation and records that it stopped
+fn watcher(
+    cancel: CancellationToken,
+    stopped: Arc<AtomicBool>,
+) -> impl std::future::Future<Output = anyhow::Result<()>> {
+    async move {
+        cancel.cancelled().await;
+        stopped.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn failing_task_cancels_its_siblings() {
+    let root = CancellationToken::new();
+    let mut group = TaskGroup::new(&root);
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    group.spawn("watcher", watcher(group.token(), stopped.clone()));
+    group.spawn("confirmation", async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        Err(anyhow::anyhow!("transaction not included"))
+    });
+
+    let error = tokio::time::timeout(Duration::from_secs(5), group.join())
+        .await
+        .expect("The group didn't stop")
+        .expect_err("The failure should be returned");
+    assert!(format!("{:#}", error).contains("transaction not included"));
+    assert!(stopped.load(Ordering::SeqCst));
+    // Only the group is cancelled, not the rest of the program
+    assert!(!root.is_cancelled());
+}
+
+#[tokio::test]
+async fn cancelling_the_parent_stops_every_task() {
+    let root = CancellationToken::new();
+    let mut group = TaskGroup::new(&root);
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    group.spawn("watcher", watcher(group.token(), stopped.clone()));
+    let poller = group.token();
+    group.spawn("poller", async move {
+        while tasks::sleep(&poller, Duration::from_millis(5)).await {}
+        Ok(())
+    });
+
+    root.cancel();
+    tokio::time::timeout(Duration::from_secs(5), group.join())
+        .await
+        .expect("The group didn't stop")
+        .expect("Cancelled tasks stop cleanly");
+    assert!(stopped.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn until_cancelled_drops_the_future() {
+    let token = CancellationToken::new();
+    token.cancel();
+    let result = tasks::until_cancelled(&token, std::future::pending::<anyhow::Result<()>>()).await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn status_reports_replace_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("status.json");
+    for done in [1, 2] {
+        status::write(Some(&file), &serde_json::json!({ "done": done })).unwrap();
+    }
+
+    let report: serde_json::Value = serde_json::from_slice(&std::fs::read(&file).unwrap()).unwrap();
+    assert_eq!(report["status"]["done"], 2);
+    assert_eq!(report["pid"], std::process::id());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
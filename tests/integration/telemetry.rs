@@ -0,0 +1,25 @@
+/// Phase durations
+use std::time::Duration;
+
+/// Library under test
+use cli_tool::telemetry;
+
+#[test]
+fn timings_roll_up_per_phase_in_order() {
+    telemetry::record_phase("confirm", Duration::from_millis(900));
+    telemetry::record_phase("connect", Duration::from_millis(100));
+    telemetry::record_phase("confirm", Duration::from_millis(300));
+    telemetry::record_phase("not-a-phase", Duration::from_secs(1));
+
+    let timings = telemetry::timings();
+    let phases: Vec<_> = timings.iter().map(|(phase, _)| *phase).collect();
+    assert_eq!(phases, ["connect", "confirm"]);
+    let confirm = timings[1].1;
+    assert_eq!(confirm.count, 2);
+    assert_eq!(confirm.total, Duration::from_millis(1_200));
+    assert_eq!(confirm.max, Duration::from_millis(900));
+
+    let report = telemetry::timing_report();
+    assert!(report.starts_with("Timings:"));
+    assert!(report.contains("confirm"));
+}
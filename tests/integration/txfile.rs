@@ -0,0 +1,88 @@
+/// Encode test transactions
+use prost::Message;
+
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Transaction protobuf types
+use cosmos::proto::cosmos::tx::v1beta1::{AuthInfo, Fee, TxBody, TxRaw};
+
+/// Library under test
+use cli_tool::{tx, txfile};
+
+/// Fixtures
+use crate::{coin, wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// Bytes of a signed-looking transaction sending 1000uosmo with a memo
+fn raw_tx() -> Vec<u8> {
+    let send = tx::msg_send(
+        wallet(TEST1_MNEMONIC).get_address(),
+        wallet(TEST2_MNEMONIC).get_address(),
+        vec![coin(1_000, "uosmo")],
+    );
+    TxRaw {
+        body_bytes: TxBody {
+            messages: vec![send.to_any()],
+            memo: "payroll".to_owned(),
+            ..Default::default()
+        }
+        .encode_to_vec(),
+        auth_info_bytes: AuthInfo {
+            fee: Some(Fee {
+                amount: vec![coin(3_500, "uosmo")],
+                gas_limit: 140_000,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+        .encode_to_vec(),
+        signatures: vec![vec![7; 64]],
+    }
+    .encode_to_vec()
+}
+
+#[test]
+fn exports_decode_into_messages_and_fee() {
+    let export = txfile::Export {
+        chain_id: "osmosis-1".to_owned(),
+        txhash: hex::encode_upper(<sha2::Sha256 as sha2::Digest>::digest(raw_tx())),
+        height: 42,
+        timestamp: "2024-07-01T12:00:00Z".to_owned(),
+        tx: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, raw_tx()),
+    };
+
+    let json = txfile::encode(&export, txfile::Format::Json).unwrap();
+    let review = txfile::review(&json).unwrap();
+    assert_eq!(review.hash_matches, Some(true));
+    assert_eq!(review.memo, "payroll");
+    assert_eq!(review.fee.amount, "3500uosmo");
+    assert_eq!(review.messages[0].value["amount"], "1000uosmo");
+    assert_eq!(review.signers.len(), 0);
+
+    // The proto form is the bytes alone
+    let proto = txfile::encode(&export, txfile::Format::Proto).unwrap();
+    assert_eq!(proto, raw_tx());
+    assert_eq!(txfile::review(&proto).unwrap().txhash, export.txhash);
+
+    // Bytes that don't hash to the exported txhash are flagged
+    let tampered = txfile::Export {
+        txhash: "00".repeat(32),
+        ..export
+    };
+    let json = txfile::encode(&tampered, txfile::Format::Json).unwrap();
+    assert_eq!(txfile::review(&json).unwrap().hash_matches, Some(false));
+}
+
+#[test]
+fn encrypted_exports_need_the_passphrase() {
+    let encrypted = txfile::encrypt(&raw_tx(), "correct horse").unwrap();
+    assert!(txfile::is_encrypted(&encrypted));
+    assert!(!txfile::is_encrypted(&raw_tx()));
+
+    assert_eq!(
+        txfile::decrypt(&encrypted, "correct horse").unwrap(),
+        raw_tx()
+    );
+    assert!(txfile::decrypt(&encrypted, "wrong horse").is_err());
+    assert!(txfile::decrypt(&encrypted[..40], "correct horse").is_err());
+}
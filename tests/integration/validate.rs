@@ -0,0 +1,112 @@
+/// Addresses of wallets
+use cosmos::HasAddress;
+
+/// Encode accounts
+use prost::Message;
+
+/// Account protobuf types
+use cosmos::proto::cosmos::auth::v1beta1 as auth;
+
+/// Library under test
+use cli_tool::auth::Activity;
+use cli_tool::{journal, validate};
+
+/// Fixtures
+use crate::{wallet, TEST1_MNEMONIC, TEST2_MNEMONIC};
+
+/// The same account under another prefix
+fn with_prefix(address: cosmos::Address, hrp: &str) -> String {
+    let (_, data) = bech32::decode(&address.to_string()).unwrap();
+    bech32::encode::<bech32::Bech32>(bech32::Hrp::parse(hrp).unwrap(), &data).unwrap()
+}
+
+#[test]
+fn mistyped_addresses_are_explained() {
+    let address = wallet(TEST1_MNEMONIC).get_address().to_string();
+    assert_eq!(
+        validate::parse_address(&address).unwrap().to_string(),
+        address
+    );
+    assert!(validate::parse_address(&address.to_uppercase()).is_ok());
+
+    // One character off breaks the checksum
+    let mut typo = address.clone();
+    let last = typo.pop().unwrap();
+    typo.push(if last == 'q' { 'p' } else { 'q' });
+    let error = validate::parse_address(&typo).unwrap_err();
+    assert!(error.to_string().contains("checksum"), "{}", error);
+
+    let mixed = format!("{}{}", &address[..6].to_uppercase(), &address[6..]);
+    assert!(validate::parse_address(&mixed).is_err());
+    assert!(validate::parse_address("osmo").is_err());
+    assert!(validate::parse_address(&address[..address.len() - 8]).is_err());
+}
+
+#[test]
+fn destinations_on_another_chain_are_refused() {
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    assert!(validate::check_prefix(to.get_address(), from.get_address()).is_ok());
+
+    let juno = validate::parse_address(&with_prefix(to.get_address(), "juno")).unwrap();
+    assert_eq!(validate::prefix(juno), "juno");
+    let error = validate::check_prefix(juno, from.get_address()).unwrap_err();
+    assert!(error.to_string().contains("ibc transfer"), "{}", error);
+}
+
+/// A journal entry of a send from `from` to `to`
+fn sent(from: &str, to: &str, code: Option<u32>) -> journal::Entry {
+    journal::Entry {
+        timestamp: chrono::Utc::now(),
+        chain: "osmo-test-5".to_owned(),
+        from: from.to_owned(),
+        to: to.to_owned(),
+        amount: "1uosmo".to_owned(),
+        fee: None,
+        txhash: None,
+        code,
+        error: None,
+    }
+}
+
+#[test]
+fn destinations_are_known_once_sent_to() {
+    let (from, to) = (wallet(TEST1_MNEMONIC), wallet(TEST2_MNEMONIC));
+    let (sender, destination) = (from.get_address().to_string(), to.get_address().to_string());
+    assert!(!validate::known_destination(&[], to.get_address()));
+
+    // A failed send doesn't make an address known, one of ours always is
+    let failed = sent(&sender, &destination, Some(5));
+    assert!(!validate::known_destination(
+        &[failed.clone()],
+        to.get_address()
+    ));
+    assert!(validate::known_destination(&[failed], from.get_address()));
+    let delivered = sent(&sender, &destination, Some(0));
+    assert!(validate::known_destination(&[delivered], to.get_address()));
+}
+
+#[test]
+fn activity_tells_unused_accounts_apart() {
+    let base = |sequence| prost_types::Any {
+        type_url: "/cosmos.auth.v1beta1.BaseAccount".to_owned(),
+        value: auth::BaseAccount {
+            address: wallet(TEST2_MNEMONIC).get_address().to_string(),
+            pub_key: None,
+            account_number: 7,
+            sequence,
+        }
+        .encode_to_vec(),
+    };
+    assert_eq!(Activity::of(None).unwrap(), Activity::Unused);
+    assert_eq!(
+        Activity::of(Some(&base(0))).unwrap(),
+        Activity::ReceivedOnly
+    );
+    assert_eq!(Activity::of(Some(&base(3))).unwrap(), Activity::Active);
+
+    let module = prost_types::Any {
+        type_url: "/cosmos.auth.v1beta1.ModuleAccount".to_owned(),
+        value: Vec::new(),
+    };
+    assert_eq!(Activity::of(Some(&module)).unwrap(), Activity::Active);
+}
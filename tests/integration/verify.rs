@@ -0,0 +1,16 @@
+/// Library under test
+use cli_tool::verify;
+
+#[test]
+fn differing_block_hashes_are_refused() {
+    let hash = [0xab; 32];
+    assert!(verify::compare(100, &hash, &hash, "https://second:9090").is_ok());
+
+    let mut forked = hash;
+    forked[31] = 0xac;
+    let error = verify::compare(100, &hash, &forked, "https://second:9090").unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("Block 100"), "{}", message);
+    assert!(message.contains("https://second:9090"), "{}", message);
+    assert!(message.contains(&hex::encode_upper(forked)), "{}", message);
+}
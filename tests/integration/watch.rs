@@ -0,0 +1,72 @@
+/// Library under test
+use cli_tool::tx::TxResponse;
+use cli_tool::watch::{self, Direction};
+
+/// Searched transaction at `height` with bank transfers of (sender, recipient, amount)
+fn response(height: i64, txhash: &str, transfers: &[(&str, &str, &str)]) -> TxResponse {
+    let mut response = TxResponse {
+        height,
+        txhash: txhash.to_owned(),
+        ..Default::default()
+    };
+    for (sender, recipient, amount) in transfers {
+        response.events.push(Default::default());
+        let event = response.events.last_mut().unwrap();
+        event.r#type = "transfer".to_owned();
+        for (key, value) in [
+            ("sender", sender),
+            ("recipient", recipient),
+            ("amount", amount),
+        ] {
+            event.attributes.push(Default::default());
+            let attribute = event.attributes.last_mut().unwrap();
+            attribute.key = key.to_owned().into();
+            attribute.value = value.to_string().into();
+        }
+    }
+    response
+}
+
+#[test]
+fn history_lists_the_latest_transactions_once_newest_first() {
+    let mut failed = response(12, "F", &[("osmo1me", "osmo1other", "9uosmo")]);
+    failed.code = 5;
+    // The sender and recipient searches overlap on a transaction doing both
+    let responses = vec![
+        response(10, "B", &[("osmo1me", "osmo1other", "5uosmo")]),
+        response(11, "C", &[("osmo1payer", "osmo1me", "3uosmo")]),
+        response(
+            11,
+            "A",
+            &[
+                ("osmo1me", "osmo1pool", "1uosmo"),
+                ("osmo1pool", "osmo1me", "2uion"),
+            ],
+        ),
+        response(9, "D", &[("osmo1payer", "osmo1me", "1uosmo")]),
+        response(11, "A", &[]),
+        failed,
+    ];
+
+    let events = watch::recent("osmo1me", responses, 3);
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| {
+            (
+                event.txhash.as_str(),
+                event.direction,
+                event.amount.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("A", Direction::Sent, "1uosmo"),
+            ("A", Direction::Received, "2uion"),
+            ("C", Direction::Received, "3uosmo"),
+            ("B", Direction::Sent, "5uosmo"),
+        ]
+    );
+    assert_eq!(events[3].counterparty, "osmo1other");
+}